@@ -5,7 +5,9 @@ use std::process;
 #[derive(Parser)]
 #[command(name = "dis68k", version, about)]
 struct Cli {
-    /// Input Amiga hunk executable file
+    /// Input executable (Amiga hunk, Atari ST PRG or m68k ELF), or
+    /// `disk.adf:path/on/disk` to read a file from an ADF image (a bare
+    /// `disk.adf` lists its files)
     input: String,
 
     /// Write output to file instead of stdout
@@ -20,6 +22,100 @@ struct Cli {
     #[arg(long)]
     hunk_info: bool,
 
+    /// Summarise the hardware registers the code accesses (no disassembly)
+    #[arg(long)]
+    hardware: bool,
+
+    /// Write the call graph of the functions found in the code as
+    /// Graphviz DOT or JSON (no disassembly)
+    #[arg(long, value_name = "FORMAT", value_parser = ["dot", "json"])]
+    call_graph: Option<String>,
+
+    /// Write the control-flow graph of the function at [HUNK/]ADDR, or
+    /// with the given name, with its disassembly (no disassembly listing)
+    #[arg(long, value_name = "FUNCTION")]
+    cfg: Option<String>,
+
+    /// Graph format for --cfg
+    #[arg(long, value_name = "FORMAT", value_parser = ["dot", "graphml"], default_value = "dot", requires = "cfg")]
+    cfg_format: String,
+
+    /// List the references to [HUNK/]ADDR, or to the label or symbol
+    /// with the given name (no disassembly)
+    #[arg(long, value_name = "ADDR|SYMBOL")]
+    xrefs: Option<String>,
+
+    /// Treat the input as a 256K/512K Kickstart ROM image
+    #[arg(long)]
+    rom: bool,
+
+    /// Second half of a split 32-bit ROM pair (input is the "hi" chip)
+    #[arg(long, value_name = "FILE")]
+    rom_pair: Option<String>,
+
+    /// Disassemble the 1024-byte bootblock of an ADF or raw bootblock file
+    #[arg(long)]
+    bootblock: bool,
+
+    /// With --bootblock: write the bootblock with a corrected checksum to FILE
+    #[arg(long, value_name = "FILE", requires = "bootblock")]
+    fix_checksum: Option<String>,
+
+    /// Treat the input as a raw binary (implied by --base/--segment)
+    #[arg(long)]
+    raw: bool,
+
+    /// Load address of a raw binary ($hex, 0xhex or decimal)
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    base: Option<u32>,
+
+    /// Entry point address in a raw binary (repeatable)
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    entry: Vec<u32>,
+
+    /// Raw binary segment: file OFFSET:LENGTH loaded at ADDR (repeatable)
+    #[arg(long, value_name = "OFFSET:LENGTH@ADDR", value_parser = parse_segment)]
+    segment: Vec<dis68k::RawSegment>,
+
+    /// Function description (.fd) file naming a library's or device's vectors
+    #[arg(long, value_name = "FILE")]
+    fd: Option<String>,
+
+    /// Directory of .fd files: LVO tables for call-site names and library vectors
+    #[arg(long, value_name = "DIR")]
+    fd_dir: Option<String>,
+
+    /// Directory of assembler include (.i) files, searched recursively:
+    /// structure fields and constants (repeatable)
+    #[arg(long, value_name = "DIR")]
+    include_dir: Vec<String>,
+
+    /// Declare that register REG points at structure STRUCT at the
+    /// instruction at ADDR in hunk HUNK (default 0), e.g. `$1A:a0=Process`
+    /// (repeatable)
+    #[arg(long, value_name = "[HUNK/]ADDR:REG=STRUCT", value_parser = parse_register_type)]
+    reg_type: Vec<dis68k::RegisterType>,
+
+    /// Project file with labels, comments, functions, data/code regions
+    /// and register types, keyed by hunk and offset
+    #[arg(long, value_name = "FILE")]
+    project: Option<String>,
+
+    /// Write the project (from --project, if given) plus the auto-generated
+    /// labels to FILE as a starting point for editing (no disassembly)
+    #[arg(long, value_name = "FILE")]
+    export_project: Option<String>,
+
+    /// Unpack PowerPacker (PP20), Imploder, CrunchMania and StoneCracker (S404) crunched executables
+    /// before disassembly
+    #[arg(long)]
+    decrunch: bool,
+
+    /// Decode code hunks from start to end instead of following control
+    /// flow from the entry point, symbols and relocation targets
+    #[arg(long)]
+    linear_sweep: bool,
+
     /// Disable Amiga OS symbol resolution
     #[arg(long)]
     no_symbols: bool,
@@ -44,70 +140,768 @@ struct Cli {
 fn main() {
     let cli = Cli::parse();
 
-    let data = match std::fs::read(&cli.input) {
-        Ok(d) => d,
+    if cli.bootblock {
+        let image = split_adf_path(&cli.input).map_or(cli.input.as_str(), |(image, _)| image);
+        run_bootblock(&cli, read_file(image));
+        return;
+    }
+
+    let data = match split_adf_path(&cli.input) {
+        Some((image, path)) => {
+            let disk = read_file(image);
+            let volume = dis68k::AdfVolume::open(&disk).unwrap_or_else(|e| {
+                eprintln!("Error reading ADF '{}': {}", image, e);
+                process::exit(1);
+            });
+            if path.is_empty() {
+                print_adf_listing(&volume, image);
+                return;
+            }
+            volume.read_file(path).unwrap_or_else(|e| {
+                eprintln!("Error reading '{}': {}", cli.input, e);
+                process::exit(1);
+            })
+        }
+        None => read_file(&cli.input),
+    };
+
+    if cli.rom || cli.rom_pair.is_some() {
+        run_rom(&cli, data);
+        return;
+    }
+
+    if cli.raw || cli.base.is_some() || !cli.segment.is_empty() {
+        run_raw(&cli, &data);
+        return;
+    }
+
+    if let Some(loader) = dis68k::detect_format(&data) {
+        if loader.format() != dis68k::ImageFormat::AmigaHunk {
+            run_image(&cli, loader, &data);
+            return;
+        }
+    }
+
+    let hunk_file = if cli.decrunch {
+        match dis68k::parse_hunk_file_decrunched(&data) {
+            Ok((h, detection)) => {
+                if let Some(d) = detection {
+                    eprintln!("Decrunched {} ({}) from '{}'", d.cruncher, d.variant, cli.input);
+                }
+                h
+            }
+            // Recognised but not unpackable: list the stub
+            Err(dis68k::Error::Crunch(e @ dis68k::crunch::CrunchError::Unsupported { .. })) => {
+                eprintln!("Warning: '{}': {}; disassembling it as it is", cli.input, e);
+                dis68k::parse_hunk_file(&data).unwrap_or_else(|e| {
+                    eprintln!("Error parsing '{}': {}", cli.input, e);
+                    process::exit(1);
+                })
+            }
+            Err(e) => {
+                eprintln!("Error parsing '{}': {}", cli.input, e);
+                process::exit(1);
+            }
+        }
+    } else {
+        if let Some(d) = dis68k::detect_cruncher(&data) {
+            eprintln!(
+                "Note: '{}' looks crunched with {} ({}); use --decrunch to unpack it",
+                cli.input, d.cruncher, d.variant
+            );
+        }
+        match dis68k::parse_hunk_file(&data) {
+            Ok(h) => h,
+            Err(e) => {
+                eprintln!("Error parsing '{}': {}", cli.input, e);
+                process::exit(1);
+            }
+        }
+    };
+
+    if cli.hunk_info {
+        print_hunk_info(&hunk_file, &cli);
+        return;
+    }
+
+    let cpu = parse_cpu(&cli);
+    if cli.hardware {
+        print_hardware_usage(&dis68k::Image::from(&hunk_file), &cli, cpu);
+        return;
+    }
+    if cli.call_graph.is_some() || cli.cfg.is_some() || cli.xrefs.is_some() {
+        // A library's vectors are functions, named like in its listing
+        let mut labeled = hunk_file.clone();
+        let vectors = match dis68k::analyze_library(&hunk_file, None, cpu) {
+            Ok(analysis) => {
+                analysis.add_symbols(&mut labeled);
+                analysis.function_entries()
+            }
+            Err(_) => Vec::new(),
+        };
+        write_analysis(&dis68k::Image::from(&labeled), &cli, cpu, &vectors);
+        return;
+    }
+    if cli.export_project.is_some() {
+        export_project(&dis68k::Image::from(&hunk_file), &cli, cpu);
+        return;
+    }
+
+    let options = listing_options(&cli, cpu);
+    let fds = load_fd_dir(&cli);
+    let resolver = build_resolver(&cli, &fds);
+
+    let fd = cli.fd.as_deref().map(|path| {
+        let text = read_file(path);
+        dis68k::parse_fd(&String::from_utf8_lossy(&text))
+    });
+    let analysis = dis68k::analyze_library(&hunk_file, fd.as_ref(), cpu).map(|analysis| {
+        // Without --fd, look for the library's own .fd file in --fd-dir
+        let name = analysis.module.resident.short_name().to_ascii_lowercase();
+        match fds.iter().find(|(n, _)| *n == name) {
+            Some((_, fd)) if cli.fd.is_none() => {
+                dis68k::analyze_library(&hunk_file, Some(fd), cpu).unwrap_or(analysis)
+            }
+            _ => analysis,
+        }
+    });
+    let listing = match analysis {
+        Ok(analysis) => dis68k::generate_library_listing(
+            &hunk_file,
+            &analysis,
+            &options,
+            resolver.as_deref(),
+        ),
         Err(e) => {
-            eprintln!("Error reading '{}': {}", cli.input, e);
+            if fd.is_some() {
+                eprintln!("Warning: --fd ignored, '{}' is not a library or device: {}", cli.input, e);
+            }
+            dis68k::generate_listing(&hunk_file, &options, resolver.as_deref())
+        }
+    };
+
+    write_output(&cli, &listing);
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    std::fs::read(path).unwrap_or_else(|e| {
+        eprintln!("Error reading '{}': {}", path, e);
+        process::exit(1);
+    })
+}
+
+/// Split `disk.adf:c/Dir` into the image path and the path on the disk.
+///
+/// A bare `disk.adf` yields an empty disk path. Returns `None` for
+/// anything that is not an ADF image.
+fn split_adf_path(input: &str) -> Option<(&str, &str)> {
+    let lower = input.to_ascii_lowercase();
+    if lower.ends_with(".adf") {
+        return Some((input, ""));
+    }
+    if std::path::Path::new(input).exists() {
+        return None;
+    }
+    let idx = lower.find(".adf:")?;
+    Some((&input[..idx + 4], &input[idx + 5..]))
+}
+
+fn print_adf_listing(volume: &dis68k::AdfVolume, image: &str) {
+    println!("ADF disk image: {}", image);
+    println!(
+        "Volume: {} ({}, {} blocks, bootblock checksum {})",
+        volume.name(),
+        volume.dos_type(),
+        volume.num_blocks,
+        if volume.bootblock.checksum_ok { "OK" } else { "BAD" }
+    );
+    println!();
+
+    let entries = volume.walk().unwrap_or_else(|e| {
+        eprintln!("Error reading ADF '{}': {}", image, e);
+        process::exit(1);
+    });
+    for (path, entry) in &entries {
+        let size = match entry.kind {
+            dis68k::EntryKind::File => entry.size.to_string(),
+            dis68k::EntryKind::Directory => "<dir>".to_string(),
+            _ => "<link>".to_string(),
+        };
+        println!("  {}  {:>8}  {}  {}", entry.protection, size, entry.date, path);
+    }
+}
+
+/// Disassemble a raw binary at its load address.
+fn run_raw(cli: &Cli, data: &[u8]) {
+    let layout = dis68k::RawLayout {
+        base_address: cli.base.unwrap_or(0),
+        segments: cli.segment.clone(),
+        entry_points: cli.entry.clone(),
+    };
+    let hunk_file = dis68k::load_raw_binary(data, &layout).unwrap_or_else(|e| {
+        eprintln!("Error loading raw binary '{}': {}", cli.input, e);
+        process::exit(1);
+    });
+
+    if cli.hunk_info {
+        print_hunk_info(&hunk_file, cli);
+        return;
+    }
+    if cli.hardware {
+        print_hardware_usage(&dis68k::Image::from(&hunk_file), cli, parse_cpu(cli));
+        return;
+    }
+    if cli.call_graph.is_some() || cli.cfg.is_some() || cli.xrefs.is_some() {
+        write_analysis(&dis68k::Image::from(&hunk_file), cli, parse_cpu(cli), &[]);
+        return;
+    }
+    if cli.export_project.is_some() {
+        export_project(&dis68k::Image::from(&hunk_file), cli, parse_cpu(cli));
+        return;
+    }
+
+    let options = listing_options(cli, parse_cpu(cli));
+    let resolver = build_resolver(cli, &load_fd_dir(cli));
+    let listing = dis68k::generate_raw_listing(&hunk_file, &options, resolver.as_deref());
+    write_output(cli, &listing);
+}
+
+/// Disassemble a non-hunk executable (Atari ST PRG, m68k ELF).
+fn run_image(cli: &Cli, loader: &dyn dis68k::Loader, data: &[u8]) {
+    let image = loader.load(data).unwrap_or_else(|e| {
+        eprintln!("Error loading {} '{}': {}", loader.format(), cli.input, e);
+        process::exit(1);
+    });
+
+    if cli.hunk_info {
+        print_image_info(&image, cli);
+        return;
+    }
+    if cli.hardware {
+        print_hardware_usage(&image, cli, parse_cpu(cli));
+        return;
+    }
+    if cli.call_graph.is_some() || cli.cfg.is_some() || cli.xrefs.is_some() {
+        write_analysis(&image, cli, parse_cpu(cli), &[]);
+        return;
+    }
+    if cli.export_project.is_some() {
+        export_project(&image, cli, parse_cpu(cli));
+        return;
+    }
+
+    let options = listing_options(cli, parse_cpu(cli));
+    let resolver = build_resolver(cli, &load_fd_dir(cli));
+    let listing = dis68k::generate_image_listing(&image, &options, resolver.as_deref());
+    write_output(cli, &listing);
+}
+
+fn print_image_info(image: &dis68k::Image, cli: &Cli) {
+    println!("{}: {}", image.format, cli.input);
+    println!("Segments: {}", image.segments.len());
+    for &entry in &image.entry_points {
+        println!("Entry: ${:08X}", entry);
+    }
+    println!();
+
+    for segment in &image.segments {
+        println!(
+            "  Segment {}: {:<4} {:<12} ${:08X}  {} bytes, {} symbols, {} fixups",
+            segment.index,
+            segment.kind.name(),
+            image.segment_label(segment.index),
+            segment.base_address,
+            segment.size,
+            segment.symbols.len(),
+            segment.fixups.len()
+        );
+        if cli.verbose {
+            for sym in &segment.symbols {
+                println!("    ${:08X}  {}", segment.base_address.wrapping_add(sym.value), sym.name);
+            }
+        }
+    }
+}
+
+/// Print the hardware registers an image's code reads and writes,
+/// grouped by chip.
+fn print_hardware_usage(image: &dis68k::Image, cli: &Cli, cpu: dis68k::CpuVariant) {
+    let project = load_project(cli);
+    let code = dis68k::discover_code(image, cpu, &project.code_entries(), cli.linear_sweep);
+    let usage = dis68k::hardware_usage(image, cpu, &code);
+    println!("Hardware access: {}", cli.input);
+    if usage.registers.is_empty() {
+        println!("  (none)");
+        return;
+    }
+
+    for (chip, total) in usage.chips() {
+        println!();
+        println!("{chip}: {total} accesses");
+        for register in usage.registers.values().filter(|r| r.chip == chip) {
+            println!(
+                "  ${:08X}  {:<18} {:>5} reads {:>5} writes",
+                register.address,
+                register.name.as_deref().unwrap_or("?"),
+                register.reads,
+                register.writes
+            );
+        }
+    }
+}
+
+/// Write the call graph (`--call-graph`), a function's control-flow
+/// graph (`--cfg`) or the references to a place (`--xrefs`) to
+/// `--output` or stdout, with functions named from the project and the
+/// image's symbols.
+fn write_analysis(
+    image: &dis68k::Image,
+    cli: &Cli,
+    cpu: dis68k::CpuVariant,
+    vectors: &[dis68k::Location],
+) {
+    let options = listing_options(cli, cpu);
+    let project = &options.project;
+    let code = dis68k::discover_code(image, cpu, &project.code_entries(), cli.linear_sweep);
+    let mut known: Vec<_> = project
+        .functions
+        .keys()
+        .map(|&location| (location, dis68k::FunctionSource::Project))
+        .collect();
+    known.extend(vectors.iter().map(|&location| (location, dis68k::FunctionSource::Vector)));
+    let graph = dis68k::find_functions(image, cpu, &code, &known);
+    let labels = dis68k::ListingLabels::new(image, &options, &code, &graph);
+
+    // Name branch targets like the listing does
+    let mut labeled = project.clone();
+    labeled.add_auto_labels(image, &code, cpu);
+    let mut names = dis68k::CompositeResolver::new();
+    names.add(Box::new(dis68k::ProjectResolver::new(&labeled, &image.segments)));
+    names.add(Box::new(dis68k::HunkSymbolResolver::from_segments(&image.segments)));
+    let mut function_labels = dis68k::AutoLabelResolver::new();
+    for function in graph.functions.values() {
+        function_labels.add_label(function.entry, function.label(), dis68k::SymbolKind::CodeLabel);
+    }
+    names.add(Box::new(function_labels));
+
+    let text = if let Some(spec) = &cli.cfg {
+        let function = find_place(image, project, &labels, spec)
+            .and_then(|location| graph.function_containing(location))
+            .unwrap_or_else(|| {
+                eprintln!("No function '{}' in '{}'", spec, cli.input);
+                process::exit(1);
+            });
+        let cfg = dis68k::build_cfg(image, cpu, function, &code.cases());
+        let opts = dis68k::output::formatter::FormatOptions {
+            uppercase: cli.uppercase,
+        };
+        // Format the blocks like the listing
+        let resolver = build_resolver(cli, &load_fd_dir(cli));
+        let names = labels.resolver(image, function.entry.hunk, resolver.as_deref());
+        match cli.cfg_format.as_str() {
+            "graphml" => cfg.to_graphml(Some(&names), &opts),
+            _ => cfg.to_dot(Some(&names), &opts),
+        }
+    } else if let Some(spec) = &cli.xrefs {
+        let target = find_place(image, project, &labels, spec).unwrap_or_else(|| {
+            eprintln!("No place '{}' in '{}'", spec, cli.input);
             process::exit(1);
+        });
+        let xrefs = dis68k::build_xrefs(image, cpu, &code, labels.small_data(), labels.bases());
+        format_xrefs(&xrefs, target, &labels.resolver(image, target.hunk, None))
+    } else {
+        match cli.call_graph.as_deref() {
+            Some("json") => graph.to_json(Some(&names)),
+            _ => graph.to_dot(Some(&names)),
         }
     };
+    write_text(cli, &text);
+}
 
-    let hunk_file = match dis68k::parse_hunk_file(&data) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("Error parsing '{}': {}", cli.input, e);
+/// The references to `target`, one per line with the referring place
+/// named by the label at or before it.
+fn format_xrefs(xrefs: &dis68k::XrefIndex, target: dis68k::Location, names: &dyn dis68k::SymbolResolver) -> String {
+    let name = |location: dis68k::Location| {
+        names
+            .resolve_nearest(location)
+            .map_or_else(|| location.to_string(), |symbol| symbol.to_string())
+    };
+    let refs = xrefs.to(target);
+    let mut text = format!("References to {} ({}): {}\n", name(target), target, refs.len());
+    for xref in refs {
+        text.push_str(&format!("  {:<10} {:<24} {}\n", xref.from.to_string(), name(xref.from), xref.kind.name()));
+    }
+    text
+}
+
+/// Find a place in an image by a name the listing shows (a project
+/// label or function, a symbol, or an auto-label like `sub_XXXX` or
+/// `data_1_0124`) or as `[HUNK/]ADDR`.
+fn find_place(
+    image: &dis68k::Image,
+    project: &dis68k::Project,
+    labels: &dis68k::ListingLabels,
+    spec: &str,
+) -> Option<dis68k::Location> {
+    if let Some(location) = labels.find(image, project, spec) {
+        return Some(location);
+    }
+    match spec.split_once('/') {
+        Some((hunk, address)) => {
+            let segment = image.segments.get(hunk.parse::<usize>().ok()?)?;
+            let address = parse_address(address).ok()?;
+            Some(dis68k::Location::new(segment.index, address.wrapping_sub(segment.base_address)))
+        }
+        None => image.locate(parse_address(spec).ok()?),
+    }
+}
+
+/// Parse `$C00000`, `0xC00000` or a decimal number.
+fn parse_address(s: &str) -> Result<u32, String> {
+    let parsed = if let Some(hex) = s.strip_prefix('$') {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|_| format!("invalid address '{s}'"))
+}
+
+/// Parse `OFFSET:LENGTH@ADDR`, each part as accepted by `parse_address`.
+fn parse_segment(s: &str) -> Result<dis68k::RawSegment, String> {
+    let err = || format!("invalid segment '{s}', expected OFFSET:LENGTH@ADDR");
+    let (range, addr) = s.split_once('@').ok_or_else(err)?;
+    let (offset, length) = range.split_once(':').ok_or_else(err)?;
+    Ok(dis68k::RawSegment {
+        file_offset: parse_address(offset)? as usize,
+        length: parse_address(length)? as usize,
+        load_address: parse_address(addr)?,
+    })
+}
+
+/// Parse `[HUNK/]ADDR:REG=STRUCT`, with ADDR as accepted by `parse_address`.
+fn parse_register_type(s: &str) -> Result<dis68k::RegisterType, String> {
+    let err = || format!("invalid register type '{s}', expected [HUNK/]ADDR:REG=STRUCT");
+    let (place, type_name) = s.split_once('=').ok_or_else(err)?;
+    let (at, register) = place.split_once(':').ok_or_else(err)?;
+    let (segment, address) = match at.split_once('/') {
+        Some((hunk, address)) => (hunk.parse().map_err(|_| err())?, address),
+        None => (0, at),
+    };
+    let register = match register.to_ascii_lowercase().as_str() {
+        "sp" => 15,
+        r => match (r.chars().next(), r.get(1..).unwrap_or("").parse::<u8>()) {
+            (Some('d'), Ok(n)) if n < 8 => n,
+            (Some('a'), Ok(n)) if n < 8 => 8 + n,
+            _ => return Err(format!("invalid register '{register}'")),
+        },
+    };
+    Ok(dis68k::RegisterType {
+        segment,
+        address: parse_address(address)?,
+        register,
+        type_name: type_name.to_string(),
+    })
+}
+
+/// Disassemble a bootblock, optionally writing a checksum-corrected copy.
+fn run_bootblock(cli: &Cli, data: Vec<u8>) {
+    let cpu = parse_cpu(cli);
+    let report = dis68k::adf::analyze_bootblock(&data, cpu).unwrap_or_else(|e| {
+        eprintln!("Error reading bootblock '{}': {}", cli.input, e);
+        process::exit(1);
+    });
+
+    if !report.checksum_ok() {
+        eprintln!(
+            "Warning: bootblock checksum is ${:08X}, expected ${:08X}",
+            report.stored_checksum, report.computed_checksum
+        );
+    }
+    if let Some(path) = &cli.fix_checksum {
+        let mut fixed = data[..dis68k::adf::bootblock::BOOTBLOCK_SIZE].to_vec();
+        dis68k::adf::fix_bootblock_checksum(&mut fixed);
+        if let Err(e) = std::fs::write(path, &fixed) {
+            eprintln!("Error writing '{}': {}", path, e);
             process::exit(1);
         }
+        eprintln!("Wrote bootblock with corrected checksum to '{}'", path);
+    }
+
+    let options = listing_options(cli, cpu);
+    let resolver = build_resolver(cli, &load_fd_dir(cli));
+    let listing =
+        dis68k::adf::generate_bootblock_listing(&data, &report, &options, resolver.as_deref());
+    write_output(cli, &listing);
+}
+
+/// Disassemble a Kickstart ROM image (optionally merged from a split pair).
+fn run_rom(cli: &Cli, data: Vec<u8>) {
+    let data = match &cli.rom_pair {
+        Some(path) => {
+            let lo = read_file(path);
+            dis68k::merge_split_rom(&data, &lo).unwrap_or_else(|e| {
+                eprintln!("Error merging ROM pair: {}", e);
+                process::exit(1);
+            })
+        }
+        None => data,
     };
 
+    let cpu = parse_cpu(cli);
+    let rom = dis68k::RomAnalysis::new(&data, cpu).unwrap_or_else(|e| {
+        eprintln!("Error loading ROM '{}': {}", cli.input, e);
+        process::exit(1);
+    });
+
     if cli.hunk_info {
-        print_hunk_info(&hunk_file, &cli);
+        print_rom_info(&rom, cli);
+        return;
+    }
+    if cli.hardware {
+        print_hardware_usage(&dis68k::Image::from(&rom.to_hunk_file()), cli, cpu);
+        return;
+    }
+    if cli.call_graph.is_some() || cli.cfg.is_some() || cli.xrefs.is_some() {
+        let image = dis68k::Image::from(&rom.to_hunk_file());
+        let vectors: Vec<dis68k::Location> = rom
+            .modules
+            .iter()
+            .flat_map(|module| &module.functions)
+            .filter_map(|func| image.locate(func.address))
+            .collect();
+        write_analysis(&image, cli, cpu, &vectors);
+        return;
+    }
+    if cli.export_project.is_some() {
+        export_project(&dis68k::Image::from(&rom.to_hunk_file()), cli, cpu);
         return;
     }
 
-    let cpu = dis68k::CpuVariant::from_str(&cli.cpu).unwrap_or_else(|| {
+    let options = listing_options(cli, cpu);
+    let resolver = build_resolver(cli, &load_fd_dir(cli));
+    let listing = dis68k::generate_rom_listing(&rom, &options, resolver.as_deref());
+    write_output(cli, &listing);
+}
+
+fn parse_cpu(cli: &Cli) -> dis68k::CpuVariant {
+    dis68k::CpuVariant::from_str(&cli.cpu).unwrap_or_else(|| {
         eprintln!(
             "Unknown CPU variant '{}'. Use: 68000, 68010, 68020, 68030, 68040, 68060",
             cli.cpu
         );
         process::exit(1);
-    });
+    })
+}
 
-    let options = dis68k::ListingOptions {
+fn listing_options(cli: &Cli, cpu: dis68k::CpuVariant) -> dis68k::ListingOptions {
+    dis68k::ListingOptions {
         show_hex: !cli.no_hex,
         show_addresses: true,
         show_line_numbers: !cli.no_line_numbers,
         uppercase: cli.uppercase,
         cpu,
         symbols: !cli.no_symbols,
+        register_types: cli.reg_type.clone(),
+        project: load_project(cli),
+        linear_sweep: cli.linear_sweep,
+    }
+}
+
+/// Read the `--project` file, or start an empty project.
+fn load_project(cli: &Cli) -> dis68k::Project {
+    let Some(path) = &cli.project else {
+        return dis68k::Project::default();
     };
+    let text = read_file(path);
+    dis68k::parse_project(&String::from_utf8_lossy(&text)).unwrap_or_else(|e| {
+        eprintln!("Error reading project '{}': {}", path, e);
+        process::exit(1);
+    })
+}
 
-    // Build the resolver chain: LVO tables for exec.library (default assumption
-    // is A6 = ExecBase; true library base tracking requires Phase 5 data-flow).
-    let resolver: Option<Box<dyn dis68k::SymbolResolver>> = if !cli.no_symbols {
+/// Write the project plus the image's auto-generated labels to
+/// `--export-project`.
+fn export_project(image: &dis68k::Image, cli: &Cli, cpu: dis68k::CpuVariant) {
+    let Some(path) = &cli.export_project else {
+        return;
+    };
+    let mut project = load_project(cli);
+    let code = dis68k::discover_code(image, cpu, &project.code_entries(), cli.linear_sweep);
+    let added = project.add_auto_labels(image, &code, cpu);
+    if let Err(e) = std::fs::write(path, project.to_toml()) {
+        eprintln!("Error writing '{}': {}", path, e);
+        process::exit(1);
+    }
+    eprintln!("Wrote project with {} auto-generated labels to '{}'", added, path);
+}
+
+/// Parse every `.fd` file in `--fd-dir`, keyed by library name.
+fn load_fd_dir(cli: &Cli) -> Vec<(String, dis68k::FdFile)> {
+    let Some(dir) = &cli.fd_dir else {
+        return Vec::new();
+    };
+    let entries = std::fs::read_dir(dir).unwrap_or_else(|e| {
+        eprintln!("Error reading '{}': {}", dir, e);
+        process::exit(1);
+    });
+    let mut fds = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if !file_name.to_ascii_lowercase().ends_with(".fd") {
+            continue;
+        }
+        let text = read_file(&entry.path().to_string_lossy());
+        fds.push((
+            dis68k::library_name_for_fd(&file_name),
+            dis68k::parse_fd(&String::from_utf8_lossy(&text)),
+        ));
+    }
+    if cli.verbose {
+        eprintln!("Loaded {} .fd files from '{}'", fds.len(), dir);
+    }
+    fds
+}
+
+/// Parse every `.i` file under the `--include-dir` directories.
+fn load_includes(cli: &Cli) -> Option<dis68k::IncludeSymbols> {
+    if cli.include_dir.is_empty() {
+        return None;
+    }
+    let mut files = Vec::new();
+    let mut dirs: Vec<std::path::PathBuf> = cli.include_dir.iter().map(Into::into).collect();
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(&dir).unwrap_or_else(|e| {
+            eprintln!("Error reading '{}': {}", dir.display(), e);
+            process::exit(1);
+        });
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("i")) {
+                files.push(path);
+            }
+        }
+    }
+    // A fixed order, so a structure defined twice always resolves the same way
+    files.sort();
+    let texts: Vec<String> = files
+        .iter()
+        .map(|path| String::from_utf8_lossy(&read_file(&path.to_string_lossy())).into_owned())
+        .collect();
+    let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+    let includes = dis68k::parse_includes(&texts);
+    if cli.verbose {
+        eprintln!(
+            "Loaded {} structures and {} constants from {} .i files",
+            includes.structs.layouts().len(),
+            includes.constants.len(),
+            texts.len()
+        );
+    }
+    Some(includes)
+}
+
+/// Check that every `--reg-type` names a built-in structure or one from
+/// `--include-dir`.
+fn check_register_types(cli: &Cli, includes: Option<&dis68k::IncludeSymbols>) {
+    for hint in &cli.reg_type {
+        let known = dis68k::symbols::structs::find_struct(&hint.type_name).is_some()
+            || includes.is_some_and(|includes| includes.structs.find(&hint.type_name).is_some());
+        if !known {
+            eprintln!("Unknown structure '{}' in --reg-type", hint.type_name);
+            process::exit(1);
+        }
+    }
+}
+
+/// Build the resolver chain: LVO tables for every library, with exec.library
+/// as the default for calls through A6 where base tracking found no value,
+/// then the hardware register names, the structures and constants from
+/// `--include-dir` and the built-in OS structure fields. Tables from
+/// `--fd-dir` take priority over the built-in ones.
+fn build_resolver(
+    cli: &Cli,
+    fds: &[(String, dis68k::FdFile)],
+) -> Option<Box<dyn dis68k::SymbolResolver>> {
+    if !cli.no_symbols {
+        let libraries: Vec<dis68k::Library> = fds
+            .iter()
+            .map(|(name, fd)| dis68k::Library::from_fd(name, fd))
+            .collect();
         let mut composite = dis68k::CompositeResolver::new();
-        composite.add(Box::new(dis68k::LvoResolver::new("exec")));
+        composite.add(Box::new(dis68k::LvoResolver::with_libraries("exec", &libraries)));
+        composite.add(Box::new(dis68k::HardwareResolver::new()));
+        let includes = load_includes(cli);
+        check_register_types(cli, includes.as_ref());
+        if let Some(includes) = includes {
+            composite.add(Box::new(dis68k::IncludeResolver::new(includes)));
+        }
+        composite.add(Box::new(dis68k::StructResolver::new()));
         Some(Box::new(composite))
     } else {
         None
-    };
-
-    let listing = dis68k::generate_listing(
-        &hunk_file,
-        &options,
-        resolver.as_deref(),
-    );
+    }
+}
 
-    // Write output
+fn write_output(cli: &Cli, listing: &[dis68k::ListingLine]) {
     let output_text: String = listing.iter().map(|l| format!("{}\n", l.text)).collect();
+    write_text(cli, &output_text);
+}
 
+/// Write `text` to `--output`, or stdout.
+fn write_text(cli: &Cli, text: &str) {
     if let Some(path) = &cli.output {
-        if let Err(e) = std::fs::write(path, &output_text) {
+        if let Err(e) = std::fs::write(path, text) {
             eprintln!("Error writing '{}': {}", path, e);
             process::exit(1);
         }
     } else {
-        print!("{output_text}");
+        print!("{text}");
+    }
+}
+
+fn print_rom_info(rom: &dis68k::RomAnalysis, cli: &Cli) {
+    let image = &rom.image;
+    let (version, revision) = image.version();
+    println!("Kickstart ROM: {}", cli.input);
+    println!(
+        "Base: ${:08X}  size: {} bytes  version: {}.{}  checksum: {}{}",
+        image.base_address,
+        image.data.len(),
+        version,
+        revision,
+        if image.checksum_ok() { "OK" } else { "BAD" },
+        if image.byte_swapped { "  (byte-swapped)" } else { "" }
+    );
+    println!("Modules: {}", rom.modules.len());
+    println!();
+
+    for module in &rom.modules {
+        let r = &module.resident;
+        println!(
+            "  ${:08X}-${:08X}  {:<24} v{:<3} pri {:>4}  {:<11} {}",
+            module.start,
+            module.end,
+            r.name,
+            r.version,
+            r.priority,
+            r.node_type_name(),
+            r.flag_names()
+        );
+        if !r.id_string.is_empty() {
+            println!("           id: \"{}\"", r.id_string);
+        }
+        if !module.functions.is_empty() {
+            println!("           functions: {}", module.functions.len());
+            if cli.verbose {
+                for func in &module.functions {
+                    println!("             {:>5}  ${:08X}  {}", func.lvo, func.address, func.name);
+                }
+            }
+        }
     }
 }
 
@@ -131,6 +925,11 @@ fn print_hunk_info(hunk_file: &dis68k::HunkFile, cli: &Cli) {
             hunk.data.len(),
         );
 
+        println!(
+            "           file offset: ${:08X} (data at ${:08X})",
+            hunk.block_offset, hunk.data_offset
+        );
+
         if let Some(name) = &hunk.name {
             println!("           name: \"{}\"", name);
         }
@@ -153,7 +952,7 @@ fn print_hunk_info(hunk_file: &dis68k::HunkFile, cli: &Cli) {
             println!("           symbols: {}", hunk.symbols.len());
             if cli.verbose {
                 for sym in &hunk.symbols {
-                    println!("             0x{:08X}  {}", sym.value, sym.name);
+                    println!("             0x{:08X}  {}", hunk.base_address.wrapping_add(sym.value), sym.name);
                 }
             }
         }
@@ -162,4 +961,30 @@ fn print_hunk_info(hunk_file: &dis68k::HunkFile, cli: &Cli) {
             println!("           debug data: present");
         }
     }
+
+    println!();
+    println!("File map:");
+    for block in &hunk_file.blocks {
+        let owner = match block.hunk_index {
+            Some(idx) => format!("hunk {idx}"),
+            None => String::new(),
+        };
+        let mut flags = Vec::new();
+        if block.is_advisory() {
+            flags.push("ADVISORY".to_string());
+        }
+        if !matches!(block.memory_flags(), dis68k::MemoryType::Any) {
+            flags.push(block.memory_flags().to_string());
+        }
+        let line = format!(
+            "  ${:08X}-${:08X}  {:<18} raw=${:08X}  {:<8} {}",
+            block.offset,
+            block.offset + block.size,
+            block.hunk_type.name(),
+            block.raw_type,
+            owner,
+            flags.join(" "),
+        );
+        println!("{}", line.trim_end());
+    }
 }
@@ -0,0 +1,19 @@
+//! ADF (Amiga Disk File) images: bootblock, OFS/FFS directories and files.
+//!
+//! An ADF is a raw sector dump of an AmigaDOS floppy (880K DD or 1760K
+//! HD). `AdfVolume` walks the root block and directory hash tables and
+//! extracts file contents, so executables on a disk can be handed
+//! straight to `parse_hunk_file`.
+
+pub mod bootblock;
+pub mod error;
+pub mod types;
+pub mod volume;
+
+pub use bootblock::{
+    analyze_bootblock, fix_bootblock_checksum, generate_bootblock_listing, BootBlock,
+    BootBlockReport,
+};
+pub use error::AdfError;
+pub use types::{DateStamp, DosType, Entry, EntryKind, Protection};
+pub use volume::AdfVolume;
@@ -0,0 +1,413 @@
+//! The 1024-byte bootblock at the start of every AmigaDOS disk.
+//!
+//! ```text
+//! $000  4 bytes  "DOS" + flags byte (bit 0 FFS, bit 1 INTL, bit 2 DIRCACHE)
+//! $004  ULONG    checksum (carry-folded sum of all longwords is $FFFFFFFF)
+//! $008  ULONG    root block number (880 on a DD floppy)
+//! $00C  ...      boot code, entered with A6 = ExecBase, A1 = IORequest
+//! ```
+
+use crate::analysis::RegisterType;
+use crate::hunk::types::{Hunk, HunkFile, Symbol};
+use crate::image::Image;
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Mnemonic, Operand};
+use crate::m68k::variants::CpuVariant;
+use crate::output::listing::{emit_sections, push_line, ListingLine, ListingOptions};
+use crate::symbols::resolver::SymbolResolver;
+use crate::symbols::structs;
+
+use super::error::AdfError;
+use super::types::DosType;
+
+/// Size of the bootblock (blocks 0 and 1).
+pub const BOOTBLOCK_SIZE: usize = 1024;
+/// Offset of the boot code within the bootblock.
+pub const BOOT_CODE_OFFSET: usize = 12;
+
+/// A parsed bootblock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootBlock {
+    pub dos_type: DosType,
+    pub checksum: u32,
+    pub root_block: u32,
+    /// Whether the stored checksum is correct. Only a bootblock with a
+    /// valid checksum is executed by the Kickstart.
+    pub checksum_ok: bool,
+}
+
+impl BootBlock {
+    pub fn parse(data: &[u8]) -> Result<BootBlock, AdfError> {
+        if data.len() < BOOTBLOCK_SIZE {
+            return Err(AdfError::BadSize { size: data.len() });
+        }
+        let id = [data[0], data[1], data[2], data[3]];
+        if &id[..3] != b"DOS" {
+            return Err(AdfError::NotDos { found: id });
+        }
+
+        Ok(BootBlock {
+            dos_type: DosType::from_flags(id[3]),
+            checksum: read_u32(data, 4),
+            root_block: read_u32(data, 8),
+            checksum_ok: bootblock_checksum(&data[..BOOTBLOCK_SIZE]) == read_u32(data, 4),
+        })
+    }
+}
+
+/// Compute the checksum the bootblock should store at offset 4.
+///
+/// The longwords (with the checksum field taken as 0) are summed with
+/// each carry added back in, and the result is inverted.
+pub fn bootblock_checksum(bootblock: &[u8]) -> u32 {
+    let mut sum: u32 = 0;
+    for (i, chunk) in bootblock.chunks_exact(4).enumerate() {
+        let v = if i == 1 {
+            0
+        } else {
+            u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+        };
+        let (s, carry) = sum.overflowing_add(v);
+        sum = s.wrapping_add(carry as u32);
+    }
+    !sum
+}
+
+/// Store the correct checksum at offset 4.
+pub fn fix_bootblock_checksum(bootblock: &mut [u8]) {
+    let sum = bootblock_checksum(&bootblock[..BOOTBLOCK_SIZE]);
+    bootblock[4..8].copy_from_slice(&sum.to_be_bytes());
+}
+
+// ─── Identification ───────────────────────────────────────────────
+
+/// Whether a recognised bootblock is harmless or a virus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootBlockKind {
+    Standard,
+    Virus,
+}
+
+/// A well-known bootblock, recognised by a byte pattern anywhere in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownBootBlock {
+    pub name: &'static str,
+    pub kind: BootBlockKind,
+    pattern: &'static [u8],
+}
+
+static KNOWN_BOOTBLOCKS: &[KnownBootBlock] = &[
+    // lea dosname(pc),a1 / jsr FindResident(a6) / ... / "dos.library"
+    KnownBootBlock {
+        name: "AmigaDOS 1.x Install",
+        kind: BootBlockKind::Standard,
+        pattern: &[
+            0x43, 0xFA, 0x00, 0x18, 0x4E, 0xAE, 0xFF, 0xA0, 0x4A, 0x80, 0x67, 0x0A, 0x20, 0x40,
+            0x20, 0x68, 0x00, 0x16, 0x70, 0x00, 0x4E, 0x75, 0x70, 0xFF, 0x4E, 0x75, b'd', b'o',
+            b's', b'.', b'l', b'i', b'b', b'r', b'a', b'r', b'y',
+        ],
+    },
+    // "Another masterpiece of The Mega-Mighty SCA !!"
+    KnownBootBlock {
+        name: "SCA virus",
+        kind: BootBlockKind::Virus,
+        pattern: b"Mega-Mighty SCA",
+    },
+    // "Virus by Byte Bandit in 9.87. Number of copys :"
+    KnownBootBlock {
+        name: "Byte Bandit virus",
+        kind: BootBlockKind::Virus,
+        pattern: b"Byte Bandit",
+    },
+];
+
+/// ExecBase fields that survive a reset; writing them is how bootblock
+/// viruses stay resident.
+const RESET_VECTORS: &[(i16, &str)] = &[
+    (42, "ColdCapture"),
+    (46, "CoolCapture"),
+    (50, "WarmCapture"),
+    (546, "KickMemPtr"),
+    (550, "KickTagPtr"),
+    (554, "KickCheckSum"),
+];
+
+/// LVO of exec `SetFunction`, used to patch DoIO and friends.
+const LVO_SET_FUNCTION: i16 = -420;
+
+/// Suspicious behaviour found in boot code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootWarning {
+    /// Bootblock offset of the instruction.
+    pub offset: u32,
+    pub message: String,
+}
+
+/// Everything known about a bootblock before disassembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootBlockReport {
+    /// `None` when the block does not start with `DOS` (it will not boot).
+    pub dos_type: Option<DosType>,
+    pub id: u32,
+    pub stored_checksum: u32,
+    pub computed_checksum: u32,
+    pub root_block: u32,
+    pub known: Vec<&'static KnownBootBlock>,
+    pub warnings: Vec<BootWarning>,
+}
+
+impl BootBlockReport {
+    pub fn checksum_ok(&self) -> bool {
+        self.stored_checksum == self.computed_checksum
+    }
+}
+
+/// Check the type and checksum of a bootblock, match it against known
+/// bootblocks, and scan its code for reset-vector writes, SetFunction
+/// calls and in-place EOR decryption.
+pub fn analyze_bootblock(data: &[u8], cpu: CpuVariant) -> Result<BootBlockReport, AdfError> {
+    if data.len() < BOOTBLOCK_SIZE {
+        return Err(AdfError::BadSize { size: data.len() });
+    }
+    let block = &data[..BOOTBLOCK_SIZE];
+    let id = read_u32(block, 0);
+
+    Ok(BootBlockReport {
+        dos_type: (&block[..3] == b"DOS").then(|| DosType::from_flags(block[3])),
+        id,
+        stored_checksum: read_u32(block, 4),
+        computed_checksum: bootblock_checksum(block),
+        root_block: read_u32(block, 8),
+        known: KNOWN_BOOTBLOCKS
+            .iter()
+            .filter(|k| block.windows(k.pattern.len()).any(|w| w == k.pattern))
+            .collect(),
+        warnings: scan_boot_code(block, cpu),
+    })
+}
+
+fn scan_boot_code(block: &[u8], cpu: CpuVariant) -> Vec<BootWarning> {
+    let mut warnings = Vec::new();
+    let mut offset = BOOT_CODE_OFFSET;
+
+    while offset < block.len() {
+        let Ok(inst) = decode_instruction(block, offset, 0, cpu) else {
+            offset += 2;
+            continue;
+        };
+        offset += inst.size_bytes as usize;
+
+        let message = match (inst.mnemonic, inst.operands.last()) {
+            (
+                Mnemonic::Move | Mnemonic::Clr,
+                Some(Operand::Ea(EffectiveAddress::AddressDisplacement(_, disp))),
+            ) => RESET_VECTORS
+                .iter()
+                .find(|(field, _)| field == disp)
+                .map(|(_, name)| format!("writes ExecBase->{name}")),
+            (Mnemonic::Jsr, Some(Operand::Ea(EffectiveAddress::AddressDisplacement(6, LVO_SET_FUNCTION)))) => {
+                Some("patches a library vector with SetFunction".to_string())
+            }
+            (
+                Mnemonic::Eor | Mnemonic::Eori,
+                Some(Operand::Ea(
+                    EffectiveAddress::AddressIndirect(_)
+                    | EffectiveAddress::AddressPostIncrement(_)
+                    | EffectiveAddress::AddressPreDecrement(_),
+                )),
+            ) => Some("decrypts memory in place (EOR through an address register)".to_string()),
+            _ => None,
+        };
+        if let Some(message) = message {
+            warnings.push(BootWarning {
+                offset: inst.address,
+                message,
+            });
+        }
+    }
+
+    warnings
+}
+
+// ─── Listing ──────────────────────────────────────────────────────
+
+/// Present the boot code (from offset 12) as a CODE hunk whose addresses
+/// are bootblock offsets.
+pub fn bootblock_to_hunk_file(data: &[u8]) -> HunkFile {
+    let end = data.len().min(BOOTBLOCK_SIZE);
+    let start = BOOT_CODE_OFFSET.min(end);
+    let mut hunk = Hunk::from_image(
+        0,
+        start as u32,
+        &data[start..end],
+        start,
+        Some("bootblock".to_string()),
+    );
+    hunk.symbols.push(Symbol {
+        name: "BootEntry".to_string(),
+        value: 0,
+    });
+    HunkFile {
+        hunks: vec![hunk],
+        first_hunk: 0,
+        last_hunk: 0,
+        blocks: Vec::new(),
+    }
+}
+
+/// Generate a disassembly listing for a bootblock.
+///
+/// The header documents the DOS type, checksum, identification and
+/// warnings, and the registers the boot code is entered with. A6 is
+/// declared to hold ExecBase at the entry, so LVO calls through it
+/// resolve against exec straight away.
+pub fn generate_bootblock_listing(
+    data: &[u8],
+    report: &BootBlockReport,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+) -> Vec<ListingLine> {
+    let mut lines = Vec::new();
+    let mut line_num: u32 = 1;
+
+    let dos_type = match report.dos_type {
+        Some(t) => format!("DOS\\{} ({t})", report.id & 0xFF),
+        None => format!("${:08X} (not DOS, will not boot)", report.id),
+    };
+    let checksum = if report.checksum_ok() {
+        format!("${:08X} (OK)", report.stored_checksum)
+    } else {
+        format!(
+            "${:08X} (BAD, expected ${:08X})",
+            report.stored_checksum, report.computed_checksum
+        )
+    };
+
+    let mut header = vec![
+        "; Amiga Bootblock Disassembly".to_string(),
+        format!("; DOS type: {dos_type}"),
+        format!("; Checksum: {checksum}"),
+        format!("; Root block: {}", report.root_block),
+    ];
+    for known in &report.known {
+        let kind = match known.kind {
+            BootBlockKind::Standard => "standard",
+            BootBlockKind::Virus => "VIRUS",
+        };
+        header.push(format!("; Identified: {} ({kind})", known.name));
+    }
+    for warning in &report.warnings {
+        header.push(format!("; Warning: ${:04X} {}", warning.offset, warning.message));
+    }
+    header.push("; Entry: A1 = IOStdReq (trackdisk.device), A6 = ExecBase".to_string());
+    header.push("; Exit: D0 = 0 and A0 = init routine to boot, D0 != 0 on failure".to_string());
+
+    for text in header {
+        push_line(&mut lines, &mut line_num, options, text);
+    }
+    push_line(&mut lines, &mut line_num, options, String::new());
+
+    let mut options = options.clone();
+    options.register_types.push(RegisterType {
+        segment: 0,
+        address: BOOT_CODE_OFFSET as u32,
+        register: 14,
+        type_name: structs::library_base_struct("exec").to_string(),
+    });
+    let image = Image::from(&bootblock_to_hunk_file(data));
+    emit_sections(&image, &options, resolver, &mut lines, &mut line_num);
+
+    lines
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbols::resolver::LvoResolver;
+
+    fn install_bootblock() -> Vec<u8> {
+        let mut block = vec![0u8; BOOTBLOCK_SIZE];
+        block[..4].copy_from_slice(b"DOS\0");
+        block[8..12].copy_from_slice(&880u32.to_be_bytes());
+        block[12..12 + KNOWN_BOOTBLOCKS[0].pattern.len()].copy_from_slice(KNOWN_BOOTBLOCKS[0].pattern);
+        fix_bootblock_checksum(&mut block);
+        block
+    }
+
+    #[test]
+    fn checks_type_and_checksum() {
+        let mut block = install_bootblock();
+        let report = analyze_bootblock(&block, CpuVariant::M68000).unwrap();
+        assert!(report.checksum_ok());
+        assert_eq!(report.dos_type.map(|t| t.ffs), Some(false));
+        assert_eq!(BootBlock::parse(&block).unwrap().root_block, 880);
+
+        block[100] ^= 0xFF;
+        let report = analyze_bootblock(&block, CpuVariant::M68000).unwrap();
+        assert!(!report.checksum_ok());
+        fix_bootblock_checksum(&mut block);
+        assert!(BootBlock::parse(&block).unwrap().checksum_ok);
+    }
+
+    #[test]
+    fn identifies_known_bootblocks() {
+        let report = analyze_bootblock(&install_bootblock(), CpuVariant::M68000).unwrap();
+        assert_eq!(report.known[0].name, "AmigaDOS 1.x Install");
+        assert!(report.warnings.is_empty());
+
+        let mut block = install_bootblock();
+        block[0x200..0x20F].copy_from_slice(b"Mega-Mighty SCA");
+        let report = analyze_bootblock(&block, CpuVariant::M68000).unwrap();
+        assert!(report.known.iter().any(|k| k.kind == BootBlockKind::Virus));
+    }
+
+    #[test]
+    fn warns_about_resident_hooks() {
+        let mut block = vec![0u8; BOOTBLOCK_SIZE];
+        block[..4].copy_from_slice(b"DOS\0");
+        block[12..24].copy_from_slice(&[
+            0x2D, 0x48, 0x00, 0x2E, // move.l a0,(46,a6)    CoolCapture
+            0xB1, 0x98, // eor.l d0,(a0)+
+            0x4E, 0xAE, 0xFE, 0x5C, // jsr (-420,a6)       SetFunction
+            0x4E, 0x75,
+        ]);
+        let report = analyze_bootblock(&block, CpuVariant::M68000).unwrap();
+        let messages: Vec<&str> = report.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains("CoolCapture"));
+        assert_eq!(report.warnings[0].offset, 12);
+        assert!(messages[1].contains("EOR"));
+        assert!(messages[2].contains("SetFunction"));
+    }
+
+    #[test]
+    fn listing_starts_at_boot_code() {
+        let block = install_bootblock();
+        let report = analyze_bootblock(&block, CpuVariant::M68000).unwrap();
+        let options = ListingOptions {
+            show_line_numbers: false,
+            ..ListingOptions::default()
+        };
+        let text: Vec<String> = generate_bootblock_listing(&block, &report, &options, None)
+            .into_iter()
+            .map(|l| l.text)
+            .collect();
+        assert!(text.iter().any(|l| l == "; DOS type: DOS\\0 (OFS)"));
+        assert!(text.iter().any(|l| l.starts_with("BootEntry:")));
+        assert!(text.iter().any(|l| l.starts_with("0000000C") && l.contains("lea.l")));
+
+        // A6 holds ExecBase on entry
+        let resolver = LvoResolver::new("dos");
+        let text: Vec<String> = generate_bootblock_listing(&block, &report, &options, Some(&resolver))
+            .into_iter()
+            .map(|l| l.text)
+            .collect();
+        let call = text.iter().find(|l| l.starts_with("00000010")).unwrap();
+        assert!(call.contains("; FindResident("), "{call}");
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+
+/// Errors that can occur while reading an ADF disk image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdfError {
+    /// The image is not a whole number of 512-byte blocks, or too small.
+    BadSize { size: usize },
+    /// The bootblock does not start with `DOS`.
+    NotDos { found: [u8; 4] },
+    /// A block number points outside the image.
+    BlockOutOfRange { block: u32 },
+    /// A block does not have the type/secondary type expected here.
+    BadBlockType { block: u32, context: &'static str },
+    /// A hash chain, extension chain or file size is inconsistent.
+    Corrupt { block: u32, context: &'static str },
+    /// No entry with this path exists on the volume.
+    NotFound { path: String },
+    /// The path names a directory or link rather than a file.
+    NotAFile { path: String },
+}
+
+impl fmt::Display for AdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdfError::BadSize { size } => {
+                write!(f, "{size} bytes is not a valid ADF image size")
+            }
+            AdfError::NotDos { found } => {
+                write!(f, "not an AmigaDOS disk (bootblock starts with {found:02X?})")
+            }
+            AdfError::BlockOutOfRange { block } => {
+                write!(f, "block {block} is outside the disk image")
+            }
+            AdfError::BadBlockType { block, context } => {
+                write!(f, "block {block} is not a valid {context}")
+            }
+            AdfError::Corrupt { block, context } => {
+                write!(f, "corrupt filesystem at block {block}: {context}")
+            }
+            AdfError::NotFound { path } => write!(f, "'{path}' not found on disk"),
+            AdfError::NotAFile { path } => write!(f, "'{path}' is not a file"),
+        }
+    }
+}
+
+impl std::error::Error for AdfError {}
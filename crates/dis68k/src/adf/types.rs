@@ -0,0 +1,136 @@
+//! Core data types for AmigaDOS (OFS/FFS) volumes.
+
+/// Block type and secondary type constants.
+///
+/// Header blocks carry a primary type in their first longword and a
+/// secondary type in their last, which tells roots, directories, files
+/// and links apart.
+pub mod block_types {
+    pub const T_HEADER: u32 = 2;
+    pub const T_DATA: u32 = 8;
+    pub const T_LIST: u32 = 16;
+
+    pub const ST_ROOT: i32 = 1;
+    pub const ST_USERDIR: i32 = 2;
+    pub const ST_SOFTLINK: i32 = 3;
+    pub const ST_LINKDIR: i32 = 4;
+    pub const ST_FILE: i32 = -3;
+    pub const ST_LINKFILE: i32 = -4;
+}
+
+/// Filesystem flavour from the fourth bootblock byte (`DOS\0`..`DOS\5`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DosType {
+    /// Fast File System (data blocks have no header).
+    pub ffs: bool,
+    /// International mode: Latin-1 aware case folding in name hashing.
+    pub intl: bool,
+    /// Directory cache blocks are present (implies international mode).
+    pub dircache: bool,
+}
+
+impl DosType {
+    pub fn from_flags(flags: u8) -> Self {
+        DosType {
+            ffs: flags & 1 != 0,
+            intl: flags & 6 != 0,
+            dircache: flags & 4 != 0,
+        }
+    }
+
+    /// Returns the conventional name, e.g. `"FFS-INTL"`.
+    pub fn name(&self) -> &'static str {
+        match (self.ffs, self.dircache, self.intl) {
+            (false, true, _) => "OFS-DC",
+            (true, true, _) => "FFS-DC",
+            (false, false, true) => "OFS-INTL",
+            (true, false, true) => "FFS-INTL",
+            (false, false, false) => "OFS",
+            (true, false, false) => "FFS",
+        }
+    }
+}
+
+impl std::fmt::Display for DosType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// What kind of object a directory entry is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    SoftLink,
+    HardLinkFile,
+    HardLinkDir,
+}
+
+/// AmigaDOS protection bits.
+///
+/// The low four bits (delete, execute, write, read) are stored inverted:
+/// a set bit *denies* the permission. The upper flags (archive, pure,
+/// script, hold) are set when active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protection(pub u32);
+
+impl std::fmt::Display for Protection {
+    /// Formats as `hsparwed`, with `-` for cleared flags.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bits = self.0;
+        let mut s = String::with_capacity(8);
+        for (bit, c) in [(7, 'h'), (6, 's'), (5, 'p'), (4, 'a')] {
+            s.push(if bits & (1 << bit) != 0 { c } else { '-' });
+        }
+        for (bit, c) in [(3, 'r'), (2, 'w'), (1, 'e'), (0, 'd')] {
+            s.push(if bits & (1 << bit) == 0 { c } else { '-' });
+        }
+        f.pad(&s)
+    }
+}
+
+/// An AmigaDOS DateStamp: days since 1978-01-01, minutes, ticks (1/50 s).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateStamp {
+    pub days: u32,
+    pub minutes: u32,
+    pub ticks: u32,
+}
+
+impl std::fmt::Display for DateStamp {
+    /// Formats as `YYYY-MM-DD HH:MM:SS`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Civil-from-days, counted from 1978-01-01 (day 2922 of the Unix epoch)
+        let z = self.days as i64 + 2922 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+            self.minutes / 60 % 24,
+            self.minutes % 60,
+            self.ticks / 50 % 60
+        )
+    }
+}
+
+/// A file, directory or link on an AmigaDOS volume.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub kind: EntryKind,
+    /// Header block number.
+    pub block: u32,
+    /// File size in bytes (0 for directories and links).
+    pub size: u32,
+    pub protection: Protection,
+    pub date: DateStamp,
+    pub comment: String,
+}
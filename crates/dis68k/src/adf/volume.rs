@@ -0,0 +1,551 @@
+//! Reading directories and files from an OFS/FFS volume.
+//!
+//! Every header block (root, directory, file, link) is 512 bytes with
+//! the same frame; fields at the end are addressed from the block end:
+//!
+//! ```text
+//! $000  type (T_HEADER)         $138  ...
+//! $004  header_key (own block)  $140  protect        (B-192)
+//! $008  high_seq (data blocks)  $144  byte_size      (B-188)
+//! $00C  ht_size / unused        $148  comment BSTR   (B-184)
+//! $010  first_data              $1A4  date stamp     (B-92)
+//! $014  checksum                $1B0  name BSTR      (B-80)
+//! $018  hash table / data block $1D4  real_entry     (B-44)
+//!       pointers (72 longwords) $1F0  hash_chain     (B-16)
+//!                               $1F4  parent         (B-12)
+//!                               $1F8  extension      (B-8)
+//!                               $1FC  sec_type       (B-4)
+//! ```
+//!
+//! Data block pointers are stored from the end of the table backwards:
+//! the first data block is in the last slot.
+
+use super::bootblock::BootBlock;
+use super::error::AdfError;
+use super::types::{block_types::*, DateStamp, DosType, Entry, EntryKind, Protection};
+
+/// Block size of floppy and hard file images.
+pub const BLOCK_SIZE: usize = 512;
+/// Longwords in a hash table / data block pointer table.
+const TABLE_SIZE: usize = BLOCK_SIZE / 4 - 56;
+/// Payload bytes in an OFS data block (after the 24-byte header).
+const OFS_DATA_SIZE: usize = BLOCK_SIZE - 24;
+
+const OFF_TABLE: usize = 24;
+const OFF_PROTECT: usize = BLOCK_SIZE - 192;
+const OFF_BYTE_SIZE: usize = BLOCK_SIZE - 188;
+const OFF_COMMENT: usize = BLOCK_SIZE - 184;
+const OFF_DATE: usize = BLOCK_SIZE - 92;
+const OFF_NAME: usize = BLOCK_SIZE - 80;
+const OFF_REAL_ENTRY: usize = BLOCK_SIZE - 44;
+const OFF_HASH_CHAIN: usize = BLOCK_SIZE - 16;
+const OFF_EXTENSION: usize = BLOCK_SIZE - 8;
+const OFF_SEC_TYPE: usize = BLOCK_SIZE - 4;
+
+/// A mounted view of an ADF image.
+#[derive(Debug, Clone)]
+pub struct AdfVolume<'a> {
+    data: &'a [u8],
+    pub bootblock: BootBlock,
+    pub root_block: u32,
+    pub num_blocks: u32,
+}
+
+impl<'a> AdfVolume<'a> {
+    /// Open an ADF image (DD, HD or any other whole number of blocks).
+    ///
+    /// The root block is normally in the middle of the disk; the
+    /// bootblock's root pointer is used if the middle block is not a root.
+    pub fn open(data: &'a [u8]) -> Result<AdfVolume<'a>, AdfError> {
+        if !data.len().is_multiple_of(BLOCK_SIZE) || data.len() < 4 * BLOCK_SIZE {
+            return Err(AdfError::BadSize { size: data.len() });
+        }
+        let bootblock = BootBlock::parse(data)?;
+        let num_blocks = (data.len() / BLOCK_SIZE) as u32;
+
+        let mut volume = AdfVolume {
+            data,
+            bootblock,
+            root_block: num_blocks / 2,
+            num_blocks,
+        };
+        if !volume.is_header(volume.root_block, ST_ROOT) {
+            let fallback = volume.bootblock.root_block;
+            if !volume.is_header(fallback, ST_ROOT) {
+                return Err(AdfError::BadBlockType {
+                    block: volume.root_block,
+                    context: "root block",
+                });
+            }
+            volume.root_block = fallback;
+        }
+        Ok(volume)
+    }
+
+    pub fn dos_type(&self) -> DosType {
+        self.bootblock.dos_type
+    }
+
+    /// The volume name stored in the root block.
+    pub fn name(&self) -> String {
+        self.block(self.root_block)
+            .map(|b| read_bstr(b, OFF_NAME, 30))
+            .unwrap_or_default()
+    }
+
+    /// List a directory (`""` for the root), sorted by name.
+    pub fn list(&self, path: &str) -> Result<Vec<Entry>, AdfError> {
+        let dir = if split_path(path).is_empty() {
+            self.root_block
+        } else {
+            let entry = self.find(path)?;
+            match entry.kind {
+                EntryKind::Directory => entry.block,
+                EntryKind::HardLinkDir => self.real_entry(entry.block)?,
+                _ => return Err(AdfError::NotFound { path: path.to_string() }),
+            }
+        };
+        self.list_block(dir)
+    }
+
+    /// Every entry on the volume with its full path, depth first.
+    pub fn walk(&self) -> Result<Vec<(String, Entry)>, AdfError> {
+        let mut out = Vec::new();
+        self.walk_dir(self.root_block, "", 0, &mut out)?;
+        Ok(out)
+    }
+
+    /// Look up a path such as `c/Dir` (case-insensitive, `/`-separated).
+    pub fn find(&self, path: &str) -> Result<Entry, AdfError> {
+        let not_found = || AdfError::NotFound { path: path.to_string() };
+        let mut dir = self.root_block;
+        let mut found = None;
+
+        for component in split_path(path) {
+            if let Some(entry) = found.take() {
+                dir = match entry {
+                    Entry { kind: EntryKind::Directory, block, .. } => block,
+                    Entry { kind: EntryKind::HardLinkDir, block, .. } => self.real_entry(block)?,
+                    _ => return Err(not_found()),
+                };
+            }
+            found = Some(self.find_in_dir(dir, component)?.ok_or_else(not_found)?);
+        }
+
+        found.ok_or_else(not_found)
+    }
+
+    /// Read a file's contents, following hard links.
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, AdfError> {
+        let entry = self.find(path)?;
+        let header = match entry.kind {
+            EntryKind::File => entry.block,
+            EntryKind::HardLinkFile => self.real_entry(entry.block)?,
+            _ => return Err(AdfError::NotAFile { path: path.to_string() }),
+        };
+        self.read_file_blocks(header)
+    }
+
+    // ─── Directories ──────────────────────────────────────────────
+
+    fn list_block(&self, dir: u32) -> Result<Vec<Entry>, AdfError> {
+        let block = self.header_block(dir)?;
+        let mut entries = Vec::new();
+
+        for slot in 0..TABLE_SIZE {
+            let mut next = read_u32(block, OFF_TABLE + slot * 4);
+            let mut steps = 0;
+            while next != 0 {
+                let entry = self.read_entry(next)?;
+                next = read_u32(self.block(next)?, OFF_HASH_CHAIN);
+                entries.push(entry);
+                steps += 1;
+                if steps > self.num_blocks {
+                    return Err(AdfError::Corrupt { block: dir, context: "hash chain loops" });
+                }
+            }
+        }
+
+        entries.sort_by_key(|e| e.name.to_lowercase());
+        Ok(entries)
+    }
+
+    fn walk_dir(
+        &self,
+        dir: u32,
+        prefix: &str,
+        depth: usize,
+        out: &mut Vec<(String, Entry)>,
+    ) -> Result<(), AdfError> {
+        if depth > 64 {
+            return Err(AdfError::Corrupt { block: dir, context: "directory nesting too deep" });
+        }
+        for entry in self.list_block(dir)? {
+            let path = if prefix.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{prefix}/{}", entry.name)
+            };
+            let subdir = (entry.kind == EntryKind::Directory).then_some(entry.block);
+            out.push((path.clone(), entry));
+            if let Some(block) = subdir {
+                self.walk_dir(block, &path, depth + 1, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Find `name` in a directory through its hash table.
+    fn find_in_dir(&self, dir: u32, name: &str) -> Result<Option<Entry>, AdfError> {
+        let intl = self.dos_type().intl;
+        let Some(wanted) = to_latin1(name) else {
+            return Ok(None);
+        };
+        let block = self.header_block(dir)?;
+        let mut next = read_u32(block, OFF_TABLE + hash_name(&wanted, intl) * 4);
+        let mut steps = 0;
+
+        while next != 0 {
+            let candidate = self.header_block(next)?;
+            let len = (candidate[OFF_NAME] as usize).min(30);
+            let stored = &candidate[OFF_NAME + 1..OFF_NAME + 1 + len];
+            if names_equal(stored, &wanted, intl) {
+                return self.read_entry(next).map(Some);
+            }
+            next = read_u32(candidate, OFF_HASH_CHAIN);
+            steps += 1;
+            if steps > self.num_blocks {
+                return Err(AdfError::Corrupt { block: dir, context: "hash chain loops" });
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_entry(&self, header: u32) -> Result<Entry, AdfError> {
+        let block = self.header_block(header)?;
+        let kind = match read_u32(block, OFF_SEC_TYPE) as i32 {
+            ST_FILE => EntryKind::File,
+            ST_USERDIR => EntryKind::Directory,
+            ST_SOFTLINK => EntryKind::SoftLink,
+            ST_LINKFILE => EntryKind::HardLinkFile,
+            ST_LINKDIR => EntryKind::HardLinkDir,
+            _ => {
+                return Err(AdfError::BadBlockType {
+                    block: header,
+                    context: "directory entry",
+                })
+            }
+        };
+
+        Ok(Entry {
+            name: read_bstr(block, OFF_NAME, 30),
+            kind,
+            block: header,
+            size: if kind == EntryKind::File { read_u32(block, OFF_BYTE_SIZE) } else { 0 },
+            protection: Protection(read_u32(block, OFF_PROTECT)),
+            date: DateStamp {
+                days: read_u32(block, OFF_DATE),
+                minutes: read_u32(block, OFF_DATE + 4),
+                ticks: read_u32(block, OFF_DATE + 8),
+            },
+            comment: read_bstr(block, OFF_COMMENT, 79),
+        })
+    }
+
+    fn real_entry(&self, link: u32) -> Result<u32, AdfError> {
+        Ok(read_u32(self.header_block(link)?, OFF_REAL_ENTRY))
+    }
+
+    // ─── Files ────────────────────────────────────────────────────
+
+    /// Collect a file's data blocks from its header and extension blocks
+    /// and concatenate their payloads.
+    fn read_file_blocks(&self, header: u32) -> Result<Vec<u8>, AdfError> {
+        let block = self.header_block(header)?;
+        let size = read_u32(block, OFF_BYTE_SIZE) as usize;
+        if size > self.data.len() {
+            return Err(AdfError::Corrupt { block: header, context: "file larger than disk" });
+        }
+
+        let ffs = self.dos_type().ffs;
+        let mut out = Vec::with_capacity(size);
+        let mut table = header;
+        let mut steps = 0;
+
+        while out.len() < size {
+            let tb = self.block(table)?;
+            let count = (read_u32(tb, 8) as usize).min(TABLE_SIZE);
+            for i in 0..count {
+                let data_block = read_u32(tb, OFF_TABLE + (TABLE_SIZE - 1 - i) * 4);
+                let db = self.block(data_block)?;
+                let payload = if ffs {
+                    db
+                } else {
+                    if read_u32(db, 0) != T_DATA {
+                        return Err(AdfError::BadBlockType {
+                            block: data_block,
+                            context: "OFS data block",
+                        });
+                    }
+                    let len = (read_u32(db, 12) as usize).min(OFS_DATA_SIZE);
+                    &db[24..24 + len]
+                };
+                let take = payload.len().min(size - out.len());
+                out.extend_from_slice(&payload[..take]);
+                if out.len() == size {
+                    break;
+                }
+            }
+
+            if out.len() < size {
+                table = read_u32(tb, OFF_EXTENSION);
+                steps += 1;
+                if table == 0 || steps > self.num_blocks {
+                    return Err(AdfError::Corrupt { block: header, context: "file shorter than its size" });
+                }
+                if read_u32(self.block(table)?, 0) != T_LIST {
+                    return Err(AdfError::BadBlockType { block: table, context: "file extension block" });
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    // ─── Blocks ───────────────────────────────────────────────────
+
+    fn block(&self, n: u32) -> Result<&'a [u8], AdfError> {
+        if n >= self.num_blocks {
+            return Err(AdfError::BlockOutOfRange { block: n });
+        }
+        let start = n as usize * BLOCK_SIZE;
+        Ok(&self.data[start..start + BLOCK_SIZE])
+    }
+
+    fn header_block(&self, n: u32) -> Result<&'a [u8], AdfError> {
+        let block = self.block(n)?;
+        if read_u32(block, 0) != T_HEADER {
+            return Err(AdfError::BadBlockType { block: n, context: "header block" });
+        }
+        Ok(block)
+    }
+
+    fn is_header(&self, n: u32, sec_type: i32) -> bool {
+        self.header_block(n)
+            .map(|b| read_u32(b, OFF_SEC_TYPE) as i32 == sec_type)
+            .unwrap_or(false)
+    }
+}
+
+/// The AmigaDOS directory hash: slot index for a name.
+pub fn hash_name(name: &[u8], intl: bool) -> usize {
+    let mut hash = name.len() as u32;
+    for &c in name {
+        hash = (hash * 13 + to_upper(c, intl) as u32) & 0x7FF;
+    }
+    hash as usize % TABLE_SIZE
+}
+
+/// AmigaDOS case folding. International mode also folds Latin-1
+/// accented letters (except the division sign).
+fn to_upper(c: u8, intl: bool) -> u8 {
+    if c.is_ascii_lowercase() || (intl && (224..=254).contains(&c) && c != 247) {
+        c - 32
+    } else {
+        c
+    }
+}
+
+fn names_equal(a: &[u8], b: &[u8], intl: bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&x, &y)| to_upper(x, intl) == to_upper(y, intl))
+}
+
+fn to_latin1(s: &str) -> Option<Vec<u8>> {
+    s.chars().map(|c| u8::try_from(c as u32).ok()).collect()
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.trim_start_matches(':')
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Read a length-prefixed Latin-1 string.
+fn read_bstr(block: &[u8], at: usize, max: usize) -> String {
+    let len = (block[at] as usize).min(max);
+    block[at + 1..at + 1 + len].iter().map(|&b| b as char).collect()
+}
+
+fn read_u32(block: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([block[at], block[at + 1], block[at + 2], block[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DD_BLOCKS: usize = 1760;
+    const ROOT: u32 = 880;
+
+    struct DiskBuilder {
+        data: Vec<u8>,
+        ffs: bool,
+        next_free: u32,
+    }
+
+    impl DiskBuilder {
+        fn new(ffs: bool) -> Self {
+            let mut data = vec![0u8; DD_BLOCKS * BLOCK_SIZE];
+            data[..4].copy_from_slice(&[b'D', b'O', b'S', ffs as u8]);
+            data[8..12].copy_from_slice(&ROOT.to_be_bytes());
+            let mut disk = DiskBuilder { data, ffs, next_free: ROOT + 1 };
+            disk.header(ROOT, ST_ROOT, "Workbench", 0);
+            disk
+        }
+
+        fn put_u32(&mut self, block: u32, at: usize, v: u32) {
+            let o = block as usize * BLOCK_SIZE + at;
+            self.data[o..o + 4].copy_from_slice(&v.to_be_bytes());
+        }
+
+        fn alloc(&mut self) -> u32 {
+            self.next_free += 1;
+            self.next_free - 1
+        }
+
+        fn header(&mut self, block: u32, sec_type: i32, name: &str, parent: u32) {
+            self.put_u32(block, 0, T_HEADER);
+            self.put_u32(block, 4, block);
+            self.put_u32(block, OFF_SEC_TYPE, sec_type as u32);
+            self.put_u32(block, BLOCK_SIZE - 12, parent);
+            let o = block as usize * BLOCK_SIZE + OFF_NAME;
+            self.data[o] = name.len() as u8;
+            self.data[o + 1..o + 1 + name.len()].copy_from_slice(name.as_bytes());
+        }
+
+        /// Link a header into its parent's hash table (chaining on collision).
+        fn link(&mut self, dir: u32, block: u32, name: &str) {
+            let slot = OFF_TABLE + hash_name(name.as_bytes(), false) * 4;
+            let o = dir as usize * BLOCK_SIZE + slot;
+            let head = u32::from_be_bytes(self.data[o..o + 4].try_into().unwrap());
+            self.put_u32(block, OFF_HASH_CHAIN, head);
+            self.put_u32(dir, slot, block);
+        }
+
+        fn mkdir(&mut self, dir: u32, name: &str) -> u32 {
+            let block = self.alloc();
+            self.header(block, ST_USERDIR, name, dir);
+            self.link(dir, block, name);
+            block
+        }
+
+        fn add_file(&mut self, dir: u32, name: &str, contents: &[u8]) -> u32 {
+            let header = self.alloc();
+            self.header(header, ST_FILE, name, dir);
+            self.put_u32(header, OFF_BYTE_SIZE, contents.len() as u32);
+            self.link(dir, header, name);
+
+            let payload = if self.ffs { BLOCK_SIZE } else { OFS_DATA_SIZE };
+            let mut table = header;
+            for (i, chunk) in contents.chunks(payload).enumerate() {
+                let slot = i % TABLE_SIZE;
+                if i > 0 && slot == 0 {
+                    let ext = self.alloc();
+                    self.put_u32(ext, 0, T_LIST);
+                    self.put_u32(ext, 4, ext);
+                    self.put_u32(table, OFF_EXTENSION, ext);
+                    table = ext;
+                }
+                let db = self.alloc();
+                self.put_u32(table, 8, slot as u32 + 1);
+                self.put_u32(table, OFF_TABLE + (TABLE_SIZE - 1 - slot) * 4, db);
+                let o = db as usize * BLOCK_SIZE;
+                if self.ffs {
+                    self.data[o..o + chunk.len()].copy_from_slice(chunk);
+                } else {
+                    self.put_u32(db, 0, T_DATA);
+                    self.put_u32(db, 4, header);
+                    self.put_u32(db, 8, i as u32 + 1);
+                    self.put_u32(db, 12, chunk.len() as u32);
+                    self.data[o + 24..o + 24 + chunk.len()].copy_from_slice(chunk);
+                }
+            }
+            header
+        }
+    }
+
+    fn sample_disk(ffs: bool) -> (Vec<u8>, Vec<u8>) {
+        let mut disk = DiskBuilder::new(ffs);
+        let c = disk.mkdir(ROOT, "c");
+        // Large enough to need an extension block on both OFS and FFS
+        let big: Vec<u8> = (0..80 * BLOCK_SIZE).map(|i| (i * 7) as u8).collect();
+        disk.add_file(c, "Dir", &big);
+        disk.add_file(c, "List", b"list");
+        disk.add_file(ROOT, "Startup-Sequence", b"c:Dir\n");
+        (disk.data, big)
+    }
+
+    #[test]
+    fn reads_files_through_extension_blocks() {
+        for ffs in [false, true] {
+            let (image, big) = sample_disk(ffs);
+            let volume = AdfVolume::open(&image).unwrap();
+            assert_eq!(volume.dos_type().ffs, ffs);
+            assert_eq!(volume.name(), "Workbench");
+            assert_eq!(volume.read_file("c/Dir").unwrap(), big);
+            assert_eq!(volume.read_file("C/LIST").unwrap(), b"list");
+            assert_eq!(volume.read_file(":startup-sequence").unwrap(), b"c:Dir\n");
+        }
+    }
+
+    #[test]
+    fn lists_and_walks_directories() {
+        let (image, _) = sample_disk(true);
+        let volume = AdfVolume::open(&image).unwrap();
+
+        let root: Vec<String> = volume.list("").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(root, ["c", "Startup-Sequence"]);
+
+        let paths: Vec<String> = volume.walk().unwrap().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, ["c", "c/Dir", "c/List", "Startup-Sequence"]);
+
+        let dir = volume.find("c/Dir").unwrap();
+        assert_eq!(dir.kind, EntryKind::File);
+        assert_eq!(dir.size, 80 * BLOCK_SIZE as u32);
+        assert_eq!(dir.protection.to_string(), "----rwed");
+    }
+
+    #[test]
+    fn reports_missing_paths_and_bad_images() {
+        let (image, _) = sample_disk(false);
+        let volume = AdfVolume::open(&image).unwrap();
+        assert!(matches!(volume.find("c/Nope"), Err(AdfError::NotFound { .. })));
+        assert!(matches!(volume.read_file("c"), Err(AdfError::NotAFile { .. })));
+        assert!(matches!(AdfVolume::open(&[0; 1000]), Err(AdfError::BadSize { .. })));
+        assert!(matches!(
+            AdfVolume::open(&[0; 4 * BLOCK_SIZE]),
+            Err(AdfError::NotDos { .. })
+        ));
+    }
+
+    #[test]
+    fn hash_matches_amigados() {
+        // (1 * 13 + 'C') % 72
+        assert_eq!(hash_name(b"c", false), 8);
+        assert_eq!(hash_name(b"c", false), hash_name(b"C", false));
+        assert!(hash_name(b"Startup-Sequence", false) < TABLE_SIZE);
+        // Latin-1 letters only fold in international mode
+        assert_ne!(hash_name(&[0xE9], false), hash_name(&[0xC9], false));
+        assert_eq!(hash_name(&[0xE9], true), hash_name(&[0xC9], true));
+    }
+
+    #[test]
+    fn formats_datestamp_and_protection() {
+        let date = DateStamp { days: 0, minutes: 0, ticks: 0 };
+        assert_eq!(date.to_string(), "1978-01-01 00:00:00");
+        let date = DateStamp { days: 5113, minutes: 12 * 60 + 34, ticks: 50 * 56 };
+        assert_eq!(date.to_string(), "1992-01-01 12:34:56");
+        assert_eq!(Protection(0b0100_0010).to_string(), "-s--rw-d");
+    }
+}
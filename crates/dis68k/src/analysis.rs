@@ -0,0 +1,23 @@
+//! Whole-image analyses over decoded code.
+//!
+//! Unlike the listing generator, which walks each code segment once from
+//! start to end, these passes follow control flow between instructions
+//! and carry facts across branches and segments.
+
+pub mod bases;
+pub mod cfg;
+pub mod flow;
+pub mod functions;
+pub mod hardware;
+pub mod jump_tables;
+pub mod small_data;
+pub mod xrefs;
+
+pub use bases::{track_library_bases, LibraryBases, RegisterType};
+pub use cfg::{build_cfg, BasicBlock, Cfg, Edge, EdgeKind};
+pub use flow::{discover_code, CodeMap};
+pub use functions::{find_functions, Call, CallGraph, Function, FunctionSource, IndirectCall};
+pub use hardware::{hardware_usage, HardwareUsage, RegisterUsage};
+pub use jump_tables::{JumpTable, TableKind};
+pub use small_data::{find_small_data, SmallData};
+pub use xrefs::{build_xrefs, Xref, XrefIndex, XrefKind};
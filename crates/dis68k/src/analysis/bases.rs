@@ -0,0 +1,1047 @@
+//! Library base tracking.
+//!
+//! A `jsr -30(a6)` means a different function in every library, so the
+//! listing needs to know which library base A6 holds at each call. This
+//! pass propagates register contents along the control flow of every
+//! code segment:
+//!
+//! - `move.l 4.w,a6` loads ExecBase;
+//! - `OpenLibrary`/`OldOpenLibrary`/`OpenResource` through ExecBase
+//!   return the library named by the string in A1 in D0;
+//! - register moves carry a base along, any other write forgets it;
+//! - a base stored into a global (`move.l d0,_DOSBase`) is known
+//!   wherever that global is loaded again. A global is an absolute or
+//!   PC-relative operand, a displacement from a register holding a known
+//!   address, or in a small-data program a displacement from A4
+//!   (`move.l d0,_DOSBase(a4)`).
+//!
+//! In a small-data program A4 holds the small-data base wherever
+//! tracking starts, and keeps it until something writes A4.
+//!
+//! A call through a register loaded from a global that holds no known
+//! base, or through a register that holds different bases on different
+//! paths, is recorded as unresolved, so the listing does not take it
+//! for a call through the default library.
+//!
+//! Unrelocated absolute addresses loaded into address registers
+//! (`lea $DFF000,a5`) are followed the same way, so hardware register
+//! accesses through a base register can be named.
+//!
+//! Pointers to OS structures are typed from `symbols::structs`: a
+//! library base points at its base structure, a pointer field loaded
+//! from a typed register (`move.l ThisTask(a6),a0`) and the result of
+//! calls like `OpenWindow` point at the field's or function's type, and
+//! the caller can declare a register's type at an instruction. Declaring
+//! a library's base structure (`ExecBase`) declares that library's base.
+//! Typed pointers stored in globals are followed like library bases.
+//!
+//! Only the instructions the code map (see `flow`) found are followed,
+//! so strings and tables in code hunks cannot load or clobber a base.
+//!
+//! Registers are merged at control-flow joins and keep a value only when
+//! every path agrees. Globals are global: the pass repeats until the set
+//! of library globals stops changing, and a global stored with two
+//! different libraries is dropped.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::image::{Image, ImageFormat, Location, SegmentKind};
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand, Size};
+use crate::m68k::variants::CpuVariant;
+use crate::symbols::{amiga, structs};
+
+use super::flow::CodeMap;
+use super::small_data::{self, find_small_data, SmallData};
+
+/// Absolute address holding ExecBase.
+const EXEC_BASE_ADDRESS: u32 = 4;
+
+// exec.library functions that return a library base in D0
+const LVO_OLD_OPEN_LIBRARY: i16 = -408;
+const LVO_OPEN_RESOURCE: i16 = -498;
+const LVO_OPEN_LIBRARY: i16 = -552;
+
+/// Upper bound on passes while the set of library globals settles.
+const MAX_PASSES: usize = 4;
+
+/// Library bases found by `track_library_bases`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryBases {
+    /// Library called by each `jsr`/`jmp d16(An)`, keyed by the
+    /// location of the instruction.
+    pub calls: BTreeMap<Location, String>,
+    /// `jsr`/`jmp d16(An)` instructions whose register was loaded from
+    /// a global holding no known base, or holds different bases on the
+    /// paths that reach them.
+    pub unresolved_calls: BTreeSet<Location>,
+    /// Globals that hold a library base, keyed by their location.
+    pub globals: BTreeMap<Location, String>,
+    /// Globals that hold a pointer to a known structure, by structure
+    /// name.
+    pub pointer_globals: BTreeMap<Location, String>,
+    /// Address registers holding an absolute address, as (register,
+    /// address), at each instruction that addresses memory through them.
+    pub absolute_bases: BTreeMap<Location, Vec<(u8, u32)>>,
+    /// Address registers pointing at a known structure, as (register,
+    /// structure name), at each instruction that addresses memory
+    /// through them.
+    pub struct_bases: BTreeMap<Location, Vec<(u8, String)>>,
+    /// Instructions that address memory through A4 while it holds the
+    /// small-data base.
+    pub small_data_uses: BTreeSet<Location>,
+}
+
+/// A caller's declaration that a register points at a structure before
+/// the instruction at `address` in `segment` runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterType {
+    pub segment: usize,
+    /// Instruction address, as listed.
+    pub address: u32,
+    /// 0-7 for d0-d7, 8-15 for a0-a7.
+    pub register: u8,
+    /// Structure name, as in `symbols::structs`.
+    pub type_name: String,
+}
+
+impl LibraryBases {
+    /// The library called by the instruction at `offset` in `segment`.
+    pub fn library_at(&self, segment: usize, offset: u32) -> Option<&str> {
+        self.calls.get(&Location::new(segment, offset)).map(String::as_str)
+    }
+
+    /// Libraries called from one segment, keyed by instruction address:
+    /// `None` for the unresolved calls.
+    pub fn calls_in(&self, image: &Image, segment: usize) -> BTreeMap<u32, Option<String>> {
+        let base = image.segments[segment].base_address;
+        let range = Location::new(segment, 0)..=Location::new(segment, u32::MAX);
+        let calls = self
+            .calls
+            .range(range.clone())
+            .map(|(location, library)| (location, Some(library.clone())));
+        let unresolved = self.unresolved_calls.range(range).map(|location| (location, None));
+        calls
+            .chain(unresolved)
+            .map(|(location, library)| (base.wrapping_add(location.offset), library))
+            .collect()
+    }
+
+    /// Instructions in one segment that address memory through the
+    /// small-data base, by instruction address.
+    pub fn small_data_uses_in(&self, image: &Image, segment: usize) -> BTreeSet<u32> {
+        let base = image.segments[segment].base_address;
+        self.small_data_uses
+            .range(Location::new(segment, 0)..=Location::new(segment, u32::MAX))
+            .map(|location| base.wrapping_add(location.offset))
+            .collect()
+    }
+
+    /// Absolute address registers in one segment, keyed by instruction
+    /// address.
+    pub fn absolute_bases_in(&self, image: &Image, segment: usize) -> BTreeMap<u32, Vec<(u8, u32)>> {
+        let base = image.segments[segment].base_address;
+        self.absolute_bases
+            .range(Location::new(segment, 0)..=Location::new(segment, u32::MAX))
+            .map(|(location, registers)| (base.wrapping_add(location.offset), registers.clone()))
+            .collect()
+    }
+
+    /// Structure-typed address registers in one segment, keyed by
+    /// instruction address.
+    pub fn struct_bases_in(&self, image: &Image, segment: usize) -> BTreeMap<u32, Vec<(u8, String)>> {
+        let base = image.segments[segment].base_address;
+        self.struct_bases
+            .range(Location::new(segment, 0)..=Location::new(segment, u32::MAX))
+            .map(|(location, registers)| (base.wrapping_add(location.offset), registers.clone()))
+            .collect()
+    }
+}
+
+/// What a register is known to hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    /// A library base, by library name (`exec`, `dos`).
+    Library(String),
+    /// The address of a location in the image.
+    Address(Location),
+    /// An address outside the image, such as a hardware register block.
+    Absolute(u32),
+    /// A pointer to a structure, by name.
+    Pointer(String),
+    /// Whatever a global without a known value holds.
+    Loaded,
+    /// One of several library bases, where paths that disagree join.
+    Libraries(Vec<String>),
+}
+
+impl Value {
+    /// The structure a register holding this value points at.
+    fn struct_type(&self) -> Option<&str> {
+        match self {
+            Value::Library(library) => Some(structs::library_base_struct(library)),
+            Value::Pointer(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// d0-d7, then a0-a7.
+type Registers = [Option<Value>; 16];
+
+/// The decoded instructions of one code segment.
+struct Code {
+    segment: usize,
+    instructions: Vec<Instruction>,
+    /// Instruction address → index into `instructions`.
+    index: BTreeMap<u32, usize>,
+}
+
+/// Work out which library each `jsr d16(An)` in the code ranges of
+/// `code_map` calls, and which structures address registers point at,
+/// starting from the caller's `register_types`.
+pub fn track_library_bases(
+    image: &Image,
+    cpu: CpuVariant,
+    code_map: &CodeMap,
+    register_types: &[RegisterType],
+) -> LibraryBases {
+    let code: Vec<Code> = image
+        .segments
+        .iter()
+        .filter(|s| s.kind == SegmentKind::Code)
+        .map(|s| decode_segment(image, s.index, code_map, cpu))
+        .collect();
+    let fixups: Vec<BTreeMap<u32, usize>> = image
+        .segments
+        .iter()
+        .map(|s| s.fixups.iter().map(|f| (f.offset, f.target)).collect())
+        .collect();
+
+    let mut tracker = Tracker {
+        image,
+        fixups,
+        absolute: image.format != ImageFormat::AmigaHunk
+            || image.segments.iter().any(|s| s.base_address != 0),
+        globals: BTreeMap::new(),
+        small_data: find_small_data(image, cpu),
+        register_types: register_types
+            .iter()
+            .map(|t| ((t.segment, t.address), (t.register as usize, t.type_name.clone())))
+            .fold(BTreeMap::new(), |mut map, (key, value)| {
+                map.entry(key).or_insert_with(Vec::new).push(value);
+                map
+            }),
+    };
+    let mut bases = LibraryBases::default();
+
+    for _ in 0..MAX_PASSES {
+        let mut calls = BTreeMap::new();
+        let mut unresolved_calls = BTreeSet::new();
+        let mut absolute_bases = BTreeMap::new();
+        let mut struct_bases = BTreeMap::new();
+        let mut small_data_uses = BTreeSet::new();
+        let mut stores: BTreeMap<Location, Option<Value>> = BTreeMap::new();
+
+        for code in &code {
+            let states = tracker.solve(code);
+            for (i, (inst, state)) in code.instructions.iter().zip(&states).enumerate() {
+                let Some(state) = state else { continue };
+                let state = &tracker.annotated(code.segment, inst, state);
+                let offset = inst.address.wrapping_sub(image.segments[code.segment].base_address);
+                match tracker.called_library(code, i, state) {
+                    Some(Some(library)) => {
+                        calls.insert(Location::new(code.segment, offset), library.to_string());
+                    }
+                    Some(None) => {
+                        unresolved_calls.insert(Location::new(code.segment, offset));
+                    }
+                    None => {}
+                }
+                let registers = absolute_registers(inst, state);
+                if !registers.is_empty() {
+                    absolute_bases.insert(Location::new(code.segment, offset), registers);
+                }
+                let registers = struct_registers(inst, state);
+                if !registers.is_empty() {
+                    struct_bases.insert(Location::new(code.segment, offset), registers);
+                }
+                if tracker.uses_small_data(inst, state) {
+                    small_data_uses.insert(Location::new(code.segment, offset));
+                }
+                if let Some((location, value)) = tracker.stored_global(code.segment, inst, state) {
+                    stores
+                        .entry(location)
+                        .and_modify(|known| {
+                            if known.as_ref() != Some(&value) {
+                                *known = None;
+                            }
+                        })
+                        .or_insert(Some(value));
+                }
+            }
+        }
+
+        let globals: BTreeMap<Location, Value> = stores
+            .into_iter()
+            .filter_map(|(location, value)| Some((location, value?)))
+            .collect();
+        let settled = globals == tracker.globals;
+        let named = |pick: fn(&Value) -> Option<&String>| -> BTreeMap<Location, String> {
+            globals
+                .iter()
+                .filter_map(|(location, value)| Some((*location, pick(value)?.clone())))
+                .collect()
+        };
+        bases = LibraryBases {
+            calls,
+            unresolved_calls,
+            globals: named(|v| match v {
+                Value::Library(library) => Some(library),
+                _ => None,
+            }),
+            pointer_globals: named(|v| match v {
+                Value::Pointer(name) => Some(name),
+                _ => None,
+            }),
+            absolute_bases,
+            struct_bases,
+            small_data_uses,
+        };
+        if settled {
+            break;
+        }
+        tracker.globals = globals;
+    }
+
+    bases
+}
+
+/// Decode the code ranges `code_map` found in one segment.
+fn decode_segment(image: &Image, segment: usize, code_map: &CodeMap, cpu: CpuVariant) -> Code {
+    let seg = &image.segments[segment];
+    let mut instructions = Vec::new();
+    let mut index = BTreeMap::new();
+    for range in code_map.ranges_in(segment) {
+        let data = &seg.data[..range.end.min(seg.data.len())];
+        let mut offset = range.start;
+        while offset < data.len() {
+            match decode_instruction(data, offset, seg.base_address, cpu) {
+                Ok(inst) => {
+                    offset += inst.size_bytes as usize;
+                    index.insert(inst.address, instructions.len());
+                    instructions.push(inst);
+                }
+                Err(_) => offset += 2,
+            }
+        }
+    }
+    Code {
+        segment,
+        instructions,
+        index,
+    }
+}
+
+struct Tracker<'a> {
+    image: &'a Image,
+    /// Per segment: fixup offset → target segment.
+    fixups: Vec<BTreeMap<u32, usize>>,
+    /// Segments sit at distinct addresses, so an unrelocated absolute
+    /// address can still be placed in a segment.
+    absolute: bool,
+    /// Library bases and typed pointers held by globals.
+    globals: BTreeMap<Location, Value>,
+    /// Where A4 points in a small-data program.
+    small_data: Option<SmallData>,
+    /// Declared register types, by (segment, instruction address).
+    register_types: BTreeMap<(usize, u32), Vec<(usize, String)>>,
+}
+
+impl Tracker<'_> {
+    /// Register contents before each instruction of `code`.
+    ///
+    /// The segment start and every instruction no path reaches start
+    /// with nothing known but the small-data base in A4.
+    fn solve(&self, code: &Code) -> Vec<Option<Registers>> {
+        let mut states: Vec<Option<Registers>> = vec![None; code.instructions.len()];
+        let mut queue = VecDeque::new();
+
+        for seed in 0..states.len() {
+            if states[seed].is_some() {
+                continue;
+            }
+            states[seed] = Some(self.entry_state());
+            queue.push_back(seed);
+
+            while let Some(i) = queue.pop_front() {
+                let state = self.annotated(code.segment, &code.instructions[i], states[i].as_ref().unwrap());
+                for (next, out) in self.successors(code, i, &state) {
+                    if merge(&mut states[next], &out) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        states
+    }
+
+    /// Registers where tracking starts: A4 holds the small-data base in a
+    /// small-data program.
+    fn entry_state(&self) -> Registers {
+        let mut state = Registers::default();
+        if let Some(small_data) = self.small_data {
+            state[8 + small_data::BASE_REGISTER as usize] = Some(Value::Address(small_data.base));
+        }
+        state
+    }
+
+    /// Whether `inst` addresses memory through A4 while it still holds
+    /// the small-data base.
+    fn uses_small_data(&self, inst: &Instruction, state: &Registers) -> bool {
+        let Some(small_data) = self.small_data else {
+            return false;
+        };
+        state[8 + small_data::BASE_REGISTER as usize] == Some(Value::Address(small_data.base))
+            && inst.operands.iter().any(|op| match op {
+                Operand::Ea(ea) => small_data::displacement(ea).is_some(),
+                _ => false,
+            })
+    }
+
+    /// Registers before `inst`, with the caller's declared types applied.
+    /// A register declared as a library's base structure (`ExecBase`)
+    /// holds that library's base.
+    fn annotated(&self, segment: usize, inst: &Instruction, state: &Registers) -> Registers {
+        let mut out = state.clone();
+        for (r, name) in self.register_types.get(&(segment, inst.address)).into_iter().flatten() {
+            out[*r] = Some(match structs::base_struct_library(name) {
+                Some(library) => Value::Library(library.to_string()),
+                None => Value::Pointer(name.clone()),
+            });
+        }
+        out
+    }
+
+    /// Instructions control can reach after `code.instructions[i]`, with
+    /// the registers on arrival.
+    fn successors(&self, code: &Code, i: usize, state: &Registers) -> Vec<(usize, Registers)> {
+        let inst = &code.instructions[i];
+        let next = code.index.get(&inst.address.wrapping_add(inst.size_bytes as u32)).copied();
+        let target = branch_target(inst).and_then(|t| code.index.get(&t).copied());
+
+        let mut out = Vec::new();
+        match inst.mnemonic {
+            Mnemonic::Bra => out.extend(target.map(|t| (t, state.clone()))),
+            Mnemonic::Bcc | Mnemonic::Dbcc => {
+                out.extend(target.map(|t| (t, state.clone())));
+                out.extend(next.map(|n| (n, state.clone())));
+            }
+            Mnemonic::Jmp | Mnemonic::Rts | Mnemonic::Rte | Mnemonic::Rtr => {}
+            Mnemonic::Jsr | Mnemonic::Bsr => {
+                out.extend(target.map(|t| (t, state.clone())));
+                out.extend(next.map(|n| (n, self.after_call(inst, state))));
+            }
+            _ => out.extend(next.map(|n| (n, self.step(code.segment, inst, state)))),
+        }
+        out
+    }
+
+    /// Registers after a subroutine or library call returns.
+    fn after_call(&self, inst: &Instruction, state: &Registers) -> Registers {
+        let result = match self.opened_library(inst, state) {
+            Some(library) => Some(Value::Library(library)),
+            None => returned_pointer(inst, state),
+        };
+        let mut out = state.clone();
+        // Scratch registers are not preserved across calls
+        for r in [0, 1, 8, 9] {
+            out[r] = None;
+        }
+        out[0] = result;
+        out
+    }
+
+    /// Library name returned by an `OpenLibrary`-style call, read from
+    /// the string that A1 points at.
+    fn opened_library(&self, inst: &Instruction, state: &Registers) -> Option<String> {
+        let [Operand::Ea(EffectiveAddress::AddressDisplacement(r, lvo))] = inst.operands[..] else {
+            return None;
+        };
+        if state[8 + r as usize] != Some(Value::Library("exec".to_string()))
+            || !matches!(lvo, LVO_OLD_OPEN_LIBRARY | LVO_OPEN_LIBRARY | LVO_OPEN_RESOURCE)
+        {
+            return None;
+        }
+        let Some(Value::Address(location)) = &state[9] else {
+            return None;
+        };
+        let data = self.image.segments[location.hunk].data.get(location.offset as usize..)?;
+        let end = data.iter().position(|&b| b == 0)?;
+        let name = std::str::from_utf8(&data[..end]).ok()?;
+        library_name(name)
+    }
+
+    /// Registers after a non-branching instruction.
+    fn step(&self, segment: usize, inst: &Instruction, state: &Registers) -> Registers {
+        let mut out = state.clone();
+
+        let value = match (&inst.mnemonic, inst.operands.first()) {
+            (Mnemonic::Move | Mnemonic::Movea, Some(Operand::Ea(src)))
+                if inst.size == Some(Size::Long) =>
+            {
+                self.load(segment, inst, src, state)
+            }
+            (Mnemonic::Lea, Some(Operand::Ea(src))) => self.address_of(segment, inst, src, state),
+            (Mnemonic::Exg, _) => {
+                if let [Operand::Ea(a), Operand::Ea(b)] = inst.operands.as_slice() {
+                    if let (Some(a), Some(b)) = (register_index(a), register_index(b)) {
+                        out.swap(a, b);
+                    }
+                }
+                return out;
+            }
+            (
+                Mnemonic::Cmp
+                | Mnemonic::Cmpa
+                | Mnemonic::Cmpi
+                | Mnemonic::Tst
+                | Mnemonic::Btst
+                | Mnemonic::Pea,
+                _,
+            ) => return out,
+            _ => None,
+        };
+
+        // Address registers stepped by (An)+ and -(An)
+        for op in &inst.operands {
+            if let Operand::Ea(
+                EffectiveAddress::AddressPostIncrement(r) | EffectiveAddress::AddressPreDecrement(r),
+            ) = op
+            {
+                out[8 + *r as usize] = None;
+            }
+        }
+
+        match inst.operands.last() {
+            Some(Operand::Ea(dest)) => {
+                if let Some(r) = register_index(dest) {
+                    out[r] = value;
+                }
+            }
+            Some(Operand::RegisterList(mask)) => {
+                for (r, value) in out.iter_mut().enumerate() {
+                    if mask & (1 << r) != 0 {
+                        *value = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+        out
+    }
+
+    /// The value `move.l src,...` reads.
+    fn load(
+        &self,
+        segment: usize,
+        inst: &Instruction,
+        src: &EffectiveAddress,
+        state: &Registers,
+    ) -> Option<Value> {
+        match src {
+            EffectiveAddress::DataDirect(_) | EffectiveAddress::AddressDirect(_) => {
+                state[register_index(src)?].clone()
+            }
+            EffectiveAddress::Immediate(v) => Some(
+                self.relocated(segment, inst, *v)
+                    .map_or(Value::Absolute(*v), Value::Address),
+            ),
+            EffectiveAddress::AbsoluteShort(_)
+            | EffectiveAddress::AbsoluteLong(_)
+            | EffectiveAddress::PcDisplacement(_) => {
+                // 4 is ExecBase even where it falls inside a segment,
+                // unless the operand is relocated
+                if absolute_address(src) == Some(EXEC_BASE_ADDRESS)
+                    && self.fixup_target(segment, inst, EXEC_BASE_ADDRESS).is_none()
+                {
+                    return Some(Value::Library("exec".to_string()));
+                }
+                match self.location_of(segment, inst, src) {
+                    Some(location) => Some(self.global(location)),
+                    None => absolute_address(src).map(|_| Value::Loaded),
+                }
+            }
+            EffectiveAddress::AddressIndirect(r) | EffectiveAddress::AddressDisplacement(r, _) => {
+                if let Some(location) = self.slot(src, state) {
+                    return Some(self.global(location));
+                }
+                let base = state[8 + *r as usize].as_ref()?.struct_type()?;
+                let target = structs::field_type(base, displacement(src), false)?;
+                Some(Value::Pointer(target.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The address `lea src,...` computes.
+    fn address_of(
+        &self,
+        segment: usize,
+        inst: &Instruction,
+        src: &EffectiveAddress,
+        state: &Registers,
+    ) -> Option<Value> {
+        match src {
+            EffectiveAddress::AddressIndirect(r) | EffectiveAddress::AddressDisplacement(r, _) => {
+                let disp = displacement(src) as i32 as u32;
+                match &state[8 + *r as usize] {
+                    Some(Value::Address(location)) => Some(Value::Address(Location::new(
+                        location.hunk,
+                        location.offset.wrapping_add(disp),
+                    ))),
+                    Some(Value::Absolute(address)) => Some(Value::Absolute(address.wrapping_add(disp))),
+                    Some(value) => {
+                        let inner = structs::field_type(value.struct_type()?, displacement(src), true)?;
+                        Some(Value::Pointer(inner.to_string()))
+                    }
+                    None => self.slot(src, state).map(Value::Address),
+                }
+            }
+            _ => match self.location_of(segment, inst, src) {
+                Some(location) => Some(Value::Address(location)),
+                None => absolute_address(src).map(Value::Absolute),
+            },
+        }
+    }
+
+    /// What a global holds: its known base or pointer, or nothing known.
+    fn global(&self, location: Location) -> Value {
+        self.globals.get(&location).cloned().unwrap_or(Value::Loaded)
+    }
+
+    /// The location a `(An)` or `d16(An)` operand refers to, when An
+    /// holds a known address.
+    fn slot(&self, ea: &EffectiveAddress, state: &Registers) -> Option<Location> {
+        let (EffectiveAddress::AddressIndirect(r) | EffectiveAddress::AddressDisplacement(r, _)) = ea else {
+            return None;
+        };
+        let disp = displacement(ea) as i32;
+        match &state[8 + *r as usize] {
+            Some(Value::Address(location)) => {
+                Some(Location::new(location.hunk, location.offset.wrapping_add(disp as u32)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The location an absolute or PC-relative operand refers to.
+    fn location_of(&self, segment: usize, inst: &Instruction, ea: &EffectiveAddress) -> Option<Location> {
+        match ea {
+            EffectiveAddress::PcDisplacement(d) => {
+                let base = self.image.segments[segment].base_address;
+                let target = inst.address.wrapping_add(2).wrapping_add(*d as i32 as u32);
+                Some(Location::new(segment, target.wrapping_sub(base)))
+            }
+            _ => self.relocated(segment, inst, absolute_address(ea)?),
+        }
+    }
+
+    /// Place an address found in `inst`'s extension words: by the
+    /// fixup at that operand, or for images at real addresses by the
+    /// segment containing it.
+    fn relocated(&self, segment: usize, inst: &Instruction, value: u32) -> Option<Location> {
+        if let Some(target) = self.fixup_target(segment, inst, value) {
+            let target_base = self.image.segments[target].base_address;
+            return Some(Location::new(target, value.wrapping_sub(target_base)));
+        }
+        if !self.absolute {
+            return None;
+        }
+        let target = self.image.segment_at(value)?;
+        Some(Location::new(target, value - self.image.segments[target].base_address))
+    }
+
+    /// Target segment of a fixup on `value` in `inst`'s extension words.
+    fn fixup_target(&self, segment: usize, inst: &Instruction, value: u32) -> Option<usize> {
+        let seg = &self.image.segments[segment];
+        let start = inst.address.wrapping_sub(seg.base_address);
+        let end = start + inst.size_bytes as u32;
+        self.fixups[segment]
+            .range(start + 2..end)
+            .find(|&(&offset, _)| offset + 4 <= end && read_u32(&seg.data, offset) == Some(value))
+            .map(|(_, &target)| target)
+    }
+
+    /// The library `code.instructions[i]`, a `jsr`/`jmp d16(An)`, calls
+    /// into: `Some(None)` when An was loaded from a global without a
+    /// known base, or could hold one of several bases.
+    fn called_library<'s>(&self, code: &Code, i: usize, state: &'s Registers) -> Option<Option<&'s str>> {
+        let inst = &code.instructions[i];
+        if !matches!(inst.mnemonic, Mnemonic::Jsr | Mnemonic::Jmp) {
+            return None;
+        }
+        let [Operand::Ea(EffectiveAddress::AddressDisplacement(r, _))] = inst.operands[..] else {
+            return None;
+        };
+        match &state[8 + r as usize] {
+            Some(Value::Library(library)) => Some(Some(library)),
+            Some(Value::Libraries(_) | Value::Loaded) => Some(None),
+            _ => None,
+        }
+    }
+
+    /// A library base or typed pointer written to a global by `move.l`.
+    fn stored_global(
+        &self,
+        segment: usize,
+        inst: &Instruction,
+        state: &Registers,
+    ) -> Option<(Location, Value)> {
+        if inst.mnemonic != Mnemonic::Move || inst.size != Some(Size::Long) {
+            return None;
+        }
+        let [Operand::Ea(src), Operand::Ea(dest)] = inst.operands.as_slice() else {
+            return None;
+        };
+        let value = state[register_index(src)?].clone()?;
+        if !matches!(value, Value::Library(_) | Value::Pointer(_)) {
+            return None;
+        }
+        let location = self.location_of(segment, inst, dest).or_else(|| self.slot(dest, state))?;
+        Some((location, value))
+    }
+}
+
+/// Address registers with an absolute address that `inst` uses as a
+/// base for a memory operand.
+fn absolute_registers(inst: &Instruction, state: &Registers) -> Vec<(u8, u32)> {
+    let mut registers = Vec::new();
+    for op in &inst.operands {
+        let (Operand::Ea(EffectiveAddress::AddressIndirect(r))
+        | Operand::Ea(EffectiveAddress::AddressDisplacement(r, _))) = op
+        else {
+            continue;
+        };
+        if let Some(Value::Absolute(address)) = &state[8 + *r as usize] {
+            if !registers.contains(&(*r, *address)) {
+                registers.push((*r, *address));
+            }
+        }
+    }
+    registers
+}
+
+/// Address registers pointing at a structure that `inst` uses as a base
+/// for a memory operand.
+fn struct_registers(inst: &Instruction, state: &Registers) -> Vec<(u8, String)> {
+    let mut registers: Vec<(u8, String)> = Vec::new();
+    for op in &inst.operands {
+        let (Operand::Ea(EffectiveAddress::AddressIndirect(r))
+        | Operand::Ea(EffectiveAddress::AddressDisplacement(r, _))) = op
+        else {
+            continue;
+        };
+        let Some(name) = state[8 + *r as usize].as_ref().and_then(Value::struct_type) else {
+            continue;
+        };
+        if !registers.iter().any(|(known, _)| known == r) {
+            registers.push((*r, name.to_string()));
+        }
+    }
+    registers
+}
+
+/// The structure pointer a library call returns, from the function's
+/// entry in `symbols::structs`.
+fn returned_pointer(inst: &Instruction, state: &Registers) -> Option<Value> {
+    let [Operand::Ea(EffectiveAddress::AddressDisplacement(r, lvo))] = inst.operands[..] else {
+        return None;
+    };
+    let Some(Value::Library(library)) = &state[8 + r as usize] else {
+        return None;
+    };
+    let entry = amiga::find_library(library, &[])?.lookup(lvo)?;
+    structs::return_type(library, entry.function_name()).map(|name| Value::Pointer(name.to_string()))
+}
+
+/// Merge `from` into the registers known at a join; true if `into`
+/// changed.
+///
+/// Library bases that disagree become the set of candidates; any other
+/// disagreement forgets the register.
+fn merge(into: &mut Option<Registers>, from: &Registers) -> bool {
+    let Some(current) = into else {
+        *into = Some(from.clone());
+        return true;
+    };
+    let mut changed = false;
+    for (known, incoming) in current.iter_mut().zip(from) {
+        if known.is_none() || known == incoming {
+            continue;
+        }
+        let merged = match (known.as_ref(), incoming) {
+            (Some(a), Some(b)) => match (libraries(a), libraries(b)) {
+                (Some(mut a), Some(b)) => {
+                    a.extend(b);
+                    a.sort();
+                    a.dedup();
+                    Some(Value::Libraries(a))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if *known != merged {
+            *known = merged;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// The library bases a value may be.
+fn libraries(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::Library(library) => Some(vec![library.clone()]),
+        Value::Libraries(libraries) => Some(libraries.clone()),
+        _ => None,
+    }
+}
+
+/// Table name for a library opened by name: `dos.library` → `dos`,
+/// `LIBS:foo.library` → `foo`.
+fn library_name(name: &str) -> Option<String> {
+    let file = name.rsplit([':', '/']).next()?;
+    let stem = [".library", ".resource", ".device"]
+        .iter()
+        .find_map(|suffix| file.strip_suffix(suffix))
+        .unwrap_or(file);
+    (!stem.is_empty()).then(|| stem.to_string())
+}
+
+/// Target address of a PC-relative branch or `jsr`/`jmp (d16,pc)`.
+fn branch_target(inst: &Instruction) -> Option<u32> {
+    let disp = inst.operands.iter().find_map(|op| match op {
+        Operand::Displacement8(d) => Some(*d as i32),
+        Operand::Displacement16(d) => Some(*d as i32),
+        Operand::Displacement32(d) => Some(*d),
+        Operand::Ea(EffectiveAddress::PcDisplacement(d)) => Some(*d as i32),
+        _ => None,
+    })?;
+    Some(inst.address.wrapping_add(2).wrapping_add(disp as u32))
+}
+
+/// Displacement of an `(An)` or `d16(An)` operand.
+fn displacement(ea: &EffectiveAddress) -> i16 {
+    match ea {
+        EffectiveAddress::AddressDisplacement(_, d) => *d,
+        _ => 0,
+    }
+}
+
+fn absolute_address(ea: &EffectiveAddress) -> Option<u32> {
+    match ea {
+        EffectiveAddress::AbsoluteShort(a) => Some(*a as i16 as i32 as u32),
+        EffectiveAddress::AbsoluteLong(a) => Some(*a),
+        _ => None,
+    }
+}
+
+/// Index into `Registers` of a register operand.
+fn register_index(ea: &EffectiveAddress) -> Option<usize> {
+    match ea {
+        EffectiveAddress::DataDirect(r) => Some(*r as usize),
+        EffectiveAddress::AddressDirect(r) => Some(8 + *r as usize),
+        _ => None,
+    }
+}
+
+fn read_u32(data: &[u8], offset: u32) -> Option<u32> {
+    let bytes = data.get(offset as usize..offset as usize + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::discover_code;
+    use crate::image::Fixup;
+
+    fn hunk_image(code: Vec<u8>, code_fixups: Vec<Fixup>) -> Image {
+        Image::test_code(code, code_fixups).with_segment(SegmentKind::Bss, vec![], vec![])
+    }
+
+    fn track(image: &Image, register_types: &[RegisterType]) -> LibraryBases {
+        let code = discover_code(image, CpuVariant::M68000, &[], false);
+        track_library_bases(image, CpuVariant::M68000, &code, register_types)
+    }
+
+    #[test]
+    fn open_library_result_flows_through_global() {
+        let mut code = vec![
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x43, 0xFA, 0x00, 0x1E, // lea (30,pc),a1 -> dosname
+            0x70, 0x00, // moveq #0,d0
+            0x4E, 0xAE, 0xFD, 0xD8, // jsr (-552,a6)
+            0x23, 0xC0, 0x00, 0x00, 0x00, 0x00, // move.l d0,_DOSBase
+            0x61, 0x02, // bsr.s sub
+            0x4E, 0x75, // rts
+            0x2C, 0x79, 0x00, 0x00, 0x00, 0x00, // sub: movea.l _DOSBase,a6
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6)
+            0x4E, 0x75, // rts
+        ];
+        code.extend_from_slice(b"dos.library\0");
+        let fixups = vec![Fixup { offset: 16, target: 1 }, Fixup { offset: 26, target: 1 }];
+
+        let bases = track(&hunk_image(code, fixups), &[]);
+        assert_eq!(bases.library_at(0, 10), Some("exec"));
+        assert_eq!(bases.library_at(0, 30), Some("dos"));
+        assert_eq!(bases.globals.get(&Location::new(1, 0)).map(String::as_str), Some("dos"));
+    }
+
+    #[test]
+    fn disagreeing_paths_forget_the_base() {
+        let code = vec![
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x67, 0x02, // beq.s join
+            0x2C, 0x40, // movea.l d0,a6
+            0x4E, 0xAE, 0xFF, 0x3A, // join: jsr (-198,a6)
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x4E, 0xAE, 0xFF, 0x3A, // jsr (-198,a6)
+        ];
+
+        let bases = track(&hunk_image(code.clone(), vec![]), &[]);
+        assert_eq!(bases.library_at(0, 8), None);
+        assert_eq!(bases.library_at(0, 16), Some("exec"));
+
+        // With segments at real addresses, 4.w is still ExecBase
+        let mut image = hunk_image(code, vec![]);
+        image.segments[1].base_address = 0x1000;
+        let bases = track(&image, &[]);
+        assert_eq!(bases.library_at(0, 16), Some("exec"));
+
+        assert_eq!(library_name("LIBS:foo.library").as_deref(), Some("foo"));
+        assert_eq!(library_name("ciaa.resource").as_deref(), Some("ciaa"));
+    }
+
+    #[test]
+    fn small_data_globals() {
+        let mut code = vec![
+            0x49, 0xF9, 0x00, 0x00, 0x00, 0x00, // lea _LinkerDB,a4
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x43, 0xFA, 0x00, 0x1E, // lea (30,pc),a1 -> dosname
+            0x70, 0x00, // moveq #0,d0
+            0x4E, 0xAE, 0xFD, 0xD8, // jsr (-552,a6)
+            0x29, 0x40, 0x00, 0x10, // move.l d0,(16,a4)
+            0x2C, 0x6C, 0x00, 0x10, // movea.l (16,a4),a6
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6)
+            0x2C, 0x6C, 0x00, 0x14, // movea.l (20,a4),a6
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6)
+            0x4E, 0x75, // rts
+        ];
+        code.extend_from_slice(b"dos.library\0");
+        let mut image = hunk_image(code, vec![Fixup { offset: 2, target: 1 }]);
+        image.segments[1].size = 0x100;
+
+        let bases = track(&image, &[]);
+        assert_eq!(bases.globals.get(&Location::new(1, 16)).map(String::as_str), Some("dos"));
+        assert_eq!(bases.library_at(0, 28), Some("dos"));
+        // A6 loaded from a global holding no known base is not exec
+        assert_eq!(bases.library_at(0, 36), None);
+        assert!(bases.unresolved_calls.contains(&Location::new(0, 36)));
+        assert!(!bases.unresolved_calls.contains(&Location::new(0, 28)));
+    }
+
+    #[test]
+    fn joined_bases_stay_unresolved() {
+        let mut code = vec![
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x43, 0xFA, 0x00, 0x1E, // lea (30,pc),a1 -> dosname
+            0x4E, 0xAE, 0xFD, 0xD8, // jsr (-552,a6)
+            0x2C, 0x40, // movea.l d0,a6
+            0x4A, 0x81, // tst.l d1
+            0x67, 0x04, // beq.s join
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x22, 0x08, // join: move.l a0,d1
+            0x24, 0x3C, 0x00, 0x00, 0x03, 0xED, // move.l #1005,d2
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6): dos Open or exec Supervisor
+            0x4E, 0x75, // rts
+        ];
+        code.extend_from_slice(b"dos.library\0");
+
+        let bases = track(&hunk_image(code, vec![]), &[]);
+        assert_eq!(bases.library_at(0, 8), Some("exec"));
+        assert_eq!(bases.library_at(0, 30), None);
+        assert!(bases.unresolved_calls.contains(&Location::new(0, 30)));
+    }
+
+    #[test]
+    fn data_in_code_is_not_tracked() {
+        let code = vec![
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x4E, 0xAE, 0xFF, 0x7C, // jsr (-132,a6) -> Forbid
+            0x4E, 0x75, // rts
+            0x2C, 0x78, 0x00, 0x04, // table bytes that decode as movea.l $4.w,a6
+            0x4E, 0xAE, 0xFF, 0xE2, // and jsr (-30,a6)
+        ];
+        let image = hunk_image(code, vec![]);
+
+        let bases = track(&image, &[]);
+        assert_eq!(bases.library_at(0, 4), Some("exec"));
+        assert_eq!(bases.library_at(0, 14), None);
+
+        // A linear sweep decodes the table as code
+        let linear = CodeMap::linear(&image);
+        let bases = track_library_bases(&image, CpuVariant::M68000, &linear, &[]);
+        assert_eq!(bases.library_at(0, 14), Some("exec"));
+    }
+
+    #[test]
+    fn absolute_base_registers() {
+        let code = vec![
+            0x4B, 0xF9, 0x00, 0xDF, 0xF0, 0x00, // lea $DFF000,a5
+            0x3B, 0x40, 0x00, 0x96, // move.w d0,($96,a5)
+            0x41, 0xED, 0x01, 0x80, // lea ($180,a5),a0
+            0x30, 0x80, // move.w d0,(a0)
+        ];
+
+        let bases = track(&hunk_image(code, vec![]), &[]);
+        assert_eq!(bases.absolute_bases.get(&Location::new(0, 6)), Some(&vec![(5, 0xDFF000)]));
+        assert_eq!(bases.absolute_bases.get(&Location::new(0, 14)), Some(&vec![(0, 0xDFF180)]));
+    }
+
+    #[test]
+    fn structure_pointers() {
+        let code = vec![
+            0x2C, 0x78, 0x00, 0x04, // movea.l $4.w,a6
+            0x20, 0x6E, 0x01, 0x14, // movea.l (276,a6),a0 -> ThisTask
+            0x20, 0x28, 0x00, 0xAC, // move.l (172,a0),d0
+            0x93, 0xC9, // suba.l a1,a1
+            0x4E, 0xAE, 0xFE, 0xDA, // jsr (-294,a6) -> FindTask
+            0x23, 0xC0, 0x00, 0x00, 0x00, 0x00, // move.l d0,_task
+            0x22, 0x79, 0x00, 0x00, 0x00, 0x00, // movea.l _task,a1
+            0x4A, 0xA9, 0x00, 0xAC, // tst.l (172,a1)
+            0x4A, 0x92, // tst.l (a2)
+            0x4E, 0x75, // rts
+        ];
+        let fixups = vec![Fixup { offset: 20, target: 1 }, Fixup { offset: 26, target: 1 }];
+        let declared = RegisterType {
+            segment: 0,
+            address: 34,
+            register: 10,
+            type_name: "Window".to_string(),
+        };
+
+        let bases = track(&hunk_image(code, fixups), &[declared]);
+        let types = |offset| bases.struct_bases.get(&Location::new(0, offset)).cloned();
+        assert_eq!(types(4), Some(vec![(6, "ExecBase".to_string())]));
+        assert_eq!(types(8), Some(vec![(0, "Task".to_string())]));
+        assert_eq!(types(30), Some(vec![(1, "Task".to_string())]));
+        assert_eq!(types(34), Some(vec![(2, "Window".to_string())]));
+        assert_eq!(bases.pointer_globals.get(&Location::new(1, 0)).map(String::as_str), Some("Task"));
+        assert!(bases.globals.is_empty());
+    }
+}
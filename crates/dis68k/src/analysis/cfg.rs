@@ -0,0 +1,500 @@
+//! Basic blocks and control-flow graphs of functions.
+//!
+//! A function's instructions (its extent, see `functions`) are split
+//! into basic blocks at its entry, at every branch target, and after
+//! every branch, jump, return and call. Edges are classified by the
+//! transfer that makes them: a taken branch, a fall-through, the back
+//! edge of a `dbcc` loop, the return from a call, or a jump-table case.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::image::{Image, Location};
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Instruction, Mnemonic};
+use crate::m68k::variants::CpuVariant;
+use crate::output::formatter::{format_instruction_with_resolver, FormatOptions};
+use crate::symbols::amiga::LvoEntry;
+use crate::symbols::resolver::{ResolvedSymbol, SymbolKind, SymbolResolver};
+
+use super::flow::{control_target, distinct_addresses, falls_through};
+use super::functions::Function;
+
+/// How control passes along an edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// A taken `bcc`, or a `bra` or `jmp`.
+    Taken,
+    /// Execution continues with the next instruction.
+    Fallthrough,
+    /// The branch of a `dbcc` back to its loop.
+    Loop,
+    /// From a `bsr` or `jsr` to the instruction after it.
+    CallReturn,
+    /// An entry of a jump table.
+    Case,
+}
+
+impl EdgeKind {
+    /// Lowercase name, as used in the exports.
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Taken => "taken",
+            EdgeKind::Fallthrough => "fallthrough",
+            EdgeKind::Loop => "loop",
+            EdgeKind::CallReturn => "call-return",
+            EdgeKind::Case => "case",
+        }
+    }
+}
+
+/// A run of instructions entered only at the top and left only at the
+/// bottom.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub start: Location,
+    pub instructions: Vec<Instruction>,
+}
+
+impl BasicBlock {
+    /// Offset just past the block's last instruction.
+    pub fn end(&self) -> u32 {
+        let size: u32 = self.instructions.iter().map(|inst| inst.size_bytes as u32).sum();
+        self.start.offset + size
+    }
+
+    /// Listed address of the block's first instruction.
+    pub fn address(&self) -> u32 {
+        self.instructions.first().map_or(0, |inst| inst.address)
+    }
+}
+
+/// An edge between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: Location,
+    pub to: Location,
+    pub kind: EdgeKind,
+}
+
+/// The control-flow graph of one function.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub entry: Location,
+    /// The blocks, by start.
+    pub blocks: BTreeMap<Location, BasicBlock>,
+    /// The edges, in block order.
+    pub edges: Vec<Edge>,
+}
+
+/// Build the control-flow graph of `function`.
+///
+/// Branches out of the function (tail calls) get no edge. Jump-table
+/// cases are added from `cases`: each jump instruction's location and
+/// the targets it dispatches to.
+pub fn build_cfg(
+    image: &Image,
+    cpu: CpuVariant,
+    function: &Function,
+    cases: &BTreeMap<Location, Vec<Location>>,
+) -> Cfg {
+    let hunk = function.entry.hunk;
+    let segment = &image.segments[hunk];
+    let distinct = distinct_addresses(image);
+
+    let mut insts: BTreeMap<u32, Instruction> = BTreeMap::new();
+    for range in &function.extent {
+        let data = &segment.data[..(range.end as usize).min(segment.data.len())];
+        let mut offset = range.start as usize;
+        while offset < data.len() {
+            let Ok(inst) = decode_instruction(data, offset, segment.base_address, cpu) else {
+                break;
+            };
+            let size = inst.size_bytes as usize;
+            insts.insert(offset as u32, inst);
+            offset += size;
+        }
+    }
+
+    // Successors of each instruction that ends a block
+    let inside = |location: Location| location.hunk == hunk && insts.contains_key(&location.offset);
+    let mut exits: BTreeMap<u32, Vec<(Location, EdgeKind)>> = BTreeMap::new();
+    for (&offset, inst) in &insts {
+        let next = Location::new(hunk, offset + inst.size_bytes as u32);
+        let target = control_target(image, segment, inst, distinct).filter(|&t| inside(t));
+        let here = Location::new(hunk, offset);
+        let successors = match inst.mnemonic {
+            Mnemonic::Bsr | Mnemonic::Jsr => vec![(next, EdgeKind::CallReturn)],
+            Mnemonic::Bcc => target
+                .map(|t| (t, EdgeKind::Taken))
+                .into_iter()
+                .chain([(next, EdgeKind::Fallthrough)])
+                .collect(),
+            Mnemonic::Dbcc => target
+                .map(|t| (t, EdgeKind::Loop))
+                .into_iter()
+                .chain([(next, EdgeKind::Fallthrough)])
+                .collect(),
+            Mnemonic::Bra | Mnemonic::Jmp => {
+                let table = cases.get(&here).into_iter().flatten().map(|&t| (t, EdgeKind::Case));
+                target.map(|t| (t, EdgeKind::Taken)).into_iter().chain(table).collect()
+            }
+            _ if !falls_through(&inst.mnemonic) => Vec::new(),
+            _ => continue,
+        };
+        let successors = successors.into_iter().filter(|&(t, _)| inside(t)).collect();
+        exits.insert(offset, successors);
+    }
+
+    let mut leaders: BTreeSet<u32> = BTreeSet::from([function.entry.offset]);
+    for (&offset, successors) in &exits {
+        leaders.extend(successors.iter().map(|(t, _)| t.offset));
+        leaders.insert(offset + insts[&offset].size_bytes as u32);
+    }
+    // A gap in the extent also starts a block
+    for range in &function.extent {
+        leaders.insert(range.start);
+    }
+
+    let mut blocks: BTreeMap<Location, BasicBlock> = BTreeMap::new();
+    let mut edges = Vec::new();
+    let mut current: Option<BasicBlock> = None;
+    for (&offset, inst) in &insts {
+        let starts = leaders.contains(&offset)
+            || current.as_ref().is_some_and(|block| block.end() != offset);
+        if starts {
+            if let Some(block) = current.take() {
+                // Ran into a leader, rather than across a gap in the extent
+                if block.end() == offset {
+                    edges.push(Edge {
+                        from: block.start,
+                        to: Location::new(hunk, offset),
+                        kind: EdgeKind::Fallthrough,
+                    });
+                }
+                blocks.insert(block.start, block);
+            }
+        }
+        let block = current.get_or_insert_with(|| BasicBlock {
+            start: Location::new(hunk, offset),
+            instructions: Vec::new(),
+        });
+        block.instructions.push(inst.clone());
+        if let Some(successors) = exits.get(&offset) {
+            let from = block.start;
+            edges.extend(successors.iter().map(|&(to, kind)| Edge { from, to, kind }));
+            if let Some(block) = current.take() {
+                blocks.insert(block.start, block);
+            }
+        }
+    }
+    if let Some(block) = current.take() {
+        blocks.insert(block.start, block);
+    }
+    edges.sort_by_key(|edge| (edge.from, edge.kind, edge.to));
+    // Cases sharing a target make one edge
+    edges.dedup();
+
+    Cfg {
+        entry: function.entry,
+        blocks,
+        edges,
+    }
+}
+
+impl Cfg {
+    /// A block's name: its label from `names`, else `sub_XXXX` for the
+    /// entry and `loc_XXXX` for the others.
+    pub fn block_name(&self, block: &BasicBlock, names: Option<&dyn SymbolResolver>) -> String {
+        match names.and_then(|n| n.resolve_location(block.start)) {
+            Some(symbol) => symbol.name,
+            None if block.start == self.entry => format!("sub_{:04X}", block.address()),
+            None => format!("loc_{:04X}", block.address()),
+        }
+    }
+
+    /// A block's disassembly, one line per instruction after its name.
+    fn block_lines(
+        &self,
+        block: &BasicBlock,
+        names: Option<&dyn SymbolResolver>,
+        opts: &FormatOptions,
+    ) -> Vec<String> {
+        let mut lines = vec![format!("{}:", self.block_name(block, names))];
+        let blocks = BlockResolver { cfg: self, names };
+        for inst in &block.instructions {
+            let formatted = format_instruction_with_resolver(inst, opts, Some(&blocks));
+            lines.push(format!("{:08X}  {:<8} {}", inst.address, formatted.mnemonic, formatted.operands));
+        }
+        lines
+    }
+
+    /// The graph in Graphviz DOT, with each block's disassembly in its
+    /// node. Edges are styled by kind.
+    pub fn to_dot(&self, names: Option<&dyn SymbolResolver>, opts: &FormatOptions) -> String {
+        let mut out = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        for block in self.blocks.values() {
+            let text: String = self
+                .block_lines(block, names, opts)
+                .iter()
+                .map(|line| format!("{}\\l", dot_escape(line.trim_end())))
+                .collect();
+            let _ = writeln!(out, "    \"{}\" [label=\"{}\"];", block.start, text);
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Taken => "color=darkgreen",
+                EdgeKind::Fallthrough => "color=black",
+                EdgeKind::Loop => "color=blue",
+                EdgeKind::CallReturn => "color=gray, style=dashed",
+                EdgeKind::Case => "color=purple",
+            };
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\", {}];",
+                edge.from,
+                edge.to,
+                edge.kind.name(),
+                style
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The graph in GraphML, with each block's name, address and
+    /// disassembly as node data and each edge's kind as edge data.
+    pub fn to_graphml(&self, names: Option<&dyn SymbolResolver>, opts: &FormatOptions) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>\n",
+            "  <key id=\"address\" for=\"node\" attr.name=\"address\" attr.type=\"long\"/>\n",
+            "  <key id=\"disassembly\" for=\"node\" attr.name=\"disassembly\" attr.type=\"string\"/>\n",
+            "  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+            "  <graph id=\"cfg\" edgedefault=\"directed\">\n",
+        ));
+        for block in self.blocks.values() {
+            let lines = self.block_lines(block, names, opts);
+            let _ = writeln!(out, "    <node id=\"{}\">", xml_escape(&block.start.to_string()));
+            let _ = writeln!(out, "      <data key=\"name\">{}</data>", xml_escape(&lines[0][..lines[0].len() - 1]));
+            let _ = writeln!(out, "      <data key=\"address\">{}</data>", block.address());
+            let text: Vec<String> = lines[1..].iter().map(|line| xml_escape(line.trim_end())).collect();
+            let _ = writeln!(out, "      <data key=\"disassembly\">{}</data>", text.join("\n"));
+            out.push_str("    </node>\n");
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"><data key=\"kind\">{}</data></edge>",
+                i,
+                xml_escape(&edge.from.to_string()),
+                xml_escape(&edge.to.to_string()),
+                edge.kind.name()
+            );
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+}
+
+/// Names branch targets in a block's disassembly: by `names` where it
+/// has a label, else by the name of the block they start. Everything
+/// else is asked of `names`, so operands read like the listing's.
+struct BlockResolver<'a> {
+    cfg: &'a Cfg,
+    names: Option<&'a dyn SymbolResolver>,
+}
+
+impl SymbolResolver for BlockResolver<'_> {
+    fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_location(location)).or_else(|| {
+            let block = self.cfg.blocks.get(&location)?;
+            Some(ResolvedSymbol::new(self.cfg.block_name(block, None), SymbolKind::CodeLabel))
+        })
+    }
+
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_nearest(location))
+    }
+
+    fn locate(&self, address: u32) -> Option<Location> {
+        let entry = &self.cfg.blocks[&self.cfg.entry];
+        let base = entry.address().wrapping_sub(entry.start.offset);
+        self.names
+            .and_then(|n| n.locate(address))
+            .or(Some(Location::new(self.cfg.entry.hunk, address.wrapping_sub(base))))
+    }
+
+    fn relocation_target(&self, words: Range<u32>, value: u32) -> Option<Location> {
+        self.names.and_then(|n| n.relocation_target(words, value))
+    }
+
+    fn base_relative_target(&self, address: u32, register: u8, displacement: i32) -> Option<Location> {
+        self.names.and_then(|n| n.base_relative_target(address, register, displacement))
+    }
+
+    fn resolve_lvo(&self, offset: i16) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_lvo(offset))
+    }
+
+    fn lvo_entry(&self, offset: i16) -> Option<LvoEntry> {
+        self.names.and_then(|n| n.lvo_entry(offset))
+    }
+
+    fn library_lvo_entry(&self, library: &str, offset: i16) -> Option<LvoEntry> {
+        self.names.and_then(|n| n.library_lvo_entry(library, offset))
+    }
+
+    fn resolve_register(&self, address: u32) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_register(address))
+    }
+
+    fn resolve_register_offset(&self, base: u32, offset: i16) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_register_offset(base, offset))
+    }
+
+    fn address_register_base(&self, address: u32, register: u8) -> Option<u32> {
+        self.names.and_then(|n| n.address_register_base(address, register))
+    }
+
+    fn address_register_type(&self, address: u32, register: u8) -> Option<String> {
+        self.names.and_then(|n| n.address_register_type(address, register))
+    }
+
+    fn resolve_struct_field(&self, type_name: &str, offset: i16) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_struct_field(type_name, offset))
+    }
+
+    fn resolve_constant(&self, value: u32) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_constant(value))
+    }
+
+    fn resolve_field_constant(&self, type_name: &str, offset: i16, value: u32) -> Option<ResolvedSymbol> {
+        self.names.and_then(|n| n.resolve_field_constant(type_name, offset, value))
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{discover_code, find_functions};
+    use crate::image::{Fixup, SegmentKind};
+    use crate::output::listing::{ListingLabels, ListingOptions};
+
+    fn cfg(image: &Image) -> Cfg {
+        let code = discover_code(image, CpuVariant::M68000, &[], false);
+        let graph = find_functions(image, CpuVariant::M68000, &code, &[]);
+        build_cfg(image, CpuVariant::M68000, &graph.functions[&Location::new(0, 0)], &BTreeMap::new())
+    }
+
+    /// A counted loop, a call and a conditional skip.
+    fn branches() -> Image {
+        let code = vec![
+            0x70, 0x03, // 0: moveq #3,d0
+            0x4E, 0x71, // 2: loop: nop
+            0x51, 0xC8, 0xFF, 0xFC, // 4: dbf d0,loop
+            0x61, 0x0A, // 8: bsr.s sub_0014
+            0x67, 0x02, // A: beq.s done
+            0x72, 0x01, // C: moveq #1,d1
+            0x4E, 0x75, // E: done: rts
+            0x4E, 0x71, // 10: padding
+            0x4E, 0x71, // 12: padding
+            0x4E, 0x75, // 14: sub_0014: rts
+        ];
+        Image::test_code(code, vec![])
+    }
+
+    #[test]
+    fn blocks_split_at_branch_targets() {
+        let cfg = cfg(&branches());
+
+        let starts: Vec<u32> = cfg.blocks.keys().map(|l| l.offset).collect();
+        assert_eq!(starts, [0x0, 0x2, 0x8, 0xA, 0xC, 0xE]);
+        let edges: Vec<(u32, u32, EdgeKind)> = cfg
+            .edges
+            .iter()
+            .map(|e| (e.from.offset, e.to.offset, e.kind))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                (0x0, 0x2, EdgeKind::Fallthrough),
+                (0x2, 0x8, EdgeKind::Fallthrough),
+                (0x2, 0x2, EdgeKind::Loop),
+                (0x8, 0xA, EdgeKind::CallReturn),
+                (0xA, 0xE, EdgeKind::Taken),
+                (0xA, 0xC, EdgeKind::Fallthrough),
+                (0xC, 0xE, EdgeKind::Fallthrough),
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_output() {
+        let cfg = cfg(&branches());
+
+        let dot = cfg.to_dot(None, &FormatOptions { uppercase: false });
+        assert!(dot.starts_with("digraph cfg {\n"), "{dot}");
+        assert!(dot.contains("\"0:$0002\" [label=\"loc_0002:\\l00000002  nop\\l00000004  dbf"), "{dot}");
+        assert!(dot.contains("00000004  dbf      d0,loc_0002\\l\"];"), "{dot}");
+        assert!(dot.contains("\"0:$0002\" -> \"0:$0002\" [label=\"loop\", color=blue];"), "{dot}");
+        assert!(dot.contains("\"0:$000A\" -> \"0:$000E\" [label=\"taken\", color=darkgreen];"), "{dot}");
+        assert!(dot.ends_with("}\n"), "{dot}");
+    }
+
+    #[test]
+    fn graphml_output() {
+        let cfg = cfg(&branches());
+
+        let graphml = cfg.to_graphml(None, &FormatOptions { uppercase: false });
+        assert!(graphml.contains("<node id=\"0:$000A\">"), "{graphml}");
+        assert!(graphml.contains("<data key=\"name\">sub_0000</data>"), "{graphml}");
+        assert!(graphml.contains("<data key=\"address\">10</data>"), "{graphml}");
+        assert!(
+            graphml.contains("<data key=\"disassembly\">0000000A  beq      loc_000E</data>"),
+            "{graphml}"
+        );
+        assert!(
+            graphml.contains("source=\"0:$0008\" target=\"0:$000A\"><data key=\"kind\">call-return</data>"),
+            "{graphml}"
+        );
+    }
+
+    #[test]
+    fn nodes_use_listing_labels() {
+        let code = vec![
+            0x49, 0xF9, 0x00, 0x00, 0x7F, 0xFE, // lea $7FFE,a4 -> hunk 1
+            0x41, 0xF9, 0x00, 0x00, 0x00, 0x04, // lea $4,a0 -> hunk 1
+            0x20, 0x2C, 0x80, 0x8C, // move.l (-32628,a4),d0
+            0x4E, 0x75, // rts
+        ];
+        let fixups = vec![Fixup { offset: 2, target: 1 }, Fixup { offset: 8, target: 1 }];
+        let image = Image::test_code(code, fixups)
+            .with_segment(SegmentKind::Data, vec![0; 16], vec![])
+            .sized(0x10000);
+        let options = ListingOptions::default();
+        let code = discover_code(&image, options.cpu, &[], false);
+        let graph = find_functions(&image, options.cpu, &code, &[]);
+        let labels = ListingLabels::new(&image, &options, &code, &graph);
+        let names = labels.resolver(&image, 0, None);
+        let cfg = build_cfg(&image, options.cpu, &graph.functions[&Location::new(0, 0)], &BTreeMap::new());
+
+        let dot = cfg.to_dot(Some(&names), &FormatOptions { uppercase: false });
+        assert!(dot.contains("00000000  lea.l    data_1_7FFE,a4\\l"), "{dot}");
+        assert!(dot.contains("00000006  lea.l    data_1_0004,a0\\l"), "{dot}");
+        assert!(dot.contains("0000000C  move.l   data_1_008A(a4),d0\\l"), "{dot}");
+    }
+}
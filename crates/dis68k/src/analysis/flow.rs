@@ -0,0 +1,518 @@
+//! Recursive-descent code discovery.
+//!
+//! Decoding a code hunk from start to end turns embedded strings, jump
+//! tables and padding into nonsense instructions, and code that follows
+//! an odd-length string can be decoded out of step. This pass follows
+//! control flow instead: from the entry point and the caller's entries,
+//! along branches and calls, stopping at `rts`, `rte`, `rtr`, `rtd`,
+//! `bra` and `jmp`. Bytes no path reaches are data.
+//!
+//! An indirect `jmp` through a jump table (see `jump_tables`) continues
+//! at each of the table's targets, and the table is kept as data.
+//!
+//! Hunk symbols, relocation targets in code hunks and the PC-relative
+//! addresses taken by `lea` and `pea` may point at data as well as
+//! code, so paths from them are traced speculatively: a path
+//! that runs into an undecodable word, an `illegal`, or the middle of an
+//! instruction already found is dropped as a whole.
+//!
+//! Code hunks that no path reaches at all, such as the regions of a ROM
+//! with no known entry, are decoded linearly as before.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::image::{Image, ImageFormat, Location, Segment, SegmentKind};
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand};
+use crate::m68k::variants::CpuVariant;
+
+use super::jump_tables::{find_jump_table, JumpTable, TableKind};
+
+/// Instructions looked back over for the setup of a jump table.
+const TABLE_WINDOW: usize = 8;
+
+/// The code found in each segment of an image, as byte ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeMap {
+    /// Per segment, in segment order: sorted, disjoint ranges of
+    /// instructions.
+    pub ranges: Vec<Vec<Range<usize>>>,
+    /// The jump tables found, by the location of their `jmp`.
+    pub jump_tables: BTreeMap<Location, JumpTable>,
+}
+
+impl CodeMap {
+    /// Every code segment is code from start to end.
+    pub fn linear(image: &Image) -> Self {
+        let ranges = image
+            .segments
+            .iter()
+            .map(|segment| match segment.kind {
+                SegmentKind::Code => std::iter::once(0..segment.data.len()).collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        CodeMap {
+            ranges,
+            jump_tables: BTreeMap::new(),
+        }
+    }
+
+    /// The code ranges of segment `segment`.
+    pub fn ranges_in(&self, segment: usize) -> &[Range<usize>] {
+        self.ranges.get(segment).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether the byte at `location` is part of an instruction.
+    pub fn is_code(&self, location: Location) -> bool {
+        let offset = location.offset as usize;
+        self.ranges_in(location.hunk)
+            .iter()
+            .any(|r| r.contains(&offset))
+    }
+
+    /// The targets of each jump through a table, by the jump's location.
+    pub fn cases(&self) -> BTreeMap<Location, Vec<Location>> {
+        self.jump_tables
+            .iter()
+            .map(|(&jump, table)| (jump, table.targets.clone()))
+            .collect()
+    }
+}
+
+/// Find the code of an image by following control flow from its entry
+/// points, `entries` (such as project functions) and, speculatively,
+/// the symbols and relocation targets in its code segments.
+///
+/// With `linear` set, or for a code segment no path reaches, code
+/// segments are decoded from start to end.
+pub fn discover_code(
+    image: &Image,
+    cpu: CpuVariant,
+    entries: &[Location],
+    linear: bool,
+) -> CodeMap {
+    let linear_map = CodeMap::linear(image);
+    if linear {
+        return linear_map;
+    }
+
+    let mut tracer = Tracer {
+        image,
+        cpu,
+        distinct: distinct_addresses(image),
+        found: vec![BTreeMap::new(); image.segments.len()],
+        pointers: Vec::new(),
+        jump_tables: BTreeMap::new(),
+    };
+
+    let strong = image
+        .entry_points
+        .iter()
+        .filter_map(|&address| image.locate(address))
+        .chain(entries.iter().copied());
+    for entry in strong {
+        tracer.trace(entry, false);
+    }
+    for entry in weak_entries(image) {
+        tracer.trace(entry, true);
+    }
+    while let Some(entry) = tracer.pointers.pop() {
+        tracer.trace(entry, true);
+    }
+
+    let ranges = tracer
+        .found
+        .iter()
+        .zip(linear_map.ranges)
+        .map(|(found, linear)| {
+            if found.is_empty() {
+                linear
+            } else {
+                merge_ranges(found)
+            }
+        })
+        .collect();
+    CodeMap {
+        ranges,
+        jump_tables: tracer.jump_tables,
+    }
+}
+
+/// Symbols and relocation targets in code segments.
+fn weak_entries(image: &Image) -> Vec<Location> {
+    let mut entries = Vec::new();
+    for segment in image
+        .segments
+        .iter()
+        .filter(|s| s.kind == SegmentKind::Code)
+    {
+        entries.extend(
+            segment
+                .symbols
+                .iter()
+                .map(|sym| Location::new(segment.index, sym.value)),
+        );
+    }
+    for segment in &image.segments {
+        for fixup in &segment.fixups {
+            let is_code = image
+                .segments
+                .get(fixup.target)
+                .is_some_and(|t| t.kind == SegmentKind::Code);
+            if is_code {
+                entries.extend(image.fixup_location(segment, fixup));
+            }
+        }
+    }
+    entries
+}
+
+/// Contiguous instructions as byte ranges.
+fn merge_ranges(found: &BTreeMap<u32, u32>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (&offset, &size) in found {
+        let (start, end) = (offset as usize, (offset + size) as usize);
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
+
+struct Tracer<'a> {
+    image: &'a Image,
+    cpu: CpuVariant,
+    /// Segments sit at distinct addresses, so an unrelocated absolute
+    /// target can still be placed in a segment.
+    distinct: bool,
+    /// Per segment: offset → size of each instruction found.
+    found: Vec<BTreeMap<u32, u32>>,
+    /// Addresses taken by `lea (d,pc)` and `pea (d,pc)` in the code
+    /// found, yet to be traced.
+    pointers: Vec<Location>,
+    /// The jump tables found, by the location of their `jmp`.
+    jump_tables: BTreeMap<Location, JumpTable>,
+}
+
+impl Tracer<'_> {
+    /// Follow control flow from `entry`. A speculative trace is kept
+    /// only if every path decodes cleanly; others stop a path where it
+    /// fails. Returns whether the trace was kept.
+    fn trace(&mut self, entry: Location, speculative: bool) -> bool {
+        let mut new: BTreeMap<Location, u32> = BTreeMap::new();
+        let mut pointers = Vec::new();
+        let mut tables: Vec<JumpTable> = Vec::new();
+        let mut queue = vec![entry];
+
+        while let Some(location) = queue.pop() {
+            let Some(segment) = self.image.segments.get(location.hunk) else {
+                continue;
+            };
+            if segment.kind != SegmentKind::Code
+                || location.offset as usize >= segment.data.len()
+                || self.found[location.hunk].contains_key(&location.offset)
+                || new.contains_key(&location)
+            {
+                continue;
+            }
+            let inst = decode_instruction(
+                &segment.data,
+                location.offset as usize,
+                segment.base_address,
+                self.cpu,
+            )
+            .ok()
+            .filter(|inst| !matches!(inst.mnemonic, Mnemonic::Dc | Mnemonic::Illegal));
+            let valid = inst.as_ref().filter(|inst| {
+                let size = inst.size_bytes as u32;
+                !self.overlaps(&new, location, size) && !self.in_table(&tables, location, size)
+            });
+            let Some(inst) = valid else {
+                if speculative {
+                    return false;
+                }
+                continue;
+            };
+
+            let size = inst.size_bytes as u32;
+            new.insert(location, size);
+            let target = control_target(self.image, segment, inst, self.distinct);
+            if let Some(target) = target {
+                queue.push(target);
+            } else if inst.mnemonic == Mnemonic::Jmp {
+                let preceding = self.preceding(&new, location);
+                if let Some(table) = find_jump_table(self.image, segment, &preceding, inst, self.cpu) {
+                    queue.extend(&table.targets);
+                    tables.push(table);
+                }
+            }
+            pointers.extend(address_taken(segment, inst));
+            if falls_through(&inst.mnemonic) {
+                queue.push(Location::new(location.hunk, location.offset + size));
+            }
+        }
+
+        for (location, size) in new {
+            self.found[location.hunk].insert(location.offset, size);
+        }
+        self.pointers.extend(pointers);
+        self.jump_tables
+            .extend(tables.into_iter().map(|table| (table.jump, table)));
+        true
+    }
+
+    /// The instructions that run straight into `location`, in order, up
+    /// to `TABLE_WINDOW` of them.
+    fn preceding(&self, new: &BTreeMap<Location, u32>, location: Location) -> Vec<Instruction> {
+        let segment = &self.image.segments[location.hunk];
+        let mut insts = Vec::new();
+        let mut offset = location.offset;
+        while insts.len() < TABLE_WINDOW {
+            let in_new = new
+                .range(Location::new(location.hunk, 0)..Location::new(location.hunk, offset))
+                .next_back()
+                .filter(|&(l, &size)| l.offset + size == offset)
+                .map(|(l, _)| l.offset);
+            let in_found = || {
+                self.found[location.hunk]
+                    .range(..offset)
+                    .next_back()
+                    .filter(|&(&start, &size)| start + size == offset)
+                    .map(|(&start, _)| start)
+            };
+            let Some(start) = in_new.or_else(in_found) else {
+                break;
+            };
+            let decoded = decode_instruction(&segment.data, start as usize, segment.base_address, self.cpu);
+            match decoded {
+                Ok(inst) if falls_through(&inst.mnemonic) => insts.push(inst),
+                _ => break,
+            }
+            offset = start;
+        }
+        insts.reverse();
+        insts
+    }
+
+    /// Whether an instruction of `size` bytes at `location` would share
+    /// bytes with a jump table, found before or in this trace.
+    fn in_table(&self, tables: &[JumpTable], location: Location, size: u32) -> bool {
+        let (start, end) = (location.offset, location.offset + size);
+        self.jump_tables
+            .values()
+            .chain(tables)
+            .filter(|table| table.table.hunk == location.hunk && table.kind != TableKind::Branches)
+            .any(|table| {
+                let range = table.range();
+                range.start < end && start < range.end
+            })
+    }
+
+    /// Whether an instruction of `size` bytes at `location` would share
+    /// bytes with an instruction already found that starts elsewhere.
+    fn overlaps(&self, new: &BTreeMap<Location, u32>, location: Location, size: u32) -> bool {
+        let (start, end) = (location.offset, location.offset + size);
+        let found = &self.found[location.hunk];
+        let before = found
+            .range(..start)
+            .next_back()
+            .map(|(&offset, &size)| offset + size > start)
+            .unwrap_or(false);
+        let inside = found.range(start + 1..end).next().is_some();
+        let new_before = new
+            .range(Location::new(location.hunk, 0)..location)
+            .next_back()
+            .is_some_and(|(l, &size)| l.hunk == location.hunk && l.offset + size > start);
+        let new_inside = new
+            .range(Location::new(location.hunk, start + 1)..Location::new(location.hunk, end))
+            .next()
+            .is_some();
+        before || inside || new_before || new_inside
+    }
+}
+
+/// Whether the segments of an image sit at distinct addresses, so an
+/// unrelocated absolute address can still be placed in a segment.
+pub(crate) fn distinct_addresses(image: &Image) -> bool {
+    image.format != ImageFormat::AmigaHunk || image.segments.iter().any(|s| s.base_address != 0)
+}
+
+/// Where a branch, call or jump in `segment` goes, if it can be told.
+pub(crate) fn control_target(
+    image: &Image,
+    segment: &Segment,
+    inst: &Instruction,
+    distinct: bool,
+) -> Option<Location> {
+    if !matches!(
+        inst.mnemonic,
+        Mnemonic::Bra | Mnemonic::Bsr | Mnemonic::Bcc | Mnemonic::Dbcc | Mnemonic::Jmp | Mnemonic::Jsr
+    ) {
+        return None;
+    }
+    let pc_relative = |disp: i32| in_segment(segment, inst.address.wrapping_add(2).wrapping_add(disp as u32));
+    inst.operands.iter().find_map(|op| match op {
+        Operand::Displacement8(d) => pc_relative(*d as i32),
+        Operand::Displacement16(d) => pc_relative(*d as i32),
+        Operand::Displacement32(d) => pc_relative(*d),
+        Operand::Ea(EffectiveAddress::PcDisplacement(d)) => pc_relative(*d as i32),
+        Operand::Ea(EffectiveAddress::AbsoluteLong(address)) => {
+            absolute_target(image, segment, inst, *address, distinct)
+        }
+        Operand::Ea(EffectiveAddress::AbsoluteShort(address)) if distinct => {
+            image.locate(*address as i16 as i32 as u32)
+        }
+        _ => None,
+    })
+}
+
+/// The location of `address` in `segment`, if it lies within its data.
+fn in_segment(segment: &Segment, address: u32) -> Option<Location> {
+    let offset = address.checked_sub(segment.base_address)?;
+    ((offset as usize) < segment.data.len()).then(|| Location::new(segment.index, offset))
+}
+
+/// Place an absolute target: by the fixup on it, or for images at real
+/// addresses by the segment containing it.
+pub(crate) fn absolute_target(
+    image: &Image,
+    segment: &Segment,
+    inst: &Instruction,
+    address: u32,
+    distinct: bool,
+) -> Option<Location> {
+    let start = inst.address.wrapping_sub(segment.base_address);
+    let end = start + inst.size_bytes as u32;
+    let fixup = segment
+        .fixups
+        .iter()
+        .find(|f| f.offset >= start + 2 && f.offset + 4 <= end && segment.read_u32(f.offset) == Some(address));
+    match fixup {
+        Some(fixup) => image.fixup_location(segment, fixup),
+        None if distinct => image.locate(address),
+        None => None,
+    }
+}
+
+/// The PC-relative address a `lea` or `pea` takes, which may be a
+/// function passed as a callback.
+fn address_taken(segment: &Segment, inst: &Instruction) -> Option<Location> {
+    if !matches!(inst.mnemonic, Mnemonic::Lea | Mnemonic::Pea) {
+        return None;
+    }
+    match inst.operands.first() {
+        Some(Operand::Ea(EffectiveAddress::PcDisplacement(d))) => {
+            in_segment(segment, inst.address.wrapping_add(2).wrapping_add(*d as i32 as u32))
+        }
+        _ => None,
+    }
+}
+
+/// Whether execution can continue with the next instruction.
+pub(crate) fn falls_through(mnemonic: &Mnemonic) -> bool {
+    !matches!(
+        mnemonic,
+        Mnemonic::Bra
+            | Mnemonic::Jmp
+            | Mnemonic::Rts
+            | Mnemonic::Rte
+            | Mnemonic::Rtr
+            | Mnemonic::Rtd
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hunk::types::Symbol;
+
+    #[test]
+    fn follows_branches_around_data() {
+        let mut code = vec![
+            0x61, 0x08, // bsr.s sub
+            0x4E, 0x75, // rts
+        ];
+        code.extend_from_slice(b"Hello\0"); // 4..10
+        code.extend_from_slice(&[
+            0x70, 0x01, // sub: moveq #1,d0
+            0x66, 0x02, // bne.s done
+            0x4E, 0x71, // nop
+            0x4E, 0x75, // done: rts
+            0x00, 0x00, // padding
+        ]);
+        let image = Image::test_code(code, vec![]);
+
+        let map = discover_code(&image, CpuVariant::M68000, &[], false);
+        assert_eq!(map.ranges_in(0), [0..4, 10..18]);
+        assert!(map.is_code(Location::new(0, 12)));
+        assert!(!map.is_code(Location::new(0, 4)));
+
+        let linear = discover_code(&image, CpuVariant::M68000, &[], true);
+        assert_eq!(linear.ranges_in(0), vec![0..20]);
+    }
+
+    #[test]
+    fn speculative_entries() {
+        let mut code = vec![
+            0x4E, 0x75, // rts
+            0x4E, 0x71, // helper: nop
+            0x4E, 0x75, // rts
+            0x4A, 0xFC, // table: illegal
+        ];
+        code.extend_from_slice(&[0x00, 0x00]);
+        let mut image = Image::test_code(code, vec![]);
+        image.segments[0].symbols = vec![
+            Symbol {
+                name: "helper".to_string(),
+                value: 2,
+            },
+            Symbol {
+                name: "table".to_string(),
+                value: 6,
+            },
+        ];
+
+        let map = discover_code(&image, CpuVariant::M68000, &[], false);
+        assert_eq!(map.ranges_in(0), vec![0..6]);
+    }
+
+    #[test]
+    fn callbacks_taken_by_pea() {
+        let code = vec![
+            0x48, 0x7A, 0x00, 0x04, // pea (4,pc) -> callback
+            0x4E, 0x75, // rts
+            0x70, 0x00, // callback: moveq #0,d0
+            0x4E, 0x75, // rts
+            0xFF, 0xFF, // padding
+        ];
+        let map = discover_code(&Image::test_code(code, vec![]), CpuVariant::M68000, &[], false);
+        assert_eq!(map.ranges_in(0), vec![0..10]);
+    }
+
+    #[test]
+    fn branch_out_of_the_segment_is_dropped() {
+        let mut image = Image::test_code(
+            vec![
+                0x60, 0xFC, // bra.s base-2
+                0x4E, 0x75, // rts
+            ],
+            vec![],
+        );
+        image.segments[0].base_address = 0x40000;
+        image.entry_points = vec![0x40000];
+        let map = discover_code(&image, CpuVariant::M68000, &[], false);
+        assert_eq!(map.ranges_in(0), vec![0..2]);
+    }
+
+    #[test]
+    fn unreached_segment_is_linear() {
+        let mut image = Image::test_code(vec![0x4E, 0x71, 0x4E, 0x75], vec![]);
+        image.entry_points.clear();
+        let map = discover_code(&image, CpuVariant::M68000, &[], false);
+        assert_eq!(map.ranges_in(0), vec![0..4]);
+    }
+}
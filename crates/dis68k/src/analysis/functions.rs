@@ -0,0 +1,505 @@
+//! Function discovery and the call graph.
+//!
+//! A function starts at an entry point, a project function, a library
+//! vector, a hunk symbol on an instruction, the target of a `bsr` or
+//! `jsr`, or a prologue (`link`, or `movem.l` to `-(sp)`) at the start
+//! of a run of code or after an instruction that does not fall through.
+//! Its extent is every instruction reachable from the entry along
+//! branches and fall-through, stopping at other functions' entries;
+//! calls are not followed, and a `bra` or `jmp` to another function is
+//! a tail call.
+//!
+//! The call graph links each `bsr`/`jsr` site to the function it calls
+//! and keeps the calls through registers or memory, which cannot be
+//! resolved statically, as indirect call sites.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::image::{Image, Location, SegmentKind};
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand, Size};
+use crate::m68k::variants::CpuVariant;
+use crate::symbols::resolver::SymbolResolver;
+
+use super::flow::{control_target, distinct_addresses, falls_through, CodeMap};
+
+/// Why an address is taken to start a function, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FunctionSource {
+    /// An entry point of the image.
+    EntryPoint,
+    /// A function in the project file.
+    Project,
+    /// A library or device function vector.
+    Vector,
+    /// A hunk symbol.
+    Symbol,
+    /// The target of a `bsr` or `jsr`.
+    Call,
+    /// A `link` or register-saving `movem.l`.
+    Prologue,
+}
+
+impl FunctionSource {
+    /// Lowercase name, as used in the JSON export.
+    pub fn name(self) -> &'static str {
+        match self {
+            FunctionSource::EntryPoint => "entry",
+            FunctionSource::Project => "project",
+            FunctionSource::Vector => "vector",
+            FunctionSource::Symbol => "symbol",
+            FunctionSource::Call => "call",
+            FunctionSource::Prologue => "prologue",
+        }
+    }
+}
+
+/// A function found in the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub entry: Location,
+    /// Listed address of the entry.
+    pub address: u32,
+    pub source: FunctionSource,
+    /// Sorted, disjoint offset ranges of the function's instructions in
+    /// the entry's segment.
+    pub extent: Vec<Range<u32>>,
+}
+
+impl Function {
+    /// The `sub_XXXX` label the listing gives an unnamed function.
+    pub fn label(&self) -> String {
+        format!("sub_{:04X}", self.address)
+    }
+
+    /// Total size of the function's instructions in bytes.
+    pub fn size(&self) -> u32 {
+        self.extent.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Whether the instruction at `location` belongs to the function.
+    pub fn contains(&self, location: Location) -> bool {
+        location.hunk == self.entry.hunk && self.extent.iter().any(|r| r.contains(&location.offset))
+    }
+}
+
+/// A `bsr` or `jsr` to a known address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Call {
+    /// The function containing the call, if any does.
+    pub caller: Option<Location>,
+    /// The calling instruction.
+    pub site: Location,
+    pub callee: Location,
+}
+
+/// A `jsr` through a register or memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndirectCall {
+    pub caller: Option<Location>,
+    pub site: Location,
+}
+
+/// The functions of an image and the calls between them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallGraph {
+    pub functions: BTreeMap<Location, Function>,
+    /// Direct calls, in site order.
+    pub calls: Vec<Call>,
+    /// Calls whose target is not known, in site order.
+    pub indirect: Vec<IndirectCall>,
+}
+
+impl CallGraph {
+    /// The calls to the function at `entry`.
+    pub fn callers(&self, entry: Location) -> impl Iterator<Item = &Call> {
+        self.calls.iter().filter(move |call| call.callee == entry)
+    }
+
+    /// The calls made by the function at `entry`.
+    pub fn callees(&self, entry: Location) -> impl Iterator<Item = &Call> {
+        self.calls.iter().filter(move |call| call.caller == Some(entry))
+    }
+
+    /// The function whose entry is nearest before `location` among
+    /// those containing it.
+    pub fn function_containing(&self, location: Location) -> Option<&Function> {
+        self.functions
+            .range(..=location)
+            .rev()
+            .map(|(_, function)| function)
+            .find(|function| function.contains(location))
+    }
+
+    /// A function's name: its label from `names`, else `sub_XXXX`.
+    pub fn name(&self, entry: Location, names: Option<&dyn SymbolResolver>) -> String {
+        let label = names.and_then(|n| n.resolve_location(entry)).map(|s| s.name);
+        match (label, self.functions.get(&entry)) {
+            (Some(label), _) => label,
+            (None, Some(function)) => function.label(),
+            (None, None) => entry.to_string(),
+        }
+    }
+
+    /// The graph in Graphviz DOT: a node per function, an edge per
+    /// caller and callee with the number of call sites, and a dashed
+    /// edge to a `?` node from each function making indirect calls.
+    pub fn to_dot(&self, names: Option<&dyn SymbolResolver>) -> String {
+        let mut out = String::from("digraph calls {\n    node [shape=box, fontname=monospace];\n");
+        for (&entry, function) in &self.functions {
+            let _ = writeln!(
+                out,
+                "    \"{}\" [label=\"{}\\n{} bytes\"];",
+                entry,
+                dot_escape(&self.name(entry, names)),
+                function.size()
+            );
+        }
+
+        let mut edges: BTreeMap<(Location, Location), usize> = BTreeMap::new();
+        for call in &self.calls {
+            if let Some(caller) = call.caller {
+                *edges.entry((caller, call.callee)).or_default() += 1;
+            }
+        }
+        for ((caller, callee), count) in edges {
+            match count {
+                1 => {
+                    let _ = writeln!(out, "    \"{caller}\" -> \"{callee}\";");
+                }
+                _ => {
+                    let _ = writeln!(out, "    \"{caller}\" -> \"{callee}\" [label=\"{count}\"];");
+                }
+            }
+        }
+
+        let indirect: BTreeSet<Location> = self.indirect.iter().filter_map(|call| call.caller).collect();
+        if !indirect.is_empty() {
+            out.push_str("    \"?\" [label=\"indirect\", shape=ellipse, style=dashed];\n");
+            for caller in indirect {
+                let _ = writeln!(out, "    \"{caller}\" -> \"?\" [style=dashed];");
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The graph as JSON: `functions` with their name, location, source,
+    /// extent and caller count, `calls` and `indirect` call sites.
+    pub fn to_json(&self, names: Option<&dyn SymbolResolver>) -> String {
+        let location = |l: Location| format!("{{\"hunk\": {}, \"offset\": {}}}", l.hunk, l.offset);
+        let caller = |c: Option<Location>| match c {
+            Some(c) => json_string(&self.name(c, names)),
+            None => "null".to_string(),
+        };
+
+        let functions = self.functions.iter().map(|(&entry, function)| {
+            let extent: Vec<String> = function.extent.iter().map(|r| format!("[{}, {}]", r.start, r.end)).collect();
+            format!(
+                "{{\"name\": {}, \"entry\": {}, \"address\": {}, \"source\": \"{}\", \"extent\": [{}], \"callers\": {}}}",
+                json_string(&self.name(entry, names)),
+                location(entry),
+                function.address,
+                function.source.name(),
+                extent.join(", "),
+                self.callers(entry).count()
+            )
+        });
+        let calls = self.calls.iter().map(|call| {
+            format!(
+                "{{\"caller\": {}, \"site\": {}, \"callee\": {}}}",
+                caller(call.caller),
+                location(call.site),
+                json_string(&self.name(call.callee, names))
+            )
+        });
+        let indirect = self
+            .indirect
+            .iter()
+            .map(|call| format!("{{\"caller\": {}, \"site\": {}}}", caller(call.caller), location(call.site)));
+        format!(
+            "{{\n  \"functions\": {},\n  \"calls\": {},\n  \"indirect\": {}\n}}\n",
+            json_array(functions),
+            json_array(calls),
+            json_array(indirect)
+        )
+    }
+}
+
+/// A JSON array with an item per line.
+fn json_array(items: impl Iterator<Item = String>) -> String {
+    let items: Vec<String> = items.map(|item| format!("\n    {item}")).collect();
+    if items.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[{}\n  ]", items.join(","))
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Find the functions in the code of an image and the calls between
+/// them. `known` adds entries the code can't show, such as project
+/// functions and library vectors.
+pub fn find_functions(
+    image: &Image,
+    cpu: CpuVariant,
+    code: &CodeMap,
+    known: &[(Location, FunctionSource)],
+) -> CallGraph {
+    let distinct = distinct_addresses(image);
+    let instructions = decode_code(image, cpu, code);
+    let is_start = |location: Location| {
+        instructions
+            .get(location.hunk)
+            .is_some_and(|insts| insts.contains_key(&location.offset))
+    };
+
+    let mut entries: BTreeMap<Location, FunctionSource> = BTreeMap::new();
+    let mut add = |location: Location, source: FunctionSource| {
+        if is_start(location) {
+            let best = entries.entry(location).or_insert(source);
+            *best = (*best).min(source);
+        }
+    };
+    for location in image.entry_points.iter().filter_map(|&a| image.locate(a)) {
+        add(location, FunctionSource::EntryPoint);
+    }
+    for &(location, source) in known {
+        add(location, source);
+    }
+    for segment in image.segments.iter().filter(|s| s.kind == SegmentKind::Code) {
+        for symbol in &segment.symbols {
+            add(Location::new(segment.index, symbol.value), FunctionSource::Symbol);
+        }
+    }
+    let mut sites = Vec::new();
+    for (segment, insts) in image.segments.iter().zip(&instructions) {
+        for (&offset, inst) in insts {
+            let site = Location::new(segment.index, offset);
+            if matches!(inst.mnemonic, Mnemonic::Bsr | Mnemonic::Jsr) {
+                let target = control_target(image, segment, inst, distinct);
+                if let Some(target) = target {
+                    add(target, FunctionSource::Call);
+                }
+                if target.is_some() || !is_library_call(inst) {
+                    sites.push((site, target));
+                }
+            }
+            let after_flow = insts
+                .range(..offset)
+                .next_back()
+                .is_none_or(|(&prev, inst)| prev + inst.size_bytes as u32 != offset || !falls_through(&inst.mnemonic));
+            if is_prologue(inst) && after_flow {
+                add(site, FunctionSource::Prologue);
+            }
+        }
+    }
+
+    let mut graph = CallGraph::default();
+    for (&entry, &source) in &entries {
+        let segment = &image.segments[entry.hunk];
+        let extent = function_extent(image, &instructions[entry.hunk], &entries, entry, code, distinct);
+        let function = Function {
+            entry,
+            address: segment.base_address.wrapping_add(entry.offset),
+            source,
+            extent,
+        };
+        graph.functions.insert(entry, function);
+    }
+    for (site, target) in sites {
+        let caller = graph.function_containing(site).map(|f| f.entry);
+        match target.filter(|t| graph.functions.contains_key(t)) {
+            Some(callee) => graph.calls.push(Call { caller, site, callee }),
+            None => graph.indirect.push(IndirectCall { caller, site }),
+        }
+    }
+    graph
+}
+
+/// Per segment: the instructions in the code ranges, by offset.
+pub(crate) fn decode_code(image: &Image, cpu: CpuVariant, code: &CodeMap) -> Vec<BTreeMap<u32, Instruction>> {
+    image
+        .segments
+        .iter()
+        .map(|segment| {
+            let mut insts = BTreeMap::new();
+            for range in code.ranges_in(segment.index) {
+                let data = &segment.data[..range.end.min(segment.data.len())];
+                let mut offset = range.start;
+                while offset < data.len() {
+                    match decode_instruction(data, offset, segment.base_address, cpu) {
+                        Ok(inst) => {
+                            let size = inst.size_bytes as usize;
+                            insts.insert(offset as u32, inst);
+                            offset += size;
+                        }
+                        Err(_) => offset += 2,
+                    }
+                }
+            }
+            insts
+        })
+        .collect()
+}
+
+/// The instructions reachable from `entry` without a call or entering
+/// another function, as offset ranges. Jumps through the jump tables in
+/// `code` reach each of their cases.
+fn function_extent(
+    image: &Image,
+    insts: &BTreeMap<u32, Instruction>,
+    entries: &BTreeMap<Location, FunctionSource>,
+    entry: Location,
+    code: &CodeMap,
+    distinct: bool,
+) -> Vec<Range<u32>> {
+    let segment = &image.segments[entry.hunk];
+    let mut seen: BTreeSet<u32> = BTreeSet::new();
+    let mut queue = vec![entry.offset];
+    while let Some(offset) = queue.pop() {
+        let location = Location::new(entry.hunk, offset);
+        if (location != entry && entries.contains_key(&location)) || !seen.insert(offset) {
+            continue;
+        }
+        let Some(inst) = insts.get(&offset) else {
+            seen.remove(&offset);
+            continue;
+        };
+        if !matches!(inst.mnemonic, Mnemonic::Bsr | Mnemonic::Jsr) {
+            let target = control_target(image, segment, inst, distinct);
+            let cases = code.jump_tables.get(&location).into_iter().flat_map(|t| &t.targets);
+            queue.extend(target.iter().chain(cases).filter(|t| t.hunk == entry.hunk).map(|t| t.offset));
+        }
+        if falls_through(&inst.mnemonic) {
+            queue.push(offset + inst.size_bytes as u32);
+        }
+    }
+
+    let mut extent: Vec<Range<u32>> = Vec::new();
+    for offset in seen {
+        let end = offset + insts[&offset].size_bytes as u32;
+        match extent.last_mut() {
+            Some(last) if last.end == offset => last.end = end,
+            _ => extent.push(offset..end),
+        }
+    }
+    extent
+}
+
+/// `link An,#d`, or `movem.l` of several registers to `-(sp)`.
+fn is_prologue(inst: &Instruction) -> bool {
+    match (&inst.mnemonic, &inst.operands[..]) {
+        (Mnemonic::Link, _) => true,
+        (Mnemonic::Movem, [Operand::RegisterList(mask), Operand::Ea(EffectiveAddress::AddressPreDecrement(7))]) => {
+            inst.size == Some(Size::Long) && mask.count_ones() > 1
+        }
+        _ => false,
+    }
+}
+
+/// `jsr -d16(An)`: a call through a library base, which the listing
+/// names from the LVO tables.
+fn is_library_call(inst: &Instruction) -> bool {
+    matches!(
+        inst.operands.first(),
+        Some(Operand::Ea(EffectiveAddress::AddressDisplacement(_, d))) if *d < 0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::discover_code;
+
+    fn graph(code: Vec<u8>) -> CallGraph {
+        let image = Image::test_code(code, vec![]);
+        let code = discover_code(&image, CpuVariant::M68000, &[], false);
+        find_functions(&image, CpuVariant::M68000, &code, &[])
+    }
+
+    #[test]
+    fn calls_and_extents() {
+        let graph = graph(vec![
+            0x61, 0x0A, // 0: bsr.s sub_000C
+            0x61, 0x08, // 2: bsr.s sub_000C
+            0x4E, 0x90, // 4: jsr (a0)
+            0x4E, 0xAE, 0xFF, 0xC4, // 6: jsr (-60,a6)
+            0x4E, 0x75, // A: rts
+            0x70, 0x00, // C: sub_000C: moveq #0,d0
+            0x67, 0x02, // E: beq.s $12
+            0x70, 0x01, // 10: moveq #1,d0
+            0x4E, 0x75, // 12: rts
+        ]);
+
+        let entry = Location::new(0, 0);
+        let sub = Location::new(0, 0x0C);
+        assert_eq!(graph.functions.keys().copied().collect::<Vec<_>>(), [entry, sub]);
+        assert_eq!(graph.functions[&entry].source, FunctionSource::EntryPoint);
+        assert_eq!(graph.functions[&entry].extent, vec![0..0x0C]);
+        assert_eq!(graph.functions[&sub].source, FunctionSource::Call);
+        assert_eq!(graph.functions[&sub].extent, vec![0x0C..0x14]);
+        assert_eq!(graph.functions[&sub].label(), "sub_000C");
+
+        assert_eq!(graph.callers(sub).count(), 2);
+        assert_eq!(graph.callees(entry).count(), 2);
+        assert_eq!(graph.indirect, [IndirectCall { caller: Some(entry), site: Location::new(0, 4) }]);
+        assert_eq!(graph.function_containing(Location::new(0, 0x10)).map(|f| f.entry), Some(sub));
+
+        let dot = graph.to_dot(None);
+        assert!(dot.contains("\"0:$0000\" -> \"0:$000C\" [label=\"2\"];"), "{dot}");
+        assert!(dot.contains("\"0:$0000\" -> \"?\" [style=dashed];"), "{dot}");
+        let json = graph.to_json(None);
+        assert!(json.contains("\"name\": \"sub_000C\", \"entry\": {\"hunk\": 0, \"offset\": 12}"), "{json}");
+        assert!(json.contains("\"source\": \"call\", \"extent\": [[12, 20]], \"callers\": 2"), "{json}");
+    }
+
+    #[test]
+    fn prologues_and_tail_calls() {
+        let graph = graph(vec![
+            0x60, 0x0A, // 0: bra.s $0C (tail call)
+            0x48, 0xE7, 0x30, 0x00, // 2: movem.l d2-d3,-(sp)
+            0x4C, 0xDF, 0x00, 0x0C, // 6: movem.l (sp)+,d2-d3
+            0x4E, 0x75, // A: rts
+            0x4E, 0x55, 0x00, 0x00, // C: link a5,#0
+            0x61, 0xF0, // 10: bsr.s $02
+            0x4E, 0x5D, // 12: unlk a5
+            0x4E, 0x75, // 14: rts
+        ]);
+
+        let sources: Vec<_> = graph.functions.values().map(|f| (f.entry.offset, f.source)).collect();
+        assert_eq!(
+            sources,
+            [
+                (0, FunctionSource::EntryPoint),
+                (2, FunctionSource::Call),
+                (0x0C, FunctionSource::Prologue),
+            ]
+        );
+        // The entry's bra to another function is a tail call
+        assert_eq!(graph.functions[&Location::new(0, 0)].extent, vec![0..2]);
+        assert_eq!(graph.functions[&Location::new(0, 0x0C)].size(), 10);
+    }
+}
@@ -0,0 +1,173 @@
+//! Hardware register usage.
+//!
+//! Counts the reads and writes the code of each segment makes to the
+//! registers in `symbols::hardware`, through absolute addresses or
+//! through address registers the base tracking pass found holding a
+//! chip's base, so a summary can show what hardware a program touches.
+
+use std::collections::BTreeMap;
+
+use crate::image::{Image, Location, SegmentKind};
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand};
+use crate::m68k::variants::CpuVariant;
+use crate::symbols::hardware;
+
+use super::bases::{track_library_bases, LibraryBases};
+use super::flow::CodeMap;
+
+/// Accesses to one hardware register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterUsage {
+    /// The chip the register belongs to (`custom`, `CIA-A`, ...).
+    pub chip: &'static str,
+    pub address: u32,
+    /// Register name, or `None` for an unnamed address in a chip's block.
+    pub name: Option<String>,
+    pub reads: usize,
+    pub writes: usize,
+}
+
+/// Hardware registers a program accesses, by address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HardwareUsage {
+    pub registers: BTreeMap<u32, RegisterUsage>,
+}
+
+impl HardwareUsage {
+    /// Total accesses per chip, in address order of the chips' first
+    /// registers.
+    pub fn chips(&self) -> Vec<(&'static str, usize)> {
+        let mut chips: Vec<(&'static str, usize)> = Vec::new();
+        for usage in self.registers.values() {
+            let count = usage.reads + usage.writes;
+            match chips.iter_mut().find(|(name, _)| *name == usage.chip) {
+                Some((_, total)) => *total += count,
+                None => chips.push((usage.chip, count)),
+            }
+        }
+        chips
+    }
+}
+
+/// Count the hardware register accesses in the code ranges of `code`.
+pub fn hardware_usage(image: &Image, cpu: CpuVariant, code: &CodeMap) -> HardwareUsage {
+    let bases = track_library_bases(image, cpu, code, &[]);
+    let mut usage = HardwareUsage::default();
+
+    for segment in image.segments.iter().filter(|s| s.kind == SegmentKind::Code) {
+        for range in code.ranges_in(segment.index) {
+            let data = &segment.data[..range.end.min(segment.data.len())];
+            let mut offset = range.start;
+            while offset < data.len() {
+                match decode_instruction(data, offset, segment.base_address, cpu) {
+                    Ok(inst) => {
+                        offset += inst.size_bytes as usize;
+                        let location = Location::new(segment.index, inst.address.wrapping_sub(segment.base_address));
+                        record_accesses(&inst, &bases, location, &mut usage);
+                    }
+                    Err(_) => offset += 2,
+                }
+            }
+        }
+    }
+
+    usage
+}
+
+fn record_accesses(
+    inst: &Instruction,
+    bases: &LibraryBases,
+    location: Location,
+    usage: &mut HardwareUsage,
+) {
+    // These compute an address or transfer control without touching memory
+    if matches!(
+        inst.mnemonic,
+        Mnemonic::Lea | Mnemonic::Pea | Mnemonic::Jmp | Mnemonic::Jsr
+    ) {
+        return;
+    }
+    let base_of = |register: u8| {
+        bases
+            .absolute_bases
+            .get(&location)?
+            .iter()
+            .find(|(r, _)| *r == register)
+            .map(|(_, base)| *base)
+    };
+
+    let last = inst.operands.len().saturating_sub(1);
+    for (i, op) in inst.operands.iter().enumerate() {
+        let Operand::Ea(ea) = op else { continue };
+        let address = match ea {
+            EffectiveAddress::AbsoluteLong(a) => *a,
+            EffectiveAddress::AddressIndirect(r) => match base_of(*r) {
+                Some(base) => base,
+                None => continue,
+            },
+            EffectiveAddress::AddressDisplacement(r, d) => match base_of(*r) {
+                Some(base) => base.wrapping_add(*d as i32 as u32),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let Some(chip) = hardware::chip_at(address) else { continue };
+
+        let entry = usage.registers.entry(address).or_insert_with(|| RegisterUsage {
+            chip: chip.name,
+            address,
+            name: hardware::register_name(address),
+            reads: 0,
+            writes: 0,
+        });
+        if i == last && writes_destination(&inst.mnemonic) {
+            entry.writes += 1;
+        } else {
+            entry.reads += 1;
+        }
+    }
+}
+
+/// Whether an instruction writes its last operand.
+pub(crate) fn writes_destination(mnemonic: &Mnemonic) -> bool {
+    !matches!(
+        mnemonic,
+        Mnemonic::Tst
+            | Mnemonic::Cmp
+            | Mnemonic::Cmpa
+            | Mnemonic::Cmpi
+            | Mnemonic::Cmpm
+            | Mnemonic::Btst
+            | Mnemonic::Chk
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::discover_code;
+
+    #[test]
+    fn counts_reads_and_writes() {
+        let code = vec![
+            0x08, 0x39, 0x00, 0x07, 0x00, 0xBF, 0xE0, 0x01, // btst #7,$BFE001
+            0x4B, 0xF9, 0x00, 0xDF, 0xF0, 0x00, // lea $DFF000,a5
+            0x3B, 0x7C, 0x83, 0x80, 0x00, 0x96, // move.w #$8380,($96,a5)
+            0x3B, 0x7C, 0x00, 0x20, 0x00, 0x96, // move.w #$0020,($96,a5)
+            0x4E, 0x75, // rts
+        ];
+        let image = Image::test_code(code, vec![]);
+
+        let code = discover_code(&image, CpuVariant::M68000, &[], false);
+        let usage = hardware_usage(&image, CpuVariant::M68000, &code);
+        let dmacon = &usage.registers[&0xDFF096];
+        assert_eq!(dmacon.name.as_deref(), Some("DMACON"));
+        assert_eq!((dmacon.reads, dmacon.writes), (0, 2));
+        let pra = &usage.registers[&0xBFE001];
+        assert_eq!((pra.chip, pra.reads, pra.writes), ("CIA-A", 1, 0));
+        assert!(!usage.registers.contains_key(&0xDFF000));
+        assert_eq!(usage.chips(), vec![("CIA-A", 1), ("custom", 2)]);
+    }
+}
@@ -0,0 +1,398 @@
+//! Jump tables behind `switch` statements and hand-written dispatchers.
+//!
+//! A jump through a table is an indirect `jmp` whose target is computed
+//! from an index register, so following control flow stops there. The
+//! idioms recognised, with d0 as the index already scaled to the entry
+//! size:
+//!
+//! - `move.w tab(pc,d0.w),d0; jmp base(pc,d0.w)`: words added to
+//!   `base`. Hand-written code jumps from `tab` itself; SAS/C and gcc
+//!   jump from `tab+2`.
+//! - `lea tab(pc),a0; move.w (a0,d0.w),d0; jmp (a0,d0.w)`, and DICE's
+//!   `lea tab(pc),a0; adda.w (a0,d0.w),a0; jmp (a0)`: words added to
+//!   `tab`.
+//! - `movea.l tab(pc,d0.w),a0; jmp (a0)` and the 68020
+//!   `jmp ([tab,pc,d0.w*4])`: longword addresses.
+//! - `jmp tab(pc,d0.w)` after scaling d0, into a run of `bra`s.
+//!
+//! A `cmp #n,d0` and a conditional branch before the dispatch bound the
+//! table to n entries, or n+1 for `bhi`, `bls`, `bgt` and `ble`.
+//! Without a bound, the table ends at its first invalid entry or where
+//! the code of its first target starts.
+
+use std::ops::Range;
+
+use crate::image::{Image, Location, Segment, SegmentKind};
+use crate::m68k::addressing::{EffectiveAddress, IndexRegister};
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Condition, Instruction, Mnemonic, Operand, Size};
+use crate::m68k::variants::CpuVariant;
+
+use super::flow::distinct_addresses;
+use super::hardware::writes_destination;
+
+/// Entries in a table without a bounds check are not read past this.
+const MAX_UNBOUNDED: usize = 256;
+/// A bounds check larger than this is taken to guard something else.
+const MAX_BOUNDED: u32 = 1024;
+
+/// What a jump table holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    /// Signed words, each added to the table's base.
+    Offsets,
+    /// Longword addresses.
+    Pointers,
+    /// `bra` instructions, jumped into.
+    Branches,
+}
+
+/// A table an indirect `jmp` dispatches through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpTable {
+    /// The `jmp`.
+    pub jump: Location,
+    pub table: Location,
+    pub kind: TableKind,
+    /// What the entries of an offset table are added to; the table
+    /// itself for the other kinds.
+    pub base: Location,
+    /// The bytes each entry takes.
+    pub entry_size: u32,
+    /// Where each entry leads, in table order. For a branch table,
+    /// these are the `bra`s themselves.
+    pub targets: Vec<Location>,
+    /// Whether a bounds check gave the number of entries.
+    pub bounded: bool,
+}
+
+impl JumpTable {
+    /// The bytes the table takes in its segment.
+    pub fn range(&self) -> Range<u32> {
+        let start = self.table.offset;
+        start..start + self.entry_size * self.targets.len() as u32
+    }
+
+    /// The location of entry `index`.
+    pub fn entry(&self, index: usize) -> Location {
+        Location::new(self.table.hunk, self.table.offset + self.entry_size * index as u32)
+    }
+}
+
+/// How a dispatch finds its target, before the table is read.
+struct Dispatch {
+    kind: TableKind,
+    table: u32,
+    base: u32,
+    index: IndexRegister,
+}
+
+/// Recognise a jump through a table at `jump`, an indirect `jmp` in
+/// `segment` that `preceding` (in order) run straight into.
+pub(crate) fn find_jump_table(
+    image: &Image,
+    segment: &Segment,
+    preceding: &[Instruction],
+    jump: &Instruction,
+    cpu: CpuVariant,
+) -> Option<JumpTable> {
+    if jump.mnemonic != Mnemonic::Jmp {
+        return None;
+    }
+    let Some(Operand::Ea(ea)) = jump.operands.first() else {
+        return None;
+    };
+    let dispatch = dispatch(jump, ea, preceding)?;
+    let at = |address: u32| Location::new(segment.index, address.wrapping_sub(segment.base_address));
+    let count = bound(preceding, dispatch.index);
+    let limit = count.map_or(MAX_UNBOUNDED, |n| n as usize);
+
+    let table = at(dispatch.table);
+    let base = at(dispatch.base);
+    let code_at = |location: Location| {
+        let segment = image.segments.get(location.hunk)?;
+        let valid = segment.kind == SegmentKind::Code
+            && location.offset.is_multiple_of(2)
+            && (location.offset as usize) < segment.data.len();
+        valid.then_some(location)
+    };
+
+    let mut targets = Vec::new();
+    let mut entry_size = match dispatch.kind {
+        TableKind::Offsets => 2,
+        _ => 4,
+    };
+    // Without a bound, stop where the code of the nearest target starts
+    let mut end = u32::MAX;
+    while targets.len() < limit {
+        let offset = table.offset + entry_size * targets.len() as u32;
+        if count.is_none() && offset >= end {
+            break;
+        }
+        let target = match dispatch.kind {
+            TableKind::Offsets => segment
+                .read_u16(offset)
+                .and_then(|value| code_at(Location::new(base.hunk, base.offset.wrapping_add(value as i16 as u32)))),
+            TableKind::Pointers => pointer_at(image, segment, offset).and_then(code_at),
+            TableKind::Branches => {
+                let inst = decode_instruction(&segment.data, offset as usize, segment.base_address, cpu).ok();
+                let inst = inst.filter(|inst| matches!(inst.mnemonic, Mnemonic::Bra | Mnemonic::Jmp));
+                match inst {
+                    Some(inst) if targets.is_empty() => {
+                        entry_size = inst.size_bytes as u32;
+                        Some(Location::new(segment.index, offset))
+                    }
+                    Some(inst) if inst.size_bytes as u32 == entry_size => Some(Location::new(segment.index, offset)),
+                    _ => None,
+                }
+            }
+        };
+        let Some(target) = target else { break };
+        if target.hunk == table.hunk && target.offset > table.offset {
+            end = end.min(target.offset);
+        }
+        targets.push(target);
+    }
+    if targets.is_empty() {
+        return None;
+    }
+
+    Some(JumpTable {
+        jump: at(jump.address),
+        table,
+        kind: dispatch.kind,
+        base,
+        entry_size,
+        targets,
+        bounded: count.is_some(),
+    })
+}
+
+/// Match the instructions that load the index and jump.
+fn dispatch(jump: &Instruction, ea: &EffectiveAddress, preceding: &[Instruction]) -> Option<Dispatch> {
+    match *ea {
+        // jmp base(pc,d0.w)
+        EffectiveAddress::PcIndex { .. } | EffectiveAddress::PcBaseDisplacement { .. } => {
+            let (base, register) = pc_indexed(jump, ea)?;
+            let (i, load) = last_write(preceding, register)?;
+            if let Some((table, index)) = word_load(load, &preceding[..i]) {
+                return Some(Dispatch { kind: TableKind::Offsets, table, base, index });
+            }
+            // Straight into a run of branches, after scaling the index
+            let scaled = matches!(load.mnemonic, Mnemonic::Add | Mnemonic::Asl | Mnemonic::Lsl);
+            scaled.then_some(Dispatch { kind: TableKind::Branches, table: base, base, index: register })
+        }
+        // jmp (d,a0,d0.w) after lea tab(pc),a0
+        EffectiveAddress::AddressIndex { reg, index_reg, displacement, .. } => {
+            let (i, load) = last_write(preceding, index_reg)?;
+            let (table, index) = word_load(load, &preceding[..i])?;
+            let start = lea_target(preceding, reg)?;
+            let base = start.wrapping_add(displacement as i32 as u32);
+            Some(Dispatch { kind: TableKind::Offsets, table, base, index })
+        }
+        // jmp (a0) after movea.l tab(pc,d0.w),a0 or adda.w (a0,d0.w),a0
+        EffectiveAddress::AddressIndirect(reg) | EffectiveAddress::AddressDisplacement(reg, 0) => {
+            let (i, load) = last_write(preceding, IndexRegister::Address(reg))?;
+            let Some(Operand::Ea(source)) = load.operands.first() else {
+                return None;
+            };
+            match (&load.mnemonic, load.size) {
+                (Mnemonic::Move | Mnemonic::Movea, Some(Size::Long)) => {
+                    let (table, index) = table_operand(load, source, &preceding[..i])?;
+                    Some(Dispatch { kind: TableKind::Pointers, table, base: table, index })
+                }
+                (Mnemonic::Adda, Some(Size::Word)) => {
+                    let (table, index) = table_operand(load, source, &preceding[..i])?;
+                    let base = lea_target(&preceding[..i], reg)?;
+                    Some(Dispatch { kind: TableKind::Offsets, table, base, index })
+                }
+                _ => None,
+            }
+        }
+        // jmp ([tab,pc,d0.w*4])
+        EffectiveAddress::PcMemoryIndirectPre { base_disp, outer_disp: 0, index_reg: Some(index), .. } => {
+            let table = jump.address.wrapping_add(2).wrapping_add(base_disp as u32);
+            Some(Dispatch { kind: TableKind::Pointers, table, base: table, index })
+        }
+        _ => None,
+    }
+}
+
+/// `move.w tab(pc,d0.w),d0`, or `move.w (a0,d0.w),d0` after
+/// `lea tab(pc),a0`: the table's address and index register.
+fn word_load(load: &Instruction, before: &[Instruction]) -> Option<(u32, IndexRegister)> {
+    if load.mnemonic != Mnemonic::Move || load.size != Some(Size::Word) {
+        return None;
+    }
+    let Some(Operand::Ea(source)) = load.operands.first() else {
+        return None;
+    };
+    table_operand(load, source, before)
+}
+
+/// The table an indexed source operand reads: PC-relative, or relative
+/// to an address register loaded by `lea tab(pc)`.
+fn table_operand(inst: &Instruction, ea: &EffectiveAddress, before: &[Instruction]) -> Option<(u32, IndexRegister)> {
+    match *ea {
+        EffectiveAddress::AddressIndex { reg, index_reg, displacement, .. } => {
+            let start = lea_target(before, reg)?;
+            Some((start.wrapping_add(displacement as i32 as u32), index_reg))
+        }
+        _ => pc_indexed(inst, ea),
+    }
+}
+
+/// The address and index register of a PC-relative indexed operand.
+fn pc_indexed(inst: &Instruction, ea: &EffectiveAddress) -> Option<(u32, IndexRegister)> {
+    let pc = inst.address.wrapping_add(2);
+    match *ea {
+        EffectiveAddress::PcIndex { index_reg, displacement, .. } => {
+            Some((pc.wrapping_add(displacement as i32 as u32), index_reg))
+        }
+        EffectiveAddress::PcBaseDisplacement { base_disp, index_reg: Some(index_reg), .. } => {
+            Some((pc.wrapping_add(base_disp as u32), index_reg))
+        }
+        _ => None,
+    }
+}
+
+/// The address `lea tab(pc),An` last put in An.
+fn lea_target(before: &[Instruction], reg: u8) -> Option<u32> {
+    let (_, inst) = last_write(before, IndexRegister::Address(reg))?;
+    match (&inst.mnemonic, inst.operands.first()) {
+        (Mnemonic::Lea, Some(Operand::Ea(EffectiveAddress::PcDisplacement(d)))) => {
+            Some(inst.address.wrapping_add(2).wrapping_add(*d as i32 as u32))
+        }
+        _ => None,
+    }
+}
+
+/// The last of `insts` that writes `register`, and its index.
+fn last_write(insts: &[Instruction], register: IndexRegister) -> Option<(usize, &Instruction)> {
+    let destination = match register {
+        IndexRegister::Data(r) => EffectiveAddress::DataDirect(r),
+        IndexRegister::Address(r) => EffectiveAddress::AddressDirect(r),
+    };
+    insts.iter().enumerate().rev().find(|(_, inst)| {
+        writes_destination(&inst.mnemonic) && inst.operands.last() == Some(&Operand::Ea(destination.clone()))
+    })
+}
+
+/// The number of entries a bounds check on `index` allows: the
+/// `cmp #n` (or `cmp` with a register set by `moveq #n`) before a
+/// conditional branch.
+fn bound(preceding: &[Instruction], index: IndexRegister) -> Option<u32> {
+    let IndexRegister::Data(register) = index else {
+        return None;
+    };
+    let branch = preceding.iter().rposition(|inst| inst.mnemonic == Mnemonic::Bcc)?;
+    let extra = match preceding[branch].condition? {
+        Condition::Cc | Condition::Cs | Condition::Ge | Condition::Lt => 0,
+        Condition::Hi | Condition::Ls | Condition::Gt | Condition::Le => 1,
+        _ => return None,
+    };
+    let compare = preceding[..branch].last()?;
+    if !matches!(compare.mnemonic, Mnemonic::Cmp | Mnemonic::Cmpi) {
+        return None;
+    }
+    let limit = match compare.operands[..] {
+        [Operand::Ea(EffectiveAddress::Immediate(n)), Operand::Ea(EffectiveAddress::DataDirect(r))] if r == register => n,
+        [Operand::Ea(EffectiveAddress::DataDirect(k)), Operand::Ea(EffectiveAddress::DataDirect(r))] if r == register => {
+            let (_, set) = last_write(&preceding[..branch - 1], IndexRegister::Data(k))?;
+            match set.operands.first() {
+                Some(Operand::MoveqImmediate(n)) if set.mnemonic == Mnemonic::Moveq => *n as i32 as u32,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let count = limit.checked_add(extra)?;
+    (1..=MAX_BOUNDED).contains(&count).then_some(count)
+}
+
+/// Where the longword at `offset` in `segment` points: by its fixup, or
+/// for images at real addresses by its value.
+fn pointer_at(image: &Image, segment: &Segment, offset: u32) -> Option<Location> {
+    match segment.fixups.iter().find(|fixup| fixup.offset == offset) {
+        Some(fixup) => image.fixup_location(segment, fixup),
+        None if distinct_addresses(image) => image.locate(segment.read_u32(offset)?),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::flow::discover_code;
+    use crate::image::Fixup;
+
+    #[test]
+    fn bounded_word_offsets() {
+        // SAS/C: the offsets are from the table plus 2
+        let code = vec![
+            0x0C, 0x40, 0x00, 0x03, // 0: cmpi.w #3,d0
+            0x64, 0x14, // 4: bcc.s $1A
+            0xD0, 0x40, // 6: add.w d0,d0
+            0x30, 0x3B, 0x00, 0x06, // 8: move.w (6,pc,d0.w),d0 -- $10
+            0x4E, 0xFB, 0x00, 0x04, // C: jmp (4,pc,d0.w) -- from $12
+            0x00, 0x06, 0x00, 0x08, 0x00, 0x06, // 10: $18, $1A, $18
+            0x4E, 0x71, // 16: nop -- unreached
+            0x4E, 0x71, // 18: nop
+            0x4E, 0x75, // 1A: rts
+        ];
+        let image = Image::test_code(code, vec![]);
+        let code = discover_code(&image, CpuVariant::M68000, &[], false);
+
+        let table = &code.jump_tables[&Location::new(0, 0xC)];
+        assert_eq!(table.kind, TableKind::Offsets);
+        assert_eq!((table.table.offset, table.base.offset), (0x10, 0x12));
+        assert!(table.bounded);
+        let targets: Vec<u32> = table.targets.iter().map(|t| t.offset).collect();
+        assert_eq!(targets, [0x18, 0x1A, 0x18]);
+        assert_eq!(table.range(), 0x10..0x16);
+        assert_eq!(code.ranges_in(0), [0..0x10, 0x18..0x1C]);
+    }
+
+    #[test]
+    fn unbounded_offsets_from_lea() {
+        // DICE: lea tab(pc),a0; adda.w (a0,d0.w),a0; jmp (a0)
+        let code = vec![
+            0xD0, 0x40, // 0: add.w d0,d0
+            0x41, 0xFA, 0x00, 0x08, // 2: lea ($C,pc),a0
+            0xD0, 0xF0, 0x00, 0x00, // 6: adda.w (0,a0,d0.w),a0
+            0x4E, 0xD0, // A: jmp (a0)
+            0x00, 0x04, 0x00, 0x06, // C: $10, $12
+            0x70, 0x01, // 10: moveq #1,d0
+            0x4E, 0x75, // 12: rts
+        ];
+        let image = Image::test_code(code, vec![]);
+        let code = discover_code(&image, CpuVariant::M68000, &[], false);
+
+        let table = &code.jump_tables[&Location::new(0, 0xA)];
+        assert!(!table.bounded);
+        assert_eq!(table.base, table.table);
+        let targets: Vec<u32> = table.targets.iter().map(|t| t.offset).collect();
+        assert_eq!(targets, [0x10, 0x12]);
+        assert_eq!(code.ranges_in(0), [0..0xC, 0x10..0x14]);
+    }
+
+    #[test]
+    fn memory_indirect_pointers() {
+        // cmpi.w #2,d0; bcc.s $14; jmp ([$C,pc,d0.w*4])
+        let code = vec![
+            0x0C, 0x40, 0x00, 0x02, // 0: cmpi.w #2,d0
+            0x64, 0x0E, // 4: bcc.s $14
+            0x4E, 0xFB, 0x05, 0x21, 0x00, 0x04, // 6: jmp ([4,pc,d0.w*4]) -- $C
+            0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x16, // C: $14, $16
+            0x4E, 0x75, 0x4E, 0x75, // 14: rts; 16: rts
+        ];
+        let fixups = vec![Fixup { offset: 0xC, target: 0 }, Fixup { offset: 0x10, target: 0 }];
+        let image = Image::test_code(code, fixups);
+        let code = discover_code(&image, CpuVariant::M68020, &[], false);
+
+        let table = &code.jump_tables[&Location::new(0, 6)];
+        assert_eq!(table.kind, TableKind::Pointers);
+        assert_eq!(table.table.offset, 0xC);
+        assert_eq!(table.targets, [Location::new(0, 0x14), Location::new(0, 0x16)]);
+    }
+}
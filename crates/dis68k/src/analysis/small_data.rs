@@ -0,0 +1,250 @@
+//! Small-data (base-relative) addressing.
+//!
+//! SAS/C, vbcc and gcc `-fbaserel` programs keep a pointer into their
+//! merged data hunk in A4 and address every global as `d16(a4)`, or
+//! `(bd,a4)` on the 68020. The linker's `_LinkerDB` symbol marks the
+//! base, $7FFE into the `__MERGED` hunk so that signed 16-bit
+//! displacements reach 64K of data. The startup code loads it with
+//! `lea _LinkerDB,a4`.
+//!
+//! This pass finds the base, so the listing can name each A4-relative
+//! operand after the data it reaches. Base tracking (see `bases`) tells
+//! which instructions still have the base in A4.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::image::{Image, Location, Segment, SegmentKind};
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Mnemonic, Operand};
+use crate::m68k::variants::CpuVariant;
+
+use super::bases::LibraryBases;
+
+/// The small-data base register.
+pub const BASE_REGISTER: u8 = 4;
+
+/// The linker symbol marking the small-data base.
+const LINKER_DB: &str = "_LinkerDB";
+
+/// The name of the hunk the linker merges small data into.
+const MERGED_HUNK: &str = "__MERGED";
+
+/// Offset of `_LinkerDB` in the `__MERGED` hunk.
+const MERGED_BASE: u32 = 0x7FFE;
+
+/// Where A4 points in a small-data program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmallData {
+    pub base: Location,
+}
+
+/// Find the small-data base of a program: the `_LinkerDB` symbol, else
+/// a relocated `lea xxx,a4` in the entry block, else $7FFE into a hunk
+/// named `__MERGED`.
+pub fn find_small_data(image: &Image, cpu: CpuVariant) -> Option<SmallData> {
+    let symbol = image.segments.iter().find_map(|segment| {
+        let sym = segment.symbols.iter().find(|sym| sym.name == LINKER_DB)?;
+        Some(Location::new(segment.index, sym.value))
+    });
+    let base = symbol
+        .or_else(|| startup_base(image, cpu))
+        .or_else(|| {
+            let merged = image
+                .segments
+                .iter()
+                .find(|s| s.name.as_deref() == Some(MERGED_HUNK))?;
+            Some(Location::new(merged.index, MERGED_BASE))
+        })?;
+    Some(SmallData { base })
+}
+
+/// The target of a relocated `lea xxx.l,a4` in the entry block: the
+/// instructions from the program entry up to the first branch, call or
+/// jump. Anything else written to A4 first rules it out.
+fn startup_base(image: &Image, cpu: CpuVariant) -> Option<Location> {
+    let entry = image.locate(*image.entry_points.first()?)?;
+    let segment = image.segments.get(entry.hunk).filter(|s| s.kind == SegmentKind::Code)?;
+    let a4 = Operand::Ea(EffectiveAddress::AddressDirect(BASE_REGISTER));
+    let mut offset = entry.offset as usize;
+    loop {
+        let inst = decode_instruction(&segment.data, offset, segment.base_address, cpu).ok()?;
+        match (&inst.mnemonic, &inst.operands[..]) {
+            (Mnemonic::Lea, [Operand::Ea(EffectiveAddress::AbsoluteLong(_)), dest]) if *dest == a4 => {
+                let fixup = segment.fixups.iter().find(|f| f.offset == offset as u32 + 2)?;
+                return image.fixup_location(segment, fixup);
+            }
+            (_, operands) if operands.contains(&a4) => return None,
+            (
+                Mnemonic::Bra
+                | Mnemonic::Bsr
+                | Mnemonic::Bcc
+                | Mnemonic::Dbcc
+                | Mnemonic::Jmp
+                | Mnemonic::Jsr
+                | Mnemonic::Rts
+                | Mnemonic::Rte
+                | Mnemonic::Rtr,
+                _,
+            ) => return None,
+            _ => offset += inst.size_bytes as usize,
+        }
+    }
+}
+
+impl SmallData {
+    /// The location `displacement` bytes from the base, if it lies
+    /// within the base's segment.
+    pub fn target(&self, image: &Image, displacement: i32) -> Option<Location> {
+        let segment = image.segments.get(self.base.hunk)?;
+        let offset = i64::from(self.base.offset) + i64::from(displacement);
+        let offset = u32::try_from(offset).ok().filter(|&o| o < segment.size)?;
+        Some(Location::new(self.base.hunk, offset))
+    }
+
+    /// Whether base tracking found the base still in A4 at the
+    /// instruction at `location`.
+    pub fn holds_base(bases: &LibraryBases, location: Location) -> bool {
+        bases.small_data_uses.contains(&location)
+    }
+
+    /// The targets of the base-relative operands in `range` of a code
+    /// segment.
+    pub fn collect_references(
+        &self,
+        image: &Image,
+        segment: &Segment,
+        range: Range<usize>,
+        cpu: CpuVariant,
+        bases: &LibraryBases,
+    ) -> BTreeSet<Location> {
+        let mut targets = BTreeSet::new();
+        let code = &segment.data[..range.end.min(segment.data.len())];
+        let mut offset = range.start;
+
+        while offset < code.len() {
+            match decode_instruction(code, offset, segment.base_address, cpu) {
+                Ok(inst) => {
+                    let location = Location::new(segment.index, offset as u32);
+                    if Self::holds_base(bases, location) {
+                        let displacements = inst.operands.iter().filter_map(|op| match op {
+                            Operand::Ea(ea) => displacement(ea),
+                            _ => None,
+                        });
+                        targets.extend(displacements.filter_map(|d| self.target(image, d)));
+                    }
+                    offset += inst.size_bytes as usize;
+                }
+                Err(_) => offset += 2.min(code.len() - offset),
+            }
+        }
+        targets
+    }
+}
+
+/// The displacement of a base-relative operand: `d16(a4)`, or
+/// `(bd,a4)` and `(bd,a4,Xn)` on the 68020.
+pub fn displacement(ea: &EffectiveAddress) -> Option<i32> {
+    match *ea {
+        EffectiveAddress::AddressDisplacement(BASE_REGISTER, disp) => Some(i32::from(disp)),
+        EffectiveAddress::AddressBaseDisplacement {
+            reg: BASE_REGISTER,
+            base_disp,
+            ..
+        } => Some(base_disp),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{discover_code, track_library_bases};
+    use crate::hunk::types::Symbol;
+    use crate::image::Fixup;
+
+    fn program(code: Vec<u8>, fixups: Vec<Fixup>) -> Image {
+        Image::test_code(code, fixups)
+            .sized(0x100)
+            .with_segment(SegmentKind::Data, vec![0; 0x20], vec![])
+            .sized(0x10000)
+    }
+
+    fn tracked(image: &Image) -> LibraryBases {
+        let code = discover_code(image, CpuVariant::M68000, &[], false);
+        track_library_bases(image, CpuVariant::M68000, &code, &[])
+    }
+
+    #[test]
+    fn startup_lea() {
+        let code = vec![
+            0x49, 0xF9, 0x00, 0x00, 0x7F, 0xFE, // lea $7FFE,a4 -> hunk 1
+            0x20, 0x2C, 0x80, 0x06, // move.l (-32762,a4),d0
+            0x4E, 0x75, // rts
+        ];
+        let image = program(code.clone(), vec![Fixup { offset: 2, target: 1 }]);
+        let bases = tracked(&image);
+        let small_data = find_small_data(&image, CpuVariant::M68000).unwrap();
+        assert_eq!(small_data.base, Location::new(1, 0x7FFE));
+        assert_eq!(small_data.target(&image, -0x7FFA), Some(Location::new(1, 4)));
+        assert_eq!(small_data.target(&image, -0x7FFF), None);
+
+        let references = small_data.collect_references(
+            &image,
+            &image.segments[0],
+            0..12,
+            CpuVariant::M68000,
+            &bases,
+        );
+        assert_eq!(references.into_iter().collect::<Vec<_>>(), [Location::new(1, 4)]);
+
+        // An unrelocated lea is not a base
+        assert_eq!(find_small_data(&program(code, vec![]), CpuVariant::M68000), None);
+    }
+
+    #[test]
+    fn base_is_lost_when_a4_is_written() {
+        let code = vec![
+            0x49, 0xF9, 0x00, 0x00, 0x00, 0x10, // lea $10,a4 -> hunk 1
+            0x28, 0x54, // movea.l (a4),a4
+            0x20, 0x2C, 0x00, 0x04, // move.l (4,a4),d0
+            0x4E, 0x75, // rts
+        ];
+        let image = program(code, vec![Fixup { offset: 2, target: 1 }]);
+        let bases = tracked(&image);
+        let small_data = find_small_data(&image, CpuVariant::M68000).unwrap();
+        assert_eq!(small_data.base, Location::new(1, 0x10));
+        assert!(!SmallData::holds_base(&bases, Location::new(0, 8)));
+        let references =
+            small_data.collect_references(&image, &image.segments[0], 0..14, CpuVariant::M68000, &bases);
+        assert!(references.is_empty());
+    }
+
+    #[test]
+    fn startup_lea_must_be_in_the_entry_block() {
+        let code = vec![
+            0x4E, 0x71, // nop
+            0x60, 0x00, 0x00, 0x02, // bra.w next
+            0x49, 0xF9, 0x00, 0x00, 0x7F, 0xFE, // next: lea $7FFE,a4 -> hunk 1
+            0x4E, 0x75, // rts
+        ];
+        let image = program(code, vec![Fixup { offset: 8, target: 1 }]);
+        assert_eq!(find_small_data(&image, CpuVariant::M68000), None);
+    }
+
+    #[test]
+    fn linker_symbol_and_merged_hunk() {
+        let mut image = program(vec![0x4E, 0x75], vec![]);
+        image.segments[1].name = Some(MERGED_HUNK.to_string());
+        let base = find_small_data(&image, CpuVariant::M68000).map(|s| s.base);
+        assert_eq!(base, Some(Location::new(1, MERGED_BASE)));
+
+        image.segments[1].symbols.push(Symbol {
+            name: LINKER_DB.to_string(),
+            value: 0x10,
+        });
+        let base = find_small_data(&image, CpuVariant::M68000).map(|s| s.base);
+        assert_eq!(base, Some(Location::new(1, 0x10)));
+    }
+}
@@ -0,0 +1,263 @@
+//! The cross-reference index: who refers to each location.
+//!
+//! Code refers to code by branches, jumps (to each case of a jump
+//! table) and calls, and to data by the operands it reads, writes or
+//! takes the address of: relocated absolute addresses, PC-relative
+//! operands and, in a small-data program, A4-relative ones. Data refers
+//! to code and data by the relocated pointers it holds.
+
+use std::collections::BTreeMap;
+
+use crate::image::{Image, Location, Segment};
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand};
+use crate::m68k::variants::CpuVariant;
+
+use super::bases::LibraryBases;
+use super::flow::{absolute_target, control_target, distinct_addresses, CodeMap};
+use super::functions::decode_code;
+use super::hardware::writes_destination;
+use super::small_data::{self, SmallData};
+
+/// How a reference uses its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum XrefKind {
+    /// A `bsr` or `jsr`.
+    Call,
+    /// A `bra` or `jmp`.
+    Jump,
+    /// A conditional branch or `dbcc`.
+    Branch,
+    /// An operand that is read.
+    Read,
+    /// An operand that is written.
+    Write,
+    /// An operand that is read and written back, such as by `addq`.
+    ReadWrite,
+    /// The address taken by `lea` or `pea`.
+    Address,
+    /// A relocated pointer in data.
+    Pointer,
+}
+
+impl XrefKind {
+    /// The short name shown in listings.
+    pub fn name(self) -> &'static str {
+        match self {
+            XrefKind::Call => "call",
+            XrefKind::Jump => "jump",
+            XrefKind::Branch => "branch",
+            XrefKind::Read => "r",
+            XrefKind::Write => "w",
+            XrefKind::ReadWrite => "rw",
+            XrefKind::Address => "addr",
+            XrefKind::Pointer => "ptr",
+        }
+    }
+}
+
+/// A reference from an instruction or a data pointer to a location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Xref {
+    /// The instruction, or the relocated longword.
+    pub from: Location,
+    pub to: Location,
+    pub kind: XrefKind,
+}
+
+/// Every reference found in an image, by target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XrefIndex {
+    /// Per target, the references to it sorted by source.
+    pub refs: BTreeMap<Location, Vec<Xref>>,
+}
+
+impl XrefIndex {
+    /// The references to `location`.
+    pub fn to(&self, location: Location) -> &[Xref] {
+        self.refs.get(&location).map_or(&[], Vec::as_slice)
+    }
+
+    /// The references made by the instruction or pointer at `location`.
+    pub fn from(&self, location: Location) -> impl Iterator<Item = &Xref> + '_ {
+        self.refs.values().flatten().filter(move |xref| xref.from == location)
+    }
+
+    /// The referenced locations in segment `segment`, with their
+    /// references.
+    pub fn targets_in(&self, segment: usize) -> impl Iterator<Item = (&Location, &Vec<Xref>)> + '_ {
+        self.refs
+            .range(Location::new(segment, 0)..=Location::new(segment, u32::MAX))
+    }
+
+    /// The number of references.
+    pub fn len(&self) -> usize {
+        self.refs.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty()
+    }
+
+    fn add(&mut self, from: Location, to: Location, kind: XrefKind) {
+        self.refs.entry(to).or_default().push(Xref { from, to, kind });
+    }
+}
+
+/// Build the cross-reference index of the code in `code` and the
+/// relocated pointers outside it.
+///
+/// A4-relative operands count as references into the small-data area
+/// where `bases` does not show A4 reloaded with something else.
+pub fn build_xrefs(
+    image: &Image,
+    cpu: CpuVariant,
+    code: &CodeMap,
+    small_data: Option<SmallData>,
+    bases: &LibraryBases,
+) -> XrefIndex {
+    let distinct = distinct_addresses(image);
+    let mut index = XrefIndex::default();
+    let within = |location: Location| {
+        image
+            .segments
+            .get(location.hunk)
+            .is_some_and(|segment| location.offset < segment.size)
+    };
+
+    for (segment, insts) in image.segments.iter().zip(decode_code(image, cpu, code)) {
+        for (offset, inst) in insts {
+            let from = Location::new(segment.index, offset);
+            let control = match inst.mnemonic {
+                Mnemonic::Bsr | Mnemonic::Jsr => Some(XrefKind::Call),
+                Mnemonic::Bra | Mnemonic::Jmp => Some(XrefKind::Jump),
+                Mnemonic::Bcc | Mnemonic::Dbcc => Some(XrefKind::Branch),
+                _ => None,
+            };
+            if let Some(kind) = control {
+                // Jumps through registers have no known target, and
+                // those through tables have one per case
+                if let Some(target) = control_target(image, segment, &inst, distinct).filter(|&t| within(t)) {
+                    index.add(from, target, kind);
+                }
+                for &target in code.jump_tables.get(&from).into_iter().flat_map(|t| &t.targets) {
+                    index.add(from, target, kind);
+                }
+                continue;
+            }
+            let base_relative = small_data.filter(|_| SmallData::holds_base(bases, from));
+            let last = inst.operands.len().saturating_sub(1);
+            for (i, op) in inst.operands.iter().enumerate() {
+                let Operand::Ea(ea) = op else { continue };
+                let Some(target) = operand_target(image, segment, &inst, ea, distinct, base_relative) else {
+                    continue;
+                };
+                if within(target) {
+                    index.add(from, target, access_kind(&inst.mnemonic, i == last));
+                }
+            }
+        }
+
+        // Pointers in data, including data in code segments
+        for fixup in &segment.fixups {
+            let from = Location::new(segment.index, fixup.offset);
+            if code.is_code(from) {
+                continue;
+            }
+            if let Some(target) = image.fixup_location(segment, fixup).filter(|&t| within(t)) {
+                index.add(from, target, XrefKind::Pointer);
+            }
+        }
+    }
+
+    for refs in index.refs.values_mut() {
+        refs.sort();
+        refs.dedup();
+    }
+    index
+}
+
+/// Where a memory operand of an instruction in `segment` points, if it
+/// can be told without tracking registers.
+fn operand_target(
+    image: &Image,
+    segment: &Segment,
+    inst: &Instruction,
+    ea: &EffectiveAddress,
+    distinct: bool,
+    small_data: Option<SmallData>,
+) -> Option<Location> {
+    let pc_relative = |disp: i32| {
+        let target = inst.address.wrapping_add(2).wrapping_add(disp as u32);
+        Some(Location::new(segment.index, target.wrapping_sub(segment.base_address)))
+    };
+    match *ea {
+        EffectiveAddress::AbsoluteLong(address) => absolute_target(image, segment, inst, address, distinct),
+        EffectiveAddress::AbsoluteShort(address) if distinct => image.locate(address as i16 as i32 as u32),
+        EffectiveAddress::PcDisplacement(d) => pc_relative(d as i32),
+        EffectiveAddress::PcIndex { displacement, .. } => pc_relative(displacement as i32),
+        EffectiveAddress::AddressDisplacement(small_data::BASE_REGISTER, d) => {
+            small_data?.target(image, d as i32)
+        }
+        _ => None,
+    }
+}
+
+/// How an instruction uses a memory operand.
+fn access_kind(mnemonic: &Mnemonic, destination: bool) -> XrefKind {
+    match mnemonic {
+        Mnemonic::Lea | Mnemonic::Pea => XrefKind::Address,
+        _ if !destination || !writes_destination(mnemonic) => XrefKind::Read,
+        Mnemonic::Move
+        | Mnemonic::Movea
+        | Mnemonic::Movem
+        | Mnemonic::Movep
+        | Mnemonic::MoveFromSr
+        | Mnemonic::Moves
+        | Mnemonic::Clr
+        | Mnemonic::Scc => XrefKind::Write,
+        _ => XrefKind::ReadWrite,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{Fixup, SegmentKind};
+
+    #[test]
+    fn code_and_data_references() {
+        let code = vec![
+            0x61, 0x0E, // 0: bsr.s $10
+            0x23, 0xC0, 0x00, 0x00, 0x00, 0x04, // 2: move.l d0,$4 (hunk 1)
+            0x52, 0xB9, 0x00, 0x00, 0x00, 0x00, // 8: addq.l #1,$0 (hunk 1)
+            0x4E, 0x75, // E: rts
+            0x41, 0xFA, 0x00, 0x06, // 10: lea ($18,pc),a0
+            0x4A, 0x79, 0x00, 0x00, 0x00, 0x00, // 14: tst.w $0 (hunk 1)
+            0x4E, 0x75, // 1A: rts
+        ];
+        let code_fixups = [4, 10, 0x16].map(|offset| Fixup { offset, target: 1 }).to_vec();
+        // A pointer to the function at $10
+        let pointer = vec![0, 0, 0, 0x10, 0, 0, 0, 0];
+        let image = Image::test_code(code, code_fixups).with_segment(
+            SegmentKind::Data,
+            pointer,
+            vec![Fixup { offset: 0, target: 0 }],
+        );
+        let code = CodeMap::linear(&image);
+        let index = build_xrefs(&image, CpuVariant::M68000, &code, None, &LibraryBases::default());
+
+        let at = Location::new;
+        let kinds = |to| index.to(to).iter().map(|x| (x.from, x.kind)).collect::<Vec<_>>();
+        assert_eq!(kinds(at(0, 0x10)), vec![(at(0, 0), XrefKind::Call), (at(1, 0), XrefKind::Pointer)]);
+        assert_eq!(kinds(at(1, 4)), vec![(at(0, 2), XrefKind::Write)]);
+        assert_eq!(
+            kinds(at(1, 0)),
+            vec![(at(0, 8), XrefKind::ReadWrite), (at(0, 0x14), XrefKind::Read)]
+        );
+        assert_eq!(kinds(at(0, 0x18)), vec![(at(0, 0x10), XrefKind::Address)]);
+        assert_eq!(index.from(at(0, 8)).count(), 1);
+        assert_eq!(index.targets_in(1).count(), 2);
+        assert_eq!(index.len(), 6);
+    }
+}
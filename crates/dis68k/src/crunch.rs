@@ -0,0 +1,92 @@
+//! Detection and unpacking of crunched (compressed) Amiga executables.
+//!
+//! Most executables from old disks are packed by a cruncher: the file is
+//! a small decruncher stub followed by compressed data, or a bare data
+//! file with a four-character ID. This module recognises the common
+//! crunchers and unpacks the ones it has a decompressor for, so the
+//! original hunk file can be disassembled instead of the stub.
+//!
+//! PowerPacker PP20, Imploder, CrunchMania and StoneCracker 4.04 (S404)
+//! streams are unpacked. Older StoneCracker formats and encrypted
+//! PowerPacker (PX20) are recognised in data files and reported as
+//! `CrunchError::Unsupported`. Titanics Cruncher streams carry no ID,
+//! and without its stub code to match they are not recognised at all.
+
+mod bits;
+pub mod crunchmania;
+pub mod detect;
+pub mod error;
+pub mod imploder;
+pub mod powerpacker;
+pub mod stonecracker;
+
+pub use detect::{detect_cruncher, Cruncher, Detection, Wrapper};
+pub use error::CrunchError;
+
+use crate::error::Error;
+use crate::hunk::parser::parse_hunk_file;
+use crate::hunk::types::HunkFile;
+
+/// Crunchers are occasionally stacked; stop unwrapping after this many layers.
+const MAX_LAYERS: usize = 4;
+
+/// Largest unpacked length believed from a 32-bit header field. Nothing
+/// that ran on an Amiga comes near it; a larger value means the header
+/// is corrupt.
+const MAX_UNPACKED_LEN: usize = 1 << 26;
+
+/// Unpack a crunched file.
+///
+/// Returns `Ok(None)` when no known cruncher is detected, or the
+/// detection and the unpacked bytes otherwise.
+pub fn decrunch(data: &[u8]) -> Result<Option<(Detection, Vec<u8>)>, CrunchError> {
+    let Some(detection) = detect_cruncher(data) else {
+        return Ok(None);
+    };
+    let stream = &data[detection.stream_offset..detection.stream_end];
+    let unpacked = match (detection.cruncher, detection.variant) {
+        (Cruncher::PowerPacker, "PP20") => powerpacker::decrunch_pp20(stream)?,
+        (Cruncher::Imploder, _) => imploder::decrunch_imploder(stream)?,
+        (Cruncher::CrunchMania, _) => crunchmania::decrunch_crunchmania(stream)?,
+        (Cruncher::StoneCracker, "S404") => stonecracker::decrunch_s404(stream)?,
+        (cruncher, _) => return Err(CrunchError::Unsupported { cruncher }),
+    };
+    Ok(Some((detection, unpacked)))
+}
+
+/// Parse a hunk executable, unpacking it first if it is crunched.
+///
+/// Each layer of crunching is removed in turn until a plain hunk file
+/// remains. Returns the parsed file together with the outermost
+/// cruncher that was removed, if any.
+pub fn parse_hunk_file_decrunched(data: &[u8]) -> Result<(HunkFile, Option<Detection>), Error> {
+    let mut outermost = None;
+    let mut current = data.to_vec();
+
+    for _ in 0..MAX_LAYERS {
+        match decrunch(&current)? {
+            Some((detection, unpacked)) => {
+                outermost.get_or_insert(detection);
+                current = unpacked;
+            }
+            None => break,
+        }
+    }
+
+    Ok((parse_hunk_file(&current)?, outermost))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detected_without_decruncher() {
+        for (id, cruncher) in [(b"S403", Cruncher::StoneCracker), (b"PX20", Cruncher::PowerPacker)] {
+            let mut data = id.to_vec();
+            data.extend_from_slice(&[0; 12]);
+            assert_eq!(decrunch(&data), Err(CrunchError::Unsupported { cruncher }));
+        }
+        assert_eq!(decrunch(&[0; 16]), Ok(None));
+    }
+}
@@ -0,0 +1,125 @@
+//! Backward bit reading shared by the decompressors.
+//!
+//! Every supported cruncher reads its packed data from the end of the
+//! stream towards the start. They differ in how many bytes each refill
+//! of the bit buffer takes, and in which end of a refill the bits come
+//! from; fields are always assembled most significant bit first.
+
+use super::error::CrunchError;
+
+/// Which bit of a refill is read first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BitOrder {
+    Lsb,
+    Msb,
+}
+
+/// Reads bits, and whole bytes, from the end of a slice towards its
+/// start.
+pub(crate) struct BackwardBits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Bytes per refill, read big-endian.
+    unit: usize,
+    order: BitOrder,
+    /// Unread bits: the low `count` bits for `Lsb`, the high ones for
+    /// `Msb`.
+    buffer: u32,
+    count: u32,
+    /// The error context when the stream runs out.
+    exhausted: &'static str,
+}
+
+impl<'a> BackwardBits<'a> {
+    /// A reader that starts at the end of `data` with no bits in hand.
+    pub(crate) fn new(data: &'a [u8], unit: usize, order: BitOrder, exhausted: &'static str) -> Self {
+        BackwardBits {
+            data,
+            pos: data.len(),
+            unit,
+            order,
+            buffer: 0,
+            count: 0,
+            exhausted,
+        }
+    }
+
+    /// Start with `count` bits already in hand: the low `count` bits of
+    /// `bits`, in the order a refill would give them.
+    pub(crate) fn with_bits(mut self, bits: u32, count: u32) -> Self {
+        self.count = count;
+        self.buffer = match self.order {
+            BitOrder::Lsb => bits,
+            BitOrder::Msb => bits.checked_shl(32 - count).unwrap_or(0),
+        };
+        self
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CrunchError> {
+        if self.pos < n {
+            return Err(CrunchError::Corrupt {
+                context: self.exhausted,
+            });
+        }
+        self.pos -= n;
+        Ok(&self.data[self.pos..self.pos + n])
+    }
+
+    /// Read one bit.
+    pub(crate) fn read_bit(&mut self) -> Result<u32, CrunchError> {
+        if self.count == 0 {
+            let word = self.take(self.unit)?.iter().fold(0u32, |w, &b| (w << 8) | b as u32);
+            self.count = 8 * self.unit as u32;
+            self.buffer = match self.order {
+                BitOrder::Lsb => word,
+                BitOrder::Msb => word << (32 - self.count),
+            };
+        }
+        self.count -= 1;
+        let bit = match self.order {
+            BitOrder::Lsb => self.buffer & 1,
+            BitOrder::Msb => self.buffer >> 31,
+        };
+        match self.order {
+            BitOrder::Lsb => self.buffer >>= 1,
+            BitOrder::Msb => self.buffer <<= 1,
+        }
+        Ok(bit)
+    }
+
+    /// Read `n` bits (n <= 32), assembling them most significant first.
+    pub(crate) fn read(&mut self, n: u32) -> Result<u32, CrunchError> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+
+    /// Read the byte before the last one read, outside the bit buffer.
+    pub(crate) fn byte(&mut self) -> Result<u8, CrunchError> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refills_from_the_end() {
+        let data = [0x12, 0x34, 0x80, 0x01];
+        let mut lsb = BackwardBits::new(&data, 2, BitOrder::Lsb, "exhausted");
+        assert_eq!(lsb.read(2).unwrap(), 0b10);
+        assert_eq!(lsb.read(14).unwrap(), 1);
+        assert_eq!(lsb.read(4).unwrap(), 0b0010);
+
+        let mut msb = BackwardBits::new(&data, 1, BitOrder::Msb, "exhausted").with_bits(0b101, 3);
+        assert_eq!(msb.read(4).unwrap(), 0b1010);
+        assert_eq!(msb.byte().unwrap(), 0x80);
+        assert_eq!(msb.read(7).unwrap(), 1);
+        assert_eq!(msb.read(8).unwrap(), 0x34);
+        assert_eq!(msb.read(8).unwrap(), 0x12);
+        assert_eq!(msb.read_bit(), Err(CrunchError::Corrupt { context: "exhausted" }));
+    }
+}
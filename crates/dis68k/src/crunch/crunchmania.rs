@@ -0,0 +1,412 @@
+//! CrunchMania (`CrM!`, `CrM2`, `Crm!`, `Crm2`) decompression.
+//!
+//! Stream layout:
+//!
+//! ```text
+//! "CrM!"                 4 bytes  ID
+//! minimum distance       2 bytes  unused when unpacking
+//! unpacked length        4 bytes
+//! packed length          4 bytes  from offset 14 to the end
+//! packed bits            n bytes  longwords read backwards
+//! last longword          4 bytes  first bits of the stream
+//! shift                  2 bytes  the last longword holds shift + 16 bits
+//! ```
+//!
+//! Bits come from each longword least significant first and are
+//! assembled most significant first; the output is written back to
+//! front. `CrM!` codes lengths and distances with fixed prefix codes,
+//! `CrM2` with Huffman tables stored in the stream. A lower-case `m`
+//! marks sampled data, stored as byte deltas after unpacking.
+
+use super::bits::{BackwardBits, BitOrder};
+use super::error::CrunchError;
+
+/// Length of the ID, minimum distance and the two lengths.
+const HEADER_LEN: usize = 14;
+/// Length of the last longword and its shift.
+const TRAILER_LEN: usize = 6;
+
+/// A canonical Huffman code read from a `CrM2` stream.
+struct Huffman {
+    /// Per code length: the first code, the number of codes, and the
+    /// index of the first code's value.
+    lengths: Vec<(u32, u32, usize)>,
+    values: Vec<u32>,
+}
+
+impl Huffman {
+    /// Read a table: the longest code length, the number of codes of
+    /// each length, then each code's value in `value_bits` bits.
+    fn read(bits: &mut BackwardBits, value_bits: u32) -> Result<Self, CrunchError> {
+        let max_depth = bits.read(4)?;
+        if max_depth == 0 {
+            return Err(CrunchError::Corrupt {
+                context: "CrunchMania Huffman table is empty",
+            });
+        }
+        let counts = (1..=max_depth)
+            .map(|depth| bits.read(depth.min(value_bits)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut lengths = Vec::new();
+        let mut values = Vec::new();
+        // Codes are numbered at the longest length, shortest codes first
+        let mut code = 0u32;
+        for (depth, count) in (1..=max_depth).zip(counts) {
+            let shift = max_depth - depth;
+            lengths.push((code >> shift, count, values.len()));
+            for _ in 0..count {
+                values.push(bits.read(value_bits)?);
+                code += 1 << shift;
+            }
+            if code > 1 << max_depth {
+                return Err(CrunchError::Corrupt {
+                    context: "CrunchMania Huffman table oversubscribed",
+                });
+            }
+        }
+        Ok(Huffman { lengths, values })
+    }
+
+    fn decode(&self, bits: &mut BackwardBits) -> Result<u32, CrunchError> {
+        let mut code = 0;
+        for &(first, count, index) in &self.lengths {
+            code = (code << 1) | bits.read_bit()?;
+            if code >= first && code - first < count {
+                return Ok(self.values[index + (code - first) as usize]);
+            }
+        }
+        Err(CrunchError::Corrupt {
+            context: "CrunchMania Huffman code not in table",
+        })
+    }
+}
+
+/// Output written back to front.
+struct Output {
+    data: Vec<u8>,
+    /// Index of the most recently written byte.
+    pos: usize,
+}
+
+impl Output {
+    fn literal(&mut self, byte: u8) -> Result<(), CrunchError> {
+        if self.pos == 0 {
+            return Err(CrunchError::Corrupt {
+                context: "CrunchMania literal overflows output",
+            });
+        }
+        self.pos -= 1;
+        self.data[self.pos] = byte;
+        Ok(())
+    }
+
+    /// Copy `count` bytes from `distance` bytes past each written byte.
+    fn copy(&mut self, distance: usize, count: usize) -> Result<(), CrunchError> {
+        if distance == 0 || self.pos + distance > self.data.len() {
+            return Err(CrunchError::Corrupt {
+                context: "CrunchMania match distance outside output",
+            });
+        }
+        if count > self.pos {
+            return Err(CrunchError::Corrupt {
+                context: "CrunchMania match overflows output",
+            });
+        }
+        for _ in 0..count {
+            self.pos -= 1;
+            self.data[self.pos] = self.data[self.pos + distance];
+        }
+        Ok(())
+    }
+}
+
+/// Whether the header of `stream` gives a packed length that ends
+/// within it and a plausible unpacked length.
+pub(crate) fn header_fits(stream: &[u8]) -> bool {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return false;
+    }
+    let read_u32 = |at: usize| u32::from_be_bytes(stream[at..at + 4].try_into().unwrap()) as usize;
+    let packed_len = read_u32(10);
+    (1..=super::MAX_UNPACKED_LEN).contains(&read_u32(6))
+        && packed_len >= TRAILER_LEN
+        && HEADER_LEN + packed_len <= stream.len()
+}
+
+/// Decompress a complete CrunchMania stream (starting at its ID).
+pub fn decrunch_crunchmania(stream: &[u8]) -> Result<Vec<u8>, CrunchError> {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return Err(CrunchError::TooShort {
+            needed: HEADER_LEN + TRAILER_LEN,
+            available: stream.len(),
+        });
+    }
+    let read_u32 = |at: usize| u32::from_be_bytes(stream[at..at + 4].try_into().unwrap()) as usize;
+    let unpacked_len = read_u32(6);
+    let packed_len = read_u32(10);
+    let end = HEADER_LEN.saturating_add(packed_len);
+    if packed_len < TRAILER_LEN || end > stream.len() {
+        return Err(CrunchError::TooShort {
+            needed: end.max(HEADER_LEN + TRAILER_LEN),
+            available: stream.len(),
+        });
+    }
+    if unpacked_len > super::MAX_UNPACKED_LEN {
+        return Err(CrunchError::Corrupt {
+            context: "CrunchMania unpacked length implausibly large",
+        });
+    }
+    let sampled = stream[2] == b'm';
+    let lzh = stream[3] == b'2';
+
+    let trailer = &stream[end - TRAILER_LEN..end];
+    let last = u32::from_be_bytes(trailer[..4].try_into().unwrap());
+    let shift = u16::from_be_bytes([trailer[4], trailer[5]]) as u32;
+    if shift > 16 {
+        return Err(CrunchError::Corrupt {
+            context: "CrunchMania bit count wider than a longword",
+        });
+    }
+    let packed = &stream[HEADER_LEN..end - TRAILER_LEN];
+    let mut bits = BackwardBits::new(packed, 4, BitOrder::Lsb, "CrunchMania bitstream exhausted")
+        .with_bits(last >> (16 - shift), shift + 16);
+
+    let mut out = Output {
+        data: vec![0u8; unpacked_len],
+        pos: unpacked_len,
+    };
+    if lzh {
+        unpack_lzh(&mut bits, &mut out)?;
+    } else {
+        unpack_fixed(&mut bits, &mut out)?;
+    }
+
+    let mut data = out.data;
+    if sampled {
+        let mut sample = 0u8;
+        for byte in &mut data {
+            sample = sample.wrapping_add(*byte);
+            *byte = sample;
+        }
+    }
+    Ok(data)
+}
+
+/// `CrM!`: a literal bit, or fixed codes for the length and distance.
+fn unpack_fixed(bits: &mut BackwardBits, out: &mut Output) -> Result<(), CrunchError> {
+    const LENGTH_BITS: [u32; 4] = [1, 2, 4, 8];
+    const LENGTH_BASE: [usize; 4] = [2, 4, 8, 24];
+    const DISTANCE_BITS: [u32; 3] = [9, 5, 14];
+    const DISTANCE_BASE: [usize; 3] = [32, 0, 544];
+
+    while out.pos > 0 {
+        if bits.read_bit()? == 1 {
+            out.literal(bits.read(8)? as u8)?;
+            continue;
+        }
+
+        // Length class: 0, 10, 110, 111
+        let class = match bits.read_bit()? {
+            0 => 0,
+            _ => match bits.read_bit()? {
+                0 => 1,
+                _ => 2 + bits.read_bit()? as usize,
+            },
+        };
+        let mut count = bits.read(LENGTH_BITS[class])? as usize + LENGTH_BASE[class];
+        if count == 23 {
+            // A run of literals
+            let count = match bits.read_bit()? {
+                1 => bits.read(5)?,
+                _ => bits.read(14)?,
+            } + 15;
+            for _ in 0..count {
+                out.literal(bits.read(8)? as u8)?;
+            }
+            continue;
+        }
+        if count > 23 {
+            count -= 1;
+        }
+
+        // Distance class: 0, 10, 11
+        let class = match bits.read_bit()? {
+            0 => 0,
+            _ => 1 + bits.read_bit()? as usize,
+        };
+        let distance = bits.read(DISTANCE_BITS[class])? as usize + DISTANCE_BASE[class];
+        out.copy(distance + 1, count)?;
+    }
+    Ok(())
+}
+
+/// `CrM2`: blocks of items coded with the block's two Huffman tables.
+fn unpack_lzh(bits: &mut BackwardBits, out: &mut Output) -> Result<(), CrunchError> {
+    loop {
+        let lengths = Huffman::read(bits, 9)?;
+        let distances = Huffman::read(bits, 4)?;
+        let items = bits.read(16)? + 1;
+        for _ in 0..items {
+            let symbol = lengths.decode(bits)?;
+            if symbol & 0x100 != 0 {
+                out.literal(symbol as u8)?;
+                continue;
+            }
+            let count = symbol as usize + 3;
+            let distance = match distances.decode(bits)? {
+                0 => bits.read(1)? as usize + 1,
+                n => (bits.read(n)? | (1 << n)) as usize + 1,
+            };
+            out.copy(distance, count)?;
+        }
+        if bits.read_bit()? == 0 {
+            break;
+        }
+    }
+    if out.pos != 0 {
+        return Err(CrunchError::Corrupt {
+            context: "CrunchMania stream ends before the output is full",
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack `(value, width)` fields into a CrunchMania stream in decode
+    /// order.
+    fn build_stream(id: &[u8; 4], unpacked_len: usize, fields: &[(u32, u32)]) -> Vec<u8> {
+        let mut bits = Vec::new();
+        for &(value, width) in fields {
+            for i in (0..width).rev() {
+                bits.push((value >> i) & 1);
+            }
+        }
+        // The last longword holds 16 to 32 bits, the rest whole longwords
+        bits.resize(bits.len().max(16), 0);
+        let mut shift = (bits.len() - 16) % 32;
+        if shift > 16 {
+            bits.resize(bits.len() + 32 - shift, 0);
+            shift = 0;
+        }
+        let to_long = |chunk: &[u32]| chunk.iter().enumerate().fold(0u32, |l, (i, &bit)| l | (bit << i));
+
+        let (first, rest) = bits.split_at(16 + shift);
+        let mut packed: Vec<u8> = Vec::new();
+        for chunk in rest.chunks(32).rev() {
+            packed.extend_from_slice(&to_long(chunk).to_be_bytes());
+        }
+        packed.extend_from_slice(&(to_long(first) << (16 - shift)).to_be_bytes());
+        packed.extend_from_slice(&(shift as u16).to_be_bytes());
+
+        let mut stream = id.to_vec();
+        stream.extend_from_slice(&[0, 0]);
+        stream.extend_from_slice(&(unpacked_len as u32).to_be_bytes());
+        stream.extend_from_slice(&(packed.len() as u32).to_be_bytes());
+        stream.extend_from_slice(&packed);
+        stream
+    }
+
+    #[test]
+    fn literals_and_match() {
+        // Back to front: "Y", "X", then 6 bytes from 2 bytes on
+        let stream = build_stream(
+            b"CrM!",
+            8,
+            &[
+                (1, 1),
+                (b'Y' as u32, 8),
+                (1, 1),
+                (b'X' as u32, 8),
+                (0, 1), // match
+                (2, 2), // length class 1
+                (2, 2), // 4 + 2
+                (2, 2), // distance class 1
+                (1, 5), // 0 + 1
+            ],
+        );
+        assert_eq!(decrunch_crunchmania(&stream).unwrap(), b"XYXYXYXY");
+    }
+
+    #[test]
+    fn literal_run() {
+        let text = b"literal runs are fifteen bytes or more";
+        let mut fields = vec![(0, 1), (6, 3), (15, 4), (1, 1), (text.len() as u32 - 15, 5)];
+        fields.extend(text.iter().rev().map(|&b| (b as u32, 8)));
+        let stream = build_stream(b"CrM!", text.len(), &fields);
+        assert_eq!(decrunch_crunchmania(&stream).unwrap(), text);
+    }
+
+    #[test]
+    fn huffman_block() {
+        // Lengths: '0' -> 'A', '10' -> 'B', '11' -> match of 3.
+        // Distances: '0' -> 1 extra bit.
+        let stream = build_stream(
+            b"CrM2",
+            6,
+            &[
+                (2, 4),
+                (1, 1),
+                (2, 2),
+                (0x141, 9),
+                (0x142, 9),
+                (0x000, 9),
+                (1, 4),
+                (1, 1),
+                (1, 4),
+                (4, 16), // 5 items
+                (0, 1),  // 'A'
+                (2, 2),  // 'B'
+                (0, 1),  // 'A'
+                (3, 2),  // match of 3
+                (0, 1),  // at distance (0 | 2) + 1
+                (0, 1),
+                (2, 2), // 'B'
+                (0, 1), // last block
+            ],
+        );
+        // The fifth item overflows the six-byte output
+        assert!(matches!(decrunch_crunchmania(&stream), Err(CrunchError::Corrupt { .. })));
+
+        let stream = build_stream(
+            b"CrM2",
+            6,
+            &[
+                (2, 4),
+                (1, 1),
+                (2, 2),
+                (0x141, 9),
+                (0x142, 9),
+                (0x000, 9),
+                (1, 4),
+                (1, 1),
+                (1, 4),
+                (3, 16),
+                (0, 1),
+                (2, 2),
+                (0, 1),
+                (3, 2),
+                (0, 1),
+                (0, 1),
+                (0, 1),
+            ],
+        );
+        assert_eq!(decrunch_crunchmania(&stream).unwrap(), b"ABAABA");
+    }
+
+    #[test]
+    fn sampled_data_is_delta_coded() {
+        let stream = build_stream(b"Crm!", 3, &[(1, 1), (0xFF, 8), (1, 1), (2, 8), (1, 1), (10, 8)]);
+        assert_eq!(decrunch_crunchmania(&stream).unwrap(), [10, 12, 11]);
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        assert!(matches!(decrunch_crunchmania(b"CrM!"), Err(CrunchError::TooShort { .. })));
+        let stream = build_stream(b"CrM!", 16, &[(1, 1), (b'A' as u32, 8)]);
+        assert!(matches!(decrunch_crunchmania(&stream), Err(CrunchError::Corrupt { .. })));
+    }
+}
@@ -0,0 +1,227 @@
+//! Cruncher recognition by ID signature.
+//!
+//! Crunched data files start with a four-character ID (`PP20`, `IMP!`,
+//! `CrM2`, `S404`, ...). Executable wrappers are ordinary hunk files
+//! whose first code hunk holds the decruncher stub followed by the same
+//! stream, ID included, so the stub hunk is searched for the same IDs.
+//! Four bytes of code can spell an ID by chance, so inside a hunk an ID
+//! only counts when the header after it fits the rest of the hunk; IDs
+//! of streams with no decompressor here are only recognised in data
+//! files.
+
+use crate::hunk::parser::parse_hunk_file;
+use crate::hunk::types::HunkType;
+
+use super::{crunchmania, imploder, powerpacker, stonecracker};
+
+/// A known Amiga cruncher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cruncher {
+    /// PowerPacker by Nico François (PP20 streams).
+    PowerPacker,
+    /// Imploder / ATN! by Peter Struijk and Albert Veli.
+    Imploder,
+    /// CrunchMania by Thomas Schwarz.
+    CrunchMania,
+    /// StoneCracker by Jouni Korhonen.
+    StoneCracker,
+}
+
+impl Cruncher {
+    /// Returns a human-readable name for this cruncher.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cruncher::PowerPacker => "PowerPacker",
+            Cruncher::Imploder => "Imploder",
+            Cruncher::CrunchMania => "CrunchMania",
+            Cruncher::StoneCracker => "StoneCracker",
+        }
+    }
+}
+
+impl std::fmt::Display for Cruncher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// How the crunched stream is packaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrapper {
+    /// A bare data file that starts with the cruncher ID.
+    DataFile,
+    /// A hunk executable whose first code hunk carries a decruncher stub.
+    Executable,
+}
+
+/// The result of recognising a crunched file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub cruncher: Cruncher,
+    pub wrapper: Wrapper,
+    /// The ID that identified the stream (e.g. `"PP20"`, `"CrM2"`, `"S404"`).
+    pub variant: &'static str,
+    /// File offset of the crunched stream (at its ID).
+    pub stream_offset: usize,
+    /// File offset one past the end of the crunched stream.
+    pub stream_end: usize,
+}
+
+/// A stream ID and the cruncher that writes it.
+struct Signature {
+    id: &'static [u8; 4],
+    variant: &'static str,
+    cruncher: Cruncher,
+    /// Whether a stream's header fits the bytes after it, for the
+    /// formats with a decompressor.
+    header_fits: Option<fn(&[u8]) -> bool>,
+}
+
+static SIGNATURES: &[Signature] = &[
+    Signature { id: b"PP20", variant: "PP20", cruncher: Cruncher::PowerPacker, header_fits: Some(powerpacker::header_fits) },
+    Signature { id: b"PX20", variant: "PX20", cruncher: Cruncher::PowerPacker, header_fits: None },
+    Signature { id: b"IMP!", variant: "IMP!", cruncher: Cruncher::Imploder, header_fits: Some(imploder::header_fits) },
+    Signature { id: b"ATN!", variant: "ATN!", cruncher: Cruncher::Imploder, header_fits: Some(imploder::header_fits) },
+    Signature { id: b"CrM!", variant: "CrM!", cruncher: Cruncher::CrunchMania, header_fits: Some(crunchmania::header_fits) },
+    Signature { id: b"CrM2", variant: "CrM2", cruncher: Cruncher::CrunchMania, header_fits: Some(crunchmania::header_fits) },
+    Signature { id: b"Crm!", variant: "Crm!", cruncher: Cruncher::CrunchMania, header_fits: Some(crunchmania::header_fits) },
+    Signature { id: b"Crm2", variant: "Crm2", cruncher: Cruncher::CrunchMania, header_fits: Some(crunchmania::header_fits) },
+    Signature { id: b"S300", variant: "S300", cruncher: Cruncher::StoneCracker, header_fits: None },
+    Signature { id: b"S310", variant: "S310", cruncher: Cruncher::StoneCracker, header_fits: None },
+    Signature { id: b"S400", variant: "S400", cruncher: Cruncher::StoneCracker, header_fits: None },
+    Signature { id: b"S401", variant: "S401", cruncher: Cruncher::StoneCracker, header_fits: None },
+    Signature { id: b"S403", variant: "S403", cruncher: Cruncher::StoneCracker, header_fits: None },
+    Signature { id: b"S404", variant: "S404", cruncher: Cruncher::StoneCracker, header_fits: Some(stonecracker::header_fits) },
+];
+
+/// Minimum stub size before an embedded ID is taken seriously. A real
+/// decruncher is never shorter than this, which keeps text such as a
+/// version string "PP20" at the start of an ordinary program from matching.
+const MIN_STUB_LEN: usize = 32;
+
+/// Recognise a crunched file.
+///
+/// Checks for a data-file ID at offset 0 first, then for an executable
+/// wrapper: a hunk file whose first code hunk contains a known ID after
+/// a plausible stub, followed by a header that fits the rest of the hunk.
+pub fn detect_cruncher(data: &[u8]) -> Option<Detection> {
+    if let Some(sig) = match_signature(data) {
+        return Some(Detection {
+            cruncher: sig.cruncher,
+            wrapper: Wrapper::DataFile,
+            variant: sig.variant,
+            stream_offset: 0,
+            stream_end: data.len(),
+        });
+    }
+
+    let hunk_file = parse_hunk_file(data).ok()?;
+    let hunk = hunk_file.hunks.iter().find(|h| h.hunk_type == HunkType::Code)?;
+    let code = &hunk.data;
+
+    // IDs are longword-aligned inside the stub hunk
+    (MIN_STUB_LEN..code.len())
+        .step_by(4)
+        .find_map(|pos| {
+            let stream = &code[pos..];
+            match_signature(stream)
+                .filter(|sig| sig.header_fits.is_some_and(|fits| fits(stream)))
+                .map(|sig| Detection {
+                    cruncher: sig.cruncher,
+                    wrapper: Wrapper::Executable,
+                    variant: sig.variant,
+                    stream_offset: hunk.data_offset + pos,
+                    stream_end: hunk.data_offset + code.len(),
+                })
+        })
+}
+
+fn match_signature(data: &[u8]) -> Option<&'static Signature> {
+    let id = data.get(..4)?;
+    SIGNATURES.iter().find(|sig| sig.id.as_slice() == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hunk::types::hunk_ids;
+
+    fn wrap_in_code_hunk(code: &[u8]) -> Vec<u8> {
+        let longs = (code.len() / 4) as u32;
+        let mut out = Vec::new();
+        out.extend_from_slice(&hunk_ids::HUNK_HEADER.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&longs.to_be_bytes());
+        out.extend_from_slice(&hunk_ids::HUNK_CODE.to_be_bytes());
+        out.extend_from_slice(&longs.to_be_bytes());
+        out.extend_from_slice(code);
+        out.extend_from_slice(&hunk_ids::HUNK_END.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn detects_data_file_ids() {
+        let d = detect_cruncher(b"CrM2\x00\x00\x00\x10\x00\x00\x00\x08").unwrap();
+        assert_eq!(d.cruncher, Cruncher::CrunchMania);
+        assert_eq!(d.wrapper, Wrapper::DataFile);
+        assert_eq!(d.variant, "CrM2");
+
+        let d = detect_cruncher(b"S404xxxxxxxx").unwrap();
+        assert_eq!(d.cruncher, Cruncher::StoneCracker);
+
+        assert_eq!(detect_cruncher(b"ATN!....").unwrap().cruncher, Cruncher::Imploder);
+    }
+
+    /// An Imploder header and tables for 256 bytes, padded to longwords.
+    fn imploder_stream() -> Vec<u8> {
+        let mut stream = b"IMP!".to_vec();
+        stream.extend_from_slice(&0x100u32.to_be_bytes());
+        stream.extend_from_slice(&12u32.to_be_bytes());
+        stream.resize(64, 0);
+        stream
+    }
+
+    #[test]
+    fn detects_id_inside_executable_stub() {
+        let mut code = [0x4E, 0x71].repeat(MIN_STUB_LEN / 2);
+        code.extend_from_slice(&imploder_stream());
+        let exe = wrap_in_code_hunk(&code);
+
+        let d = detect_cruncher(&exe).unwrap();
+        assert_eq!(d.cruncher, Cruncher::Imploder);
+        assert_eq!(d.wrapper, Wrapper::Executable);
+        // header (24) + CODE type/size (8) + stub
+        assert_eq!(d.stream_offset, 24 + 8 + MIN_STUB_LEN);
+        assert_eq!(d.stream_end, 24 + 8 + code.len());
+    }
+
+    #[test]
+    fn plain_executable_is_not_crunched() {
+        let exe = wrap_in_code_hunk(&[0x4E, 0x75, 0x00, 0x00]);
+        assert_eq!(detect_cruncher(&exe), None);
+        assert_eq!(detect_cruncher(b"\x00\x00"), None);
+    }
+
+    #[test]
+    fn id_in_code_needs_a_fitting_header() {
+        let stub = [0x4E, 0x71].repeat(MIN_STUB_LEN / 2);
+
+        // The end offset points past the hunk
+        let mut stream = imploder_stream();
+        stream[8..12].copy_from_slice(&0x1000u32.to_be_bytes());
+        let mut code = stub.clone();
+        code.extend_from_slice(&stream);
+        assert_eq!(detect_cruncher(&wrap_in_code_hunk(&code)), None);
+
+        // A string in a program, and an ID with no decompressor
+        for text in [b"CrM2 packs it!!!".as_slice(), b"S403\0\0\0\x10\0\0\0\x10\0\0\0\x10"] {
+            let mut code = stub.clone();
+            code.extend_from_slice(text);
+            code.resize(code.len() + 32, 0);
+            assert_eq!(detect_cruncher(&wrap_in_code_hunk(&code)), None);
+        }
+    }
+}
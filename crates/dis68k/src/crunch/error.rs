@@ -0,0 +1,32 @@
+use std::fmt;
+
+use super::detect::Cruncher;
+
+/// Errors that can occur while unpacking a crunched file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrunchError {
+    /// The crunched stream is shorter than its header or trailer requires.
+    TooShort { needed: usize, available: usize },
+    /// The cruncher was recognised but there is no decompressor for it.
+    Unsupported { cruncher: Cruncher },
+    /// The stream ran out of bits, or a match referenced data outside the output.
+    Corrupt { context: &'static str },
+}
+
+impl fmt::Display for CrunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrunchError::TooShort { needed, available } => {
+                write!(f, "crunched data too short: need {needed} bytes, only {available} available")
+            }
+            CrunchError::Unsupported { cruncher } => {
+                write!(f, "{cruncher} detected, but decrunching it is not supported")
+            }
+            CrunchError::Corrupt { context } => {
+                write!(f, "corrupt crunched data: {context}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CrunchError {}
@@ -0,0 +1,336 @@
+//! Imploder (`IMP!`, `ATN!`) decompression.
+//!
+//! Stream layout:
+//!
+//! ```text
+//! "IMP!"                 4 bytes  ID
+//! unpacked length        4 bytes
+//! end offset             4 bytes  even; where the packed bytes end
+//! packed bytes           n bytes  read backwards from the end offset
+//! first packed bytes    12 bytes  displaced by the header
+//! first literal run      4 bytes  bytes to copy before the first match
+//! bit buffer             2 bytes  first bits of the stream, then padding
+//! distance bases        16 bytes  8 words
+//! distance widths       12 bytes
+//! checksum               4 bytes  not checked
+//! ```
+//!
+//! Bytes and bits come from the same backward stream: the bit buffer is
+//! a byte whose lowest set bit marks its end, refilled from the stream
+//! when it runs out, so literal bytes sit between the bytes holding the
+//! bits. Bits are read most significant first, and the output is
+//! written back to front. The file's tables set the distance ranges for
+//! each of the four match lengths; the literal run lengths use fixed
+//! ranges.
+
+use super::bits::{BackwardBits, BitOrder};
+use super::error::CrunchError;
+
+/// Length of the ID, unpacked length and end offset.
+const HEADER_LEN: usize = 12;
+/// Length of the tables after the end offset.
+const TRAILER_LEN: usize = 0x32;
+
+/// Width of the short, middle and long literal run lengths, by match
+/// length class.
+const LITERAL_BITS: [[u32; 4]; 3] = [[1, 1, 1, 1], [2, 3, 3, 4], [4, 5, 7, 14]];
+/// Shortest long literal run, by match length class.
+const LITERAL_BASE: [usize; 4] = [6, 10, 10, 18];
+
+/// Whether the header of `stream` gives an even end offset with the
+/// tables after it within the stream, and a plausible unpacked length.
+pub(crate) fn header_fits(stream: &[u8]) -> bool {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return false;
+    }
+    let read_u32 = |at: usize| u32::from_be_bytes(stream[at..at + 4].try_into().unwrap()) as usize;
+    let end = read_u32(8);
+    (1..=super::MAX_UNPACKED_LEN).contains(&read_u32(4))
+        && end >= HEADER_LEN
+        && end % 2 == 0
+        && end.saturating_add(TRAILER_LEN) <= stream.len()
+}
+
+/// Decompress a complete Imploder stream (starting at its ID).
+pub fn decrunch_imploder(stream: &[u8]) -> Result<Vec<u8>, CrunchError> {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return Err(CrunchError::TooShort {
+            needed: HEADER_LEN + TRAILER_LEN,
+            available: stream.len(),
+        });
+    }
+    let read_u32 = |at: usize| u32::from_be_bytes(stream[at..at + 4].try_into().unwrap()) as usize;
+    let unpacked_len = read_u32(4);
+    let end = read_u32(8);
+    if end < HEADER_LEN || end % 2 != 0 {
+        return Err(CrunchError::Corrupt {
+            context: "Imploder end offset not an even offset past the header",
+        });
+    }
+    if end.saturating_add(TRAILER_LEN) > stream.len() {
+        return Err(CrunchError::TooShort {
+            needed: end.saturating_add(TRAILER_LEN),
+            available: stream.len(),
+        });
+    }
+    if unpacked_len > super::MAX_UNPACKED_LEN {
+        return Err(CrunchError::Corrupt {
+            context: "Imploder unpacked length implausibly large",
+        });
+    }
+
+    let trailer = &stream[end..end + TRAILER_LEN];
+    let mut literals = u32::from_be_bytes(trailer[12..16].try_into().unwrap()) as usize;
+    let distance_base: Vec<usize> = trailer[0x12..0x22]
+        .chunks(2)
+        .map(|w| u16::from_be_bytes([w[0], w[1]]) as usize)
+        .collect();
+    let distance_bits = &trailer[0x22..0x2E];
+    if distance_bits.iter().any(|&b| b > 16) {
+        return Err(CrunchError::Corrupt {
+            context: "Imploder distance wider than 16 bits",
+        });
+    }
+
+    // The bit buffer's bits sit above its lowest set bit
+    let buffer = trailer[0x10];
+    let count = 7u32.saturating_sub(buffer.trailing_zeros());
+    let packed = [&trailer[..12], &stream[HEADER_LEN..end]].concat();
+    let mut input = BackwardBits::new(&packed, 1, BitOrder::Msb, "Imploder stream exhausted")
+        .with_bits(u32::from(buffer) >> (8 - count), count);
+    let mut out = vec![0u8; unpacked_len];
+    // Index of the most recently written byte; output grows downwards.
+    let mut pos = unpacked_len;
+
+    loop {
+        if literals > pos {
+            return Err(CrunchError::Corrupt {
+                context: "Imploder literal run overflows output",
+            });
+        }
+        for _ in 0..literals {
+            pos -= 1;
+            out[pos] = input.byte()?;
+        }
+        if pos == 0 {
+            break;
+        }
+
+        // Match length class and length: 0, 10, 110, 1110, 11110 and
+        // three bits, or 11111 and a byte
+        let (class, count) = if input.read_bit()? == 0 {
+            (0, 2)
+        } else if input.read_bit()? == 0 {
+            (1, 3)
+        } else if input.read_bit()? == 0 {
+            (2, 4)
+        } else if input.read_bit()? == 0 {
+            (3, 5)
+        } else if input.read_bit()? == 0 {
+            (3, input.read(3)? as usize + 6)
+        } else {
+            match input.byte()? {
+                0 => {
+                    return Err(CrunchError::Corrupt {
+                        context: "Imploder match length of zero",
+                    })
+                }
+                n => (3, n as usize),
+            }
+        };
+
+        // The literal run after the match: 0, 10 or 11 and its bits
+        literals = if input.read_bit()? == 0 {
+            input.read(LITERAL_BITS[0][class])? as usize
+        } else if input.read_bit()? == 0 {
+            input.read(LITERAL_BITS[1][class])? as usize + 2
+        } else {
+            input.read(LITERAL_BITS[2][class])? as usize + LITERAL_BASE[class]
+        };
+
+        // The distance: 00 near, 01 middle, 1 far
+        let distance = if input.read_bit()? == 1 {
+            input.read(distance_bits[8 + class] as u32)? as usize + distance_base[4 + class]
+        } else if input.read_bit()? == 1 {
+            input.read(distance_bits[4 + class] as u32)? as usize + distance_base[class]
+        } else {
+            input.read(distance_bits[class] as u32)? as usize
+        } + 1;
+
+        if pos + distance > unpacked_len {
+            return Err(CrunchError::Corrupt {
+                context: "Imploder match distance outside output",
+            });
+        }
+        if count > pos {
+            return Err(CrunchError::Corrupt {
+                context: "Imploder match overflows output",
+            });
+        }
+        for _ in 0..count {
+            pos -= 1;
+            out[pos] = out[pos + distance];
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A field of the stream in decode order.
+    enum Field {
+        Bits(u32, u32),
+        Byte(u8),
+    }
+    use Field::{Bits, Byte};
+
+    /// Distance bases and widths: near 0-1, middle 2-17, far 18-273.
+    const TABLES: [u8; 28] = [
+        0, 2, 0, 2, 0, 2, 0, 2, 0, 18, 0, 18, 0, 18, 0, 18, 1, 1, 1, 1, 4, 4, 4, 4, 8, 8, 8, 8,
+    ];
+
+    /// Pack `fields` into an Imploder stream, with `literals` as the
+    /// first literal run.
+    fn build_stream(unpacked_len: usize, literals: u32, fields: &[Field]) -> Vec<u8> {
+        // Bytes in the order they are read, with the bits of each bit
+        // byte filled in as they are used
+        let mut first_bits = Vec::new();
+        let mut read = Vec::new();
+        let mut bit_byte: Option<(usize, u32)> = None;
+        for field in fields {
+            match *field {
+                Byte(b) => read.push(b),
+                Bits(value, width) => {
+                    for i in (0..width).rev() {
+                        let bit = ((value >> i) & 1) as u8;
+                        if first_bits.len() < 7 && bit_byte.is_none() {
+                            first_bits.push(bit);
+                            continue;
+                        }
+                        let (at, used) = match bit_byte {
+                            Some((at, used)) if used < 8 => (at, used),
+                            _ => {
+                                read.push(0);
+                                (read.len() - 1, 0)
+                            }
+                        };
+                        read[at] |= bit << (7 - used);
+                        bit_byte = Some((at, used + 1));
+                    }
+                }
+            }
+        }
+        // The buffer's bits end with a marker bit
+        let buffer = first_bits.iter().fold(0u8, |b, &bit| (b << 1) | bit);
+        let buffer = ((buffer << 1) | 1) << (7 - first_bits.len());
+
+        // Bytes are read from the end; the first 12 move to the trailer
+        read.resize(read.len().max(12).next_multiple_of(2), 0);
+        read.reverse();
+        let end = HEADER_LEN + read.len() - 12;
+
+        let mut stream = b"IMP!".to_vec();
+        stream.extend_from_slice(&(unpacked_len as u32).to_be_bytes());
+        stream.extend_from_slice(&(end as u32).to_be_bytes());
+        stream.extend_from_slice(&read[12..]);
+        stream.extend_from_slice(&read[..12]);
+        stream.extend_from_slice(&literals.to_be_bytes());
+        stream.extend_from_slice(&[buffer, 0]);
+        stream.extend_from_slice(&TABLES);
+        stream.extend_from_slice(&[0; 4]);
+        stream
+    }
+
+    #[test]
+    fn literals_and_match() {
+        // Back to front: "Y", "X", then 6 bytes from 2 bytes on
+        let stream = build_stream(
+            8,
+            2,
+            &[
+                Byte(b'Y'),
+                Byte(b'X'),
+                Bits(0b11110, 5), // class 3
+                Bits(0, 3),       // 6 bytes
+                Bits(0, 2),       // no more literals
+                Bits(0b00, 2),    // near
+                Bits(1, 1),       // distance 1
+            ],
+        );
+        assert_eq!(decrunch_imploder(&stream).unwrap(), b"XYXYXYXY");
+    }
+
+    #[test]
+    fn every_range() {
+        let literals: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let tail = b"then twenty literals";
+        let mut fields: Vec<Field> = literals.iter().map(|&b| Byte(b)).collect();
+        fields.extend([
+            Bits(0b10, 2),    // class 1, 3 bytes
+            Bits(0b10, 2),    // middle literal run
+            Bits(1, 3),       // 2 + 1
+            Bits(0b01, 2),    // middle distance
+            Bits(5, 4),       // 2 + 5
+        ]);
+        fields.extend(tail[17..].iter().rev().map(|&b| Byte(b)));
+        fields.extend([
+            Bits(0b11111, 5), // class 3, length in a byte
+            Byte(40),
+            Bits(0b10, 2),    // middle literal run
+            Bits(15, 4),      // 2 + 15
+            Bits(1, 1),       // far distance
+            Bits(232, 8),     // 18 + 232
+        ]);
+        fields.extend(tail[..17].iter().rev().map(|&b| Byte(b)));
+        fields.extend([
+            Bits(0b11111, 5), // class 3, 2 bytes
+            Byte(2),
+            Bits(0, 2),       // no more literals
+            Bits(0b00, 2),    // near
+            Bits(0, 1),       // distance 0
+        ]);
+        let len = 300 + 3 + 3 + 40 + 17 + 2;
+        let stream = build_stream(len, 300, &fields);
+
+        let mut expected = vec![0u8; len];
+        let mut pos = len;
+        fn copy(expected: &mut [u8], pos: &mut usize, count: usize, distance: usize) {
+            for _ in 0..count {
+                *pos -= 1;
+                expected[*pos] = expected[*pos + distance];
+            }
+        }
+        for &b in &literals {
+            pos -= 1;
+            expected[pos] = b;
+        }
+        copy(&mut expected, &mut pos, 3, 8);
+        for &b in tail[17..].iter().rev() {
+            pos -= 1;
+            expected[pos] = b;
+        }
+        copy(&mut expected, &mut pos, 40, 251);
+        for &b in tail[..17].iter().rev() {
+            pos -= 1;
+            expected[pos] = b;
+        }
+        copy(&mut expected, &mut pos, 2, 1);
+        assert_eq!(pos, 0);
+        assert_eq!(decrunch_imploder(&stream).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_bad_streams() {
+        assert!(matches!(decrunch_imploder(b"IMP!"), Err(CrunchError::TooShort { .. })));
+        let stream = build_stream(8, 2, &[Byte(b'Y'), Byte(b'X'), Bits(0b11111, 5), Byte(0)]);
+        assert_eq!(
+            decrunch_imploder(&stream),
+            Err(CrunchError::Corrupt { context: "Imploder match length of zero" })
+        );
+        let stream = build_stream(8, 9, &[]);
+        assert!(matches!(decrunch_imploder(&stream), Err(CrunchError::Corrupt { .. })));
+    }
+}
@@ -0,0 +1,229 @@
+//! PowerPacker (PP20) decompression.
+//!
+//! Stream layout:
+//!
+//! ```text
+//! "PP20"                 4 bytes  ID
+//! efficiency table       4 bytes  offset bit-lengths for match kinds 0-3
+//! packed bits            n bytes  read backwards from the end
+//! unpacked length        3 bytes  24-bit big-endian
+//! skip bits              1 byte   bits to discard before decoding
+//! ```
+//!
+//! The bitstream is consumed from the end towards the start, least
+//! significant bit first, and the output is also written back to front.
+
+use super::bits::{BackwardBits, BitOrder};
+use super::error::CrunchError;
+
+/// Length of the ID plus efficiency table.
+const HEADER_LEN: usize = 8;
+/// Length of the unpacked-size / skip-bits trailer.
+const TRAILER_LEN: usize = 4;
+
+/// Whether `stream` has a PP20 header: an efficiency table of widths
+/// that never shrink, and a nonzero unpacked length.
+pub(crate) fn header_fits(stream: &[u8]) -> bool {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return false;
+    }
+    let widths = &stream[4..HEADER_LEN];
+    let trailer = &stream[stream.len() - TRAILER_LEN..];
+    widths.iter().all(|w| (1..=15).contains(w))
+        && widths.windows(2).all(|w| w[0] <= w[1])
+        && trailer[..3] != [0, 0, 0]
+}
+
+/// Decompress a complete PP20 stream (starting at its `PP20` ID).
+pub fn decrunch_pp20(stream: &[u8]) -> Result<Vec<u8>, CrunchError> {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return Err(CrunchError::TooShort {
+            needed: HEADER_LEN + TRAILER_LEN,
+            available: stream.len(),
+        });
+    }
+
+    let offset_lens = &stream[4..HEADER_LEN];
+    if offset_lens.iter().any(|&n| n > 24) {
+        return Err(CrunchError::Corrupt {
+            context: "PP20 field wider than 24 bits",
+        });
+    }
+    let trailer = &stream[stream.len() - TRAILER_LEN..];
+    let unpacked_len =
+        ((trailer[0] as usize) << 16) | ((trailer[1] as usize) << 8) | trailer[2] as usize;
+    let skip_bits = trailer[3] as u32;
+
+    let packed = &stream[HEADER_LEN..stream.len() - TRAILER_LEN];
+    let mut bits = BackwardBits::new(packed, 1, BitOrder::Lsb, "PP20 bitstream exhausted");
+    bits.read(skip_bits)?;
+
+    let mut out = vec![0u8; unpacked_len];
+    // Index of the most recently written byte; output grows downwards.
+    let mut pos = unpacked_len;
+
+    while pos > 0 {
+        if bits.read(1)? == 0 {
+            // Literal run: 2-bit counts, extended while the count is 3
+            let mut todo = 1;
+            loop {
+                let x = bits.read(2)?;
+                todo += x as usize;
+                if x != 3 {
+                    break;
+                }
+            }
+            for _ in 0..todo {
+                if pos == 0 {
+                    return Err(CrunchError::Corrupt {
+                        context: "PP20 literal run overflows output",
+                    });
+                }
+                pos -= 1;
+                out[pos] = bits.read(8)? as u8;
+            }
+            if pos == 0 {
+                break;
+            }
+        }
+
+        // Match: 2 bits select the offset width and base length
+        let kind = bits.read(2)? as usize;
+        let mut offset_bits = offset_lens[kind] as u32;
+        let mut todo = kind + 2;
+        let offset;
+        if kind == 3 {
+            if bits.read(1)? == 0 {
+                offset_bits = 7;
+            }
+            offset = bits.read(offset_bits)? as usize;
+            loop {
+                let x = bits.read(3)?;
+                todo += x as usize;
+                if x != 7 {
+                    break;
+                }
+            }
+        } else {
+            offset = bits.read(offset_bits)? as usize;
+        }
+
+        if pos + offset >= unpacked_len {
+            return Err(CrunchError::Corrupt {
+                context: "PP20 match offset outside output",
+            });
+        }
+        for _ in 0..todo {
+            if pos == 0 {
+                return Err(CrunchError::Corrupt {
+                    context: "PP20 match overflows output",
+                });
+            }
+            let byte = out[pos + offset];
+            pos -= 1;
+            out[pos] = byte;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack `(value, width)` fields into a PP20 stream in decode order.
+    fn build_stream(unpacked_len: usize, fields: &[(u32, u32)]) -> Vec<u8> {
+        let mut bits = Vec::new();
+        for &(value, width) in fields {
+            for i in (0..width).rev() {
+                bits.push((value >> i) & 1);
+            }
+        }
+        // Leading skip bits pad the stream to a whole number of bytes
+        let skip = (8 - bits.len() % 8) % 8;
+        let mut padded = vec![0; skip];
+        padded.extend(bits);
+
+        // The decoder consumes the last byte first, LSB first
+        let mut bytes: Vec<u8> = padded
+            .chunks(8)
+            .map(|chunk| chunk.iter().enumerate().fold(0u8, |b, (i, &bit)| b | ((bit as u8) << i)))
+            .collect();
+        bytes.reverse();
+
+        let mut stream = b"PP20".to_vec();
+        stream.extend_from_slice(&[9, 10, 11, 11]);
+        stream.extend_from_slice(&bytes);
+        stream.extend_from_slice(&[
+            (unpacked_len >> 16) as u8,
+            (unpacked_len >> 8) as u8,
+            unpacked_len as u8,
+            skip as u8,
+        ]);
+        stream
+    }
+
+    #[test]
+    fn literals_then_long_match() {
+        // Output is produced back to front: "Y", "X", then a 6-byte match
+        // at offset 1 repeats the pair.
+        let stream = build_stream(
+            8,
+            &[
+                (0, 1),    // literal run follows
+                (1, 2),    // run length 1 + 1
+                (b'Y' as u32, 8),
+                (b'X' as u32, 8),
+                (3, 2),    // match kind 3
+                (0, 1),    // 7-bit offset
+                (1, 7),    // offset 1
+                (1, 3),    // length 5 + 1
+            ],
+        );
+        assert_eq!(decrunch_pp20(&stream).unwrap(), b"XYXYXYXY");
+    }
+
+    #[test]
+    fn short_match_uses_efficiency_table() {
+        // Literal "A", then kind-0 match (length 2, 9-bit offset 0)
+        let stream = build_stream(3, &[(0, 1), (0, 2), (b'A' as u32, 8), (0, 2), (0, 9)]);
+        assert_eq!(decrunch_pp20(&stream).unwrap(), b"AAA");
+    }
+
+    #[test]
+    fn decrunched_hunk_file_is_parsed() {
+        use crate::crunch::{parse_hunk_file_decrunched, Cruncher, Wrapper};
+        use crate::hunk::types::hunk_ids;
+
+        let mut exe = Vec::new();
+        for word in [hunk_ids::HUNK_HEADER, 0, 1, 0, 0, 1, hunk_ids::HUNK_CODE, 1, 0x4E75_0000, hunk_ids::HUNK_END] {
+            exe.extend_from_slice(&u32::to_be_bytes(word));
+        }
+
+        // One literal run covering the whole file, emitted back to front
+        let mut fields = vec![(0, 1)];
+        let mut extra = exe.len() - 1;
+        while extra >= 3 {
+            fields.push((3, 2));
+            extra -= 3;
+        }
+        fields.push((extra as u32, 2));
+        fields.extend(exe.iter().rev().map(|&b| (b as u32, 8)));
+        let stream = build_stream(exe.len(), &fields);
+
+        let (hunk_file, detection) = parse_hunk_file_decrunched(&stream).unwrap();
+        let detection = detection.unwrap();
+        assert_eq!(detection.cruncher, Cruncher::PowerPacker);
+        assert_eq!(detection.wrapper, Wrapper::DataFile);
+        assert_eq!(hunk_file.hunks.len(), 1);
+        assert_eq!(hunk_file.hunks[0].data, vec![0x4E, 0x75, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        assert!(matches!(decrunch_pp20(b"PP20"), Err(CrunchError::TooShort { .. })));
+        let stream = build_stream(16, &[(0, 1), (0, 2), (b'A' as u32, 8)]);
+        assert!(matches!(decrunch_pp20(&stream), Err(CrunchError::Corrupt { .. })));
+    }
+}
@@ -0,0 +1,287 @@
+//! StoneCracker 4.04 (`S404`) decompression.
+//!
+//! Stream layout:
+//!
+//! ```text
+//! "S404"                 4 bytes  ID
+//! security length        4 bytes  unused when unpacking
+//! unpacked length        4 bytes
+//! packed length          4 bytes  from offset 16 to the end
+//! packed bits            n bytes  words read backwards
+//! efficiency             2 bytes  width of the longest distances
+//! first word             2 bytes  its top bits start the stream
+//! bit count              2 bytes  how many of those bits are used
+//! ```
+//!
+//! Bits are read most significant first, and the output is written back
+//! to front. Each step starts with a 9-bit code: a literal byte below
+//! `$100`, a run of literals at `$13E`/`$13F`, and otherwise a match
+//! whose length class and distance range the code's low bits select.
+//! Older StoneCracker formats (`S300`-`S403`) are not unpacked.
+
+use super::bits::{BackwardBits, BitOrder};
+use super::error::CrunchError;
+
+/// Length of the ID, security length and the two lengths.
+const HEADER_LEN: usize = 16;
+/// Length of the efficiency, first word and bit count.
+const TRAILER_LEN: usize = 6;
+
+/// Distances below this are coded in 5 bits, from here to
+/// `FAR_DISTANCE` in 9.
+const MIDDLE_DISTANCE: u32 = 32;
+/// Distances from here on are coded in the stream's efficiency.
+const FAR_DISTANCE: u32 = 544;
+
+/// Read the rest of a distance whose first bits came with the code.
+/// `far` and `near` are the bits of `flags` that select the long and
+/// short ranges; the bits of `flags` below the selecting one are the top
+/// bits of the distance.
+fn read_distance(
+    bits: &mut BackwardBits,
+    flags: u32,
+    far: u32,
+    near: u32,
+    efficiency: u32,
+) -> Result<u32, CrunchError> {
+    let (mask, width, base) = if flags & far != 0 {
+        (far - 1, efficiency, FAR_DISTANCE)
+    } else if flags & near != 0 {
+        (near - 1, 5, 0)
+    } else {
+        (near - 1, 9, MIDDLE_DISTANCE)
+    };
+    let rest = width - mask.count_ones();
+    Ok((((flags & mask) << rest) | bits.read(rest)?) + base)
+}
+
+/// Whether the header of `stream` gives a packed length that ends
+/// within it, a plausible unpacked length and an efficiency in range.
+pub(crate) fn header_fits(stream: &[u8]) -> bool {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return false;
+    }
+    let read_u32 = |at: usize| u32::from_be_bytes(stream[at..at + 4].try_into().unwrap()) as usize;
+    let packed_len = read_u32(12);
+    let end = HEADER_LEN + packed_len;
+    (1..=super::MAX_UNPACKED_LEN).contains(&read_u32(8))
+        && packed_len >= TRAILER_LEN
+        && end <= stream.len()
+        && (6..=16).contains(&u16::from_be_bytes([stream[end - 6], stream[end - 5]]))
+}
+
+/// Decompress a complete `S404` stream (starting at its ID).
+pub fn decrunch_s404(stream: &[u8]) -> Result<Vec<u8>, CrunchError> {
+    if stream.len() < HEADER_LEN + TRAILER_LEN {
+        return Err(CrunchError::TooShort {
+            needed: HEADER_LEN + TRAILER_LEN,
+            available: stream.len(),
+        });
+    }
+    let read_u32 = |at: usize| u32::from_be_bytes(stream[at..at + 4].try_into().unwrap()) as usize;
+    let read_u16 = |at: usize| u16::from_be_bytes([stream[at], stream[at + 1]]) as u32;
+    let unpacked_len = read_u32(8);
+    let packed_len = read_u32(12);
+    let end = HEADER_LEN.saturating_add(packed_len);
+    if packed_len < TRAILER_LEN || end > stream.len() {
+        return Err(CrunchError::TooShort {
+            needed: end.max(HEADER_LEN + TRAILER_LEN),
+            available: stream.len(),
+        });
+    }
+    if unpacked_len > super::MAX_UNPACKED_LEN {
+        return Err(CrunchError::Corrupt {
+            context: "S404 unpacked length implausibly large",
+        });
+    }
+
+    let efficiency = read_u16(end - 6);
+    if !(6..=16).contains(&efficiency) {
+        return Err(CrunchError::Corrupt {
+            context: "S404 efficiency out of range",
+        });
+    }
+    // Some StoneCracker versions leave junk in the upper bits of the count
+    let left = read_u16(end - 2) & 0xF;
+    let packed = &stream[HEADER_LEN..end - TRAILER_LEN];
+    let mut bits = BackwardBits::new(packed, 2, BitOrder::Msb, "S404 bitstream exhausted")
+        .with_bits(read_u16(end - 4) >> (16 - left), left);
+
+    let mut out = vec![0u8; unpacked_len];
+    // Index of the most recently written byte; output grows downwards.
+    let mut pos = unpacked_len;
+
+    while pos > 0 {
+        let code = bits.read(9)?;
+        if code < 0x100 {
+            pos -= 1;
+            out[pos] = code as u8;
+            continue;
+        }
+
+        if code == 0x13E || code == 0x13F {
+            // A run of 14 to 45 literals
+            let count = (((code & 1) << 4) | bits.read(4)?) as usize + 14;
+            if count > pos {
+                return Err(CrunchError::Corrupt {
+                    context: "S404 literal run overflows output",
+                });
+            }
+            for _ in 0..count {
+                pos -= 1;
+                out[pos] = bits.read(8)? as u8;
+            }
+            continue;
+        }
+
+        let (count, distance) = match code {
+            // $180-$1FF: 2 or 3 bytes
+            0x180.. => {
+                let count = if code & 0x40 != 0 { 3 } else { 2 };
+                (count, read_distance(&mut bits, code & 0x3F, 0x20, 0x10, efficiency)?)
+            }
+            // $140-$17F: 4 to 7 bytes
+            0x140.. => {
+                let count = ((code >> 4) & 3) as usize + 4;
+                (count, read_distance(&mut bits, code & 0xF, 0x8, 0x4, efficiency)?)
+            }
+            // $120-$13D: 8 to 22 bytes
+            0x120.. => {
+                let count = ((code >> 1) & 0xF) as usize + 8;
+                let distance = if code & 1 != 0 {
+                    bits.read(efficiency)? + FAR_DISTANCE
+                } else {
+                    let flags = bits.read(6)?;
+                    read_distance(&mut bits, flags, 0, 0x20, efficiency)?
+                };
+                (count, distance)
+            }
+            // $100-$11F: 23 bytes or more, the excess continued in bytes
+            // while it is $FF
+            _ => {
+                let mut extra = ((code & 0x1F) << 3) | bits.read(3)?;
+                let mut count = 23;
+                while extra == 0xFF {
+                    count += 0xFF;
+                    extra = bits.read(8)?;
+                }
+                count += extra as usize;
+                let flags = bits.read(7)?;
+                (count, read_distance(&mut bits, flags, 0x40, 0x20, efficiency)?)
+            }
+        };
+
+        let distance = distance as usize + 1;
+        if pos + distance > unpacked_len {
+            return Err(CrunchError::Corrupt {
+                context: "S404 match distance outside output",
+            });
+        }
+        if count > pos {
+            return Err(CrunchError::Corrupt {
+                context: "S404 match overflows output",
+            });
+        }
+        for _ in 0..count {
+            pos -= 1;
+            out[pos] = out[pos + distance];
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pack `(value, width)` fields into an S404 stream in decode order.
+    fn build_stream(unpacked_len: usize, efficiency: u16, fields: &[(u32, u32)]) -> Vec<u8> {
+        let mut bits = Vec::new();
+        for &(value, width) in fields {
+            for i in (0..width).rev() {
+                bits.push((value >> i) & 1);
+            }
+        }
+        // The first word holds the odd bits, the rest whole words
+        let left = bits.len() % 16;
+        let to_word = |chunk: &[u32]| chunk.iter().fold(0u16, |w, &bit| (w << 1) | bit as u16);
+        let first = to_word(&bits[..left]) << ((16 - left) % 16);
+
+        let mut packed = Vec::new();
+        for chunk in bits[left..].chunks(16).rev() {
+            packed.extend_from_slice(&to_word(chunk).to_be_bytes());
+        }
+        packed.extend_from_slice(&efficiency.to_be_bytes());
+        packed.extend_from_slice(&first.to_be_bytes());
+        packed.extend_from_slice(&(left as u16).to_be_bytes());
+
+        let mut stream = b"S404".to_vec();
+        stream.extend_from_slice(&0u32.to_be_bytes());
+        stream.extend_from_slice(&(unpacked_len as u32).to_be_bytes());
+        stream.extend_from_slice(&(packed.len() as u32).to_be_bytes());
+        stream.extend_from_slice(&packed);
+        stream
+    }
+
+    #[test]
+    fn literals_and_short_match() {
+        // Back to front: "Y", "X", then 6 bytes from 2 bytes on
+        let stream = build_stream(
+            8,
+            10,
+            &[
+                (b'Y' as u32, 9),
+                (b'X' as u32, 9),
+                (0x164, 9), // 4 + 2 bytes, near distance, high bits 0
+                (1, 3),     // distance 1
+            ],
+        );
+        assert_eq!(decrunch_s404(&stream).unwrap(), b"XYXYXYXY");
+    }
+
+    #[test]
+    fn literal_run() {
+        let text = b"a run of twenty bytes";
+        let mut fields = vec![(0x13E, 9), (text.len() as u32 - 14, 4)];
+        fields.extend(text.iter().rev().map(|&b| (b as u32, 8)));
+        let stream = build_stream(text.len(), 10, &fields);
+        assert_eq!(decrunch_s404(&stream).unwrap(), text);
+    }
+
+    #[test]
+    fn long_match_at_far_distance() {
+        let literals: Vec<u8> = (0..600).map(|i| (i % 251) as u8).collect();
+        let mut fields: Vec<(u32, u32)> = literals.iter().map(|&b| (b as u32, 9)).collect();
+        fields.extend([
+            (0x100, 9),  // 23 + excess
+            (7, 3),      // excess 7
+            (0x40, 7),   // far distance, high bits 0
+            (11, 4),     // 544 + 11
+            (0x124, 9),  // 8 + 2 bytes, no far bit
+            (0x21, 6),   // near distance 1
+        ]);
+        let stream = build_stream(640, 10, &fields);
+
+        let mut expected = vec![0u8; 640];
+        for (i, &b) in literals.iter().enumerate() {
+            expected[639 - i] = b;
+        }
+        for i in (10..40).rev() {
+            expected[i] = expected[i + 556];
+        }
+        for i in (0..10).rev() {
+            expected[i] = expected[i + 2];
+        }
+        assert_eq!(decrunch_s404(&stream).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_bad_streams() {
+        assert!(matches!(decrunch_s404(b"S404"), Err(CrunchError::TooShort { .. })));
+        let stream = build_stream(4, 3, &[(b'A' as u32, 9)]);
+        assert!(matches!(decrunch_s404(&stream), Err(CrunchError::Corrupt { .. })));
+        let stream = build_stream(4, 10, &[(b'A' as u32, 9)]);
+        assert!(matches!(decrunch_s404(&stream), Err(CrunchError::Corrupt { .. })));
+    }
+}
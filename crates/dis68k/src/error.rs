@@ -1,13 +1,27 @@
 use std::fmt;
 
+use crate::adf::error::AdfError;
+use crate::crunch::error::CrunchError;
 use crate::hunk::error::HunkError;
+use crate::image::error::ImageError;
+use crate::library::error::LibraryError;
 use crate::m68k::decode::DecodeError;
+use crate::project::error::ProjectError;
+use crate::raw::error::RawError;
+use crate::rom::error::RomError;
 
 /// Unified error type for the dis68k library.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     Hunk(HunkError),
     Decode(DecodeError),
+    Crunch(CrunchError),
+    Rom(RomError),
+    Adf(AdfError),
+    Raw(RawError),
+    Image(ImageError),
+    Library(LibraryError),
+    Project(ProjectError),
 }
 
 impl fmt::Display for Error {
@@ -15,6 +29,13 @@ impl fmt::Display for Error {
         match self {
             Error::Hunk(e) => write!(f, "hunk error: {e}"),
             Error::Decode(e) => write!(f, "decode error: {e}"),
+            Error::Crunch(e) => write!(f, "decrunch error: {e}"),
+            Error::Rom(e) => write!(f, "ROM error: {e}"),
+            Error::Adf(e) => write!(f, "ADF error: {e}"),
+            Error::Raw(e) => write!(f, "raw binary error: {e}"),
+            Error::Image(e) => write!(f, "image error: {e}"),
+            Error::Library(e) => write!(f, "library error: {e}"),
+            Error::Project(e) => write!(f, "project file error: {e}"),
         }
     }
 }
@@ -32,3 +53,45 @@ impl From<DecodeError> for Error {
         Error::Decode(e)
     }
 }
+
+impl From<CrunchError> for Error {
+    fn from(e: CrunchError) -> Self {
+        Error::Crunch(e)
+    }
+}
+
+impl From<RomError> for Error {
+    fn from(e: RomError) -> Self {
+        Error::Rom(e)
+    }
+}
+
+impl From<AdfError> for Error {
+    fn from(e: AdfError) -> Self {
+        Error::Adf(e)
+    }
+}
+
+impl From<RawError> for Error {
+    fn from(e: RawError) -> Self {
+        Error::Raw(e)
+    }
+}
+
+impl From<ImageError> for Error {
+    fn from(e: ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+impl From<LibraryError> for Error {
+    fn from(e: LibraryError) -> Self {
+        Error::Library(e)
+    }
+}
+
+impl From<ProjectError> for Error {
+    fn from(e: ProjectError) -> Self {
+        Error::Project(e)
+    }
+}
@@ -179,6 +179,16 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
         hunk_sizes.push(size_bytes);
     }
 
+    // Metadata blocks (e.g. HUNK_NAME) seen before their hunk's content block
+    let mut pending_metadata: Vec<(HunkType, usize)> = Vec::new();
+    let mut blocks = vec![BlockInfo {
+        offset: 0,
+        size: cursor.position(),
+        raw_type: magic,
+        hunk_type: HunkType::Header,
+        hunk_index: None,
+    }];
+
     // --- Parse the hunk content ---
     let mut hunks: Vec<Hunk> = Vec::with_capacity(num_hunks);
     let mut current_hunk_idx: usize = 0;
@@ -203,16 +213,19 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
             Err(_) => break, // EOF handled gracefully if between hunks
         };
 
+        let block_start = cursor.position() - 4;
+        let block_hunk_idx = current_hunk_idx;
         let mem_flags = MemoryType::from_flags(type_word);
         let hunk_type = HunkType::from_raw(type_word).ok_or(HunkError::UnknownHunkType {
             raw: type_word,
-            offset: cursor.position() - 4,
+            offset: block_start,
         })?;
 
         match hunk_type {
             HunkType::Code | HunkType::Data => {
                 let data_longs = cursor.read_u32_be()? as usize;
                 let data_bytes = data_longs * 4;
+                let data_offset = cursor.position();
                 let content = cursor.read_bytes(data_bytes)?.to_vec();
 
                 let alloc_size = if current_hunk_idx < hunk_sizes.len() {
@@ -238,10 +251,14 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
                     memory_type,
                     alloc_size,
                     data: content,
+                    base_address: 0,
                     relocations: Vec::new(),
                     symbols: Vec::new(),
                     name: None,
                     debug_data: None,
+                    block_offset: block_start,
+                    data_offset,
+                    metadata_offsets: Vec::new(),
                 });
             }
 
@@ -269,10 +286,14 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
                     memory_type,
                     alloc_size,
                     data: Vec::new(),
+                    base_address: 0,
                     relocations: Vec::new(),
                     symbols: Vec::new(),
                     name: None,
                     debug_data: None,
+                    block_offset: block_start,
+                    data_offset: cursor.position(),
+                    metadata_offsets: Vec::new(),
                 });
             }
 
@@ -299,10 +320,6 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
 
             HunkType::End => {
                 current_hunk_idx += 1;
-                // If we've parsed all hunks, we can stop
-                if current_hunk_idx >= num_hunks {
-                    break;
-                }
             }
 
             HunkType::Name => {
@@ -336,6 +353,7 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
 
             HunkType::Overlay | HunkType::Break => {
                 // Overlay executables — skip for now
+                record_block(&mut blocks, &mut hunks, &mut pending_metadata, type_word, hunk_type, block_start, 4, block_hunk_idx);
                 break;
             }
 
@@ -354,6 +372,14 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
                 });
             }
         }
+
+        let block_size = cursor.position() - block_start;
+        record_block(&mut blocks, &mut hunks, &mut pending_metadata, type_word, hunk_type, block_start, block_size, block_hunk_idx);
+
+        // If we've parsed all hunks, we can stop
+        if hunk_type == HunkType::End && current_hunk_idx >= num_hunks {
+            break;
+        }
     }
 
     if hunks.len() != num_hunks {
@@ -367,9 +393,47 @@ pub fn parse_hunk_file(data: &[u8]) -> Result<HunkFile, HunkError> {
         hunks,
         first_hunk,
         last_hunk,
+        blocks,
     })
 }
 
+/// Append a block to the file-order block list and, for metadata blocks,
+/// note its offset on the content hunk it belongs to.
+///
+/// Metadata that precedes its hunk's content block is held in `pending`
+/// and attached once that content block is recorded.
+#[allow(clippy::too_many_arguments)]
+fn record_block(
+    blocks: &mut Vec<BlockInfo>,
+    hunks: &mut [Hunk],
+    pending: &mut Vec<(HunkType, usize)>,
+    raw_type: u32,
+    hunk_type: HunkType,
+    offset: usize,
+    size: usize,
+    hunk_index: usize,
+) {
+    blocks.push(BlockInfo {
+        offset,
+        size,
+        raw_type,
+        hunk_type,
+        hunk_index: Some(hunk_index),
+    });
+
+    let current = hunks.last_mut().filter(|hunk| hunk.index == hunk_index);
+    if matches!(hunk_type, HunkType::Code | HunkType::Data | HunkType::Bss) {
+        if let Some(hunk) = current {
+            hunk.metadata_offsets.append(pending);
+        }
+        return;
+    }
+    match current {
+        Some(hunk) => hunk.metadata_offsets.push((hunk_type, offset)),
+        None => pending.push((hunk_type, offset)),
+    }
+}
+
 /// Parse HUNK_RELOC32: groups of (count, target_hunk, offsets...) until count == 0.
 fn parse_reloc32(cursor: &mut Cursor<'_>, hunks: &mut [Hunk]) -> Result<(), HunkError> {
     loop {
@@ -684,6 +748,143 @@ mod tests {
         assert_eq!(result.hunks[0].symbols[0].value, 0);
     }
 
+    #[test]
+    fn records_block_map_and_offsets() {
+        let mut out = Vec::new();
+
+        // Header (24 bytes)
+        out.extend_from_slice(&hunk_ids::HUNK_HEADER.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&2u32.to_be_bytes());
+
+        // HUNK_CODE at 0x18, data at 0x20
+        out.extend_from_slice(&hunk_ids::HUNK_CODE.to_be_bytes());
+        out.extend_from_slice(&2u32.to_be_bytes());
+        out.extend_from_slice(&[0x4E, 0xB9, 0x00, 0x00, 0x00, 0x00, 0x4E, 0x75]);
+
+        // HUNK_RELOC32 at 0x28
+        out.extend_from_slice(&hunk_ids::HUNK_RELOC32.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&2u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+
+        // HUNK_DEBUG at 0x3C
+        out.extend_from_slice(&hunk_ids::HUNK_DEBUG.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        // HUNK_END at 0x48
+        out.extend_from_slice(&hunk_ids::HUNK_END.to_be_bytes());
+
+        let result = parse_hunk_file(&out).unwrap();
+        let hunk = &result.hunks[0];
+        assert_eq!(hunk.block_offset, 0x18);
+        assert_eq!(hunk.data_offset, 0x20);
+        assert_eq!(
+            hunk.metadata_offsets,
+            vec![
+                (HunkType::Reloc32, 0x28),
+                (HunkType::Debug, 0x3C),
+                (HunkType::End, 0x48),
+            ]
+        );
+
+        let layout: Vec<(usize, usize, HunkType)> = result
+            .blocks
+            .iter()
+            .map(|b| (b.offset, b.size, b.hunk_type))
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                (0x00, 0x18, HunkType::Header),
+                (0x18, 0x10, HunkType::Code),
+                (0x28, 0x14, HunkType::Reloc32),
+                (0x3C, 0x0C, HunkType::Debug),
+                (0x48, 0x04, HunkType::End),
+            ]
+        );
+        assert_eq!(result.blocks[1].raw_type, hunk_ids::HUNK_CODE);
+        assert!(!result.blocks[3].is_advisory());
+        assert_eq!(result.blocks[0].hunk_index, None);
+        assert_eq!(result.blocks[4].hunk_index, Some(0));
+    }
+
+    #[test]
+    fn name_before_content_block_is_attached() {
+        let mut out = Vec::new();
+
+        // Header (24 bytes)
+        out.extend_from_slice(&hunk_ids::HUNK_HEADER.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+
+        // HUNK_NAME "main" at 0x18, ahead of the code it names
+        out.extend_from_slice(&hunk_ids::HUNK_NAME.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(b"main");
+
+        // HUNK_CODE at 0x24
+        out.extend_from_slice(&hunk_ids::HUNK_CODE.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&[0x4E, 0x75, 0x00, 0x00]);
+
+        // HUNK_END at 0x30
+        out.extend_from_slice(&hunk_ids::HUNK_END.to_be_bytes());
+
+        let result = parse_hunk_file(&out).unwrap();
+        let hunk = &result.hunks[0];
+        assert_eq!(hunk.block_offset, 0x24);
+        assert_eq!(
+            hunk.metadata_offsets,
+            vec![(HunkType::Name, 0x18), (HunkType::End, 0x30)]
+        );
+        assert_eq!(result.blocks[1].hunk_index, Some(0));
+    }
+
+    #[test]
+    fn decodes_memory_flags_and_advisory_blocks() {
+        assert_eq!(MemoryType::from_flags(hunk_ids::HUNKF_CHIP | 4), MemoryType::Chip);
+        assert_eq!(MemoryType::from_flags(hunk_ids::HUNKF_FAST | 4), MemoryType::Fast);
+
+        let mut out = Vec::new();
+
+        // Header: one FAST hunk of 1 longword
+        out.extend_from_slice(&hunk_ids::HUNK_HEADER.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&(hunk_ids::HUNKF_FAST | 1).to_be_bytes());
+
+        // HUNK_CODE carrying the CHIP flag, which overrides the header
+        out.extend_from_slice(&(hunk_ids::HUNK_CODE | hunk_ids::HUNKF_CHIP).to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&[0x4E, 0x75, 0x00, 0x00]);
+
+        // Advisory HUNK_DEBUG
+        out.extend_from_slice(&(hunk_ids::HUNK_DEBUG | hunk_ids::HUNKF_ADVISORY).to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        out.extend_from_slice(&hunk_ids::HUNK_END.to_be_bytes());
+
+        let result = parse_hunk_file(&out).unwrap();
+        assert_eq!(result.hunks[0].memory_type, MemoryType::Chip);
+        assert_eq!(result.hunks[0].debug_data, Some(vec![0xDE, 0xAD, 0xBE, 0xEF]));
+        assert_eq!(result.blocks[1].raw_type, 0x400003E9);
+        assert_eq!(result.blocks[1].memory_flags(), MemoryType::Chip);
+        assert_eq!(result.blocks[2].hunk_type, HunkType::Debug);
+        assert!(result.blocks[2].is_advisory());
+    }
+
     #[test]
     fn error_on_bad_magic() {
         let data = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
@@ -20,8 +20,8 @@ impl MemoryType {
     pub fn from_flags(word: u32) -> Self {
         match (word >> 30) & 0x3 {
             0 => MemoryType::Any,
-            1 => MemoryType::Fast,
-            2 => MemoryType::Chip,
+            1 => MemoryType::Chip,
+            2 => MemoryType::Fast,
             _ => MemoryType::Extended(word),
         }
     }
@@ -107,6 +107,13 @@ pub mod hunk_ids {
     pub const HUNK_RELOC32SHORT: u32 = 0x3FC;
     pub const HUNK_RELRELOC32: u32 = 0x3FD;
     pub const HUNK_ABSRELOC16: u32 = 0x3FE;
+
+    /// Advisory flag (bit 29): a loader may skip the block if it doesn't know the type.
+    pub const HUNKF_ADVISORY: u32 = 1 << 29;
+    /// Chip memory flag (bit 30).
+    pub const HUNKF_CHIP: u32 = 1 << 30;
+    /// Fast memory flag (bit 31).
+    pub const HUNKF_FAST: u32 = 1 << 31;
 }
 
 impl HunkType {
@@ -114,7 +121,7 @@ impl HunkType {
     /// Returns `None` for unrecognized type IDs.
     pub fn from_raw(raw: u32) -> Option<Self> {
         // Mask off the upper 2 bits (memory type flags) and the advisory bit
-        let id = raw & 0x3FFFFFFF;
+        let id = raw & 0x1FFFFFFF;
         match id {
             hunk_ids::HUNK_UNIT => Some(HunkType::Unit),
             hunk_ids::HUNK_NAME => Some(HunkType::Name),
@@ -175,7 +182,7 @@ impl HunkType {
 
 impl std::fmt::Display for HunkType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.name())
+        f.pad(self.name())
     }
 }
 
@@ -211,6 +218,9 @@ pub struct Hunk {
     pub alloc_size: u32,
     /// Raw bytes of the hunk content. Empty for BSS hunks.
     pub data: Vec<u8>,
+    /// Address the hunk's offsets are relative to. Zero for relocatable
+    /// hunks; the fixed load address for memory images such as ROMs.
+    pub base_address: u32,
     /// Relocation entries attached to this hunk.
     pub relocations: Vec<Relocation>,
     /// Symbols defined in this hunk.
@@ -219,6 +229,73 @@ pub struct Hunk {
     pub name: Option<String>,
     /// Raw debug data, if present.
     pub debug_data: Option<Vec<u8>>,
+    /// File offset of the CODE/DATA/BSS block's type word.
+    pub block_offset: usize,
+    /// File offset of the first content byte (where it would start, for BSS).
+    pub data_offset: usize,
+    /// File offsets of the metadata blocks attached to this hunk
+    /// (RELOC32, SYMBOL, DEBUG, NAME, END, ...), in file order.
+    pub metadata_offsets: Vec<(HunkType, usize)>,
+}
+
+impl Hunk {
+    /// Build a synthetic CODE hunk over a memory image loaded at
+    /// `base_address` (ROM regions, bootblocks, raw binaries).
+    /// `file_offset` is where `data` starts in the source file.
+    pub fn from_image(
+        index: usize,
+        base_address: u32,
+        data: &[u8],
+        file_offset: usize,
+        name: Option<String>,
+    ) -> Hunk {
+        Hunk {
+            index,
+            hunk_type: HunkType::Code,
+            memory_type: MemoryType::Any,
+            alloc_size: data.len() as u32,
+            data: data.to_vec(),
+            base_address,
+            relocations: Vec::new(),
+            symbols: Vec::new(),
+            name,
+            debug_data: None,
+            block_offset: file_offset,
+            data_offset: file_offset,
+            metadata_offsets: Vec::new(),
+        }
+    }
+}
+
+/// One block as it appears in the file, in file order.
+///
+/// Unlike `Hunk`, which holds interpreted content, this records the
+/// physical layout: where each block starts, how long it is, and the
+/// type word exactly as stored (memory and advisory bits intact).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Byte offset of the block's type word within the file.
+    pub offset: usize,
+    /// Total block length in bytes, including the type word.
+    pub size: usize,
+    /// The raw type word as stored in the file.
+    pub raw_type: u32,
+    /// Decoded block type.
+    pub hunk_type: HunkType,
+    /// Index of the content hunk this block belongs to (`None` for the header).
+    pub hunk_index: Option<usize>,
+}
+
+impl BlockInfo {
+    /// Whether the advisory bit (bit 29) is set in the raw type word.
+    pub fn is_advisory(&self) -> bool {
+        self.raw_type & hunk_ids::HUNKF_ADVISORY != 0
+    }
+
+    /// Memory flags carried in the upper 2 bits of the raw type word.
+    pub fn memory_flags(&self) -> MemoryType {
+        MemoryType::from_flags(self.raw_type)
+    }
 }
 
 /// A fully parsed Amiga hunk executable.
@@ -230,6 +307,8 @@ pub struct HunkFile {
     pub first_hunk: u32,
     /// Last hunk index from the header.
     pub last_hunk: u32,
+    /// Every block in the file (header included), in file order.
+    pub blocks: Vec<BlockInfo>,
 }
 
 impl std::fmt::Display for MemoryType {
@@ -0,0 +1,60 @@
+//! Executable formats other than (and including) Amiga hunk files.
+//!
+//! Each format has a `Loader` that recognises its magic number and turns
+//! the file into a format-agnostic `Image`: a list of code, data and BSS
+//! segments placed at addresses, with symbols and fixups. The listing
+//! generator works on `Image`, so a new format only needs a loader.
+//!
+//! Built-in loaders: Amiga hunk, Atari ST GEMDOS (PRG/TOS/TTP) and ELF32
+//! big-endian m68k objects and executables.
+
+pub mod elf;
+pub mod error;
+pub mod loader;
+pub mod prg;
+pub mod types;
+
+pub use elf::{parse_elf, ElfLoader};
+pub use error::ImageError;
+pub use loader::{detect_format, load_image, loaders, HunkLoader, Loader};
+pub use prg::{parse_prg, PrgLoader};
+pub use types::{Fixup, Image, ImageFormat, Location, Segment, SegmentKind};
+
+use crate::output::listing::{emit_image_sections, push_line, ListingLine, ListingOptions};
+use crate::symbols::resolver::SymbolResolver;
+
+/// Generate a disassembly listing for an image from any loader.
+pub fn generate_image_listing(
+    image: &Image,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+) -> Vec<ListingLine> {
+    let mut lines = Vec::new();
+    let mut line_num: u32 = 1;
+
+    push_line(&mut lines, &mut line_num, options, format!("; {} Disassembly", image.format));
+    for segment in &image.segments {
+        push_line(
+            &mut lines,
+            &mut line_num,
+            options,
+            format!(
+                "; Segment {}: {:<10} {:<4} ${:08X}-${:08X} ({} bytes)",
+                segment.index,
+                image.segment_label(segment.index),
+                segment.kind.name(),
+                segment.base_address,
+                segment.base_address as u64 + segment.size as u64,
+                segment.size
+            ),
+        );
+    }
+    for &entry in &image.entry_points {
+        push_line(&mut lines, &mut line_num, options, format!("; Entry: ${entry:08X}"));
+    }
+    push_line(&mut lines, &mut line_num, options, String::new());
+
+    emit_image_sections(image, options, resolver, &mut lines, &mut line_num);
+
+    lines
+}
@@ -0,0 +1,454 @@
+//! ELF32 big-endian executables and objects for the 68k (`EM_68K`).
+//!
+//! Allocated sections become segments: `SHT_PROGBITS` with
+//! `SHF_EXECINSTR` is code, other `SHT_PROGBITS` is data and
+//! `SHT_NOBITS` is BSS. Symbols come from `.symtab` and fixups from
+//! `SHT_RELA` sections.
+//!
+//! Executables carry real section addresses. Relocatable objects have
+//! every section at address 0, so their sections are laid out one after
+//! another (respecting alignment) the way a linker would place them, and
+//! their relocations are applied to that layout: each field gets its
+//! symbol's address plus the addend, less the field's own address for
+//! PC-relative types. Only `R_68K_32` fields become fixups.
+
+use crate::error::Error;
+use crate::hunk::types::Symbol;
+
+use super::error::ImageError;
+use super::loader::Loader;
+use super::types::{Fixup, Image, ImageFormat, Segment, SegmentKind};
+
+const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+const ELFCLASS32: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const EM_68K: u16 = 4;
+const ET_REL: u16 = 1;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+
+const SHF_ALLOC: u32 = 0x2;
+const SHF_EXECINSTR: u32 = 0x4;
+
+const R_68K_32: u8 = 1;
+const R_68K_16: u8 = 2;
+const R_68K_8: u8 = 3;
+const R_68K_PC32: u8 = 4;
+const R_68K_PC16: u8 = 5;
+const R_68K_PC8: u8 = 6;
+
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+const SHN_LORESERVE: u16 = 0xFF00;
+
+const SECTION_HEADER_LEN: usize = 40;
+const SYMBOL_LEN: usize = 16;
+const RELA_LEN: usize = 12;
+
+pub struct ElfLoader;
+
+impl Loader for ElfLoader {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::Elf
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.len() >= 52
+            && data[..4] == ELF_MAGIC
+            && data[4] == ELFCLASS32
+            && data[5] == ELFDATA2MSB
+            && read_u16(data, 18) == EM_68K
+    }
+
+    fn load(&self, data: &[u8]) -> Result<Image, Error> {
+        Ok(parse_elf(data)?)
+    }
+}
+
+/// One entry of the section header table.
+struct SectionHeader {
+    name: u32,
+    sh_type: u32,
+    flags: u32,
+    addr: u32,
+    offset: usize,
+    size: usize,
+    link: usize,
+    info: usize,
+    align: u32,
+}
+
+/// Parse an ELF32 big-endian m68k file.
+pub fn parse_elf(data: &[u8]) -> Result<Image, ImageError> {
+    if !ElfLoader.detect(data) {
+        return Err(ImageError::Malformed { context: "not a 32-bit big-endian m68k ELF file" });
+    }
+    let e_type = read_u16(data, 16);
+    let entry = read_u32(data, 24);
+    let shoff = read_u32(data, 32) as usize;
+    let shentsize = read_u16(data, 46) as usize;
+    let shnum = read_u16(data, 48) as usize;
+    let shstrndx = read_u16(data, 50) as usize;
+    if shnum > 0 && shentsize < SECTION_HEADER_LEN {
+        return Err(ImageError::Unsupported { context: "section header size", value: shentsize as u32 });
+    }
+
+    let sections = (0..shnum)
+        .map(|i| {
+            let at = shoff + i * shentsize;
+            let h = data
+                .get(at..at + SECTION_HEADER_LEN)
+                .ok_or(ImageError::Truncated { context: "section header", offset: at })?;
+            Ok(SectionHeader {
+                name: read_u32(h, 0),
+                sh_type: read_u32(h, 4),
+                flags: read_u32(h, 8),
+                addr: read_u32(h, 12),
+                offset: read_u32(h, 16) as usize,
+                size: read_u32(h, 20) as usize,
+                link: read_u32(h, 24) as usize,
+                info: read_u32(h, 28) as usize,
+                align: read_u32(h, 32),
+            })
+        })
+        .collect::<Result<Vec<_>, ImageError>>()?;
+
+    let contents = |s: &SectionHeader| {
+        data.get(s.offset..s.offset + s.size)
+            .ok_or(ImageError::Truncated { context: "section contents", offset: s.offset })
+    };
+    let shstrtab = match sections.get(shstrndx) {
+        Some(s) => contents(s)?,
+        None => &[][..],
+    };
+
+    // Allocated sections → segments
+    let relocatable = e_type == ET_REL;
+    let mut segments = Vec::new();
+    let mut segment_of = vec![None; sections.len()];
+    let mut next_addr: u32 = 0;
+    for (i, sec) in sections.iter().enumerate() {
+        if sec.flags & SHF_ALLOC == 0 || !matches!(sec.sh_type, SHT_PROGBITS | SHT_NOBITS) {
+            continue;
+        }
+        let kind = match (sec.sh_type, sec.flags & SHF_EXECINSTR != 0) {
+            (SHT_NOBITS, _) => SegmentKind::Bss,
+            (_, true) => SegmentKind::Code,
+            _ => SegmentKind::Data,
+        };
+        let base_address = if relocatable {
+            let align = sec.align.max(1);
+            let base = next_addr
+                .div_ceil(align)
+                .checked_mul(align)
+                .ok_or(ImageError::Malformed { context: "section alignment overflows the address space" })?;
+            next_addr = u32::try_from(sec.size)
+                .ok()
+                .and_then(|size| base.checked_add(size))
+                .ok_or(ImageError::Malformed { context: "section size overflows the address space" })?;
+            base
+        } else {
+            sec.addr
+        };
+        segment_of[i] = Some(segments.len());
+        segments.push(Segment {
+            index: segments.len(),
+            kind,
+            name: Some(read_cstr(shstrtab, sec.name as usize)),
+            base_address,
+            size: sec.size as u32,
+            data: if kind == SegmentKind::Bss { Vec::new() } else { contents(sec)?.to_vec() },
+            memory: None,
+            symbols: Vec::new(),
+            fixups: Vec::new(),
+            file_offset: sec.offset,
+        });
+    }
+
+    // Symbols: (segment, address) per symbol table index
+    let mut symbol_targets: Vec<Option<(usize, u32)>> = Vec::new();
+    if let Some(symtab) = sections.iter().find(|s| s.sh_type == SHT_SYMTAB) {
+        let strtab = sections.get(symtab.link).map(contents).transpose()?.unwrap_or(&[]);
+        for sym in contents(symtab)?.chunks_exact(SYMBOL_LEN) {
+            let value = read_u32(sym, 4);
+            let sym_type = sym[12] & 0xF;
+            let shndx = read_u16(sym, 14);
+            let seg = if shndx == 0 || shndx >= SHN_LORESERVE {
+                None
+            } else {
+                segment_of.get(shndx as usize).copied().flatten()
+            };
+            let address = |seg: usize| {
+                if relocatable {
+                    segments[seg].base_address.wrapping_add(value)
+                } else {
+                    value
+                }
+            };
+            symbol_targets.push(seg.map(|seg| (seg, address(seg))));
+
+            let name = read_cstr(strtab, read_u32(sym, 0) as usize);
+            let Some(seg) = seg else { continue };
+            if name.is_empty() || sym_type == STT_SECTION || sym_type == STT_FILE {
+                continue;
+            }
+            let segment = &mut segments[seg];
+            let offset = if relocatable { value } else { value.wrapping_sub(segment.base_address) };
+            segment.symbols.push(Symbol { name, value: offset });
+        }
+    }
+
+    // RELA sections: fixups in the section named by sh_info
+    for rela in sections.iter().filter(|s| s.sh_type == SHT_RELA) {
+        let Some(seg) = segment_of.get(rela.info).copied().flatten() else {
+            continue;
+        };
+        for entry in contents(rela)?.chunks_exact(RELA_LEN) {
+            let r_offset = read_u32(entry, 0);
+            let info = read_u32(entry, 4);
+            let (sym_index, r_type) = ((info >> 8) as usize, info as u8);
+            let addend = read_u32(entry, 8);
+            // Undefined symbols have no address to point at
+            let Some((target, address)) = symbol_targets.get(sym_index).copied().flatten() else {
+                continue;
+            };
+            let segment = &mut segments[seg];
+            let offset = if relocatable {
+                r_offset
+            } else {
+                r_offset.wrapping_sub(segment.base_address)
+            };
+            if relocatable {
+                let value = address.wrapping_add(addend);
+                let pc_relative = value.wrapping_sub(segment.base_address.wrapping_add(offset));
+                let field = match r_type {
+                    R_68K_32 => Some((value, 4)),
+                    R_68K_16 => Some((value, 2)),
+                    R_68K_8 => Some((value, 1)),
+                    R_68K_PC32 => Some((pc_relative, 4)),
+                    R_68K_PC16 => Some((pc_relative, 2)),
+                    R_68K_PC8 => Some((pc_relative, 1)),
+                    _ => None,
+                };
+                let Some((value, len)) = field else { continue };
+                let Some(bytes) = segment.data.get_mut(offset as usize..offset as usize + len) else {
+                    continue;
+                };
+                bytes.copy_from_slice(&value.to_be_bytes()[4 - len..]);
+            }
+            if r_type == R_68K_32 {
+                segment.fixups.push(Fixup { offset, target });
+            }
+        }
+    }
+
+    let entry_points = if relocatable {
+        segments
+            .iter()
+            .find(|s| s.kind == SegmentKind::Code)
+            .map(|s| s.base_address)
+            .into_iter()
+            .collect()
+    } else {
+        vec![entry]
+    };
+
+    Ok(Image {
+        format: ImageFormat::Elf,
+        segments,
+        entry_points,
+    })
+}
+
+fn read_cstr(table: &[u8], at: usize) -> String {
+    table
+        .get(at..)
+        .unwrap_or(&[])
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| b as char)
+        .collect()
+}
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Location;
+
+    struct Section {
+        name: &'static str,
+        sh_type: u32,
+        flags: u32,
+        addr: u32,
+        data: Vec<u8>,
+        link: u32,
+        info: u32,
+        align: u32,
+    }
+
+    fn section(name: &'static str, sh_type: u32, flags: u32, data: Vec<u8>) -> Section {
+        Section { name, sh_type, flags, addr: 0, data, link: 0, info: 0, align: 2 }
+    }
+
+    /// Assemble an ELF file from sections (index 0 is added as SHT_NULL).
+    fn build_elf(e_type: u16, entry: u32, mut sections: Vec<Section>) -> Vec<u8> {
+        let mut shstrtab = vec![0u8];
+        let mut names = Vec::new();
+        for s in &sections {
+            names.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(s.name.as_bytes());
+            shstrtab.push(0);
+        }
+        names.push(shstrtab.len() as u32);
+        shstrtab.extend_from_slice(b".shstrtab\0");
+        sections.push(section(".shstrtab", 3, 0, shstrtab));
+
+        let mut out = vec![0u8; 52];
+        let mut offsets = Vec::new();
+        for s in &sections {
+            offsets.push(out.len() as u32);
+            out.extend_from_slice(&s.data);
+            while !out.len().is_multiple_of(4) {
+                out.push(0);
+            }
+        }
+        let shoff = out.len() as u32;
+        out.extend_from_slice(&[0; SECTION_HEADER_LEN]);
+        for (i, s) in sections.iter().enumerate() {
+            for v in [names[i], s.sh_type, s.flags, s.addr, offsets[i], s.data.len() as u32, s.link, s.info, s.align, 0] {
+                out.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+
+        out[..4].copy_from_slice(&ELF_MAGIC);
+        out[4] = ELFCLASS32;
+        out[5] = ELFDATA2MSB;
+        out[6] = 1;
+        out[16..18].copy_from_slice(&e_type.to_be_bytes());
+        out[18..20].copy_from_slice(&EM_68K.to_be_bytes());
+        out[24..28].copy_from_slice(&entry.to_be_bytes());
+        out[32..36].copy_from_slice(&shoff.to_be_bytes());
+        out[46..48].copy_from_slice(&(SECTION_HEADER_LEN as u16).to_be_bytes());
+        out[48..50].copy_from_slice(&(sections.len() as u16 + 1).to_be_bytes());
+        out[50..52].copy_from_slice(&(sections.len() as u16).to_be_bytes());
+        out
+    }
+
+    fn symbol(name: u32, value: u32, info: u8, shndx: u16) -> Vec<u8> {
+        let mut s = Vec::new();
+        s.extend_from_slice(&name.to_be_bytes());
+        s.extend_from_slice(&value.to_be_bytes());
+        s.extend_from_slice(&0u32.to_be_bytes());
+        s.extend_from_slice(&[info, 0]);
+        s.extend_from_slice(&shndx.to_be_bytes());
+        s
+    }
+
+    fn rela(offset: u32, symbol: u32, r_type: u8, addend: i32) -> Vec<u8> {
+        let mut r = Vec::new();
+        r.extend_from_slice(&offset.to_be_bytes());
+        r.extend_from_slice(&((symbol << 8) | r_type as u32).to_be_bytes());
+        r.extend_from_slice(&addend.to_be_bytes());
+        r
+    }
+
+    #[test]
+    fn loads_relocatable_object() {
+        // .text: movea.l #msg+2,a0 / lea msg(pc),a1 / rts    .data: "hi\0\0"
+        let text = vec![0x20, 0x7C, 0, 0, 0, 0, 0x43, 0xFA, 0, 0, 0x4E, 0x75];
+        let strtab = b"\0_start\0msg\0".to_vec();
+        let mut symtab = symbol(0, 0, 0, 0);
+        symtab.extend(symbol(0, 0, STT_SECTION, 2)); // section symbol for .data
+        symtab.extend(symbol(1, 0, 0x10, 1)); // _start in .text
+        symtab.extend(symbol(8, 0, 0x10, 2)); // msg in .data
+        let mut relocs = rela(2, 3, R_68K_32, 2);
+        relocs.extend(rela(8, 1, R_68K_PC16, 0));
+
+        let mut sections = vec![
+            section(".text", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, text),
+            section(".data", SHT_PROGBITS, SHF_ALLOC | 1, b"hi\0\0".to_vec()),
+            section(".bss", SHT_NOBITS, SHF_ALLOC | 1, Vec::new()),
+            section(".symtab", SHT_SYMTAB, 0, symtab),
+            section(".strtab", 3, 0, strtab),
+            section(".rela.text", SHT_RELA, 0, relocs),
+        ];
+        sections[3].link = 5;
+        sections[5].link = 4;
+        sections[5].info = 1;
+
+        let elf = build_elf(ET_REL, 0, sections);
+        assert!(ElfLoader.detect(&elf));
+        let image = parse_elf(&elf).unwrap();
+
+        let names: Vec<&str> = image.segments.iter().map(|s| s.name.as_deref().unwrap()).collect();
+        assert_eq!(names, [".text", ".data", ".bss"]);
+        let bases: Vec<u32> = image.segments.iter().map(|s| s.base_address).collect();
+        assert_eq!(bases, [0, 12, 16]);
+        assert_eq!(image.segments[2].kind, SegmentKind::Bss);
+        assert_eq!(image.segments[0].symbols[0].name, "_start");
+        assert_eq!(image.segments[1].symbols[0].name, "msg");
+        assert_eq!(image.entry_points, [0]);
+
+        // The absolute field holds msg+2 and is a fixup into .data
+        let text = &image.segments[0];
+        assert_eq!(text.fixups, [Fixup { offset: 2, target: 1 }]);
+        assert_eq!(text.read_u32(2), Some(14));
+        assert_eq!(image.fixup_location(text, &text.fixups[0]), Some(Location::new(1, 2)));
+        // The PC-relative one is resolved in place: 8 + 4 = 12
+        assert_eq!(text.read_u16(8), Some(4));
+    }
+
+    #[test]
+    fn executable_keeps_section_addresses() {
+        let mut text = section(".text", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, vec![0x4E, 0x75]);
+        text.addr = 0x80000;
+        let image = parse_elf(&build_elf(2, 0x80000, vec![text])).unwrap();
+        assert_eq!(image.segments[0].base_address, 0x80000);
+        assert_eq!(image.entry_points, [0x80000]);
+    }
+
+    #[test]
+    fn rejects_sections_past_the_address_space() {
+        let text = || section(".text", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, vec![0x4E, 0x75, 0, 0]);
+        let bss = || section(".bss", SHT_NOBITS, SHF_ALLOC | 1, Vec::new());
+
+        // A .bss size that runs past 4 GB; its header is the third after SHT_NULL and .text
+        let mut elf = build_elf(ET_REL, 0, vec![text(), bss()]);
+        let at = read_u32(&elf, 32) as usize + 2 * SECTION_HEADER_LEN + 20;
+        elf[at..at + 4].copy_from_slice(&0xFFFF_FFFEu32.to_be_bytes());
+        assert_eq!(
+            parse_elf(&elf).unwrap_err(),
+            ImageError::Malformed { context: "section size overflows the address space" }
+        );
+
+        // An alignment that rounds the next base past 4 GB
+        let mut data = section(".data", SHT_PROGBITS, SHF_ALLOC | 1, vec![0; 4]);
+        data.align = 0xC000_0000;
+        let mut bss = bss();
+        bss.align = 0xC000_0000;
+        let elf = build_elf(ET_REL, 0, vec![text(), data, bss]);
+        assert_eq!(
+            parse_elf(&elf).unwrap_err(),
+            ImageError::Malformed { context: "section alignment overflows the address space" }
+        );
+    }
+
+    #[test]
+    fn rejects_other_machines() {
+        let mut elf = build_elf(ET_REL, 0, Vec::new());
+        elf[19] = 3; // EM_386
+        assert!(!ElfLoader.detect(&elf));
+        assert!(parse_elf(&elf).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt;
+
+/// Errors that can occur while loading a non-hunk executable format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// No loader recognises the input.
+    UnknownFormat,
+    /// A structure extends past the end of the file.
+    Truncated { context: &'static str, offset: usize },
+    /// A header field has a value the loader cannot handle.
+    Unsupported { context: &'static str, value: u32 },
+    /// The file is internally inconsistent.
+    Malformed { context: &'static str },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::UnknownFormat => write!(f, "unrecognised executable format"),
+            ImageError::Truncated { context, offset } => {
+                write!(f, "file truncated reading {context} at offset 0x{offset:X}")
+            }
+            ImageError::Unsupported { context, value } => {
+                write!(f, "unsupported {context}: 0x{value:X}")
+            }
+            ImageError::Malformed { context } => write!(f, "malformed file: {context}"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
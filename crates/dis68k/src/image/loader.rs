@@ -0,0 +1,56 @@
+//! The `Loader` trait and format auto-detection.
+
+use crate::error::Error;
+use crate::hunk::parser::parse_hunk_file;
+use crate::hunk::types::hunk_ids;
+
+use super::elf::ElfLoader;
+use super::error::ImageError;
+use super::prg::PrgLoader;
+use super::types::{Image, ImageFormat};
+
+/// An executable format backend.
+pub trait Loader {
+    /// The format this loader reads.
+    fn format(&self) -> ImageFormat;
+
+    /// Cheap check of the file's magic number.
+    fn detect(&self, data: &[u8]) -> bool;
+
+    /// Parse the file into an `Image`.
+    fn load(&self, data: &[u8]) -> Result<Image, Error>;
+}
+
+/// Amiga hunk executables, via `parse_hunk_file`.
+pub struct HunkLoader;
+
+impl Loader for HunkLoader {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::AmigaHunk
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.get(..4) == Some(&hunk_ids::HUNK_HEADER.to_be_bytes())
+    }
+
+    fn load(&self, data: &[u8]) -> Result<Image, Error> {
+        Ok(Image::from(&parse_hunk_file(data)?))
+    }
+}
+
+/// Every built-in loader, in detection order.
+pub fn loaders() -> &'static [&'static dyn Loader] {
+    &[&HunkLoader, &PrgLoader, &ElfLoader]
+}
+
+/// Find the loader whose magic number matches `data`.
+pub fn detect_format(data: &[u8]) -> Option<&'static dyn Loader> {
+    loaders().iter().copied().find(|l| l.detect(data))
+}
+
+/// Auto-detect the format and load `data`.
+pub fn load_image(data: &[u8]) -> Result<Image, Error> {
+    detect_format(data)
+        .ok_or(ImageError::UnknownFormat)?
+        .load(data)
+}
@@ -0,0 +1,270 @@
+//! Atari ST GEMDOS executables (`.PRG`, `.TOS`, `.TTP`).
+//!
+//! ```text
+//! $00  UWORD  $601A (BRA.S over the header)
+//! $02  ULONG  TEXT size
+//! $06  ULONG  DATA size
+//! $0A  ULONG  BSS size
+//! $0E  ULONG  symbol table size
+//! $12  ULONG  reserved
+//! $16  ULONG  program flags
+//! $1A  UWORD  absflag (non-zero: no fixup table)
+//! $1C         TEXT, DATA, symbol table, fixup table
+//! ```
+//!
+//! The program is loaded contiguously (TEXT, DATA, BSS). The fixup table
+//! starts with a longword offset of the first fixup (0 = none), followed
+//! by byte deltas: 0 ends the table, 1 advances 254 bytes without a fixup,
+//! and any even value advances that far and fixes up the longword there.
+//! Each fixed-up longword holds an offset from the TEXT start.
+
+use crate::error::Error;
+use crate::hunk::types::Symbol;
+
+use super::error::ImageError;
+use super::loader::Loader;
+use super::types::{Fixup, Image, ImageFormat, Segment, SegmentKind};
+
+/// `BRA.S` over the 28-byte header.
+pub const PRG_MAGIC: u16 = 0x601A;
+const HEADER_LEN: usize = 28;
+/// Size of one DRI symbol table entry.
+const SYMBOL_LEN: usize = 14;
+
+/// DRI symbol type bits.
+mod sym_types {
+    pub const BSS: u16 = 0x0100;
+    pub const TEXT: u16 = 0x0200;
+    pub const DATA: u16 = 0x0400;
+    /// GST extended name: the next entry holds 14 more name bytes.
+    pub const EXTENDED: u16 = 0x0048;
+}
+
+pub struct PrgLoader;
+
+impl Loader for PrgLoader {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::AtariPrg
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.len() >= HEADER_LEN && read_u16(data, 0) == PRG_MAGIC
+    }
+
+    fn load(&self, data: &[u8]) -> Result<Image, Error> {
+        Ok(parse_prg(data)?)
+    }
+}
+
+/// Parse a GEMDOS executable.
+pub fn parse_prg(data: &[u8]) -> Result<Image, ImageError> {
+    if data.len() < HEADER_LEN || read_u16(data, 0) != PRG_MAGIC {
+        return Err(ImageError::Malformed { context: "missing $601A PRG header" });
+    }
+    let text_len = read_u32(data, 2) as usize;
+    let data_len = read_u32(data, 6) as usize;
+    let bss_len = read_u32(data, 10);
+    let sym_len = read_u32(data, 14) as usize;
+    let absflag = read_u16(data, 26);
+
+    let text_start = HEADER_LEN;
+    let data_start = text_start + text_len;
+    let sym_start = data_start + data_len;
+    let fixup_start = sym_start + sym_len;
+    if fixup_start > data.len() {
+        return Err(ImageError::Truncated { context: "TEXT/DATA/symbols", offset: data.len() });
+    }
+
+    let bases = [0, text_len as u32, (text_len + data_len) as u32];
+    let mut segments = vec![
+        make_segment(0, SegmentKind::Code, "TEXT", bases[0], &data[text_start..data_start], text_start),
+        make_segment(1, SegmentKind::Data, "DATA", bases[1], &data[data_start..sym_start], data_start),
+        Segment {
+            size: bss_len,
+            ..make_segment(2, SegmentKind::Bss, "BSS", bases[2], &[], sym_start)
+        },
+    ];
+
+    read_symbols(&data[sym_start..fixup_start], &mut segments);
+
+    if absflag == 0 && fixup_start + 4 <= data.len() {
+        let program = &data[text_start..sym_start];
+        for at in read_fixups(data, fixup_start)? {
+            if at + 4 > program.len() {
+                return Err(ImageError::Malformed { context: "fixup outside TEXT/DATA" });
+            }
+            let value = read_u32(program, at);
+            let seg = if at < text_len { 0 } else { 1 };
+            let target = bases.iter().rposition(|&b| value >= b).unwrap_or(0);
+            segments[seg].fixups.push(Fixup {
+                offset: at as u32 - bases[seg],
+                target,
+            });
+        }
+    }
+
+    Ok(Image {
+        format: ImageFormat::AtariPrg,
+        segments,
+        entry_points: vec![0],
+    })
+}
+
+fn make_segment(
+    index: usize,
+    kind: SegmentKind,
+    name: &str,
+    base_address: u32,
+    bytes: &[u8],
+    file_offset: usize,
+) -> Segment {
+    Segment {
+        index,
+        kind,
+        name: Some(name.to_string()),
+        base_address,
+        size: bytes.len() as u32,
+        data: bytes.to_vec(),
+        memory: None,
+        symbols: Vec::new(),
+        fixups: Vec::new(),
+        file_offset,
+    }
+}
+
+/// Decode the fixup table into offsets from the TEXT start.
+fn read_fixups(data: &[u8], start: usize) -> Result<Vec<usize>, ImageError> {
+    let first = read_u32(data, start) as usize;
+    if first == 0 {
+        return Ok(Vec::new());
+    }
+    let mut fixups = vec![first];
+    let mut at = first;
+    let mut pos = start + 4;
+    loop {
+        let delta = *data
+            .get(pos)
+            .ok_or(ImageError::Truncated { context: "fixup table", offset: pos })?;
+        pos += 1;
+        match delta {
+            0 => break,
+            1 => at += 254,
+            d if d % 2 == 0 => {
+                at += d as usize;
+                fixups.push(at);
+            }
+            _ => return Err(ImageError::Malformed { context: "odd fixup delta" }),
+        }
+    }
+    Ok(fixups)
+}
+
+/// Read DRI/GST symbols into their segments.
+///
+/// Values are normally program-relative (offsets from the TEXT start);
+/// a value below its segment's start is taken as segment-relative.
+fn read_symbols(table: &[u8], segments: &mut [Segment]) {
+    let mut entries = table.chunks_exact(SYMBOL_LEN);
+    while let Some(entry) = entries.next() {
+        let kind = read_u16(entry, 8);
+        let value = read_u32(entry, 10);
+        let mut name: Vec<u8> = entry[..8].to_vec();
+        if kind & sym_types::EXTENDED == sym_types::EXTENDED {
+            if let Some(ext) = entries.next() {
+                name.extend_from_slice(ext);
+            }
+        }
+        let name: String = name
+            .iter()
+            .take_while(|&&b| b != 0)
+            .map(|&b| b as char)
+            .collect();
+
+        let seg = if kind & sym_types::TEXT != 0 {
+            0
+        } else if kind & sym_types::DATA != 0 {
+            1
+        } else if kind & sym_types::BSS != 0 {
+            2
+        } else {
+            continue;
+        };
+        if name.is_empty() {
+            continue;
+        }
+        let base = segments[seg].base_address;
+        let offset = if value >= base { value - base } else { value };
+        segments[seg].symbols.push(Symbol { name, value: offset });
+    }
+}
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_prg(text: &[u8], data_seg: &[u8], symbols: &[u8], fixups: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&PRG_MAGIC.to_be_bytes());
+        for v in [text.len(), data_seg.len(), 64, symbols.len(), 0, 0] {
+            out.extend_from_slice(&(v as u32).to_be_bytes());
+        }
+        out.extend_from_slice(&0u16.to_be_bytes());
+        out.extend_from_slice(text);
+        out.extend_from_slice(data_seg);
+        out.extend_from_slice(symbols);
+        out.extend_from_slice(fixups);
+        out
+    }
+
+    #[test]
+    fn loads_segments_fixups_and_symbols() {
+        // move.l #DATA,d0 / rts / nop / nop
+        let text = [0x20, 0x3C, 0x00, 0x00, 0x00, 0x0C, 0x4E, 0x75, 0x4E, 0x71, 0x4E, 0x71];
+        // DATA: pointer back into TEXT (offset 6)
+        let data_seg = [0x00, 0x00, 0x00, 0x06];
+        let mut symbols = Vec::new();
+        symbols.extend_from_slice(b"_main\0\0\0");
+        symbols.extend_from_slice(&(0xA000u16 | sym_types::TEXT).to_be_bytes());
+        symbols.extend_from_slice(&0u32.to_be_bytes());
+        symbols.extend_from_slice(b"_table\0\0");
+        symbols.extend_from_slice(&(0xA000u16 | sym_types::DATA).to_be_bytes());
+        symbols.extend_from_slice(&12u32.to_be_bytes());
+        // Fixups at TEXT+2 and TEXT+12 (= DATA+0)
+        let fixups = [0x00, 0x00, 0x00, 0x02, 10, 0];
+
+        let image = parse_prg(&build_prg(&text, &data_seg, &symbols, &fixups)).unwrap();
+        assert_eq!(image.format, ImageFormat::AtariPrg);
+        let bases: Vec<u32> = image.segments.iter().map(|s| s.base_address).collect();
+        assert_eq!(bases, [0, 12, 16]);
+        assert_eq!(image.segments[2].size, 64);
+
+        assert_eq!(image.segments[0].fixups, [Fixup { offset: 2, target: 1 }]);
+        assert_eq!(image.segments[1].fixups, [Fixup { offset: 0, target: 0 }]);
+        assert_eq!(image.segments[0].symbols[0].name, "_main");
+        assert_eq!(image.segments[1].symbols[0].value, 0);
+    }
+
+    #[test]
+    fn fixup_table_skip_and_end() {
+        let mut data = vec![0, 0, 0, 4, 1, 2, 0];
+        data.extend_from_slice(&[0; 4]);
+        assert_eq!(read_fixups(&data, 0).unwrap(), [4, 260]);
+        assert_eq!(read_fixups(&[0, 0, 0, 0], 0).unwrap(), Vec::<usize>::new());
+        assert!(read_fixups(&[0, 0, 0, 4, 3, 0], 0).is_err());
+    }
+
+    #[test]
+    fn detected_by_magic() {
+        let prg = build_prg(&[0x4E, 0x75], &[], &[], &[0, 0, 0, 0]);
+        assert!(PrgLoader.detect(&prg));
+        assert!(!PrgLoader.detect(&[0x00, 0x00, 0x03, 0xF3]));
+    }
+}
@@ -0,0 +1,262 @@
+//! Format-agnostic program image: segments placed at addresses.
+
+use crate::hunk::types::{HunkFile, HunkType, Symbol};
+
+/// The executable format an image was loaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Amiga hunk executable (also used for ROMs, bootblocks and raw
+    /// binaries presented as synthetic hunks).
+    AmigaHunk,
+    /// Atari ST GEMDOS executable (PRG/TOS/TTP).
+    AtariPrg,
+    /// ELF32 big-endian for m68k.
+    Elf,
+}
+
+impl ImageFormat {
+    /// Returns a human-readable name for this format.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFormat::AmigaHunk => "Amiga Hunk Executable",
+            ImageFormat::AtariPrg => "Atari ST GEMDOS Executable",
+            ImageFormat::Elf => "m68k ELF",
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// What a segment contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    Code,
+    Data,
+    /// Zero-initialised memory with no file contents.
+    Bss,
+}
+
+impl SegmentKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SegmentKind::Code => "CODE",
+            SegmentKind::Data => "DATA",
+            SegmentKind::Bss => "BSS",
+        }
+    }
+}
+
+/// A place in an image: segment (hunk) index and offset within it.
+///
+/// Hunk files list every hunk from address 0, so an address alone does
+/// not say which hunk it is in; a location does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub hunk: usize,
+    pub offset: u32,
+}
+
+impl Location {
+    pub fn new(hunk: usize, offset: u32) -> Self {
+        Location { hunk, offset }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:${:04X}", self.hunk, self.offset)
+    }
+}
+
+/// A longword in a segment that the loader adjusts to point at a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixup {
+    /// Byte offset within the segment.
+    pub offset: u32,
+    /// Segment the relocated value points into.
+    pub target: usize,
+}
+
+/// A contiguous piece of the program placed at `base_address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Index of this segment in the image (0-based).
+    pub index: usize,
+    pub kind: SegmentKind,
+    /// Section name (hunk name, ELF section name, `TEXT`/`DATA`/`BSS`).
+    pub name: Option<String>,
+    /// Address the segment's offsets are relative to.
+    pub base_address: u32,
+    /// Size in memory (may exceed `data.len()` for BSS).
+    pub size: u32,
+    /// File contents. Empty for BSS.
+    pub data: Vec<u8>,
+    /// Format-specific memory attribute, e.g. Amiga `CHIP`/`FAST`.
+    pub memory: Option<String>,
+    /// Symbols with segment-relative values.
+    pub symbols: Vec<Symbol>,
+    pub fixups: Vec<Fixup>,
+    /// File offset of the segment contents.
+    pub file_offset: usize,
+}
+
+impl Segment {
+    /// Whether `address` falls inside this segment.
+    pub fn contains(&self, address: u32) -> bool {
+        address >= self.base_address && (address - self.base_address) < self.size.max(1)
+    }
+
+    /// The big-endian word at `offset` in the segment contents.
+    pub fn read_u16(&self, offset: u32) -> Option<u16> {
+        let at = offset as usize;
+        let bytes = self.data.get(at..at.checked_add(2)?)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// The big-endian longword at `offset` in the segment contents.
+    pub fn read_u32(&self, offset: u32) -> Option<u32> {
+        let at = offset as usize;
+        let bytes = self.data.get(at..at.checked_add(4)?)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// A loaded program in any supported format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub format: ImageFormat,
+    pub segments: Vec<Segment>,
+    /// Entry point addresses (the program start first).
+    pub entry_points: Vec<u32>,
+}
+
+impl Image {
+    /// Index of the segment containing `address`.
+    pub fn segment_at(&self, address: u32) -> Option<usize> {
+        self.segments.iter().position(|s| s.contains(address))
+    }
+
+    /// The location of `address` in the first segment containing it.
+    /// Only meaningful for images whose segments sit at distinct
+    /// addresses.
+    pub fn locate(&self, address: u32) -> Option<Location> {
+        let segment = &self.segments[self.segment_at(address)?];
+        Some(Location::new(segment.index, address - segment.base_address))
+    }
+
+    /// Where the relocated longword at `fixup` in `segment` points: its
+    /// value, less the base address of the target segment.
+    pub fn fixup_location(&self, segment: &Segment, fixup: &Fixup) -> Option<Location> {
+        let value = segment.read_u32(fixup.offset)?;
+        let target = self.segments.get(fixup.target)?;
+        Some(Location::new(fixup.target, value.wrapping_sub(target.base_address)))
+    }
+
+    /// The listed address of `location`.
+    pub fn address_of(&self, location: Location) -> Option<u32> {
+        let segment = self.segments.get(location.hunk)?;
+        Some(segment.base_address.wrapping_add(location.offset))
+    }
+
+    /// How listings refer to a segment: `hunk_N` for hunk files, else
+    /// the section name (or `seg_N`).
+    pub fn segment_label(&self, index: usize) -> String {
+        match (self.format, self.segments.get(index).and_then(|s| s.name.as_ref())) {
+            (ImageFormat::AmigaHunk, _) | (_, None) => {
+                let prefix = if self.format == ImageFormat::AmigaHunk { "hunk" } else { "seg" };
+                format!("{prefix}_{index}")
+            }
+            (_, Some(name)) => name.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Image {
+    /// A hunk image of one code segment at address 0, entered at its
+    /// start, for tests.
+    pub(crate) fn test_code(code: Vec<u8>, fixups: Vec<Fixup>) -> Image {
+        Image {
+            format: ImageFormat::AmigaHunk,
+            segments: Vec::new(),
+            entry_points: vec![0],
+        }
+        .with_segment(SegmentKind::Code, code, fixups)
+    }
+
+    /// Add a segment at address 0 holding `data`, sized to fit it.
+    pub(crate) fn with_segment(mut self, kind: SegmentKind, data: Vec<u8>, fixups: Vec<Fixup>) -> Image {
+        self.segments.push(Segment {
+            index: self.segments.len(),
+            kind,
+            name: None,
+            base_address: 0,
+            size: data.len() as u32,
+            data,
+            memory: None,
+            symbols: vec![],
+            fixups,
+            file_offset: 0,
+        });
+        self
+    }
+
+    /// Set the size in memory of the last segment added.
+    pub(crate) fn sized(mut self, size: u32) -> Image {
+        if let Some(segment) = self.segments.last_mut() {
+            segment.size = size;
+        }
+        self
+    }
+}
+
+impl From<&HunkFile> for Image {
+    fn from(hunk_file: &HunkFile) -> Image {
+        let segments = hunk_file
+            .hunks
+            .iter()
+            .map(|hunk| Segment {
+                index: hunk.index,
+                kind: match hunk.hunk_type {
+                    HunkType::Data => SegmentKind::Data,
+                    HunkType::Bss => SegmentKind::Bss,
+                    _ => SegmentKind::Code,
+                },
+                name: hunk.name.clone(),
+                base_address: hunk.base_address,
+                size: hunk.alloc_size,
+                data: hunk.data.clone(),
+                memory: Some(hunk.memory_type.to_string()),
+                symbols: hunk.symbols.clone(),
+                fixups: hunk
+                    .relocations
+                    .iter()
+                    .flat_map(|r| {
+                        r.offsets.iter().map(move |&offset| Fixup {
+                            offset,
+                            target: r.target_hunk as usize,
+                        })
+                    })
+                    .collect(),
+                file_offset: hunk.data_offset,
+            })
+            .collect::<Vec<_>>();
+
+        let entry_points = segments
+            .iter()
+            .find(|s| s.kind == SegmentKind::Code)
+            .map(|s| s.base_address)
+            .into_iter()
+            .collect();
+
+        Image {
+            format: ImageFormat::AmigaHunk,
+            segments,
+            entry_points,
+        }
+    }
+}
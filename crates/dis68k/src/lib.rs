@@ -1,18 +1,42 @@
+pub mod adf;
+pub mod analysis;
+pub mod crunch;
 pub mod error;
 pub mod hunk;
+pub mod image;
+pub mod library;
 pub mod m68k;
 pub mod output;
+pub mod project;
+pub mod raw;
+pub mod rom;
 pub mod symbols;
 
+pub use adf::{AdfVolume, Entry, EntryKind};
+pub use analysis::{
+    build_cfg, build_xrefs, discover_code, find_functions, find_small_data, hardware_usage,
+    track_library_bases, BasicBlock, CallGraph, Cfg, CodeMap, EdgeKind, FunctionSource,
+    HardwareUsage, LibraryBases, RegisterType, RegisterUsage, SmallData, Xref, XrefIndex, XrefKind,
+};
+pub use crunch::{detect_cruncher, parse_hunk_file_decrunched, Cruncher, Detection};
 pub use error::Error;
 pub use hunk::parser::parse_hunk_file;
-pub use hunk::types::{Hunk, HunkFile, HunkType, MemoryType, Relocation, Symbol};
+pub use hunk::types::{BlockInfo, Hunk, HunkFile, HunkType, MemoryType, Relocation, Symbol};
+pub use image::{
+    detect_format, generate_image_listing, load_image, Image, ImageFormat, Loader, Location,
+    Segment, SegmentKind,
+};
+pub use library::{analyze_library, generate_library_listing, LibraryAnalysis};
 pub use m68k::decode::decode_instruction;
 pub use m68k::instruction::{Condition, Instruction, Mnemonic, Operand, Size};
 pub use m68k::addressing::EffectiveAddress;
 pub use m68k::variants::CpuVariant;
-pub use output::listing::{generate_listing, ListingLine, ListingOptions};
+pub use output::listing::{generate_listing, ListingLabels, ListingLine, ListingOptions};
+pub use project::{parse_project, Project, ProjectError};
+pub use raw::{generate_raw_listing, load_raw_binary, RawLayout, RawSegment};
+pub use rom::{generate_rom_listing, merge_split_rom, RomAnalysis, RomImage};
 pub use symbols::{
-    AutoLabelResolver, CompositeResolver, HunkSymbolResolver, LvoResolver, SymbolResolver,
-    collect_branch_targets,
+    AutoLabelResolver, CompositeResolver, FdFile, HardwareResolver, HunkSymbolResolver,
+    IncludeResolver, IncludeSymbols, Library, LvoResolver, ProjectResolver, ResolvedSymbol,
+    StructResolver, SymbolKind, SymbolResolver, collect_branch_targets, library_name_for_fd, parse_fd, parse_includes,
 };
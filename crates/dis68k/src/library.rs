@@ -0,0 +1,394 @@
+//! Disk-based shared libraries and devices (`LIBS:`, `DEVS:`).
+//!
+//! A `.library` or `.device` file is an ordinary hunk executable whose
+//! first CODE hunk holds a RomTag, the same Resident structure ROM
+//! modules use. `rt_Init` leads to an InitTable (RTF_AUTOINIT) or an
+//! init routine that calls MakeLibrary/MakeFunctions, and from there to
+//! the function vector table.
+//!
+//! The pointers in these structures are relocated, so the hunks are
+//! first linked into one flat image (each hunk at its own offset, with
+//! RELOC32 applied) and the ROM analysis runs on that. Results are
+//! mapped back to hunk offsets and added to the hunks as symbols.
+
+pub mod error;
+
+pub use error::LibraryError;
+
+use std::collections::BTreeMap;
+
+use crate::hunk::types::{HunkFile, HunkType, Symbol};
+use crate::image::{Image, Location};
+use crate::m68k::variants::CpuVariant;
+use crate::output::listing::{emit_sections, push_line, ListingLine, ListingOptions};
+use crate::rom::image::RomImage;
+use crate::rom::resident::{
+    find_make_functions_tables, find_residents, name_functions, read_function_table,
+    FunctionTable, ResidentInit, RomModule,
+};
+use crate::rom::add_module_labels;
+use crate::symbols::fd::FdFile;
+use crate::symbols::resolver::SymbolResolver;
+
+/// The RomTag and function vectors of a library or device file.
+///
+/// Addresses in `module` are offsets into the linked image; use
+/// `locate` to turn them into hunk offsets.
+#[derive(Debug, Clone)]
+pub struct LibraryAnalysis {
+    pub module: RomModule,
+    /// Start of each hunk in the linked image.
+    pub hunk_starts: Vec<u32>,
+    /// Allocation size of each hunk.
+    hunk_sizes: Vec<u32>,
+}
+
+impl LibraryAnalysis {
+    /// The hunk and hunk offset of a linked-image address.
+    pub fn locate(&self, address: u32) -> Option<(usize, u32)> {
+        self.hunk_starts
+            .iter()
+            .zip(&self.hunk_sizes)
+            .position(|(&start, &size)| address >= start && address - start < size)
+            .map(|i| (i, address - self.hunk_starts[i]))
+    }
+
+    /// `hunk_N+$XXXX` for a linked-image address.
+    pub fn describe(&self, address: u32) -> String {
+        match self.locate(address) {
+            Some((hunk, offset)) => format!("hunk_{hunk}+${offset:04X}"),
+            None => format!("${address:08X} (outside the file)"),
+        }
+    }
+
+    /// The hunk locations of the library's function vectors.
+    pub fn function_entries(&self) -> Vec<Location> {
+        self.module
+            .functions
+            .iter()
+            .filter_map(|func| self.locate(func.address))
+            .map(|(hunk, offset)| Location::new(hunk, offset))
+            .collect()
+    }
+
+    /// Labels for the RomTag, init code, tables and functions, keyed by
+    /// hunk and offset.
+    pub fn labels(&self) -> BTreeMap<(usize, u32), String> {
+        let mut labels = BTreeMap::new();
+        add_module_labels(&self.module, &mut labels);
+        labels
+            .into_iter()
+            .filter_map(|(addr, name)| Some((self.locate(addr)?, name)))
+            .collect()
+    }
+
+    /// Add `labels` to the hunks' symbol lists. A hunk's own symbols win
+    /// where both name the same offset.
+    pub fn add_symbols(&self, hunk_file: &mut HunkFile) {
+        for ((index, offset), name) in self.labels() {
+            let Some(hunk) = hunk_file.hunks.get_mut(index) else {
+                continue;
+            };
+            if !hunk.symbols.iter().any(|s| s.value == offset) {
+                hunk.symbols.push(Symbol { name, value: offset });
+            }
+        }
+        for hunk in &mut hunk_file.hunks {
+            hunk.symbols.sort_by_key(|s| s.value);
+        }
+    }
+}
+
+/// Link the hunks into one image, each hunk following the previous one.
+fn link_hunks(hunk_file: &HunkFile) -> Result<(RomImage, Vec<u32>), LibraryError> {
+    let mut starts = Vec::with_capacity(hunk_file.hunks.len());
+    let mut size: u32 = 0;
+    for hunk in &hunk_file.hunks {
+        starts.push(size);
+        size += hunk.alloc_size.max(hunk.data.len() as u32).next_multiple_of(4);
+    }
+
+    let mut data = vec![0u8; size as usize];
+    for (hunk, &start) in hunk_file.hunks.iter().zip(&starts) {
+        let start = start as usize;
+        data[start..start + hunk.data.len()].copy_from_slice(&hunk.data);
+    }
+    for (hunk, &start) in hunk_file.hunks.iter().zip(&starts) {
+        for reloc in &hunk.relocations {
+            let target = starts.get(reloc.target_hunk as usize).copied().unwrap_or(0);
+            for &offset in &reloc.offsets {
+                if offset as usize + 4 > hunk.data.len() {
+                    return Err(LibraryError::BadRelocation { hunk: hunk.index, offset });
+                }
+                let at = (start + offset) as usize;
+                let value = u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+                data[at..at + 4].copy_from_slice(&value.wrapping_add(target).to_be_bytes());
+            }
+        }
+    }
+
+    let image = RomImage {
+        base_address: 0,
+        data,
+        byte_swapped: false,
+    };
+    Ok((image, starts))
+}
+
+/// Find the RomTag in the first CODE hunk and decode the function table.
+///
+/// Function names come from `fd` when given, then the standard library
+/// or device vectors, then the built-in LVO tables.
+pub fn analyze_library(
+    hunk_file: &HunkFile,
+    fd: Option<&FdFile>,
+    cpu: CpuVariant,
+) -> Result<LibraryAnalysis, LibraryError> {
+    let code = hunk_file
+        .hunks
+        .iter()
+        .position(|h| h.hunk_type == HunkType::Code)
+        .ok_or(LibraryError::NoCodeHunk)?;
+    let (image, hunk_starts) = link_hunks(hunk_file)?;
+    let hunk_sizes: Vec<u32> = hunk_file
+        .hunks
+        .iter()
+        .map(|h| h.alloc_size.max(h.data.len() as u32))
+        .collect();
+    let code_start = hunk_starts[code];
+    let code_end = code_start + hunk_sizes[code];
+
+    let resident = find_residents(&image)
+        .into_iter()
+        .find(|r| r.address >= code_start && r.address < code_end)
+        .ok_or(LibraryError::NoResident)?;
+
+    let analysis = LibraryAnalysis {
+        module: RomModule {
+            resident: resident.clone(),
+            start: code_start,
+            end: code_end,
+            function_tables: Vec::new(),
+            functions: Vec::new(),
+        },
+        hunk_starts,
+        hunk_sizes,
+    };
+
+    let mut table_addrs = Vec::new();
+    match &resident.init {
+        ResidentInit::AutoInit(init) => table_addrs.push(init.func_table),
+        ResidentInit::Routine(routine) => {
+            if let Some((hunk, _)) = analysis.locate(*routine) {
+                let end = analysis.hunk_starts[hunk] + analysis.hunk_sizes[hunk];
+                table_addrs.extend(find_make_functions_tables(&image, *routine, end, cpu));
+            }
+        }
+    }
+    table_addrs.dedup();
+
+    let function_tables: Vec<FunctionTable> = table_addrs
+        .into_iter()
+        .filter_map(|addr| read_function_table(&image, addr))
+        .collect();
+    let functions = function_tables
+        .iter()
+        .flat_map(|t| name_functions(&resident, t, fd))
+        .collect();
+
+    Ok(LibraryAnalysis {
+        module: RomModule {
+            function_tables,
+            functions,
+            ..analysis.module
+        },
+        ..analysis
+    })
+}
+
+/// Generate a disassembly listing for a library or device file.
+///
+/// The header describes the RomTag, init path and function table; the
+/// hunks follow with the discovered labels added as symbols.
+pub fn generate_library_listing(
+    hunk_file: &HunkFile,
+    analysis: &LibraryAnalysis,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+) -> Vec<ListingLine> {
+    let mut lines = Vec::new();
+    let mut line_num: u32 = 1;
+    let r = &analysis.module.resident;
+
+    let mut header = vec![
+        format!("; Amiga {} Disassembly", module_kind(r.node_type)),
+        format!(
+            "; Resident: {} v{} ({}, pri {}, {})",
+            r.name,
+            r.version,
+            r.node_type_name(),
+            r.priority,
+            r.flag_names()
+        ),
+    ];
+    if !r.id_string.is_empty() {
+        header.push(format!("; Id: {}", r.id_string));
+    }
+    header.push(format!("; RomTag: {}", analysis.describe(r.address)));
+    match &r.init {
+        ResidentInit::AutoInit(init) => {
+            header.push(format!(
+                "; InitTable: {} (base size {} bytes)",
+                analysis.describe(init.address),
+                init.data_size
+            ));
+            if init.init_routine != 0 {
+                header.push(format!("; Init: {}", analysis.describe(init.init_routine)));
+            }
+        }
+        ResidentInit::Routine(routine) => {
+            header.push(format!("; Init: {}", analysis.describe(*routine)));
+        }
+    }
+    for table in &analysis.module.function_tables {
+        header.push(format!(
+            "; Function table: {} ({}, {} vectors)",
+            analysis.describe(table.address),
+            if table.word_relative { "word-relative" } else { "absolute" },
+            table.entries.len()
+        ));
+    }
+    for func in &analysis.module.functions {
+        header.push(format!(
+            ";   {:>5}  {:<16} {}",
+            func.lvo,
+            analysis.describe(func.address),
+            func.name
+        ));
+    }
+    header.push(format!("; Hunks: {}", hunk_file.hunks.len()));
+
+    for text in header {
+        push_line(&mut lines, &mut line_num, options, text);
+    }
+    push_line(&mut lines, &mut line_num, options, String::new());
+
+    let mut labeled = hunk_file.clone();
+    analysis.add_symbols(&mut labeled);
+    emit_sections(&Image::from(&labeled), options, resolver, &mut lines, &mut line_num);
+
+    lines
+}
+
+fn module_kind(node_type: u8) -> &'static str {
+    use crate::rom::resident::node_types;
+    match node_type {
+        node_types::NT_LIBRARY => "Library",
+        node_types::NT_DEVICE => "Device",
+        node_types::NT_RESOURCE => "Resource",
+        _ => "Resident Module",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hunk::types::{Hunk, Relocation};
+    use crate::rom::resident::{node_types, rt_flags};
+    use crate::symbols::fd::parse_fd;
+
+    /// A two-hunk library: CODE holds the RomTag, names and functions,
+    /// DATA holds the InitTable and an absolute function table.
+    fn library_file() -> HunkFile {
+        let mut code = vec![0x70, 0xFF, 0x4E, 0x75]; // moveq #-1,d0 / rts
+        let romtag = code.len() as u32;
+        let push = |code: &mut Vec<u8>, v: u32| code.extend_from_slice(&v.to_be_bytes());
+        code.extend_from_slice(&0x4AFCu16.to_be_bytes());
+        push(&mut code, romtag); // rt_MatchTag (relocated)
+        push(&mut code, 0x40); // rt_EndSkip (relocated)
+        code.extend_from_slice(&[rt_flags::RTF_AUTOINIT, 37, node_types::NT_LIBRARY, 0]);
+        push(&mut code, 0x30); // rt_Name (relocated)
+        push(&mut code, 0x30); // rt_IdString (relocated)
+        push(&mut code, 0); // rt_Init → DATA+0 (relocated)
+        code.resize(0x30, 0x4E);
+        code.extend_from_slice(b"test.library\0\0\0\0");
+        // Five functions, each moveq #0,d0 / rts
+        for _ in 0..5 {
+            code.extend_from_slice(&[0x70, 0x00, 0x4E, 0x75]);
+        }
+        let mut code_hunk = Hunk::from_image(0, 0, &code, 0, None);
+        code_hunk.relocations.push(Relocation {
+            target_hunk: 0,
+            offsets: vec![romtag + 2, romtag + 6, romtag + 14, romtag + 18],
+        });
+        code_hunk.relocations.push(Relocation {
+            target_hunk: 1,
+            offsets: vec![romtag + 22],
+        });
+
+        let mut data = Vec::new();
+        for v in [0x22, 16, 0, 0] {
+            push(&mut data, v); // InitTable: size, funcs → DATA+16, no data, no init
+        }
+        let mut relocs = vec![4];
+        for i in 0..5u32 {
+            relocs.push(data.len() as u32);
+            push(&mut data, 0x40 + i * 4);
+        }
+        push(&mut data, 0xFFFF_FFFF);
+        let mut data_hunk = Hunk::from_image(1, 0, &data, 0, None);
+        data_hunk.hunk_type = HunkType::Data;
+        data_hunk.relocations = vec![
+            Relocation { target_hunk: 1, offsets: vec![relocs[0]] },
+            Relocation { target_hunk: 0, offsets: relocs[1..].to_vec() },
+        ];
+
+        HunkFile {
+            first_hunk: 0,
+            last_hunk: 1,
+            hunks: vec![code_hunk, data_hunk],
+            blocks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn finds_romtag_and_function_table() {
+        let file = library_file();
+        let analysis = analyze_library(&file, None, CpuVariant::M68000).unwrap();
+        let r = &analysis.module.resident;
+        assert_eq!(r.name, "test.library");
+        assert_eq!(analysis.locate(r.address), Some((0, 4)));
+
+        let table = &analysis.module.function_tables[0];
+        assert!(!table.word_relative);
+        assert_eq!(analysis.locate(table.address), Some((1, 16)));
+
+        let names: Vec<&str> = analysis.module.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["test_LibOpen", "test_LibClose", "test_LibExpunge", "test_LibReserved", "test_lvo_30"]
+        );
+        let labels = analysis.labels();
+        assert_eq!(labels.get(&(0, 0x40)).map(String::as_str), Some("test_LibOpen"));
+        assert_eq!(labels.get(&(1, 0)).map(String::as_str), Some("test_InitTable"));
+        assert_eq!(analysis.function_entries()[1], Location::new(0, 0x44));
+    }
+
+    #[test]
+    fn fd_names_public_functions() {
+        let fd = parse_fd("##base _TestBase\n##bias 30\nTestMe(x)(d0)\n##end\n");
+        let analysis = analyze_library(&library_file(), Some(&fd), CpuVariant::M68000).unwrap();
+        assert_eq!(analysis.module.functions[4].name, "test_TestMe");
+        assert_eq!(analysis.module.functions[4].lvo, -30);
+    }
+
+    #[test]
+    fn plain_executable_has_no_resident() {
+        let mut file = library_file();
+        file.hunks[0].data[4] = 0;
+        assert_eq!(
+            analyze_library(&file, None, CpuVariant::M68000).unwrap_err(),
+            LibraryError::NoResident
+        );
+    }
+}
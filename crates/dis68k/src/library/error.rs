@@ -0,0 +1,26 @@
+use std::fmt;
+
+/// Errors that can occur while analyzing a library or device file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryError {
+    /// The hunk file has no CODE hunk.
+    NoCodeHunk,
+    /// No RomTag was found in the first CODE hunk.
+    NoResident,
+    /// A relocation patches a longword outside its hunk.
+    BadRelocation { hunk: usize, offset: u32 },
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::NoCodeHunk => write!(f, "no CODE hunk"),
+            LibraryError::NoResident => write!(f, "no RomTag in the first CODE hunk"),
+            LibraryError::BadRelocation { hunk, offset } => {
+                write!(f, "relocation at hunk {hunk} offset ${offset:X} is outside the hunk")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {}
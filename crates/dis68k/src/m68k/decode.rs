@@ -1289,6 +1289,7 @@ fn decode_group8(ctx: &mut DecodeCtx<'_>, opcode: u16) -> Result<Instruction, De
 
 // ─── Group 9: SUB / SUBA / SUBX ─────────────────────────────────
 
+#[allow(clippy::manual_range_patterns)]
 fn decode_group9(ctx: &mut DecodeCtx<'_>, opcode: u16) -> Result<Instruction, DecodeError> {
     let dn = ((opcode >> 9) & 0x7) as u8;
     let op_mode = (opcode >> 6) & 0x7;
@@ -1357,6 +1358,7 @@ fn decode_group9(ctx: &mut DecodeCtx<'_>, opcode: u16) -> Result<Instruction, De
 
 // ─── Group B: CMP / CMPA / CMPM / EOR ───────────────────────────
 
+#[allow(clippy::manual_range_patterns)]
 fn decode_group_b(ctx: &mut DecodeCtx<'_>, opcode: u16) -> Result<Instruction, DecodeError> {
     let dn = ((opcode >> 9) & 0x7) as u8;
     let op_mode = (opcode >> 6) & 0x7;
@@ -1511,6 +1513,7 @@ fn decode_group_c(ctx: &mut DecodeCtx<'_>, opcode: u16) -> Result<Instruction, D
 
 // ─── Group D: ADD / ADDA / ADDX ─────────────────────────────────
 
+#[allow(clippy::manual_range_patterns)]
 fn decode_group_d(ctx: &mut DecodeCtx<'_>, opcode: u16) -> Result<Instruction, DecodeError> {
     let dn = ((opcode >> 9) & 0x7) as u8;
     let op_mode = (opcode >> 6) & 0x7;
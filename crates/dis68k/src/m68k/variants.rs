@@ -34,6 +34,7 @@ impl std::fmt::Display for CpuVariant {
 
 impl CpuVariant {
     /// Parse a variant from a string like "68000" or "68020".
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "68000" | "m68000" | "M68000" => Some(CpuVariant::M68000),
@@ -1,2 +1,3 @@
+pub mod call_args;
 pub mod formatter;
 pub mod listing;
@@ -0,0 +1,353 @@
+//! Call-site argument annotations for library calls.
+//!
+//! `RegisterState` follows what is loaded into each register since the
+//! start of the current basic block, so a `jsr -30(a6)` can be annotated
+//! with the function's arguments, their registers and the values found:
+//!
+//! ```text
+//! jsr      (-30,a6)  ; Open(name=d1, accessMode=d2)  d1 = str_name "ram:log", d2 = #1005 MODE_OLDFILE
+//! ```
+
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand, Size};
+use crate::symbols::amiga::LvoEntry;
+use crate::symbols::constants::ConstantSet;
+use crate::symbols::resolver::SymbolResolver;
+
+use super::formatter::{format_ea, format_operand_named};
+use super::listing::detect_string;
+
+/// Longest string shown in an annotation.
+const MAX_STRING_LEN: usize = 40;
+
+/// A value known to be in a register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    /// An immediate, from `moveq`/`move.l #n` or `clr.l`.
+    Immediate(u32),
+    /// An address, from `lea`.
+    Address(u32),
+    /// The longword at an address, from `move.l label,dn`.
+    Contents(u32),
+    /// Anything else, as operand text.
+    Operand(String),
+}
+
+/// Register contents tracked through a basic block.
+#[derive(Debug, Clone, Default)]
+pub struct RegisterState {
+    /// d0-d7, then a0-a7.
+    values: [Option<Value>; 16],
+}
+
+impl RegisterState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget everything, at the start of a basic block.
+    pub fn clear(&mut self) {
+        self.values = Default::default();
+    }
+
+    /// Apply one instruction's effect on the registers. Operands kept as
+    /// text are named through `resolver`, like in the listing.
+    pub fn update(&mut self, inst: &Instruction, resolver: Option<&dyn SymbolResolver>) {
+        match inst.mnemonic {
+            Mnemonic::Bra
+            | Mnemonic::Bcc
+            | Mnemonic::Dbcc
+            | Mnemonic::Jmp
+            | Mnemonic::Rts
+            | Mnemonic::Rte
+            | Mnemonic::Rtr => {
+                self.clear();
+                return;
+            }
+            // Scratch registers are not preserved across calls
+            Mnemonic::Jsr | Mnemonic::Bsr => {
+                for r in [0, 1, 8, 9] {
+                    self.values[r] = None;
+                }
+                return;
+            }
+            Mnemonic::Cmp
+            | Mnemonic::Cmpa
+            | Mnemonic::Cmpi
+            | Mnemonic::Tst
+            | Mnemonic::Btst
+            | Mnemonic::Pea => return,
+            _ => {}
+        }
+
+        let value = match (&inst.mnemonic, inst.operands.first()) {
+            (Mnemonic::Moveq, Some(Operand::MoveqImmediate(n))) => {
+                Some(Value::Immediate(*n as i32 as u32))
+            }
+            (Mnemonic::Move, Some(Operand::Ea(src))) if inst.size == Some(Size::Long) => {
+                Some(self.load(src, inst, resolver))
+            }
+            (Mnemonic::Movea, Some(Operand::Ea(src))) => Some(match (src, inst.size) {
+                (EffectiveAddress::Immediate(v), Some(Size::Word)) => {
+                    Value::Immediate(*v as u16 as i16 as i32 as u32)
+                }
+                _ => self.load(src, inst, resolver),
+            }),
+            (Mnemonic::Lea, Some(Operand::Ea(src))) => Some(self.address_of(src, inst, resolver)),
+            (Mnemonic::Clr, _) if inst.size == Some(Size::Long) => Some(Value::Immediate(0)),
+            _ => None,
+        };
+
+        if let (Mnemonic::Exg, [Operand::Ea(a), Operand::Ea(b)]) =
+            (&inst.mnemonic, inst.operands.as_slice())
+        {
+            if let (Some(a), Some(b)) = (register_index(a), register_index(b)) {
+                self.values.swap(a, b);
+            }
+            return;
+        }
+
+        // Address registers stepped by (An)+ and -(An)
+        for op in &inst.operands {
+            if let Operand::Ea(
+                EffectiveAddress::AddressPostIncrement(r) | EffectiveAddress::AddressPreDecrement(r),
+            ) = op
+            {
+                self.values[8 + *r as usize] = None;
+            }
+        }
+
+        match inst.operands.last() {
+            Some(Operand::Ea(dest)) => {
+                if let Some(r) = register_index(dest) {
+                    self.values[r] = value;
+                }
+            }
+            Some(Operand::RegisterList(mask)) => {
+                for r in 0..16 {
+                    if mask & (1 << r) != 0 {
+                        self.values[r] = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The value of a source operand.
+    fn load(&self, src: &EffectiveAddress, inst: &Instruction, resolver: Option<&dyn SymbolResolver>) -> Value {
+        match src {
+            EffectiveAddress::Immediate(v) => Value::Immediate(*v),
+            EffectiveAddress::DataDirect(_) | EffectiveAddress::AddressDirect(_) => {
+                let r = register_index(src).unwrap();
+                self.values[r]
+                    .clone()
+                    .unwrap_or_else(|| Value::Operand(format_ea(src)))
+            }
+            EffectiveAddress::AbsoluteShort(a) => Value::Contents(*a as i16 as i32 as u32),
+            EffectiveAddress::AbsoluteLong(a) => Value::Contents(*a),
+            EffectiveAddress::PcDisplacement(d) => Value::Contents(pc_target(inst, *d)),
+            _ => Value::Operand(format_operand_named(&Operand::Ea(src.clone()), inst, resolver)),
+        }
+    }
+
+    /// The address computed by `lea`.
+    fn address_of(&self, src: &EffectiveAddress, inst: &Instruction, resolver: Option<&dyn SymbolResolver>) -> Value {
+        match src {
+            EffectiveAddress::AbsoluteShort(a) => Value::Address(*a as i16 as i32 as u32),
+            EffectiveAddress::AbsoluteLong(a) => Value::Address(*a),
+            EffectiveAddress::PcDisplacement(d) => Value::Address(pc_target(inst, *d)),
+            EffectiveAddress::AddressIndirect(r) | EffectiveAddress::AddressDisplacement(r, _) => {
+                let disp = match src {
+                    EffectiveAddress::AddressDisplacement(_, d) => *d as i32 as u32,
+                    _ => 0,
+                };
+                // A small-data address, such as `lea _buffer(a4),a1`
+                let base_relative = resolver.and_then(|res| {
+                    let location = res.base_relative_target(inst.address, *r, disp as i32)?;
+                    res.resolve_nearest(location)
+                });
+                match (&self.values[8 + *r as usize], base_relative) {
+                    (Some(Value::Address(a)), _) => Value::Address(a.wrapping_add(disp)),
+                    (None, Some(label)) => Value::Operand(label.to_string()),
+                    _ if disp == 0 => Value::Operand(format!("a{r}")),
+                    _ => Value::Operand(format!("a{r}+{}", disp as i32)),
+                }
+            }
+            _ => Value::Operand(format_ea(src)),
+        }
+    }
+
+    fn get(&self, register: &str) -> Option<&Value> {
+        let index = match register.split_at(1) {
+            ("d", n) => n.parse::<usize>().ok()?,
+            ("a", n) => 8 + n.parse::<usize>().ok()?,
+            _ => return None,
+        };
+        self.values.get(index)?.as_ref()
+    }
+}
+
+/// Annotation for a library call: the function with its argument
+/// registers, then the values known to be in those registers.
+///
+/// `data` is the code segment at `base_address`, for strings behind
+/// `lea` addresses.
+pub fn format_call(
+    entry: &LvoEntry,
+    state: &RegisterState,
+    data: &[u8],
+    base_address: u32,
+    resolver: &dyn SymbolResolver,
+) -> String {
+    let args: Vec<String> = entry
+        .params
+        .iter()
+        .map(|p| format!("{}={}", p.name, p.registers.join("/")))
+        .collect();
+    let mut text = format!("{}({})", entry.function_name(), args.join(", "));
+
+    let values: Vec<String> = entry
+        .params
+        .iter()
+        .filter_map(|p| {
+            let [register] = p.registers.as_slice() else {
+                return None;
+            };
+            let value = state.get(register)?;
+            Some(format!(
+                "{register} = {}",
+                describe(value, p.constants, data, base_address, resolver)
+            ))
+        })
+        .collect();
+    if !values.is_empty() {
+        text.push_str("  ");
+        text.push_str(&values.join(", "));
+    }
+    text
+}
+
+fn describe(
+    value: &Value,
+    constants: Option<&ConstantSet>,
+    data: &[u8],
+    base_address: u32,
+    resolver: &dyn SymbolResolver,
+) -> String {
+    match value {
+        Value::Immediate(v) => {
+            let mut text = if (-0x8000..0x10000).contains(&(*v as i32)) {
+                format!("#{}", *v as i32)
+            } else {
+                format!("#${v:08X}")
+            };
+            if let Some(name) = constants.and_then(|c| c.describe(*v)) {
+                text.push(' ');
+                text.push_str(&name);
+            }
+            text
+        }
+        Value::Address(a) => {
+            let label = resolver.resolve_address(*a).map(|s| s.to_string());
+            let string = string_at(data, base_address, *a);
+            match (label, string) {
+                (Some(label), Some(s)) => format!("{label} {s}"),
+                (Some(label), None) => label,
+                (None, Some(s)) => s,
+                (None, None) => format!("${a:08X}"),
+            }
+        }
+        Value::Contents(a) => match resolver.resolve_address(*a) {
+            Some(label) => format!("({label})"),
+            None => format!("(${a:08X})"),
+        },
+        Value::Operand(text) => text.clone(),
+    }
+}
+
+/// The quoted NUL-terminated string at `address`, if there is one.
+fn string_at(data: &[u8], base_address: u32, address: u32) -> Option<String> {
+    let offset = address.checked_sub(base_address)? as usize;
+    let end = detect_string(data, offset)?;
+    let text: String = data[offset..end].iter().map(|&b| b as char).collect();
+    if text.len() > MAX_STRING_LEN {
+        Some(format!("\"{}...\"", &text[..MAX_STRING_LEN]))
+    } else {
+        Some(format!("\"{text}\""))
+    }
+}
+
+/// Index into `RegisterState::values` of a register operand.
+fn register_index(ea: &EffectiveAddress) -> Option<usize> {
+    match ea {
+        EffectiveAddress::DataDirect(r) => Some(*r as usize),
+        EffectiveAddress::AddressDirect(r) => Some(8 + *r as usize),
+        _ => None,
+    }
+}
+
+/// Target of `(d16,pc)` in the first extension word.
+fn pc_target(inst: &Instruction, disp: i16) -> u32 {
+    inst.address.wrapping_add(2).wrapping_add(disp as i32 as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::m68k::decode::decode_instruction;
+    use crate::m68k::variants::CpuVariant;
+    use crate::symbols::resolver::LvoResolver;
+
+    /// Run the state over `code` and annotate the `jsr` at its end.
+    fn annotate(code: &[u8], library: &str) -> String {
+        let resolver = LvoResolver::new(library);
+        let mut state = RegisterState::new();
+        let mut offset = 0;
+        loop {
+            let inst = decode_instruction(code, offset, 0, CpuVariant::M68000).unwrap();
+            if let [Operand::Ea(EffectiveAddress::AddressDisplacement(6, lvo))] = inst.operands[..] {
+                let entry = resolver.lvo_entry(lvo).unwrap();
+                return format_call(&entry, &state, code, 0, &resolver);
+            }
+            state.update(&inst, None);
+            offset += inst.size_bytes as usize;
+        }
+    }
+
+    #[test]
+    fn open_shows_string_and_mode() {
+        let code = [
+            0x41, 0xFA, 0x00, 0x0E, // lea (14,pc),a0 -> $10
+            0x22, 0x08, // move.l a0,d1
+            0x24, 0x3C, 0x00, 0x00, 0x03, 0xED, // move.l #1005,d2
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6)
+            b'r', b'a', b'm', b':', b'l', b'o', b'g', 0,
+        ];
+        assert_eq!(
+            annotate(&code, "dos"),
+            "Open(name=d1, accessMode=d2)  d1 = \"ram:log\", d2 = #1005 MODE_OLDFILE"
+        );
+    }
+
+    #[test]
+    fn branches_and_calls_forget_values() {
+        let code = [
+            0x70, 0x01, // moveq #1,d0
+            0x72, 0x00, // moveq #0,d1
+            0x61, 0x00, 0x00, 0x02, // bsr.w (clobbers d0/d1/a0/a1)
+            0x4E, 0xAE, 0xFF, 0x3A, // jsr (-198,a6)
+        ];
+        assert_eq!(annotate(&code, "exec"), "AllocMem(byteSize=d0, requirements=d1)");
+
+        let code = [
+            0x20, 0x3C, 0x00, 0x00, 0x01, 0x00, // move.l #256,d0
+            0x22, 0x3C, 0x00, 0x01, 0x00, 0x01, // move.l #$10001,d1
+            0x4E, 0xAE, 0xFF, 0x3A, // jsr (-198,a6)
+        ];
+        assert_eq!(
+            annotate(&code, "exec"),
+            "AllocMem(byteSize=d0, requirements=d1)  d0 = #256, d1 = #$00010001 MEMF_PUBLIC|MEMF_CLEAR"
+        );
+    }
+}
@@ -9,6 +9,7 @@ pub struct FormatOptions {
     pub uppercase: bool,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for FormatOptions {
     fn default() -> Self {
         FormatOptions { uppercase: false }
@@ -87,7 +88,8 @@ pub fn format_instruction(inst: &Instruction, opts: &FormatOptions) -> Formatted
 /// Format a decoded instruction with symbol resolution for branch targets.
 ///
 /// Like `format_instruction`, but displacement operands are resolved to
-/// label names when possible (e.g., `beq loc_001A` instead of `beq $0000001A`).
+/// label names when possible (e.g., `beq loc_001A` instead of `beq $0000001A`),
+/// and hardware registers are named (`DMACON`, `INTENA(a5)`).
 pub fn format_instruction_with_resolver(
     inst: &Instruction,
     opts: &FormatOptions,
@@ -168,6 +170,12 @@ fn format_operands_with_resolver(
     result
 }
 
+/// Format one operand of `inst` the way the listing does, naming what
+/// `resolver` knows.
+pub(crate) fn format_operand_named(op: &Operand, inst: &Instruction, resolver: Option<&dyn SymbolResolver>) -> String {
+    format_operand_with_resolver(op, inst, &FormatOptions::default(), resolver)
+}
+
 fn format_operand_with_resolver(
     op: &Operand,
     inst: &Instruction,
@@ -180,7 +188,7 @@ fn format_operand_with_resolver(
             if let Some(res) = resolver {
                 if target >= 0 {
                     if let Some(label) = res.resolve_address(target as u32) {
-                        return label;
+                        return label.to_string();
                     }
                 }
             }
@@ -191,7 +199,7 @@ fn format_operand_with_resolver(
             if let Some(res) = resolver {
                 if target >= 0 {
                     if let Some(label) = res.resolve_address(target as u32) {
-                        return label;
+                        return label.to_string();
                     }
                 }
             }
@@ -202,17 +210,101 @@ fn format_operand_with_resolver(
             if let Some(res) = resolver {
                 if target >= 0 {
                     if let Some(label) = res.resolve_address(target as u32) {
-                        return label;
+                        return label.to_string();
                     }
                 }
             }
             format!("${target:08X}")
         }
+        Operand::Ea(EffectiveAddress::AbsoluteLong(address)) => resolver
+            .and_then(|res| {
+                if let Some(label) = relocated_label(res, inst, *address) {
+                    return Some(label);
+                }
+                // `lea $DFF000,a5` loads a chip's base, not its first register
+                let is_base = matches!(inst.mnemonic, Mnemonic::Lea | Mnemonic::Pea)
+                    && res.resolve_register_offset(*address, 0).is_some();
+                if is_base {
+                    return None;
+                }
+                res.resolve_register(*address).map(|name| name.to_string())
+            })
+            .unwrap_or_else(|| format_operand(op, inst, opts)),
+        Operand::Ea(EffectiveAddress::AddressDisplacement(reg, disp)) => resolver
+            .and_then(|res| {
+                let name = match res.address_register_base(inst.address, *reg) {
+                    Some(base) => res.resolve_register_offset(base, *disp)?.to_string(),
+                    None => match res.address_register_type(inst.address, *reg) {
+                        Some(type_name) => res.resolve_struct_field(&type_name, *disp)?.to_string(),
+                        None => base_relative_label(res, inst, *reg, i32::from(*disp))?,
+                    },
+                };
+                Some(format!("{name}(a{reg})"))
+            })
+            .unwrap_or_else(|| format_operand(op, inst, opts)),
+        // `(bd,a4)`: a 32-bit small-data reference
+        Operand::Ea(EffectiveAddress::AddressBaseDisplacement { reg, base_disp, .. }) => resolver
+            .and_then(|res| {
+                let name = base_relative_label(res, inst, *reg, *base_disp)?;
+                let text = format_operand(op, inst, opts);
+                let rest = text.strip_prefix(&format!("({base_disp},"))?;
+                Some(format!("({name},{rest}"))
+            })
+            .unwrap_or_else(|| format_operand(op, inst, opts)),
+        Operand::Ea(EffectiveAddress::Immediate(value)) => resolver
+            .and_then(|res| {
+                relocated_label(res, inst, *value)
+                    .or_else(|| res.resolve_constant(*value).map(|name| name.to_string()))
+                    .or_else(|| field_constant(res, inst, *value))
+            })
+            .map(|name| format!("#{name}"))
+            .unwrap_or_else(|| format_operand(op, inst, opts)),
         // All other operands delegate to the original formatter
         _ => format_operand(op, inst, opts),
     }
 }
 
+/// Name a longword operand that a relocation points into a hunk, such
+/// as `lea _buffer,a0` or `move.l #data_2_0124,d0`.
+fn relocated_label(res: &dyn SymbolResolver, inst: &Instruction, value: u32) -> Option<String> {
+    let words = inst.address.wrapping_add(2)..inst.address.wrapping_add(inst.size_bytes as u32);
+    let location = res.relocation_target(words, value)?;
+    res.resolve_nearest(location).map(|label| label.to_string())
+}
+
+/// Name an immediate compared with or stored into a field of a typed
+/// register, as in `move.b #NT_TASK,ln_Type(a1)`.
+fn field_constant(res: &dyn SymbolResolver, inst: &Instruction, value: u32) -> Option<String> {
+    if !matches!(inst.mnemonic, Mnemonic::Move | Mnemonic::Cmpi) {
+        return None;
+    }
+    let Some(Operand::Ea(EffectiveAddress::AddressDisplacement(reg, disp))) = inst.operands.last() else {
+        return None;
+    };
+    let type_name = res.address_register_type(inst.address, *reg)?;
+    // Immediates are decoded unsigned, but constants may be negative
+    let signed = match inst.size {
+        Some(Size::Byte) => value as u8 as i8 as u32,
+        Some(Size::Word) => value as u16 as i16 as u32,
+        _ => value,
+    };
+    res.resolve_field_constant(&type_name, *disp, value)
+        .or_else(|| res.resolve_field_constant(&type_name, *disp, signed))
+        .map(|name| name.to_string())
+}
+
+/// Name a base-relative operand, such as `_count(a4)` in a small-data
+/// program.
+fn base_relative_label(
+    res: &dyn SymbolResolver,
+    inst: &Instruction,
+    register: u8,
+    displacement: i32,
+) -> Option<String> {
+    let location = res.base_relative_target(inst.address, register, displacement)?;
+    res.resolve_nearest(location).map(|label| label.to_string())
+}
+
 fn format_operands(inst: &Instruction, opts: &FormatOptions) -> String {
     if inst.operands.is_empty() {
         return String::new();
@@ -264,7 +356,7 @@ fn format_operand(op: &Operand, inst: &Instruction, _opts: &FormatOptions) -> St
     }
 }
 
-fn format_ea(ea: &EffectiveAddress) -> String {
+pub(crate) fn format_ea(ea: &EffectiveAddress) -> String {
     match ea {
         EffectiveAddress::DataDirect(n) => format!("d{n}"),
         EffectiveAddress::AddressDirect(n) => {
@@ -521,6 +613,7 @@ fn format_register_list(mask: u16, inst: &Instruction) -> String {
     parts.join("/")
 }
 
+#[allow(clippy::unused_unit)]
 fn format_reg_range(parts: &mut Vec<String>, mask: u16, prefix: &str) -> () {
     let mut i = 0u8;
     while i < 8 {
@@ -625,7 +718,19 @@ mod tests {
 
     #[test]
     fn format_branch_with_resolver() {
-        use crate::symbols::resolver::AutoLabelResolver;
+        use crate::image::Location;
+        use crate::symbols::resolver::{AutoLabelResolver, ResolvedSymbol, SymbolResolver};
+
+        // Hunk 0, listed from address 0
+        struct Hunk0(AutoLabelResolver);
+        impl SymbolResolver for Hunk0 {
+            fn locate(&self, address: u32) -> Option<Location> {
+                Some(Location::new(0, address))
+            }
+            fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+                self.0.resolve_location(location)
+            }
+        }
 
         let mut inst = make_inst(
             Mnemonic::Bcc,
@@ -634,7 +739,7 @@ mod tests {
         );
         inst.condition = Some(Condition::Eq);
         // address=0, target = 0 + 2 + 6 = 8
-        let resolver = AutoLabelResolver::from_targets(vec![8]);
+        let resolver = Hunk0(AutoLabelResolver::from_targets(0, 0, vec![8]));
         let fmt = format_instruction_with_resolver(
             &inst,
             &FormatOptions::default(),
@@ -644,6 +749,73 @@ mod tests {
         assert_eq!(fmt.operands, "loc_0008");
     }
 
+    #[test]
+    fn format_custom_registers() {
+        use crate::symbols::resolver::HardwareResolver;
+
+        let resolver = HardwareResolver::new();
+        let inst = make_inst(
+            Mnemonic::Move,
+            Some(Size::Word),
+            vec![
+                Operand::Ea(EffectiveAddress::Immediate(0x8380)),
+                Operand::Ea(EffectiveAddress::AbsoluteLong(0xDFF096)),
+            ],
+        );
+        let fmt = format_instruction_with_resolver(&inst, &FormatOptions::default(), Some(&resolver));
+        assert_eq!(fmt.operands, "#$8380,DMACON");
+
+        // Loading the base keeps the address
+        let inst = make_inst(
+            Mnemonic::Lea,
+            Some(Size::Long),
+            vec![
+                Operand::Ea(EffectiveAddress::AbsoluteLong(0xDFF000)),
+                Operand::Ea(EffectiveAddress::AddressDirect(5)),
+            ],
+        );
+        let fmt = format_instruction_with_resolver(&inst, &FormatOptions::default(), Some(&resolver));
+        assert_eq!(fmt.operands, "$00DFF000,a5");
+    }
+
+    #[test]
+    fn format_field_constant() {
+        use crate::symbols::include::parse_includes;
+        use crate::symbols::resolver::{IncludeResolver, ResolvedSymbol};
+
+        // A2 points at a MyData
+        struct Typed(IncludeResolver);
+        impl SymbolResolver for Typed {
+            fn address_register_type(&self, _address: u32, register: u8) -> Option<String> {
+                (register == 2).then(|| "MyData".to_string())
+            }
+            fn resolve_struct_field(&self, type_name: &str, offset: i16) -> Option<ResolvedSymbol> {
+                self.0.resolve_struct_field(type_name, offset)
+            }
+            fn resolve_field_constant(&self, type_name: &str, offset: i16, value: u32) -> Option<ResolvedSymbol> {
+                self.0.resolve_field_constant(type_name, offset, value)
+            }
+        }
+        let text = " STRUCTURE MyData,0\n UWORD md_Mode\n LABEL md_SIZE\nMODE_FAST EQU 1234\nMODE_OFF EQU -2\n";
+        let resolver = Typed(IncludeResolver::new(parse_includes(&[text])));
+        let format = |mnemonic, value, reg| {
+            let inst = make_inst(
+                mnemonic,
+                Some(Size::Word),
+                vec![
+                    Operand::Ea(EffectiveAddress::Immediate(value)),
+                    Operand::Ea(EffectiveAddress::AddressDisplacement(reg, 0)),
+                ],
+            );
+            format_instruction_with_resolver(&inst, &FormatOptions::default(), Some(&resolver)).operands
+        };
+        assert_eq!(format(Mnemonic::Move, 1234, 2), "#MODE_FAST,md_Mode(a2)");
+        assert_eq!(format(Mnemonic::Cmpi, 0xFFFE, 2), "#MODE_OFF,md_Mode(a2)");
+        // Without a known field the value stays a number
+        assert_eq!(format(Mnemonic::Move, 1234, 3), "#$04D2,(0,a3)");
+        assert_eq!(format(Mnemonic::Addi, 1234, 2), "#$04D2,md_Mode(a2)");
+    }
+
     #[test]
     fn format_branch_without_resolver_unchanged() {
         let mut inst = make_inst(
@@ -1,13 +1,26 @@
-use crate::hunk::types::{Hunk, HunkFile, HunkType};
+use std::ops::Range;
+
+use crate::analysis::small_data::{self, SmallData};
+use crate::analysis::{
+    build_xrefs, discover_code, find_functions, find_small_data, track_library_bases, CallGraph,
+    CodeMap, FunctionSource, LibraryBases, RegisterType, TableKind, XrefIndex,
+};
+use crate::hunk::types::HunkFile;
+use crate::image::{Fixup, Image, ImageFormat, Location, Segment, SegmentKind};
 use crate::m68k::addressing::EffectiveAddress;
 use crate::m68k::decode::{decode_instruction, DecodeError};
-use crate::m68k::instruction::{Mnemonic, Operand};
+use crate::m68k::instruction::{Instruction, Mnemonic, Operand, Size};
 use crate::m68k::variants::CpuVariant;
+use crate::project::{DataType, Project};
 use crate::symbols::resolver::{
-    AutoLabelResolver, CompositeResolver, HunkSymbolResolver, SymbolResolver,
+    AutoLabelResolver, CompositeResolver, HunkSymbolResolver, ProjectResolver, ResolvedSymbol,
+    SymbolKind, SymbolResolver,
 };
-use crate::symbols::labels::collect_branch_targets;
+use crate::symbols::amiga::LvoEntry;
+use crate::symbols::hardware;
+use crate::symbols::labels::collect_targets_in;
 
+use super::call_args::{format_call, RegisterState};
 use super::formatter::{format_instruction, format_instruction_with_resolver, FormatOptions};
 
 /// Options controlling the listing output.
@@ -20,6 +33,15 @@ pub struct ListingOptions {
     pub cpu: CpuVariant,
     /// Enable symbol resolution (auto-labels, LVO comments, hunk symbols).
     pub symbols: bool,
+    /// Structure types declared for registers at given instructions,
+    /// for naming `d16(An)` operands the data-flow pass can't type.
+    pub register_types: Vec<RegisterType>,
+    /// Labels, comments, region overrides and register hints from a
+    /// project file.
+    pub project: Project,
+    /// Decode code hunks from start to end instead of following control
+    /// flow from their entry points.
+    pub linear_sweep: bool,
 }
 
 impl Default for ListingOptions {
@@ -31,6 +53,9 @@ impl Default for ListingOptions {
             uppercase: false,
             cpu: CpuVariant::M68000,
             symbols: true,
+            register_types: Vec::new(),
+            project: Project::default(),
+            linear_sweep: false,
         }
     }
 }
@@ -58,10 +83,6 @@ pub fn generate_listing(
     let mut lines = Vec::new();
     let mut line_num: u32 = 1;
 
-    let fmt_opts = FormatOptions {
-        uppercase: options.uppercase,
-    };
-
     // File header comment
     push_line(
         &mut lines,
@@ -77,175 +98,703 @@ pub fn generate_listing(
     );
     push_line(&mut lines, &mut line_num, options, String::new());
 
-    for hunk in &hunk_file.hunks {
+    emit_sections(&Image::from(hunk_file), options, resolver, &mut lines, &mut line_num);
+
+    lines
+}
+
+/// Emit a `SECTION` block for every segment: the section header, symbol
+/// comments, and the disassembled or formatted segment contents.
+///
+/// Shared by `generate_listing` and listings of other images (Kickstart
+/// ROMs, raw binaries, PRG and ELF files).
+pub(crate) fn emit_sections(
+    image: &Image,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+    lines: &mut Vec<ListingLine>,
+    line_num: &mut u32,
+) {
+    emit_segments(image, options, resolver, false, lines, line_num);
+}
+
+/// Emit sections for an image whose segments sit at distinct addresses
+/// (ROM regions, raw binary segments, PRG and ELF sections).
+///
+/// An address in any segment names a label there, since addresses
+/// cannot collide. In hunk files every hunk starts at 0, so an address
+/// only names a label in the segment being listed.
+pub(crate) fn emit_image_sections(
+    image: &Image,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+    lines: &mut Vec<ListingLine>,
+    line_num: &mut u32,
+) {
+    emit_segments(image, options, resolver, true, lines, line_num);
+}
+
+fn emit_segments(
+    image: &Image,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+    distinct_addresses: bool,
+    lines: &mut Vec<ListingLine>,
+    line_num: &mut u32,
+) {
+    let code = discover_code(image, options.cpu, &options.project.code_entries(), options.linear_sweep);
+    let regions: Vec<Vec<Region>> = image
+        .segments
+        .iter()
+        .map(|segment| segment_regions(&options.project, segment, &code))
+        .collect();
+    let graph = if options.symbols {
+        let known: Vec<_> = options
+            .project
+            .functions
+            .keys()
+            .map(|&location| (location, FunctionSource::Project))
+            .collect();
+        find_functions(image, options.cpu, &code, &known)
+    } else {
+        CallGraph::default()
+    };
+    let labels = ListingLabels::build(image, options, &code, &regions, &graph, distinct_addresses);
+    let bases = &labels.bases;
+    let xrefs = if options.symbols {
+        build_xrefs(image, options.cpu, &code, labels.small_data, bases)
+    } else {
+        XrefIndex::default()
+    };
+
+    for (segment, regions) in image.segments.iter().zip(&regions) {
         // Section header
-        let section_type = match hunk.hunk_type {
-            HunkType::Code => "CODE",
-            HunkType::Data => "DATA",
-            HunkType::Bss => "BSS",
-            _ => "UNKNOWN",
+        let name = segment
+            .name
+            .clone()
+            .unwrap_or_else(|| image.segment_label(segment.index));
+        let unit = match image.format {
+            ImageFormat::AmigaHunk => "hunk",
+            _ => "segment",
         };
-        let default_name = format!("hunk_{}", hunk.index);
-        let name = hunk.name.as_deref().unwrap_or(&default_name);
-        push_line(&mut lines, &mut line_num, options, String::new());
+        let memory = match &segment.memory {
+            Some(mem) => format!(", mem={mem}"),
+            None => String::new(),
+        };
+        let location = if segment.base_address != 0 {
+            format!(" at ${:08X}", segment.base_address)
+        } else {
+            String::new()
+        };
+        push_line(lines, line_num, options, String::new());
         push_line(
-            &mut lines,
-            &mut line_num,
+            lines,
+            line_num,
             options,
             format!(
-                "; ──── SECTION {}, {} (hunk {}, {} bytes, mem={}) ────",
+                "; ──── SECTION {}, {} ({} {}, {} bytes{}){} ────",
                 name,
-                section_type,
-                hunk.index,
-                hunk.alloc_size,
-                hunk.memory_type
+                segment.kind.name(),
+                unit,
+                segment.index,
+                segment.size,
+                memory,
+                location
             ),
         );
 
         // Emit symbols as comments
-        if !hunk.symbols.is_empty() {
+        if !segment.symbols.is_empty() {
             push_line(
-                &mut lines,
-                &mut line_num,
+                lines,
+                line_num,
                 options,
                 "; Symbols:".to_string(),
             );
-            for sym in &hunk.symbols {
+            for sym in &segment.symbols {
                 push_line(
-                    &mut lines,
-                    &mut line_num,
+                    lines,
+                    line_num,
                     options,
-                    format!(";   ${:08X}  {}", sym.value, sym.name),
+                    format!(";   ${:08X}  {}", segment.base_address.wrapping_add(sym.value), sym.name),
                 );
             }
         }
 
-        push_line(&mut lines, &mut line_num, options, String::new());
-
-        match hunk.hunk_type {
-            HunkType::Code => {
-                // Build a per-hunk composite resolver if symbols are enabled
-                if options.symbols {
-                    let hunk_resolver = build_code_resolver(hunk, resolver, options.cpu);
-                    disassemble_code(
-                        &hunk.data,
-                        &mut lines,
-                        &mut line_num,
-                        options,
-                        &fmt_opts,
-                        Some(&hunk_resolver),
-                    );
-                } else {
-                    disassemble_code(
-                        &hunk.data,
-                        &mut lines,
-                        &mut line_num,
-                        options,
-                        &fmt_opts,
-                        None,
-                    );
+        push_line(lines, line_num, options, String::new());
+
+        let listing_resolver = labels.segment_resolver(image, segment.index, resolver);
+        // Project labels are shown even without symbols
+        let segment_resolver = (options.symbols || !labels.labels.is_empty())
+            .then_some(&listing_resolver as &dyn SymbolResolver);
+
+        let notes = if options.symbols {
+            SegmentNotes {
+                libraries: bases.calls_in(image, segment.index),
+                functions: function_headers(&graph, segment.index),
+                relocations: build_relocation_map(image, segment),
+                xrefs: xref_comments(&xrefs, segment.index, &listing_resolver),
+                table_entries: table_entries(&code, image, segment.index, &listing_resolver),
+            }
+        } else {
+            SegmentNotes::default()
+        };
+
+        match segment.kind {
+            SegmentKind::Code | SegmentKind::Data => {
+                for region in regions {
+                    match region.kind {
+                        RegionKind::Code => disassemble_code(
+                            segment,
+                            region.range.clone(),
+                            lines,
+                            line_num,
+                            options,
+                            segment_resolver,
+                            &notes,
+                        ),
+                        _ => format_data_section(
+                            segment,
+                            region,
+                            lines,
+                            line_num,
+                            options,
+                            segment_resolver,
+                            &notes,
+                        ),
+                    }
+                }
+                // Memory allocated past the hunk's contents, such as the
+                // BSS part of a merged small-data hunk
+                if segment.size as usize > segment.data.len() {
+                    let start = segment.data.len() as u32;
+                    format_reserved(segment, start, lines, line_num, options, segment_resolver, &notes);
                 }
             }
-            HunkType::Data => {
-                let reloc_offsets = if options.symbols {
-                    build_relocation_map(hunk)
-                } else {
-                    std::collections::BTreeMap::new()
-                };
-                format_data_section(
-                    &hunk.data,
-                    &mut lines,
-                    &mut line_num,
-                    options,
-                    &reloc_offsets,
-                );
+            SegmentKind::Bss => {
+                format_reserved(segment, 0, lines, line_num, options, segment_resolver, &notes)
             }
-            HunkType::Bss => {
-                let text = format_bss_line(hunk.alloc_size, options);
-                push_line(&mut lines, &mut line_num, options, text);
+        }
+    }
+}
+
+/// The labels a listing of an image shows, with the library bases and
+/// small-data base its operands are named through.
+///
+/// `--xrefs` and `--cfg` name places and format instructions through
+/// these, so they read like the listing.
+pub struct ListingLabels {
+    labels: CompositeResolver,
+    bases: LibraryBases,
+    small_data: Option<SmallData>,
+    symbols: bool,
+    distinct_addresses: bool,
+}
+
+impl ListingLabels {
+    /// The labels of a listing of `image` with `options`, given the code
+    /// found in it and its functions.
+    pub fn new(image: &Image, options: &ListingOptions, code: &CodeMap, graph: &CallGraph) -> Self {
+        let regions: Vec<Vec<Region>> = image
+            .segments
+            .iter()
+            .map(|segment| segment_regions(&options.project, segment, code))
+            .collect();
+        let distinct_addresses = image.format != ImageFormat::AmigaHunk
+            || image.segments.iter().any(|s| s.base_address != 0);
+        Self::build(image, options, code, &regions, graph, distinct_addresses)
+    }
+
+    fn build(
+        image: &Image,
+        options: &ListingOptions,
+        code: &CodeMap,
+        regions: &[Vec<Region>],
+        graph: &CallGraph,
+        distinct_addresses: bool,
+    ) -> Self {
+        let bases = if options.symbols {
+            let mut register_types = options.register_types.clone();
+            register_types.extend(options.project.register_types(image));
+            track_library_bases(image, options.cpu, code, &register_types)
+        } else {
+            LibraryBases::default()
+        };
+        let small_data = if options.symbols { find_small_data(image, options.cpu) } else { None };
+        let labels = build_labels(image, regions, options, &bases, small_data, graph, code);
+        ListingLabels {
+            labels,
+            bases,
+            small_data,
+            symbols: options.symbols,
+            distinct_addresses,
+        }
+    }
+
+    /// The library bases and structure pointers found in registers.
+    pub fn bases(&self) -> &LibraryBases {
+        &self.bases
+    }
+
+    /// Where A4 points, in a small-data program.
+    pub fn small_data(&self) -> Option<SmallData> {
+        self.small_data
+    }
+
+    /// A resolver naming places and the operands of `segment` like the
+    /// listing: these labels first, then `external` (typically the LVO
+    /// tables).
+    pub fn resolver<'a>(
+        &'a self,
+        image: &'a Image,
+        segment: usize,
+        external: Option<&'a dyn SymbolResolver>,
+    ) -> impl SymbolResolver + 'a {
+        self.segment_resolver(image, segment, external)
+    }
+
+    fn segment_resolver<'a>(
+        &'a self,
+        image: &'a Image,
+        segment: usize,
+        external: Option<&'a dyn SymbolResolver>,
+    ) -> ListingResolver<'a> {
+        let segment = &image.segments[segment];
+        ListingResolver {
+            labels: &self.labels,
+            external,
+            image,
+            segment,
+            distinct_addresses: self.distinct_addresses,
+            small_data: self.small_data,
+            fixups: if self.symbols {
+                segment.fixups.iter().map(|f| (f.offset, f)).collect()
+            } else {
+                Default::default()
+            },
+            absolute_bases: self.bases.absolute_bases_in(image, segment.index),
+            struct_bases: self.bases.struct_bases_in(image, segment.index),
+            small_data_uses: self.bases.small_data_uses_in(image, segment.index),
+        }
+    }
+
+    /// The place a label of the listing names: a project label or
+    /// function, a symbol, or an auto-label such as `sub_0124`,
+    /// `loc_0142` or `data_1_0124`.
+    pub fn find(&self, image: &Image, project: &Project, name: &str) -> Option<Location> {
+        let labels = project.labels.iter().map(|(&location, label)| (location, label.as_str()));
+        let functions = project.functions.iter().map(|(&location, f)| (location, f.name.as_str()));
+        let symbols = image.segments.iter().flat_map(|segment| {
+            segment
+                .symbols
+                .iter()
+                .map(|sym| (Location::new(segment.index, sym.value), sym.name.as_str()))
+        });
+        if let Some((location, _)) = labels.chain(functions).chain(symbols).find(|&(_, n)| n == name) {
+            return Some(location);
+        }
+
+        // An auto-label gives its address, or its segment and offset
+        let hex = |digits: &str| u32::from_str_radix(digits, 16).ok();
+        let candidates: Vec<Location> = match name.split('_').collect::<Vec<_>>()[..] {
+            [_, address] => hex(address)
+                .map(|address| {
+                    image
+                        .segments
+                        .iter()
+                        .map(|s| Location::new(s.index, address.wrapping_sub(s.base_address)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            [_, hunk, offset] => hunk
+                .parse()
+                .ok()
+                .zip(hex(offset))
+                .map(|(hunk, offset)| vec![Location::new(hunk, offset)])
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        candidates
+            .into_iter()
+            .find(|&location| self.labels.resolve_location(location).is_some_and(|s| s.name == name))
+    }
+}
+
+/// Build the labels of every segment of an image.
+///
+/// Combines: project labels (highest priority) → hunk symbols →
+/// `sub_XXXX` auto-labels for functions → `switch_XXXX` and `case_XXXX`
+/// for jump tables and their targets → auto-labels for branch/jump
+/// targets in code regions → auto-labels
+/// like `data_2_0124` for the targets of relocated and small-data
+/// operands in code regions. Only project labels are used when symbols
+/// are off.
+fn build_labels(
+    image: &Image,
+    regions: &[Vec<Region>],
+    options: &ListingOptions,
+    bases: &LibraryBases,
+    small_data: Option<SmallData>,
+    graph: &CallGraph,
+    code: &CodeMap,
+) -> CompositeResolver {
+    let mut labels = CompositeResolver::new();
+
+    // Labels and function names from the project file
+    let project = ProjectResolver::new(&options.project, &image.segments);
+    if !project.is_empty() {
+        labels.add(Box::new(project));
+    }
+    if !options.symbols {
+        return labels;
+    }
+
+    // Hunk symbols next (user-defined labels take priority)
+    labels.add(Box::new(HunkSymbolResolver::from_segments(&image.segments)));
+
+    // Auto-generated labels for functions, ahead of other branch targets
+    let mut auto = AutoLabelResolver::new();
+    for function in graph.functions.values() {
+        auto.add_label(function.entry, function.label(), SymbolKind::CodeLabel);
+    }
+
+    // Jump tables and their cases
+    let address = |location: Location| image.segments[location.hunk].base_address.wrapping_add(location.offset);
+    for table in code.jump_tables.values() {
+        let name = format!("switch_{:04X}", address(table.table));
+        auto.add_label(table.table, name, SymbolKind::CodeLabel);
+        if table.kind != TableKind::Branches {
+            for &target in &table.targets {
+                auto.add_label(target, format!("case_{:04X}", address(target)), SymbolKind::CodeLabel);
             }
-            _ => {}
         }
     }
 
-    lines
+    // Auto-generated labels from branch/jump targets
+    for (segment, regions) in image.segments.iter().zip(regions) {
+        for region in regions.iter().filter(|region| region.kind == RegionKind::Code) {
+            let targets = collect_targets_in(
+                &segment.data,
+                segment.base_address,
+                region.range.clone(),
+                options.cpu,
+            );
+            auto.add_targets(segment.index, segment.base_address, targets);
+        }
+    }
+
+    // Auto-generated labels from relocated operands, after the branch
+    // targets so a `loc_XXXX` label keeps its name
+    for (segment, regions) in image.segments.iter().zip(regions) {
+        let in_code = |offset: u32| {
+            regions.iter().any(|region| {
+                region.kind == RegionKind::Code && region.range.contains(&(offset as usize))
+            })
+        };
+        for fixup in segment.fixups.iter().filter(|f| in_code(f.offset)) {
+            let Some(location) = image.fixup_location(segment, fixup) else {
+                continue;
+            };
+            if location.offset < image.segments[location.hunk].size {
+                add_data_label(&mut auto, image, location);
+            }
+        }
+    }
+
+    // Auto-generated labels from small-data operands
+    if let Some(small_data) = small_data {
+        for (segment, regions) in image.segments.iter().zip(regions) {
+            for region in regions.iter().filter(|region| region.kind == RegionKind::Code) {
+                let range = region.range.clone();
+                for location in small_data.collect_references(image, segment, range, options.cpu, bases) {
+                    add_data_label(&mut auto, image, location);
+                }
+            }
+        }
+    }
+    if !auto.is_empty() {
+        labels.add(Box::new(auto));
+    }
+
+    labels
 }
 
-/// A resolver that combines per-hunk resolvers with an external resolver.
+/// Label `location` after its segment's kind, index and offset, as in
+/// `data_2_0124`.
+fn add_data_label(auto: &mut AutoLabelResolver, image: &Image, location: Location) {
+    let segment = &image.segments[location.hunk];
+    let name = format!(
+        "{}_{}_{:04X}",
+        segment.kind.name().to_lowercase(),
+        location.hunk,
+        location.offset
+    );
+    auto.add_label(location, name, SymbolKind::for_segment(segment.kind));
+}
+
+/// The resolver for one segment of a listing: the image's labels, then
+/// the caller's resolver (typically the LVO tables).
 ///
-/// Queries the owned composite first, then falls back to the external
-/// resolver (typically the LVO tables passed in by the caller).
+/// Addresses in operands are located in the segment being listed, or
+/// in any segment of an image at distinct addresses; relocated
+/// operands are located by their fixups and A4-relative ones in a
+/// small-data program by the base. Also answers which absolute
+/// addresses and structure pointers the data-flow pass found in address
+/// registers.
 struct ListingResolver<'a> {
-    local: CompositeResolver,
+    labels: &'a CompositeResolver,
     external: Option<&'a dyn SymbolResolver>,
+    image: &'a Image,
+    segment: &'a Segment,
+    distinct_addresses: bool,
+    small_data: Option<SmallData>,
+    /// The segment's fixups by offset.
+    fixups: std::collections::BTreeMap<u32, &'a Fixup>,
+    absolute_bases: std::collections::BTreeMap<u32, Vec<(u8, u32)>>,
+    struct_bases: std::collections::BTreeMap<u32, Vec<(u8, String)>>,
+    /// Instructions that address memory through the small-data base.
+    small_data_uses: std::collections::BTreeSet<u32>,
 }
 
 impl<'a> SymbolResolver for ListingResolver<'a> {
-    fn resolve_lvo(&self, offset: i16) -> Option<String> {
-        self.local.resolve_lvo(offset)
-            .or_else(|| self.external.and_then(|e| e.resolve_lvo(offset)))
+    fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.labels
+            .resolve_location(location)
+            .or_else(|| self.external.and_then(|e| e.resolve_location(location)))
     }
 
-    fn resolve_address(&self, address: u32) -> Option<String> {
-        self.local.resolve_address(address)
-            .or_else(|| self.external.and_then(|e| e.resolve_address(address)))
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        let external = self.external.and_then(|e| e.resolve_nearest(location));
+        match (self.labels.resolve_nearest(location), external) {
+            (Some(label), Some(other)) if other.offset < label.offset => Some(other),
+            (label, other) => label.or(other),
+        }
     }
-}
 
-/// Build a resolver for a code hunk.
-///
-/// Combines: hunk symbols (highest priority) → auto-labels → external resolver (LVO etc.)
-fn build_code_resolver<'a>(
-    hunk: &Hunk,
-    external: Option<&'a dyn SymbolResolver>,
-    cpu: CpuVariant,
-) -> ListingResolver<'a> {
-    let mut local = CompositeResolver::new();
+    fn locate(&self, address: u32) -> Option<Location> {
+        if self.distinct_addresses {
+            return self.image.locate(address);
+        }
+        let offset = address.wrapping_sub(self.segment.base_address);
+        (offset < self.segment.size).then(|| Location::new(self.segment.index, offset))
+    }
 
-    // Hunk symbols first (user-defined labels take priority)
-    if !hunk.symbols.is_empty() {
-        local.add(Box::new(HunkSymbolResolver::from_hunk(hunk)));
+    fn relocation_target(&self, words: Range<u32>, value: u32) -> Option<Location> {
+        let base = self.segment.base_address;
+        let (start, end) = (words.start.wrapping_sub(base), words.end.wrapping_sub(base));
+        self.fixups
+            .range(start..end)
+            .filter(|&(&offset, _)| offset + 4 <= end)
+            .find(|&(&offset, _)| self.segment.read_u32(offset) == Some(value))
+            .and_then(|(_, fixup)| self.image.fixup_location(self.segment, fixup))
     }
 
-    // Auto-generated labels from branch/jump targets
-    let targets = collect_branch_targets(&hunk.data, 0, cpu);
-    if !targets.is_empty() {
-        local.add(Box::new(AutoLabelResolver::from_targets(targets)));
+    fn base_relative_target(&self, address: u32, register: u8, displacement: i32) -> Option<Location> {
+        let small_data = self.small_data?;
+        if register != small_data::BASE_REGISTER || !self.small_data_uses.contains(&address) {
+            return None;
+        }
+        small_data.target(self.image, displacement)
+    }
+
+    fn resolve_lvo(&self, offset: i16) -> Option<ResolvedSymbol> {
+        self.external.and_then(|e| e.resolve_lvo(offset))
+    }
+
+    fn lvo_entry(&self, offset: i16) -> Option<LvoEntry> {
+        self.external.and_then(|e| e.lvo_entry(offset))
+    }
+
+    fn library_lvo_entry(&self, library: &str, offset: i16) -> Option<LvoEntry> {
+        self.external.and_then(|e| e.library_lvo_entry(library, offset))
+    }
+
+    fn resolve_register(&self, address: u32) -> Option<ResolvedSymbol> {
+        self.external.and_then(|e| e.resolve_register(address))
+    }
+
+    fn resolve_register_offset(&self, base: u32, offset: i16) -> Option<ResolvedSymbol> {
+        self.external.and_then(|e| e.resolve_register_offset(base, offset))
+    }
+
+    fn address_register_base(&self, address: u32, register: u8) -> Option<u32> {
+        self.absolute_bases
+            .get(&address)?
+            .iter()
+            .find(|(r, _)| *r == register)
+            .map(|(_, base)| *base)
+    }
+
+    fn address_register_type(&self, address: u32, register: u8) -> Option<String> {
+        self.struct_bases
+            .get(&address)?
+            .iter()
+            .find(|(r, _)| *r == register)
+            .map(|(_, name)| name.clone())
+    }
+
+    fn resolve_struct_field(&self, type_name: &str, offset: i16) -> Option<ResolvedSymbol> {
+        self.external.and_then(|e| e.resolve_struct_field(type_name, offset))
+    }
+
+    fn resolve_constant(&self, value: u32) -> Option<ResolvedSymbol> {
+        self.external.and_then(|e| e.resolve_constant(value))
     }
 
-    ListingResolver { local, external }
+    fn resolve_field_constant(&self, type_name: &str, offset: i16, value: u32) -> Option<ResolvedSymbol> {
+        self.external.and_then(|e| e.resolve_field_constant(type_name, offset, value))
+    }
+}
+
+/// What the whole-image analyses found in a segment.
+#[derive(Debug, Default)]
+struct SegmentNotes {
+    /// The library base in the register of each `jsr d16(An)` base
+    /// tracking followed, by address: `None` where it could not be known.
+    libraries: std::collections::BTreeMap<u32, Option<String>>,
+    /// A header comment for each function entry, by offset.
+    functions: std::collections::BTreeMap<u32, String>,
+    /// The segment each relocated longword points into, by offset.
+    relocations: std::collections::BTreeMap<u32, String>,
+    /// The `; XREF:` comment lines above each referenced label, by
+    /// offset.
+    xrefs: std::collections::BTreeMap<u32, Vec<String>>,
+    /// The size and value of each jump table entry, by offset.
+    table_entries: std::collections::BTreeMap<u32, (u32, String)>,
+}
+
+/// `; Function: 2 callers, 86 bytes` for each function in a segment.
+fn function_headers(graph: &CallGraph, segment: usize) -> std::collections::BTreeMap<u32, String> {
+    graph
+        .functions
+        .values()
+        .filter(|function| function.entry.hunk == segment)
+        .map(|function| {
+            let callers = match graph.callers(function.entry).count() {
+                0 => "no callers".to_string(),
+                1 => "1 caller".to_string(),
+                n => format!("{n} callers"),
+            };
+            let header = format!("; Function: {callers}, {} bytes", function.size());
+            (function.entry.offset, header)
+        })
+        .collect()
 }
 
-/// Build a map from byte offset → target hunk index for relocation annotations.
-fn build_relocation_map(hunk: &Hunk) -> std::collections::BTreeMap<u32, u32> {
-    let mut map = std::collections::BTreeMap::new();
-    for reloc in &hunk.relocations {
-        for &offset in &reloc.offsets {
-            map.insert(offset, reloc.target_hunk);
+/// `; XREF: loc_0142 (r), sub_0300 (call)` for each label in a segment
+/// that something refers to, five references to a line. A reference is
+/// named by the label at or before it, so a function calling another
+/// from several places is shown once.
+fn xref_comments(
+    xrefs: &XrefIndex,
+    segment: usize,
+    labels: &dyn SymbolResolver,
+) -> std::collections::BTreeMap<u32, Vec<String>> {
+    xrefs
+        .targets_in(segment)
+        .filter(|&(&location, _)| labels.resolve_location(location).is_some())
+        .map(|(location, refs)| {
+            let mut names: Vec<String> = Vec::new();
+            for xref in refs {
+                let source = labels
+                    .resolve_nearest(xref.from)
+                    .map_or_else(|| xref.from.to_string(), |symbol| symbol.name);
+                let name = format!("{source} ({})", xref.kind.name());
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            let comments = names
+                .chunks(5)
+                .map(|chunk| format!("; XREF: {}", chunk.join(", ")))
+                .collect();
+            (location.offset, comments)
+        })
+        .collect()
+}
+
+/// The entries of the jump tables in a segment: `case_0C2E-switch_0C22`
+/// for an offset from the table (less the distance to the base when the
+/// jump adds it to a later address), or `case_0C2E` for a pointer.
+fn table_entries(
+    code: &CodeMap,
+    image: &Image,
+    segment: usize,
+    labels: &dyn SymbolResolver,
+) -> std::collections::BTreeMap<u32, (u32, String)> {
+    let name = |location: Location| match labels.resolve_location(location) {
+        Some(symbol) => symbol.name,
+        None => format!("${:08X}", image.segments[location.hunk].base_address.wrapping_add(location.offset)),
+    };
+    let mut entries = std::collections::BTreeMap::new();
+    for table in code.jump_tables.values().filter(|t| t.table.hunk == segment) {
+        let value = |target: Location| match table.kind {
+            TableKind::Offsets => {
+                let delta = table.base.offset as i64 - table.table.offset as i64;
+                let adjust = match delta {
+                    0 => String::new(),
+                    d if d > 0 => format!("-{d}"),
+                    d => format!("+{}", -d),
+                };
+                format!("{}-{}{adjust}", name(target), name(table.table))
+            }
+            _ => name(target),
+        };
+        if table.kind == TableKind::Branches {
+            continue;
+        }
+        for (i, &target) in table.targets.iter().enumerate() {
+            entries.insert(table.entry(i).offset, (table.entry_size, value(target)));
         }
     }
-    map
+    entries
+}
+
+/// Build a map from byte offset → target segment label for relocation annotations.
+fn build_relocation_map(image: &Image, segment: &Segment) -> std::collections::BTreeMap<u32, String> {
+    segment
+        .fixups
+        .iter()
+        .map(|fixup| (fixup.offset, image.segment_label(fixup.target)))
+        .collect()
 }
 
+/// Disassemble the code in `range` of a segment.
+#[allow(clippy::needless_borrow)]
 fn disassemble_code(
-    data: &[u8],
+    segment: &Segment,
+    range: Range<usize>,
     lines: &mut Vec<ListingLine>,
     line_num: &mut u32,
     options: &ListingOptions,
-    fmt_opts: &FormatOptions,
     resolver: Option<&dyn SymbolResolver>,
+    notes: &SegmentNotes,
 ) {
-    let mut offset = 0usize;
+    let fmt_opts = &FormatOptions {
+        uppercase: options.uppercase,
+    };
+    // Instructions may not run on into a following data region
+    let data = &segment.data[..range.end];
+    let base_address = segment.base_address;
+    let mut offset = range.start;
+    let mut registers = RegisterState::new();
 
     while offset < data.len() {
-        // Emit label if this address has one
+        if let Some(header) = notes.functions.get(&(offset as u32)) {
+            push_line(lines, line_num, options, header.clone());
+        }
+        emit_annotations(&options.project, segment.index, offset as u32, lines, line_num, options);
+
+        // Emit label if this address has one; a label starts a basic block
         if let Some(ref res) = resolver {
-            if let Some(label) = res.resolve_address(offset as u32) {
+            if let Some(label) = res.resolve_location(Location::new(segment.index, offset as u32)) {
+                emit_xrefs(notes, offset as u32, lines, line_num, options);
                 push_line(lines, line_num, options, format!("{label}:"));
+                registers.clear();
             }
         }
 
-        match decode_instruction(data, offset, 0, options.cpu) {
+        match decode_instruction(data, offset, base_address, options.cpu) {
             Ok(inst) => {
                 let formatted = if resolver.is_some() {
                     format_instruction_with_resolver(&inst, fmt_opts, resolver)
@@ -254,12 +803,15 @@ fn disassemble_code(
                 };
 
                 // Build the LVO comment if applicable
-                let comment = resolver
-                    .as_ref()
-                    .and_then(|res| detect_lvo_comment(&inst.mnemonic, &inst.operands, *res));
+                let comment = resolver.as_ref().and_then(|res| {
+                    let library = notes.libraries.get(&inst.address).map(Option::as_deref);
+                    detect_lvo_comment(&inst, library, *res, &registers, &segment.data, base_address)
+                        .or_else(|| detect_register_write(&inst, *res))
+                });
+                registers.update(&inst, resolver);
 
                 let mut text = format_code_line(
-                    offset as u32,
+                    inst.address,
                     &formatted.hex_bytes,
                     &formatted.mnemonic,
                     &formatted.operands,
@@ -278,7 +830,7 @@ fn disassemble_code(
                 while offset < data.len() {
                     let byte = data[offset];
                     let text = format_code_line(
-                        offset as u32,
+                        base_address + offset as u32,
                         &format!("{byte:02X}"),
                         "dc.b",
                         &format!("${byte:02X}"),
@@ -290,11 +842,12 @@ fn disassemble_code(
                 break;
             }
             Err(_) => {
+                registers.clear();
                 // Unknown opcode — emit dc.w and advance 2 bytes
                 if offset + 1 < data.len() {
                     let w = u16::from_be_bytes([data[offset], data[offset + 1]]);
                     let text = format_code_line(
-                        offset as u32,
+                        base_address + offset as u32,
                         &format!("{:04X}", w),
                         "dc.w",
                         &format!("${w:04X}"),
@@ -305,7 +858,7 @@ fn disassemble_code(
                 } else {
                     let byte = data[offset];
                     let text = format_code_line(
-                        offset as u32,
+                        base_address + offset as u32,
                         &format!("{byte:02X}"),
                         "dc.b",
                         &format!("${byte:02X}"),
@@ -319,24 +872,67 @@ fn disassemble_code(
     }
 }
 
-/// Detect if an instruction is a JSR/JMP through (displacement,A6) and
+/// Detect if an instruction is a JSR/JMP through (displacement,An) and
 /// resolve the displacement as an LVO name.
+///
+/// `library` is what the data-flow pass found in An: a library base, or
+/// `Some(None)` for a base it could not know, which gets no comment.
+/// With nothing found, only calls through A6 are resolved, against the
+/// resolver's default library (ExecBase).
+///
+/// When the resolver knows the function's arguments, the comment shows
+/// them with their registers and the values `registers` has tracked
+/// for them in this basic block.
 fn detect_lvo_comment(
-    mnemonic: &Mnemonic,
-    operands: &[Operand],
+    inst: &Instruction,
+    library: Option<Option<&str>>,
     resolver: &dyn SymbolResolver,
+    registers: &RegisterState,
+    data: &[u8],
+    base_address: u32,
 ) -> Option<String> {
-    if !matches!(mnemonic, Mnemonic::Jsr | Mnemonic::Jmp) {
+    if !matches!(inst.mnemonic, Mnemonic::Jsr | Mnemonic::Jmp) {
         return None;
     }
+    let [Operand::Ea(EffectiveAddress::AddressDisplacement(reg, disp))] = inst.operands[..] else {
+        return None;
+    };
 
-    for op in operands {
-        if let Operand::Ea(EffectiveAddress::AddressDisplacement(6, disp)) = op {
-            return resolver.resolve_lvo(*disp);
-        }
+    match library {
+        Some(Some(library)) => match resolver.library_lvo_entry(library, disp) {
+            Some(entry) => Some(format_call(&entry, registers, data, base_address, resolver)),
+            None => Some(format!("{library} LVO {disp}")),
+        },
+        Some(None) => None,
+        None if reg == 6 => match resolver.lvo_entry(disp) {
+            Some(entry) => Some(format_call(&entry, registers, data, base_address, resolver)),
+            None => resolver.resolve_lvo(disp).map(|lvo| lvo.name),
+        },
+        None => None,
     }
+}
 
-    None
+/// Name the bits of an immediate written to a hardware register, such
+/// as `move.w #$8380,DMACON(a5)`, or of the bit a `btst`/`bset`/`bclr`/
+/// `bchg` works on (`btst #7,CIAAPRA` tests the fire button).
+fn detect_register_write(inst: &Instruction, resolver: &dyn SymbolResolver) -> Option<String> {
+    let [Operand::Ea(EffectiveAddress::Immediate(value)), Operand::Ea(dest)] = &inst.operands[..] else {
+        return None;
+    };
+    let value = match inst.mnemonic {
+        Mnemonic::Move if matches!(inst.size, Some(Size::Byte | Size::Word)) => *value,
+        Mnemonic::Btst | Mnemonic::Bset | Mnemonic::Bclr | Mnemonic::Bchg => 1 << (value % 8),
+        _ => return None,
+    };
+    let address = match dest {
+        EffectiveAddress::AbsoluteLong(a) => *a,
+        EffectiveAddress::AddressDisplacement(r, d) => resolver
+            .address_register_base(inst.address, *r)?
+            .wrapping_add(*d as i32 as u32),
+        EffectiveAddress::AddressIndirect(r) => resolver.address_register_base(inst.address, *r)?,
+        _ => return None,
+    };
+    hardware::describe_register_value(address, value)
 }
 
 fn format_code_line(
@@ -365,78 +961,299 @@ fn format_code_line(
     parts.join("  ")
 }
 
+/// How a stretch of a segment is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionKind {
+    Code,
+    /// Data formatted by guesswork: strings, then `dc.l`/`dc.b`.
+    Data,
+    /// Data whose type the project gives.
+    Typed(DataType),
+}
+
+/// A byte range of a segment and how to show it.
+struct Region {
+    range: Range<usize>,
+    kind: RegionKind,
+}
+
+/// Split a segment into regions: the code found in it, its jump tables
+/// as words or longwords, and data elsewhere, overridden by the
+/// project's code and data ranges. Data ranges win where the two
+/// overlap.
+fn segment_regions(project: &Project, segment: &Segment, found: &CodeMap) -> Vec<Region> {
+    let len = segment.data.len();
+    let code = project
+        .code
+        .iter()
+        .filter(|r| r.hunk == segment.index)
+        .map(|r| (r.offset, r.length, RegionKind::Code));
+    let data = project
+        .data
+        .iter()
+        .filter(|r| r.hunk == segment.index)
+        .map(|r| (r.offset, r.length, RegionKind::Typed(r.data_type)));
+    let overrides: Vec<_> = code.chain(data).collect();
+
+    let mut kinds = vec![RegionKind::Data; len];
+    for table in found.jump_tables.values().filter(|t| t.table.hunk == segment.index) {
+        let data_type = match table.kind {
+            TableKind::Offsets => DataType::Word,
+            TableKind::Pointers => DataType::Long,
+            TableKind::Branches => continue,
+        };
+        let range = table.range();
+        kinds[(range.start as usize).min(len)..(range.end as usize).min(len)].fill(RegionKind::Typed(data_type));
+    }
+    for range in found.ranges_in(segment.index) {
+        kinds[range.start.min(len)..range.end.min(len)].fill(RegionKind::Code);
+    }
+    for (offset, length, kind) in overrides {
+        let start = (offset as usize).min(len);
+        let end = (offset as u64 + length as u64).min(len as u64) as usize;
+        kinds[start..end].fill(kind);
+    }
+    let mut regions = Vec::new();
+    let mut start = 0;
+    for i in 1..=len {
+        if i == len || kinds[i] != kinds[start] {
+            regions.push(Region {
+                range: start..i,
+                kind: kinds[start],
+            });
+            start = i;
+        }
+    }
+    regions
+}
+
+/// Emit the project's notes for the line at `offset` in a segment: a
+/// function's signature and the user's comment, above its label.
+fn emit_annotations(
+    project: &Project,
+    segment: usize,
+    offset: u32,
+    lines: &mut Vec<ListingLine>,
+    line_num: &mut u32,
+    options: &ListingOptions,
+) {
+    let function = project.functions.get(&Location::new(segment, offset));
+    if let Some(signature) = function.and_then(|f| f.signature.as_ref()) {
+        push_line(lines, line_num, options, format!("; {signature}"));
+    }
+    if let Some(comment) = project.comments.get(&Location::new(segment, offset)) {
+        for line in comment.lines() {
+            push_line(lines, line_num, options, format!("; {line}"));
+        }
+    }
+}
+
+/// Emit the references to the label at `offset` in a segment.
+fn emit_xrefs(
+    notes: &SegmentNotes,
+    offset: u32,
+    lines: &mut Vec<ListingLine>,
+    line_num: &mut u32,
+    options: &ListingOptions,
+) {
+    for comment in notes.xrefs.get(&offset).into_iter().flatten() {
+        push_line(lines, line_num, options, comment.clone());
+    }
+}
+
+/// Format a data region of a segment: as the type the project gives,
+/// or else as strings and `dc.l`/`dc.b` guesses.
+///
+/// An item never spans a label or project comment, so each gets a line
+/// of its own.
 fn format_data_section(
-    data: &[u8],
+    segment: &Segment,
+    region: &Region,
     lines: &mut Vec<ListingLine>,
     line_num: &mut u32,
     options: &ListingOptions,
-    reloc_map: &std::collections::BTreeMap<u32, u32>,
+    labels: Option<&dyn SymbolResolver>,
+    notes: &SegmentNotes,
 ) {
-    // Try to detect ASCII strings; otherwise emit as hex dc.l/dc.b
-    let mut offset = 0usize;
+    let reloc_map = &notes.relocations;
+    let data = &segment.data[..region.range.end];
+    let base_address = segment.base_address;
+    let project = &options.project;
+    let location = |offset: usize| Location::new(segment.index, offset as u32);
+    let marked = |offset: usize| {
+        project.comments.contains_key(&location(offset))
+            || project.functions.contains_key(&location(offset))
+            || labels.is_some_and(|l| l.resolve_location(location(offset)).is_some())
+    };
+    // Whether an item of `size` bytes fits at `offset` without a mark inside
+    let fits = |offset: usize, size: usize| {
+        offset + size <= data.len() && !(offset + 1..offset + size).any(marked)
+    };
+    let mut offset = region.range.start;
 
     while offset < data.len() {
-        // Check for ASCII string run (at least 4 printable chars ending in null)
-        if let Some(str_end) = detect_string(data, offset) {
-            let s = String::from_utf8_lossy(&data[offset..str_end]);
-            let text = format_data_line(
-                offset as u32,
-                "dc.b",
-                &format!("\"{s}\",0"),
-                options,
-            );
-            push_line(lines, line_num, options, text);
-            offset = str_end + 1; // skip the null terminator
-            // Align to even boundary
-            if offset % 2 != 0 && offset < data.len() {
-                offset += 1;
-            }
-            continue;
+        emit_annotations(project, segment.index, offset as u32, lines, line_num, options);
+        if let Some(label) = labels.and_then(|l| l.resolve_location(location(offset))) {
+            emit_xrefs(notes, offset as u32, lines, line_num, options);
+            push_line(lines, line_num, options, format!("{label}:"));
         }
 
-        // Emit as dc.l if aligned and enough bytes
-        if offset % 4 == 0 && offset + 4 <= data.len() {
-            let val = u32::from_be_bytes([
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3],
-            ]);
-            let hex = format!(
-                "{:02X}{:02X}{:02X}{:02X}",
-                data[offset],
-                data[offset + 1],
-                data[offset + 2],
-                data[offset + 3]
-            );
-            let mut text = String::new();
-            if options.show_addresses {
-                text.push_str(&format!("{:08X}  ", offset));
+        let entry = notes
+            .table_entries
+            .get(&(offset as u32))
+            .filter(|&&(size, _)| fits(offset, size as usize));
+        let (text, size) = match (entry, region.kind) {
+            (Some((size, value)), _) => {
+                let size = *size as usize;
+                let hex: String = data[offset..offset + size].iter().map(|b| format!("{b:02X}")).collect();
+                let directive = if size == 2 { "dc.w" } else { "dc.l" };
+                let address = base_address + offset as u32;
+                (format_code_line(address, &hex, directive, value, options), size)
             }
-            if options.show_hex {
-                text.push_str(&format!("{hex:<20}  "));
+            (None, RegionKind::Typed(data_type)) => {
+                format_typed_item(data, offset, base_address, data_type, &fits, reloc_map, options)
             }
-            text.push_str(&format!("dc.l     ${val:08X}"));
+            (None, _) => guess_data_item(data, offset, base_address, &fits, reloc_map, options),
+        };
+        push_line(lines, line_num, options, text);
+        offset += size;
+    }
+}
+
+/// Format the item at `offset` by guesswork: an ASCII string (at least
+/// 4 printable chars ending in null), else `dc.l` when aligned, else a
+/// single `dc.b`. Returns the line and the bytes it covers.
+fn guess_data_item(
+    data: &[u8],
+    offset: usize,
+    base_address: u32,
+    fits: &dyn Fn(usize, usize) -> bool,
+    reloc_map: &std::collections::BTreeMap<u32, String>,
+    options: &ListingOptions,
+) -> (String, usize) {
+    if let Some(str_end) = detect_string(data, offset).filter(|&end| fits(offset, end + 1 - offset)) {
+        let s = String::from_utf8_lossy(&data[offset..str_end]);
+        let text = format_data_line(
+            base_address + offset as u32,
+            "dc.b",
+            &format!("\"{s}\",0"),
+            options,
+        );
+        let mut end = str_end + 1; // skip the null terminator
+        // Align to even boundary
+        if !end.is_multiple_of(2) && end < data.len() && fits(offset, end + 1 - offset) {
+            end += 1;
+        }
+        return (text, end - offset);
+    }
 
-            // Annotate relocation sites
-            if let Some(target_hunk) = reloc_map.get(&(offset as u32)) {
-                text.push_str(&format!("  ; -> hunk_{target_hunk}"));
+    if offset.is_multiple_of(4) && fits(offset, 4) {
+        return (format_long_item(data, offset, base_address, reloc_map, options), 4);
+    }
+    (format_byte_item(data, offset, base_address, options), 1)
+}
+
+/// Format the item at `offset` in a range of known type: up to 8 bytes,
+/// a word, a longword or a string per line. Falls back to a single
+/// `dc.b` where a word or longword would run past the range or a mark.
+fn format_typed_item(
+    data: &[u8],
+    offset: usize,
+    base_address: u32,
+    data_type: DataType,
+    fits: &dyn Fn(usize, usize) -> bool,
+    reloc_map: &std::collections::BTreeMap<u32, String>,
+    options: &ListingOptions,
+) -> (String, usize) {
+    let address = base_address + offset as u32;
+    match data_type {
+        DataType::Byte => {
+            let mut size = 1;
+            while size < 8 && fits(offset, size + 1) {
+                size += 1;
+            }
+            let bytes = &data[offset..offset + size];
+            let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+            let values: Vec<String> = bytes.iter().map(|b| format!("${b:02X}")).collect();
+            (format_code_line(address, &hex, "dc.b", &values.join(","), options), size)
+        }
+        DataType::Word if fits(offset, 2) => {
+            let w = u16::from_be_bytes([data[offset], data[offset + 1]]);
+            (format_code_line(address, &format!("{w:04X}"), "dc.w", &format!("${w:04X}"), options), 2)
+        }
+        DataType::Long if fits(offset, 4) => {
+            (format_long_item(data, offset, base_address, reloc_map, options), 4)
+        }
+        DataType::String => {
+            // Up to and including the null, stopping short of a mark
+            let mut end = offset;
+            while end < data.len() {
+                end += 1;
+                if data[end - 1] == 0 || !fits(offset, end + 1 - offset) {
+                    break;
+                }
             }
+            let value = format_string_bytes(&data[offset..end]);
+            (format_data_line(address, "dc.b", &value, options), end - offset)
+        }
+        _ => (format_byte_item(data, offset, base_address, options), 1),
+    }
+}
 
-            push_line(lines, line_num, options, text);
-            offset += 4;
-        } else {
-            let byte = data[offset];
-            let text = format_data_line(
-                offset as u32,
-                "dc.b",
-                &format!("${byte:02X}"),
-                options,
-            );
-            push_line(lines, line_num, options, text);
-            offset += 1;
+/// `dc.b` operands for text: printable runs in quotes, other bytes as
+/// numbers, e.g. `"Hello",10,0`.
+fn format_string_bytes(bytes: &[u8]) -> String {
+    let mut parts = Vec::new();
+    let mut run = String::new();
+    for &b in bytes {
+        if (b.is_ascii_graphic() || b == b' ') && b != b'"' {
+            run.push(b as char);
+            continue;
+        }
+        if !run.is_empty() {
+            parts.push(format!("\"{run}\""));
+            run.clear();
         }
+        parts.push(match b {
+            0 => "0".to_string(),
+            10 => "10".to_string(),
+            _ => format!("${b:02X}"),
+        });
+    }
+    if !run.is_empty() {
+        parts.push(format!("\"{run}\""));
+    }
+    parts.join(",")
+}
+
+fn format_long_item(
+    data: &[u8],
+    offset: usize,
+    base_address: u32,
+    reloc_map: &std::collections::BTreeMap<u32, String>,
+    options: &ListingOptions,
+) -> String {
+    let bytes = [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]];
+    let val = u32::from_be_bytes(bytes);
+    let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    let mut text = format_code_line(
+        base_address + offset as u32,
+        &hex,
+        "dc.l",
+        &format!("${val:08X}"),
+        options,
+    );
+
+    // Annotate relocation sites
+    if let Some(target) = reloc_map.get(&(offset as u32)) {
+        text.push_str(&format!("  ; -> {target}"));
     }
+    text
+}
+
+fn format_byte_item(data: &[u8], offset: usize, base_address: u32, options: &ListingOptions) -> String {
+    let byte = data[offset];
+    format_data_line(base_address + offset as u32, "dc.b", &format!("${byte:02X}"), options)
 }
 
 fn format_data_line(address: u32, directive: &str, value: &str, options: &ListingOptions) -> String {
@@ -451,10 +1268,41 @@ fn format_data_line(address: u32, directive: &str, value: &str, options: &Listin
     parts.join("  ")
 }
 
-fn format_bss_line(size: u32, options: &ListingOptions) -> String {
+/// Reserve the memory of a segment from `start` to its end with
+/// `ds.b`, split at its labels.
+fn format_reserved(
+    segment: &Segment,
+    start: u32,
+    lines: &mut Vec<ListingLine>,
+    line_num: &mut u32,
+    options: &ListingOptions,
+    labels: Option<&dyn SymbolResolver>,
+    notes: &SegmentNotes,
+) {
+    let label_at = |offset: u32| {
+        labels.and_then(|l| l.resolve_location(Location::new(segment.index, offset)))
+    };
+    let mut ends: Vec<u32> = (start + 1..segment.size)
+        .filter(|&offset| label_at(offset).is_some())
+        .collect();
+    ends.push(segment.size);
+
+    let mut start = start;
+    for end in ends {
+        if let Some(label) = label_at(start) {
+            emit_xrefs(notes, start, lines, line_num, options);
+            push_line(lines, line_num, options, format!("{label}:"));
+        }
+        let text = format_bss_line(end - start, segment.base_address + start, options);
+        push_line(lines, line_num, options, text);
+        start = end;
+    }
+}
+
+fn format_bss_line(size: u32, base_address: u32, options: &ListingOptions) -> String {
     let mut parts = Vec::new();
     if options.show_addresses {
-        parts.push("00000000".to_string());
+        parts.push(format!("{base_address:08X}"));
     }
     if options.show_hex {
         parts.push(format!("{:<20}", ""));
@@ -463,7 +1311,7 @@ fn format_bss_line(size: u32, options: &ListingOptions) -> String {
     parts.join("  ")
 }
 
-fn detect_string(data: &[u8], offset: usize) -> Option<usize> {
+pub(crate) fn detect_string(data: &[u8], offset: usize) -> Option<usize> {
     // Look for at least 4 printable ASCII bytes followed by a null
     let mut end = offset;
     while end < data.len() && data[end] != 0 {
@@ -479,7 +1327,7 @@ fn detect_string(data: &[u8], offset: usize) -> Option<usize> {
     }
 }
 
-fn push_line(
+pub(crate) fn push_line(
     lines: &mut Vec<ListingLine>,
     line_num: &mut u32,
     options: &ListingOptions,
@@ -496,3 +1344,316 @@ fn push_line(
     });
     *line_num += 1;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hunk::types::Symbol;
+    use crate::symbols::resolver::LvoResolver;
+
+    #[test]
+    fn jump_table_entries() {
+        let code = vec![
+            0xD0, 0x40, // add.w d0,d0
+            0x30, 0x3B, 0x00, 0x06, // move.w (6,pc,d0.w),d0
+            0x4E, 0xFB, 0x00, 0x02, // jmp (2,pc,d0.w)
+            0x00, 0x04, 0x00, 0x06, // switch_000A
+            0x70, 0x01, // case_000E: moveq #1,d0
+            0x4E, 0x75, // case_0010: rts
+        ];
+        let image = Image::test_code(code, vec![]);
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            ..ListingOptions::default()
+        };
+
+        let mut lines = Vec::new();
+        emit_sections(&image, &options, None, &mut lines, &mut 1);
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .filter(|l| !l.is_empty() && !l.starts_with(';'))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "sub_0000:",
+                "00000000  add.w    d0,d0",
+                "00000002  move.w   (6,pc,d0.w),d0",
+                "00000006  jmp      (2,pc,d0.w)",
+                "switch_000A:",
+                "0000000A  dc.w     case_000E-switch_000A",
+                "0000000C  dc.w     case_0010-switch_000A",
+                "case_000E:",
+                "0000000E  moveq    #1,d0",
+                "case_0010:",
+                "00000010  rts     ",
+            ]
+        );
+    }
+
+    #[test]
+    fn lvo_comments_skip_unresolved_bases() {
+        let code = vec![
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6), A6 untracked -> Supervisor
+            0x2C, 0x7A, 0x00, 0x0A, // movea.l (10,pc),a6
+            0x4E, 0xAE, 0xFF, 0xE2, // jsr (-30,a6), unresolved
+            0x4E, 0x75, // rts
+            0x00, 0x00, // padding
+            0x00, 0x00, 0x00, 0x00, // an unknown base
+        ];
+        let image = Image::test_code(code, vec![]);
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            ..ListingOptions::default()
+        };
+        let resolver = LvoResolver::new("exec");
+
+        let mut lines = Vec::new();
+        emit_sections(&image, &options, Some(&resolver), &mut lines, &mut 1);
+        let line = |address: &str| {
+            lines
+                .iter()
+                .find(|l| l.text.starts_with(address))
+                .map(|l| l.text.clone())
+                .unwrap()
+        };
+        assert!(line("00000000").ends_with("; Supervisor(userFunction=a5)"));
+        assert_eq!(line("00000008"), "00000008  jsr      (-30,a6)");
+    }
+
+    #[test]
+    fn relocated_operands_name_other_hunks() {
+        let code = vec![
+            0x41, 0xF9, 0x00, 0x00, 0x00, 0x04, // lea $4,a0 -> hunk 1
+            0x20, 0x3C, 0x00, 0x00, 0x00, 0x10, // move.l #$10,d0 -> hunk 2
+            0x4E, 0xB9, 0x00, 0x00, 0x00, 0x12, // jsr $12 -> hunk 0
+            0x4E, 0x75, // rts
+        ];
+        let fixups = vec![
+            Fixup { offset: 2, target: 1 },
+            Fixup { offset: 8, target: 2 },
+            Fixup { offset: 14, target: 0 },
+        ];
+        let data = vec![0, 0, 0, 1, 0, 0, 0, 2];
+        let image = Image::test_code(code, fixups)
+            .with_segment(SegmentKind::Data, data, vec![])
+            .with_segment(SegmentKind::Bss, vec![], vec![])
+            .sized(32);
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            ..ListingOptions::default()
+        };
+
+        let mut lines = Vec::new();
+        emit_sections(&image, &options, None, &mut lines, &mut 1);
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .filter(|l| !l.is_empty() && !l.starts_with(';'))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "sub_0000:",
+                "00000000  lea.l    data_1_0004,a0",
+                "00000006  move.l   #bss_2_0010,d0",
+                "0000000C  jsr      sub_0012",
+                "sub_0012:",
+                "00000012  rts     ",
+                "00000000  dc.l     $00000001",
+                "data_1_0004:",
+                "00000004  dc.l     $00000002",
+                "00000000  ds.b     16",
+                "bss_2_0010:",
+                "00000010  ds.b     16",
+            ]
+        );
+    }
+
+    #[test]
+    fn symbols_at_load_address() {
+        let mut image = Image::test_code(vec![0x4E, 0x71, 0x4E, 0x75], vec![]);
+        image.segments[0].base_address = 0x40000;
+        image.segments[0].symbols.push(Symbol {
+            name: "entry_00040002".to_string(),
+            value: 2,
+        });
+        image.entry_points = vec![0x40002];
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            ..ListingOptions::default()
+        };
+
+        let mut lines = Vec::new();
+        emit_sections(&image, &options, None, &mut lines, &mut 1);
+        assert!(lines.iter().any(|l| l.text == ";   $00040002  entry_00040002"));
+    }
+
+    #[test]
+    fn function_headers() {
+        let code = vec![
+            0x61, 0x04, // bsr.s sub_0006
+            0x61, 0x02, // bsr.s sub_0006
+            0x4E, 0x75, // rts
+            0x4E, 0x75, // sub_0006: rts
+        ];
+        let image = Image::test_code(code, vec![]);
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            ..ListingOptions::default()
+        };
+
+        let mut lines = Vec::new();
+        emit_sections(&image, &options, None, &mut lines, &mut 1);
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .filter(|l| !l.is_empty() && !l.starts_with("; ────"))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "; Function: no callers, 6 bytes",
+                "sub_0000:",
+                "00000000  bsr      sub_0006",
+                "00000002  bsr      sub_0006",
+                "00000004  rts     ",
+                "; Function: 2 callers, 2 bytes",
+                "; XREF: sub_0000 (call)",
+                "sub_0006:",
+                "00000006  rts     ",
+            ]
+        );
+    }
+
+    fn referenced_data() -> Image {
+        let code = vec![
+            0x49, 0xF9, 0x00, 0x00, 0x7F, 0xFE, // lea $7FFE,a4 -> hunk 1
+            0x41, 0xF9, 0x00, 0x00, 0x00, 0x04, // lea $4,a0 -> hunk 1
+            0x29, 0x40, 0x80, 0x0A, // move.l d0,(-32758,a4)
+            0x4E, 0x75, // rts
+        ];
+        let fixups = vec![Fixup { offset: 2, target: 1 }, Fixup { offset: 8, target: 1 }];
+        Image::test_code(code, fixups)
+            .with_segment(SegmentKind::Data, vec![0; 16], vec![])
+            .sized(0x10000)
+    }
+
+    #[test]
+    fn listing_labels_by_address() {
+        let image = referenced_data();
+        let options = ListingOptions::default();
+        let code = discover_code(&image, options.cpu, &[], false);
+        let graph = find_functions(&image, options.cpu, &code, &[]);
+        let labels = ListingLabels::new(&image, &options, &code, &graph);
+        let resolver = labels.resolver(&image, 0, None);
+
+        let name = |hunk, offset| resolver.resolve_nearest(Location::new(hunk, offset)).map(|s| s.to_string());
+        assert_eq!(name(1, 4).as_deref(), Some("data_1_0004"));
+        assert_eq!(name(1, 8).as_deref(), Some("data_1_0008"));
+        assert_eq!(name(1, 10).as_deref(), Some("data_1_0008+$2"));
+        assert_eq!(name(0, 6).as_deref(), Some("sub_0000+$6"));
+        assert_eq!(labels.small_data().map(|s| s.base), Some(Location::new(1, 0x7FFE)));
+    }
+
+    #[test]
+    fn listing_labels_by_name() {
+        let image = referenced_data();
+        let mut options = ListingOptions::default();
+        options.project.labels.insert(Location::new(1, 12), "_buffer".to_string());
+        let code = discover_code(&image, options.cpu, &[], false);
+        let graph = find_functions(&image, options.cpu, &code, &[]);
+        let labels = ListingLabels::new(&image, &options, &code, &graph);
+
+        let find = |name| labels.find(&image, &options.project, name);
+        assert_eq!(find("sub_0000"), Some(Location::new(0, 0)));
+        assert_eq!(find("data_1_0004"), Some(Location::new(1, 4)));
+        assert_eq!(find("data_1_0008"), Some(Location::new(1, 8)));
+        assert_eq!(find("data_1_7FFE"), Some(Location::new(1, 0x7FFE)));
+        assert_eq!(find("_buffer"), Some(Location::new(1, 12)));
+        // Only labels the listing shows
+        assert_eq!(find("data_1_0002"), None);
+        assert_eq!(find("loc_0006"), None);
+        assert_eq!(find("code_0_0004"), None);
+    }
+
+    #[test]
+    fn xrefs_above_data_labels() {
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            ..ListingOptions::default()
+        };
+
+        let mut lines = Vec::new();
+        emit_sections(&referenced_data(), &options, None, &mut lines, &mut 1);
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .filter(|l| l.starts_with("; XREF") || l.ends_with(':'))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "sub_0000:",
+                "; XREF: sub_0000 (addr)",
+                "data_1_0004:",
+                "; XREF: sub_0000 (w)",
+                "data_1_0008:",
+                "; XREF: sub_0000 (addr)",
+                "data_1_7FFE:",
+            ]
+        );
+    }
+
+    #[test]
+    fn small_data_operands() {
+        let code = vec![
+            0x49, 0xF9, 0x00, 0x00, 0x7F, 0xFE, // lea $7FFE,a4 -> hunk 1
+            0x20, 0x2C, 0x80, 0x06, // move.l (-32762,a4),d0
+            0x20, 0x34, 0x01, 0x70, 0x00, 0x00, 0x00, 0x10, // move.l (16.l,a4),d0
+            0x4E, 0x75, // rts
+        ];
+        let image = Image::test_code(code, vec![Fixup { offset: 2, target: 1 }])
+            .with_segment(SegmentKind::Data, vec![0; 8], vec![])
+            .sized(0x10000);
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            cpu: CpuVariant::M68020,
+            ..ListingOptions::default()
+        };
+
+        let mut lines = Vec::new();
+        emit_sections(&image, &options, None, &mut lines, &mut 1);
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|l| l.text.as_str())
+            .filter(|l| !l.is_empty() && !l.starts_with(';'))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "sub_0000:",
+                "00000000  lea.l    data_1_7FFE,a4",
+                "00000006  move.l   data_1_0004(a4),d0",
+                "0000000A  move.l   (data_1_800E,a4),d0",
+                "00000012  rts     ",
+                "00000000  dc.l     $00000000",
+                "data_1_0004:",
+                "00000004  dc.l     $00000000",
+                "00000008  ds.b     32758",
+                "data_1_7FFE:",
+                "00007FFE  ds.b     16",
+                "data_1_800E:",
+                "0000800E  ds.b     32754",
+            ]
+        );
+    }
+}
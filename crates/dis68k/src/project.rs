@@ -0,0 +1,327 @@
+//! Project files: what an analyst has learned about a binary.
+//!
+//! A project holds user labels, comments, function names and
+//! signatures, data type and code/data region overrides, and
+//! register-type hints, all keyed by hunk (segment) index and offset
+//! within the hunk. It is stored as a small TOML subset (see
+//! `format`) so it can be edited by hand and kept next to the binary.
+//!
+//! The listing honours a project passed in `ListingOptions::project`;
+//! `add_auto_labels` seeds a new project with the labels the listing
+//! would generate, as a starting point for renaming.
+
+pub mod error;
+pub mod format;
+
+pub use error::ProjectError;
+pub use format::parse_project;
+
+use std::collections::BTreeMap;
+
+use crate::analysis::{find_functions, CodeMap, RegisterType, TableKind};
+use crate::image::{Image, Location, SegmentKind};
+use crate::m68k::variants::CpuVariant;
+use crate::symbols::labels::collect_targets_in;
+
+/// Everything the user has recorded about a binary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Project {
+    /// Label names.
+    pub labels: BTreeMap<Location, String>,
+    /// Comments shown above the line at a location. May span several
+    /// lines.
+    pub comments: BTreeMap<Location, String>,
+    /// Function entry points; the name doubles as the label.
+    pub functions: BTreeMap<Location, Function>,
+    /// Ranges to show as typed data, in code or data hunks.
+    pub data: Vec<DataRange>,
+    /// Ranges of data hunks to disassemble as code.
+    pub code: Vec<CodeRange>,
+    /// Structure types of registers at given instructions.
+    pub registers: Vec<RegisterHint>,
+}
+
+/// A named function, with an optional free-form signature such as
+/// `BOOL ParseArgs(char *line)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub signature: Option<String>,
+}
+
+/// How to format a data range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    /// `dc.b` bytes.
+    Byte,
+    /// A table of `dc.w` words.
+    Word,
+    /// A table of `dc.l` longwords.
+    Long,
+    /// NUL-terminated text as `dc.b "..."`.
+    String,
+}
+
+impl DataType {
+    /// The name used in project files.
+    pub fn name(self) -> &'static str {
+        match self {
+            DataType::Byte => "byte",
+            DataType::Word => "word",
+            DataType::Long => "long",
+            DataType::String => "string",
+        }
+    }
+
+    /// Parse a name as used in project files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "byte" => Some(DataType::Byte),
+            "word" => Some(DataType::Word),
+            "long" => Some(DataType::Long),
+            "string" => Some(DataType::String),
+            _ => None,
+        }
+    }
+
+    /// Size of one element in bytes (1 for strings).
+    pub fn element_size(self) -> u32 {
+        match self {
+            DataType::Byte | DataType::String => 1,
+            DataType::Word => 2,
+            DataType::Long => 4,
+        }
+    }
+}
+
+/// `length` bytes at `offset` in `hunk` are data of type `data_type`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRange {
+    pub hunk: usize,
+    pub offset: u32,
+    pub length: u32,
+    pub data_type: DataType,
+}
+
+/// `length` bytes at `offset` in `hunk` are code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeRange {
+    pub hunk: usize,
+    pub offset: u32,
+    pub length: u32,
+}
+
+/// Register `register` points at a `type_name` structure at the
+/// instruction at `offset` in `hunk`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterHint {
+    pub hunk: usize,
+    pub offset: u32,
+    /// 0-7 for d0-d7, 8-15 for a0-a7.
+    pub register: u8,
+    pub type_name: String,
+}
+
+impl Project {
+    /// Whether the project records nothing.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+            && self.comments.is_empty()
+            && self.functions.is_empty()
+            && self.data.is_empty()
+            && self.code.is_empty()
+            && self.registers.is_empty()
+    }
+
+    /// The label at `location`: a function name, else a user label.
+    pub fn label(&self, location: Location) -> Option<&str> {
+        self.functions
+            .get(&location)
+            .map(|f| f.name.as_str())
+            .or_else(|| self.labels.get(&location).map(String::as_str))
+    }
+
+    /// Every label and function name in `hunk`, by offset.
+    pub fn labels_in(&self, hunk: usize) -> BTreeMap<u32, &str> {
+        let range = Location::new(hunk, 0)..=Location::new(hunk, u32::MAX);
+        let mut labels: BTreeMap<u32, &str> = self
+            .labels
+            .range(range.clone())
+            .map(|(location, name)| (location.offset, name.as_str()))
+            .collect();
+        for (location, function) in self.functions.range(range) {
+            labels.insert(location.offset, &function.name);
+        }
+        labels
+    }
+
+    /// The register hints as declarations for `track_library_bases`,
+    /// at listed addresses in `image`. Hints for hunks the image does
+    /// not have are dropped.
+    pub fn register_types(&self, image: &Image) -> Vec<RegisterType> {
+        self.registers
+            .iter()
+            .filter_map(|hint| {
+                let segment = image.segments.get(hint.hunk)?;
+                Some(RegisterType {
+                    segment: hint.hunk,
+                    address: segment.base_address.wrapping_add(hint.offset),
+                    register: hint.register,
+                    type_name: hint.type_name.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Where the project says code starts: its functions and code
+    /// ranges, as entry points for finding the rest of the code.
+    pub fn code_entries(&self) -> Vec<Location> {
+        let ranges = self.code.iter().map(|r| Location::new(r.hunk, r.offset));
+        self.functions.keys().copied().chain(ranges).collect()
+    }
+
+    /// Add the `sub_XXXX`, `switch_XXXX`, `case_XXXX` and `loc_XXXX`
+    /// labels the listing generates for functions, jump tables and their
+    /// cases, and branch and jump targets in the code found in code
+    /// hunks, where neither the project nor the hunk's symbols name the
+    /// target already.
+    ///
+    /// Returns the number of labels added.
+    pub fn add_auto_labels(&mut self, image: &Image, code: &CodeMap, cpu: CpuVariant) -> usize {
+        let graph = find_functions(image, cpu, code, &[]);
+        let address = |location: Location| image.segments[location.hunk].base_address.wrapping_add(location.offset);
+        let mut tables = BTreeMap::new();
+        for table in code.jump_tables.values() {
+            tables.insert(table.table, format!("switch_{:04X}", address(table.table)));
+            if table.kind != TableKind::Branches {
+                for &target in &table.targets {
+                    tables.entry(target).or_insert_with(|| format!("case_{:04X}", address(target)));
+                }
+            }
+        }
+        let mut added = 0;
+        for segment in &image.segments {
+            if segment.kind != SegmentKind::Code {
+                continue;
+            }
+            let targets = code.ranges_in(segment.index).iter().flat_map(|range| {
+                collect_targets_in(&segment.data, segment.base_address, range.clone(), cpu)
+            });
+            let functions = graph
+                .functions
+                .values()
+                .filter(|f| f.entry.hunk == segment.index)
+                .map(|f| f.address);
+            let cases = tables.keys().filter(|l| l.hunk == segment.index).map(|&l| address(l));
+            for target in targets.chain(functions).chain(cases).collect::<std::collections::BTreeSet<u32>>() {
+                let location = Location::new(segment.index, target.wrapping_sub(segment.base_address));
+                let named = self.label(location).is_some()
+                    || segment.symbols.iter().any(|sym| sym.value == location.offset);
+                if !named {
+                    let name = match (graph.functions.get(&location), tables.get(&location)) {
+                        (Some(function), _) => function.label(),
+                        (None, Some(name)) => name.clone(),
+                        (None, None) => format!("loc_{target:04X}"),
+                    };
+                    self.labels.insert(location, name);
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::listing::ListingOptions;
+    use crate::raw::{generate_raw_listing, load_raw_binary, RawLayout};
+
+    #[test]
+    fn auto_labels() {
+        // bra.s $4; nop; nop; bsr.s $0 -- the entry point symbol names $0
+        let data = [0x60, 0x02, 0x4E, 0x71, 0x4E, 0x71, 0x61, 0xF8];
+        let hunk_file = load_raw_binary(&data, &RawLayout::default()).unwrap();
+        let image = Image::from(&hunk_file);
+
+        let mut project = Project::default();
+        project.functions.insert(
+            Location::new(0, 6),
+            Function {
+                name: "Helper".to_string(),
+                signature: None,
+            },
+        );
+        let code = CodeMap::linear(&image);
+        assert_eq!(project.add_auto_labels(&image, &code, CpuVariant::M68000), 1);
+        assert_eq!(project.label(Location::new(0, 4)), Some("loc_0004"));
+        assert_eq!(project.label(Location::new(0, 0)), None);
+        assert_eq!(project.labels_in(0).len(), 2);
+    }
+
+    #[test]
+    fn listing_honours_project() {
+        // lea (4,pc),a0; bra.s $100E; "Hi!",0; dc.w 1,2; rts
+        let data = [
+            0x41, 0xFA, 0x00, 0x04, 0x60, 0x08, b'H', b'i', b'!', 0x00, 0x00, 0x01, 0x00, 0x02,
+            0x4E, 0x75,
+        ];
+        let layout = RawLayout {
+            base_address: 0x1000,
+            ..RawLayout::default()
+        };
+        let hunk_file = load_raw_binary(&data, &layout).unwrap();
+
+        let project = parse_project(
+            "
+[[function]]
+offset = 0
+name = \"Start\"
+signature = \"void Start(void)\"
+
+[[data]]
+offset = 6
+length = 4
+type = \"string\"
+
+[[data]]
+offset = 10
+length = 4
+type = \"word\"
+
+[[comment]]
+offset = 14
+text = \"done\"
+",
+        )
+        .unwrap();
+        let options = ListingOptions {
+            show_line_numbers: false,
+            show_hex: false,
+            project,
+            ..ListingOptions::default()
+        };
+        let text: Vec<String> = generate_raw_listing(&hunk_file, &options, None)
+            .into_iter()
+            .map(|l| l.text)
+            .collect();
+        let start = text.iter().position(|l| l == "; void Start(void)").unwrap();
+        assert_eq!(
+            text[start..],
+            [
+                "; void Start(void)",
+                "Start:",
+                "00001000  lea.l    (4,pc),a0",
+                "00001004  bra      loc_100E",
+                "00001006  dc.b     \"Hi!\",0",
+                "0000100A  dc.w     $0001",
+                "0000100C  dc.w     $0002",
+                "; done",
+                "; XREF: Start (jump)",
+                "loc_100E:",
+                "0000100E  rts     ",
+            ]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// Errors that can occur while reading a project file.
+///
+/// Line numbers are 1-based. Errors about a whole entry, such as a
+/// missing key, give the line of its `[[table]]` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectError {
+    /// A line that is not a table header, `key = value` or a comment.
+    Syntax { line: usize },
+    /// A `[[table]]` header naming no known kind of entry.
+    UnknownTable { line: usize, name: String },
+    /// A `key = value` line before the first table header.
+    KeyOutsideTable { line: usize },
+    /// A key the entry's table does not have.
+    UnknownKey { line: usize, key: String },
+    /// The same key given twice in one entry.
+    DuplicateKey { line: usize, key: String },
+    /// A value of the wrong type or out of range for its key.
+    BadValue { line: usize, key: String },
+    /// An entry lacks a required key.
+    MissingKey { line: usize, key: &'static str },
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Syntax { line } => write!(f, "line {line}: syntax error"),
+            ProjectError::UnknownTable { line, name } => {
+                write!(f, "line {line}: unknown table [[{name}]]")
+            }
+            ProjectError::KeyOutsideTable { line } => {
+                write!(f, "line {line}: key outside a [[table]]")
+            }
+            ProjectError::UnknownKey { line, key } => write!(f, "line {line}: unknown key '{key}'"),
+            ProjectError::DuplicateKey { line, key } => {
+                write!(f, "line {line}: duplicate key '{key}'")
+            }
+            ProjectError::BadValue { line, key } => write!(f, "line {line}: bad value for '{key}'"),
+            ProjectError::MissingKey { line, key } => {
+                write!(f, "line {line}: entry is missing '{key}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
@@ -0,0 +1,479 @@
+//! Reading and writing project files.
+//!
+//! The format is the subset of TOML a project needs: `#` comments,
+//! `[[table]]` headers starting an entry, and `key = value` lines with
+//! integer (decimal, `0x`, `0o` or `0b`) or string (`"basic"` or
+//! `'literal'`) values. Each entry is one table:
+//!
+//! ```toml
+//! [[function]]
+//! hunk = 0
+//! offset = 0x0040
+//! name = "ParseArgs"
+//! signature = "BOOL ParseArgs(char *line)"
+//!
+//! [[data]]
+//! hunk = 1
+//! offset = 0x0100
+//! length = 32
+//! type = "word"
+//! ```
+//!
+//! `hunk` defaults to 0, so single-segment binaries can leave it out.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::image::Location;
+
+use super::error::ProjectError;
+use super::{CodeRange, DataRange, DataType, Function, Project, RegisterHint};
+
+/// The tables a project file may contain, with their keys.
+const TABLES: &[(&str, &[&str])] = &[
+    ("label", &["hunk", "offset", "name"]),
+    ("comment", &["hunk", "offset", "text"]),
+    ("function", &["hunk", "offset", "name", "signature"]),
+    ("data", &["hunk", "offset", "length", "type"]),
+    ("code", &["hunk", "offset", "length"]),
+    ("register", &["hunk", "offset", "register", "type"]),
+];
+
+#[derive(Debug)]
+enum Value {
+    Integer(i64),
+    String(String),
+}
+
+/// One `[[table]]` and its keys, each with the line it was set on.
+struct Entry {
+    table: &'static str,
+    line: usize,
+    keys: BTreeMap<String, (usize, Value)>,
+}
+
+/// Parse a project file.
+pub fn parse_project(text: &str) -> Result<Project, ProjectError> {
+    let mut entries: Vec<Entry> = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = trimmed.strip_prefix("[[") {
+            let (name, rest) = header.split_once("]]").ok_or(ProjectError::Syntax { line })?;
+            if !is_comment_or_empty(rest) {
+                return Err(ProjectError::Syntax { line });
+            }
+            let name = name.trim();
+            let table = TABLES
+                .iter()
+                .map(|(table, _)| *table)
+                .find(|table| *table == name)
+                .ok_or_else(|| ProjectError::UnknownTable {
+                    line,
+                    name: name.to_string(),
+                })?;
+            entries.push(Entry {
+                table,
+                line,
+                keys: BTreeMap::new(),
+            });
+            continue;
+        }
+
+        let (key, value) = trimmed.split_once('=').ok_or(ProjectError::Syntax { line })?;
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(ProjectError::Syntax { line });
+        }
+        let entry = entries.last_mut().ok_or(ProjectError::KeyOutsideTable { line })?;
+        if !table_keys(entry.table).contains(&key) {
+            return Err(ProjectError::UnknownKey {
+                line,
+                key: key.to_string(),
+            });
+        }
+        let value = parse_value(value.trim()).ok_or_else(|| ProjectError::BadValue {
+            line,
+            key: key.to_string(),
+        })?;
+        if entry.keys.insert(key.to_string(), (line, value)).is_some() {
+            return Err(ProjectError::DuplicateKey {
+                line,
+                key: key.to_string(),
+            });
+        }
+    }
+
+    let mut project = Project::default();
+    for entry in &entries {
+        entry.apply(&mut project)?;
+    }
+    Ok(project)
+}
+
+fn table_keys(table: &str) -> &'static [&'static str] {
+    TABLES
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map_or(&[], |(_, keys)| keys)
+}
+
+fn is_comment_or_empty(s: &str) -> bool {
+    let s = s.trim();
+    s.is_empty() || s.starts_with('#')
+}
+
+/// Parse a value and check that only a comment follows it.
+fn parse_value(s: &str) -> Option<Value> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let (text, rest) = parse_basic_string(rest)?;
+        return is_comment_or_empty(rest).then_some(Value::String(text));
+    }
+    if let Some(rest) = s.strip_prefix('\'') {
+        let (text, rest) = rest.split_once('\'')?;
+        return is_comment_or_empty(rest).then(|| Value::String(text.to_string()));
+    }
+    let number = s.split('#').next()?.trim();
+    parse_integer(number).map(Value::Integer)
+}
+
+/// Parse the body of a `"..."` string, returning the text and what
+/// follows the closing quote.
+fn parse_basic_string(s: &str) -> Option<(String, &str)> {
+    let mut text = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((text, &s[i + 1..])),
+            '\\' => {
+                let (_, escape) = chars.next()?;
+                match escape {
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    'r' => text.push('\r'),
+                    '"' => text.push('"'),
+                    '\\' => text.push('\\'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let code = u32::from_str_radix(&hex, 16).ok()?;
+                        text.push(char::from_u32(code)?);
+                    }
+                    _ => return None,
+                }
+            }
+            _ => text.push(c),
+        }
+    }
+    None
+}
+
+fn parse_integer(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (radix, digits) = if let Some(hex) = s.strip_prefix("0x") {
+        (16, hex)
+    } else if let Some(octal) = s.strip_prefix("0o") {
+        (8, octal)
+    } else if let Some(binary) = s.strip_prefix("0b") {
+        (2, binary)
+    } else {
+        (10, s)
+    };
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    let value = i64::from_str_radix(&digits.replace('_', ""), radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Parse `d0`-`d7`, `a0`-`a7` or `sp` as 0-15.
+fn parse_register(name: &str) -> Option<u8> {
+    let name = name.to_ascii_lowercase();
+    if name == "sp" {
+        return Some(15);
+    }
+    let (kind, number) = name.split_at_checked(1)?;
+    let number: u8 = number.parse().ok().filter(|n| *n < 8)?;
+    match kind {
+        "d" => Some(number),
+        "a" => Some(number + 8),
+        _ => None,
+    }
+}
+
+fn register_name(register: u8) -> String {
+    if register < 8 {
+        format!("d{register}")
+    } else {
+        format!("a{}", register - 8)
+    }
+}
+
+impl Entry {
+    fn integer(&self, key: &str, max: u64) -> Result<Option<u64>, ProjectError> {
+        match self.keys.get(key) {
+            None => Ok(None),
+            Some((_, Value::Integer(v))) if *v >= 0 && *v as u64 <= max => Ok(Some(*v as u64)),
+            Some((line, _)) => Err(ProjectError::BadValue {
+                line: *line,
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<Option<&str>, ProjectError> {
+        match self.keys.get(key) {
+            None => Ok(None),
+            Some((_, Value::String(s))) => Ok(Some(s)),
+            Some((line, _)) => Err(ProjectError::BadValue {
+                line: *line,
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    fn required<T>(&self, key: &'static str, value: Option<T>) -> Result<T, ProjectError> {
+        value.ok_or(ProjectError::MissingKey {
+            line: self.line,
+            key,
+        })
+    }
+
+    fn bad_value(&self, key: &str) -> ProjectError {
+        ProjectError::BadValue {
+            line: self.keys.get(key).map_or(self.line, |(line, _)| *line),
+            key: key.to_string(),
+        }
+    }
+
+    /// The entry's location; `hunk` defaults to 0.
+    fn location(&self) -> Result<Location, ProjectError> {
+        let hunk = self.integer("hunk", u32::MAX as u64)?.unwrap_or(0) as usize;
+        let offset = self.integer("offset", u32::MAX as u64)?;
+        Ok(Location::new(hunk, self.required("offset", offset)? as u32))
+    }
+
+    fn length(&self) -> Result<u32, ProjectError> {
+        let length = self.integer("length", u32::MAX as u64)?;
+        match self.required("length", length)? {
+            0 => Err(self.bad_value("length")),
+            length => Ok(length as u32),
+        }
+    }
+
+    fn apply(&self, project: &mut Project) -> Result<(), ProjectError> {
+        let location = self.location()?;
+        let (hunk, offset) = (location.hunk, location.offset);
+        match self.table {
+            "label" => {
+                let name = self.required("name", self.string("name")?)?;
+                project.labels.insert(location, name.to_string());
+            }
+            "comment" => {
+                let text = self.required("text", self.string("text")?)?;
+                project.comments.insert(location, text.to_string());
+            }
+            "function" => {
+                let name = self.required("name", self.string("name")?)?;
+                let signature = self.string("signature")?.map(str::to_string);
+                project.functions.insert(
+                    location,
+                    Function {
+                        name: name.to_string(),
+                        signature,
+                    },
+                );
+            }
+            "data" => {
+                let length = self.length()?;
+                let name = self.required("type", self.string("type")?)?;
+                let data_type = DataType::from_name(name).ok_or_else(|| self.bad_value("type"))?;
+                project.data.push(DataRange {
+                    hunk,
+                    offset,
+                    length,
+                    data_type,
+                });
+            }
+            "code" => {
+                let length = self.length()?;
+                project.code.push(CodeRange {
+                    hunk,
+                    offset,
+                    length,
+                });
+            }
+            _ => {
+                let name = self.required("register", self.string("register")?)?;
+                let register = parse_register(name).ok_or_else(|| self.bad_value("register"))?;
+                let type_name = self.required("type", self.string("type")?)?;
+                project.registers.push(RegisterHint {
+                    hunk,
+                    offset,
+                    register,
+                    type_name: type_name.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quote a string as a TOML basic string.
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl Project {
+    /// Write the project in the format `parse_project` reads.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("# dis68k project file\n");
+        for (location, function) in &self.functions {
+            write_header(&mut out, "function", location.hunk, location.offset);
+            let _ = writeln!(out, "name = {}", quote(&function.name));
+            if let Some(signature) = &function.signature {
+                let _ = writeln!(out, "signature = {}", quote(signature));
+            }
+        }
+        for (location, name) in &self.labels {
+            write_header(&mut out, "label", location.hunk, location.offset);
+            let _ = writeln!(out, "name = {}", quote(name));
+        }
+        for (location, text) in &self.comments {
+            write_header(&mut out, "comment", location.hunk, location.offset);
+            let _ = writeln!(out, "text = {}", quote(text));
+        }
+        for range in &self.data {
+            write_header(&mut out, "data", range.hunk, range.offset);
+            let _ = writeln!(out, "length = {}", range.length);
+            let _ = writeln!(out, "type = {}", quote(range.data_type.name()));
+        }
+        for range in &self.code {
+            write_header(&mut out, "code", range.hunk, range.offset);
+            let _ = writeln!(out, "length = {}", range.length);
+        }
+        for hint in &self.registers {
+            write_header(&mut out, "register", hint.hunk, hint.offset);
+            let _ = writeln!(out, "register = {}", quote(&register_name(hint.register)));
+            let _ = writeln!(out, "type = {}", quote(&hint.type_name));
+        }
+        out
+    }
+}
+
+fn write_header(out: &mut String, table: &str, hunk: usize, offset: u32) {
+    let _ = write!(out, "\n[[{table}]]\nhunk = {hunk}\noffset = 0x{offset:04X}\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let text = r#"
+# Notes on the game loader
+[[function]]
+offset = 0x40           # hunk defaults to 0
+name = "ParseArgs"
+signature = "BOOL ParseArgs(char *line)"
+
+[[label]]
+hunk = 1
+offset = 0x1_00
+name = 'Table'
+
+[[comment]]
+hunk = 0
+offset = 64
+text = "Reads \"argv\"\nthen returns"
+
+[[data]]
+hunk = 1
+offset = 0x100
+length = 32
+type = "word"
+
+[[code]]
+hunk = 1
+offset = 0x200
+length = 0x40
+
+[[register]]
+offset = 0x48
+register = "A2"
+type = "Window"
+"#;
+        let project = parse_project(text).unwrap();
+        assert_eq!(project.label(Location::new(0, 0x40)), Some("ParseArgs"));
+        assert_eq!(project.label(Location::new(1, 0x100)), Some("Table"));
+        assert_eq!(project.comments[&Location::new(0, 64)], "Reads \"argv\"\nthen returns");
+        assert_eq!(project.data[0].data_type, DataType::Word);
+        assert_eq!(project.code[0].length, 0x40);
+        assert_eq!(project.registers[0].register, 10);
+
+        assert_eq!(parse_project(&project.to_toml()).unwrap(), project);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_project("name = \"x\""),
+            Err(ProjectError::KeyOutsideTable { line: 1 })
+        );
+        assert_eq!(
+            parse_project("[[labels]]"),
+            Err(ProjectError::UnknownTable {
+                line: 1,
+                name: "labels".to_string()
+            })
+        );
+        assert_eq!(
+            parse_project("[[label]]\noffset = 4\nsize = 2"),
+            Err(ProjectError::UnknownKey {
+                line: 3,
+                key: "size".to_string()
+            })
+        );
+        assert_eq!(
+            parse_project("[[label]]\noffset = 4\n\n[[data]]\noffset = 8\nlength = 2\ntype = \"word\""),
+            Err(ProjectError::MissingKey { line: 1, key: "name" })
+        );
+        assert_eq!(
+            parse_project("[[data]]\noffset = 8\nlength = 2\ntype = \"float\""),
+            Err(ProjectError::BadValue {
+                line: 4,
+                key: "type".to_string()
+            })
+        );
+        assert_eq!(
+            parse_project("[[label]]\noffset = $10"),
+            Err(ProjectError::BadValue {
+                line: 2,
+                key: "offset".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Raw binary input: flat memory images with a user-supplied layout.
+//!
+//! Trackloaded games, demo parts ripped from memory and chip-RAM dumps
+//! have no hunk structure. The caller says where the bytes were loaded
+//! (and optionally how the file splits into separately placed segments)
+//! and which addresses are entry points; each segment becomes a
+//! synthetic CODE hunk at its load address.
+
+pub mod error;
+
+pub use error::RawError;
+
+use crate::hunk::types::{Hunk, HunkFile, Symbol};
+use crate::image::Image;
+use crate::output::listing::{emit_image_sections, push_line, ListingLine, ListingOptions};
+use crate::symbols::resolver::SymbolResolver;
+
+/// A slice of the input file placed at a fixed address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawSegment {
+    pub file_offset: usize,
+    pub length: usize,
+    pub load_address: u32,
+}
+
+/// How a raw binary is laid out in memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawLayout {
+    /// Load address of the whole file when `segments` is empty.
+    pub base_address: u32,
+    /// Explicit segments; when empty, the whole file is one segment at
+    /// `base_address`.
+    pub segments: Vec<RawSegment>,
+    /// Entry point addresses. Defaults to the first segment's start.
+    pub entry_points: Vec<u32>,
+}
+
+/// Build a hunk file from a raw binary.
+///
+/// Every segment becomes a CODE hunk with its `base_address` set to the
+/// load address, so instruction addresses, branch targets and labels are
+/// absolute. Entry points are added as `entry_XXXXXXXX` symbols.
+pub fn load_raw_binary(data: &[u8], layout: &RawLayout) -> Result<HunkFile, RawError> {
+    let mut segments = if layout.segments.is_empty() {
+        vec![RawSegment {
+            file_offset: 0,
+            length: data.len(),
+            load_address: layout.base_address,
+        }]
+    } else {
+        layout.segments.clone()
+    };
+
+    for seg in &segments {
+        if seg.file_offset.checked_add(seg.length).is_none_or(|end| end > data.len()) {
+            return Err(RawError::SegmentOutOfRange {
+                file_offset: seg.file_offset,
+                length: seg.length,
+                file_size: data.len(),
+            });
+        }
+    }
+    segments.sort_by_key(|s| s.load_address);
+    for pair in segments.windows(2) {
+        if pair[0].load_address as u64 + pair[0].length as u64 > pair[1].load_address as u64 {
+            return Err(RawError::OverlappingSegments {
+                first: pair[0].load_address,
+                second: pair[1].load_address,
+            });
+        }
+    }
+
+    let entry_points = if layout.entry_points.is_empty() {
+        segments.iter().map(|s| s.load_address).take(1).collect()
+    } else {
+        layout.entry_points.clone()
+    };
+
+    let mut hunks: Vec<Hunk> = segments
+        .iter()
+        .enumerate()
+        .map(|(index, seg)| {
+            Hunk::from_image(
+                index,
+                seg.load_address,
+                &data[seg.file_offset..seg.file_offset + seg.length],
+                seg.file_offset,
+                Some(format!("seg_{:08X}", seg.load_address)),
+            )
+        })
+        .collect();
+
+    for &entry in &entry_points {
+        let hunk = hunks
+            .iter_mut()
+            .find(|h| entry >= h.base_address && entry - h.base_address < h.alloc_size)
+            .ok_or(RawError::EntryOutsideSegments { address: entry })?;
+        hunk.symbols.push(Symbol {
+            name: format!("entry_{entry:08X}"),
+            value: entry - hunk.base_address,
+        });
+    }
+
+    Ok(HunkFile {
+        first_hunk: 0,
+        last_hunk: hunks.len().saturating_sub(1) as u32,
+        hunks,
+        blocks: Vec::new(),
+    })
+}
+
+/// Generate a disassembly listing for a raw binary laid out by `load_raw_binary`.
+pub fn generate_raw_listing(
+    hunk_file: &HunkFile,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+) -> Vec<ListingLine> {
+    let mut lines = Vec::new();
+    let mut line_num: u32 = 1;
+
+    push_line(&mut lines, &mut line_num, options, "; Raw Binary Disassembly".to_string());
+    for hunk in &hunk_file.hunks {
+        push_line(
+            &mut lines,
+            &mut line_num,
+            options,
+            format!(
+                "; Segment: ${:08X}-${:08X} (file offset ${:X}, {} bytes)",
+                hunk.base_address,
+                hunk.base_address as u64 + hunk.alloc_size as u64,
+                hunk.data_offset,
+                hunk.alloc_size
+            ),
+        );
+    }
+    for hunk in &hunk_file.hunks {
+        for sym in &hunk.symbols {
+            push_line(
+                &mut lines,
+                &mut line_num,
+                options,
+                format!("; Entry: ${:08X}", hunk.base_address.wrapping_add(sym.value)),
+            );
+        }
+    }
+    push_line(&mut lines, &mut line_num, options, String::new());
+
+    emit_image_sections(&Image::from(hunk_file), options, resolver, &mut lines, &mut line_num);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bra.s +4 / nop / nop / rts
+    const CODE: [u8; 8] = [0x60, 0x04, 0x4E, 0x71, 0x4E, 0x71, 0x4E, 0x75];
+
+    #[test]
+    fn single_segment_at_base() {
+        let layout = RawLayout {
+            base_address: 0x40000,
+            ..RawLayout::default()
+        };
+        let hunk_file = load_raw_binary(&CODE, &layout).unwrap();
+        assert_eq!(hunk_file.hunks.len(), 1);
+        assert_eq!(hunk_file.hunks[0].base_address, 0x40000);
+        assert_eq!(hunk_file.hunks[0].symbols[0].name, "entry_00040000");
+
+        let options = ListingOptions {
+            show_line_numbers: false,
+            ..ListingOptions::default()
+        };
+        let text: Vec<String> = generate_raw_listing(&hunk_file, &options, None)
+            .into_iter()
+            .map(|l| l.text)
+            .collect();
+        assert!(text.iter().any(|l| l.starts_with("entry_00040000:")));
+        // The branch target is computed relative to the base and labelled
+        assert!(text.iter().any(|l| l.starts_with("00040000") && l.contains("loc_40006")));
+        assert!(text.iter().any(|l| l.starts_with("loc_40006:")));
+    }
+
+    #[test]
+    fn multiple_segments_and_entries() {
+        let mut data = CODE.to_vec();
+        data.extend_from_slice(&CODE);
+        let layout = RawLayout {
+            base_address: 0,
+            segments: vec![
+                RawSegment { file_offset: 8, length: 8, load_address: 0x70000 },
+                RawSegment { file_offset: 0, length: 8, load_address: 0x1000 },
+            ],
+            entry_points: vec![0x1000, 0x70002],
+        };
+        let hunk_file = load_raw_binary(&data, &layout).unwrap();
+        let bases: Vec<u32> = hunk_file.hunks.iter().map(|h| h.base_address).collect();
+        assert_eq!(bases, [0x1000, 0x70000]);
+        assert_eq!(hunk_file.hunks[1].symbols[0].value, 2);
+    }
+
+    #[test]
+    fn rejects_bad_layouts() {
+        let seg = |file_offset, length, load_address| RawSegment { file_offset, length, load_address };
+        let layout = RawLayout {
+            segments: vec![seg(4, 8, 0)],
+            ..RawLayout::default()
+        };
+        assert!(matches!(
+            load_raw_binary(&CODE, &layout),
+            Err(RawError::SegmentOutOfRange { .. })
+        ));
+
+        let layout = RawLayout {
+            segments: vec![seg(0, 8, 0x100), seg(0, 8, 0x104)],
+            ..RawLayout::default()
+        };
+        assert!(matches!(
+            load_raw_binary(&CODE, &layout),
+            Err(RawError::OverlappingSegments { first: 0x100, second: 0x104 })
+        ));
+
+        let layout = RawLayout {
+            entry_points: vec![0x2000],
+            ..RawLayout::default()
+        };
+        assert!(matches!(
+            load_raw_binary(&CODE, &layout),
+            Err(RawError::EntryOutsideSegments { address: 0x2000 })
+        ));
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+/// Errors that can occur while laying out a raw binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawError {
+    /// A segment's file range extends past the end of the input.
+    SegmentOutOfRange {
+        file_offset: usize,
+        length: usize,
+        file_size: usize,
+    },
+    /// Two segments are loaded at overlapping addresses.
+    OverlappingSegments { first: u32, second: u32 },
+    /// An entry point does not fall inside any segment.
+    EntryOutsideSegments { address: u32 },
+}
+
+impl fmt::Display for RawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawError::SegmentOutOfRange {
+                file_offset,
+                length,
+                file_size,
+            } => write!(
+                f,
+                "segment at file offset ${file_offset:X} with length ${length:X} exceeds the {file_size}-byte input"
+            ),
+            RawError::OverlappingSegments { first, second } => {
+                write!(f, "segments at ${first:08X} and ${second:08X} overlap")
+            }
+            RawError::EntryOutsideSegments { address } => {
+                write!(f, "entry point ${address:08X} is not inside any segment")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RawError {}
@@ -0,0 +1,323 @@
+//! Kickstart ROM image disassembly.
+//!
+//! A ROM is not a hunk file: it is a flat image mapped at a fixed address
+//! ($F80000 or $FC0000). Modules are found by scanning for their RomTag
+//! (Resident) structures, and each module is presented to the listing
+//! generator as a synthetic CODE hunk placed at its real address, so the
+//! normal section/label/LVO machinery applies unchanged.
+
+pub mod error;
+pub mod image;
+pub mod resident;
+
+pub use error::RomError;
+pub use image::{merge_split_rom, RomImage};
+pub use resident::{analyze_rom, FunctionTable, LibraryFunction, Resident, ResidentInit, RomModule};
+
+use std::collections::BTreeMap;
+
+use crate::hunk::types::{Hunk, HunkFile, Symbol};
+use crate::image::Image;
+use crate::output::listing::{emit_image_sections, push_line, ListingLine, ListingOptions};
+use crate::symbols::resolver::SymbolResolver;
+
+/// A Kickstart ROM together with its discovered modules.
+#[derive(Debug, Clone)]
+pub struct RomAnalysis {
+    pub image: RomImage,
+    pub modules: Vec<RomModule>,
+}
+
+impl RomAnalysis {
+    /// Load and analyze a ROM image (see `RomImage::load`).
+    pub fn new(data: &[u8], cpu: crate::m68k::variants::CpuVariant) -> Result<RomAnalysis, RomError> {
+        let image = RomImage::load(data)?;
+        let modules = analyze_rom(&image, cpu);
+        Ok(RomAnalysis { image, modules })
+    }
+
+    /// Labels for every RomTag, init routine, function table and library
+    /// function, keyed by absolute address. The first name wins.
+    pub fn labels(&self) -> BTreeMap<u32, String> {
+        let mut labels = BTreeMap::new();
+        for module in &self.modules {
+            add_module_labels(module, &mut labels);
+        }
+        labels.retain(|&addr, _| self.image.contains(addr));
+        labels
+    }
+
+    /// Present the ROM as a hunk file: one CODE hunk per module, plus
+    /// hunks for the header and any gaps between modules. Every hunk
+    /// carries its absolute `base_address`.
+    pub fn to_hunk_file(&self) -> HunkFile {
+        let rom = &self.image;
+        let mut regions: Vec<(u32, u32, String)> = Vec::new();
+        let mut cursor = rom.base_address;
+
+        for module in &self.modules {
+            if module.start > cursor {
+                regions.push((cursor, module.start, region_name(rom, cursor)));
+            }
+            let start = module.start.max(cursor);
+            if module.end > start {
+                regions.push((start, module.end, module.resident.name.clone()));
+                cursor = module.end;
+            }
+        }
+        if cursor < rom.end_address() {
+            regions.push((cursor, rom.end_address(), region_name(rom, cursor)));
+        }
+
+        let labels = self.labels();
+        let hunks = regions
+            .into_iter()
+            .enumerate()
+            .map(|(index, (start, end, name))| {
+                let lo = (start - rom.base_address) as usize;
+                let hi = (end - rom.base_address) as usize;
+                let symbols = labels
+                    .range(start..end)
+                    .map(|(&addr, name)| Symbol {
+                        name: name.clone(),
+                        value: addr - start,
+                    })
+                    .collect();
+                let mut hunk = Hunk::from_image(index, start, &rom.data[lo..hi], lo, Some(name));
+                hunk.symbols = symbols;
+                hunk
+            })
+            .collect::<Vec<_>>();
+
+        HunkFile {
+            first_hunk: 0,
+            last_hunk: hunks.len().saturating_sub(1) as u32,
+            hunks,
+            blocks: Vec::new(),
+        }
+    }
+}
+
+/// Add labels for a module's RomTag, init routine or InitTable, function
+/// tables and functions. Existing labels are kept.
+pub(crate) fn add_module_labels(module: &RomModule, labels: &mut BTreeMap<u32, String>) {
+    let r = &module.resident;
+    let prefix = r.short_name();
+    labels.entry(r.address).or_insert_with(|| format!("{prefix}_RomTag"));
+    match &r.init {
+        ResidentInit::Routine(addr) => {
+            labels.entry(*addr).or_insert_with(|| format!("{prefix}_Init"));
+        }
+        ResidentInit::AutoInit(init) => {
+            labels.entry(init.address).or_insert_with(|| format!("{prefix}_InitTable"));
+            if init.init_routine != 0 {
+                labels.entry(init.init_routine).or_insert_with(|| format!("{prefix}_Init"));
+            }
+        }
+    }
+    for (i, table) in module.function_tables.iter().enumerate() {
+        let name = if i == 0 {
+            format!("{prefix}_FuncTable")
+        } else {
+            format!("{prefix}_FuncTable{i}")
+        };
+        labels.entry(table.address).or_insert(name);
+    }
+    for func in &module.functions {
+        labels.entry(func.address).or_insert_with(|| func.name.clone());
+    }
+}
+
+fn region_name(rom: &RomImage, start: u32) -> String {
+    if start == rom.base_address {
+        "rom_header".to_string()
+    } else {
+        format!("rom_{start:06X}")
+    }
+}
+
+/// Generate a disassembly listing for an analyzed Kickstart ROM.
+///
+/// The header summarises the ROM (base, size, version, checksum) and its
+/// module table; each region then follows as a section at its real address.
+pub fn generate_rom_listing(
+    rom: &RomAnalysis,
+    options: &ListingOptions,
+    resolver: Option<&dyn SymbolResolver>,
+) -> Vec<ListingLine> {
+    let mut lines = Vec::new();
+    let mut line_num: u32 = 1;
+    let image = &rom.image;
+    let (version, revision) = image.version();
+
+    let header = [
+        "; Amiga Kickstart ROM Disassembly".to_string(),
+        format!(
+            "; Base: ${:08X}, {} bytes{}",
+            image.base_address,
+            image.data.len(),
+            if image.byte_swapped { " (byte-swapped dump)" } else { "" }
+        ),
+        format!(
+            "; Version: {version}.{revision}, checksum {}",
+            if image.checksum_ok() { "OK" } else { "BAD" }
+        ),
+        format!("; Modules: {}", rom.modules.len()),
+    ];
+    for text in header {
+        push_line(&mut lines, &mut line_num, options, text);
+    }
+    for module in &rom.modules {
+        let r = &module.resident;
+        push_line(
+            &mut lines,
+            &mut line_num,
+            options,
+            format!(
+                ";   ${:08X}  {:<24} v{:<3} pri {:>4}  {:<11} {:<20} {} functions",
+                r.address,
+                r.name,
+                r.version,
+                r.priority,
+                r.node_type_name(),
+                r.flag_names(),
+                module.functions.len()
+            ),
+        );
+    }
+    push_line(&mut lines, &mut line_num, options, String::new());
+
+    let hunk_file = rom.to_hunk_file();
+    emit_image_sections(&Image::from(&hunk_file), options, resolver, &mut lines, &mut line_num);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::m68k::variants::CpuVariant;
+    use crate::rom::image::ROM_256K;
+    use crate::rom::resident::{node_types, rt_flags};
+
+    const BASE: u32 = 0xFC0000;
+
+    fn put(rom: &mut [u8], addr: u32, bytes: &[u8]) {
+        let at = (addr - BASE) as usize;
+        rom[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn put_u32(rom: &mut [u8], addr: u32, v: u32) {
+        put(rom, addr, &v.to_be_bytes());
+    }
+
+    /// A 256K ROM holding one AUTOINIT "exec.library" module with a
+    /// word-relative function table of six vectors.
+    fn synthetic_rom() -> Vec<u8> {
+        let mut rom = vec![0u8; ROM_256K];
+        put(&mut rom, BASE, &[0x11, 0x11, 0x4E, 0xF9]);
+        put_u32(&mut rom, BASE + 4, BASE + 0x300);
+        put_u32(&mut rom, BASE + 0x0C, 0x0022_0005); // 34.5
+
+        let tag = BASE + 0x100;
+        put(&mut rom, tag, &[0x4A, 0xFC]);
+        put_u32(&mut rom, tag + 2, tag);
+        put_u32(&mut rom, tag + 6, BASE + 0x400);
+        put(
+            &mut rom,
+            tag + 10,
+            &[rt_flags::RTF_AUTOINIT | rt_flags::RTF_COLDSTART, 34, node_types::NT_LIBRARY, 126],
+        );
+        put_u32(&mut rom, tag + 14, BASE + 0x200);
+        put_u32(&mut rom, tag + 18, BASE + 0x210);
+        put_u32(&mut rom, tag + 22, BASE + 0x220);
+        put(&mut rom, BASE + 0x200, b"exec.library\0");
+        put(&mut rom, BASE + 0x210, b"exec 34.5\0");
+
+        // InitTable
+        put_u32(&mut rom, BASE + 0x220, 0x300);
+        put_u32(&mut rom, BASE + 0x224, BASE + 0x240);
+        put_u32(&mut rom, BASE + 0x228, 0);
+        put_u32(&mut rom, BASE + 0x22C, BASE + 0x300);
+
+        // Word-relative function table: six vectors at $300, $302, ...
+        put(&mut rom, BASE + 0x240, &[0xFF, 0xFF]);
+        for i in 0..6u16 {
+            put(&mut rom, BASE + 0x242 + 2 * i as u32, &(0xC0 + 2 * i).to_be_bytes());
+        }
+        put(&mut rom, BASE + 0x24E, &[0xFF, 0xFF]);
+        for i in 0..6u32 {
+            put(&mut rom, BASE + 0x300 + 2 * i, &[0x4E, 0x75]);
+        }
+        rom
+    }
+
+    #[test]
+    fn finds_resident_and_names_vectors() {
+        let analysis = RomAnalysis::new(&synthetic_rom(), CpuVariant::M68000).unwrap();
+        assert_eq!(analysis.image.version(), (34, 5));
+        assert_eq!(analysis.modules.len(), 1);
+
+        let module = &analysis.modules[0];
+        assert_eq!(module.resident.name, "exec.library");
+        assert_eq!(module.resident.id_string, "exec 34.5");
+        assert_eq!(module.resident.priority, 126);
+        assert_eq!((module.start, module.end), (BASE + 0x100, BASE + 0x400));
+        assert!(matches!(module.resident.init, ResidentInit::AutoInit(_)));
+
+        let table = &module.function_tables[0];
+        assert!(table.word_relative);
+        assert_eq!(table.entries.len(), 6);
+        assert_eq!(table.entries[0], BASE + 0x300);
+
+        let names: Vec<&str> = module.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "exec_LibOpen",
+                "exec_LibClose",
+                "exec_LibExpunge",
+                "exec_LibReserved",
+                "exec_Supervisor",
+                "exec_ExitIntr",
+            ]
+        );
+        assert_eq!(module.functions[5].lvo, -36);
+    }
+
+    #[test]
+    fn hunk_file_covers_whole_rom() {
+        let analysis = RomAnalysis::new(&synthetic_rom(), CpuVariant::M68000).unwrap();
+        let hunk_file = analysis.to_hunk_file();
+        let bases: Vec<u32> = hunk_file.hunks.iter().map(|h| h.base_address).collect();
+        assert_eq!(bases, [BASE, BASE + 0x100, BASE + 0x400]);
+        assert_eq!(hunk_file.hunks[1].name.as_deref(), Some("exec.library"));
+        let total: usize = hunk_file.hunks.iter().map(|h| h.data.len()).sum();
+        assert_eq!(total, ROM_256K);
+
+        let module = &hunk_file.hunks[1];
+        // $FC0300 is both the init routine and LibOpen; the init label wins
+        let sym = module.symbols.iter().find(|s| s.value == 0x200).unwrap();
+        assert_eq!(sym.name, "exec_Init");
+        let sym = module.symbols.iter().find(|s| s.name == "exec_LibClose").unwrap();
+        assert_eq!(sym.value, 0x202);
+    }
+
+    #[test]
+    fn listing_uses_absolute_addresses_and_labels() {
+        let analysis = RomAnalysis::new(&synthetic_rom(), CpuVariant::M68000).unwrap();
+        let options = ListingOptions {
+            show_line_numbers: false,
+            ..ListingOptions::default()
+        };
+        let text: Vec<String> = generate_rom_listing(&analysis, &options, None)
+            .into_iter()
+            .map(|l| l.text)
+            .collect();
+        assert_eq!(text[0], "; Amiga Kickstart ROM Disassembly");
+        assert!(text.iter().any(|l| l.contains("exec.library") && l.contains("COLDSTART|AUTOINIT")));
+        assert!(text.iter().any(|l| l.contains("SECTION exec.library, CODE") && l.contains("at $00FC0100")));
+        assert!(text.iter().any(|l| l.starts_with("exec_Supervisor:")));
+        assert!(text.iter().any(|l| l.contains("00FC0300")));
+    }
+}
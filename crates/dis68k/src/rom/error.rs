@@ -0,0 +1,25 @@
+use std::fmt;
+
+/// Errors that can occur while loading a ROM image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    /// The image is not one of the supported ROM sizes (256K or 512K).
+    BadSize { size: usize },
+    /// The two halves of a split ROM pair differ in length.
+    MismatchedHalves { hi: usize, lo: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::BadSize { size } => {
+                write!(f, "unsupported ROM size {size} bytes (expected 256K or 512K)")
+            }
+            RomError::MismatchedHalves { hi, lo } => {
+                write!(f, "split ROM halves differ in size: {hi} vs {lo} bytes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
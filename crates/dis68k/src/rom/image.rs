@@ -0,0 +1,189 @@
+//! Raw Kickstart ROM images: size detection, byte order and split pairs.
+
+use super::error::RomError;
+
+/// Size of a 256K Kickstart (1.x), mapped at $FC0000.
+pub const ROM_256K: usize = 256 * 1024;
+/// Size of a 512K Kickstart (2.0+), mapped at $F80000.
+pub const ROM_512K: usize = 512 * 1024;
+
+/// A Kickstart ROM image mapped at its fixed address.
+#[derive(Debug, Clone)]
+pub struct RomImage {
+    /// Address of the first ROM byte ($F80000 or $FC0000).
+    pub base_address: u32,
+    /// ROM contents in 68k (big-endian) byte order.
+    pub data: Vec<u8>,
+    /// Whether the input had to be byte-swapped.
+    pub byte_swapped: bool,
+}
+
+impl RomImage {
+    /// Load a 256K or 512K ROM image, correcting byte-swapped dumps.
+    ///
+    /// Every Kickstart starts with a `$1111` or `$1114` marker followed by
+    /// `JMP abs.l` (`$4EF9`). A dump taken from a little-endian EPROM
+    /// reader has each word reversed, which shows up as `F9 4E` there.
+    pub fn load(data: &[u8]) -> Result<RomImage, RomError> {
+        let base_address = match data.len() {
+            ROM_256K => 0x00FC_0000,
+            ROM_512K => 0x00F8_0000,
+            size => return Err(RomError::BadSize { size }),
+        };
+
+        let byte_swapped = data[2] == 0xF9 && data[3] == 0x4E;
+        let data = if byte_swapped {
+            data.chunks_exact(2).flat_map(|w| [w[1], w[0]]).collect()
+        } else {
+            data.to_vec()
+        };
+
+        Ok(RomImage {
+            base_address,
+            data,
+            byte_swapped,
+        })
+    }
+
+    /// One past the last mapped address.
+    pub fn end_address(&self) -> u32 {
+        self.base_address + self.data.len() as u32
+    }
+
+    /// Whether `address` lies inside the ROM.
+    pub fn contains(&self, address: u32) -> bool {
+        address >= self.base_address && address < self.end_address()
+    }
+
+    /// Byte offset of `address` within the image.
+    pub fn offset_of(&self, address: u32) -> Option<usize> {
+        self.contains(address)
+            .then(|| (address - self.base_address) as usize)
+    }
+
+    pub fn read_u8(&self, address: u32) -> Option<u8> {
+        self.offset_of(address).map(|o| self.data[o])
+    }
+
+    pub fn read_u16(&self, address: u32) -> Option<u16> {
+        let o = self.offset_of(address)?;
+        let b = self.data.get(o..o + 2)?;
+        Some(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn read_u32(&self, address: u32) -> Option<u32> {
+        let o = self.offset_of(address)?;
+        let b = self.data.get(o..o + 4)?;
+        Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Read a NUL-terminated string, stopping at 256 bytes or a
+    /// non-printable character.
+    pub fn read_cstring(&self, address: u32) -> Option<String> {
+        let start = self.offset_of(address)?;
+        let bytes: Vec<u8> = self.data[start..]
+            .iter()
+            .take(256)
+            .take_while(|&&b| b != 0)
+            .copied()
+            .collect();
+        let printable = bytes
+            .iter()
+            .all(|&b| b.is_ascii_graphic() || b == b' ' || b == b'\r' || b == b'\n' || b == b'\t');
+        printable.then(|| String::from_utf8_lossy(&bytes).trim_end().to_string())
+    }
+
+    /// Kickstart version and revision from the ROM header (offset $0C).
+    pub fn version(&self) -> (u16, u16) {
+        let v = self.read_u32(self.base_address + 0x0C).unwrap_or(0);
+        ((v >> 16) as u16, v as u16)
+    }
+
+    /// Whether the ROM checksum is valid.
+    ///
+    /// The 32-bit sum of all longwords, with each carry added back in,
+    /// must be $FFFFFFFF.
+    pub fn checksum_ok(&self) -> bool {
+        let mut sum: u32 = 0;
+        for chunk in self.data.chunks_exact(4) {
+            let v = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let (s, carry) = sum.overflowing_add(v);
+            sum = s.wrapping_add(carry as u32);
+        }
+        sum == 0xFFFF_FFFF
+    }
+}
+
+/// Merge a split 32-bit ROM pair into one image.
+///
+/// A1200/A3000/A4000 ROMs are two 16-bit EPROMs: the "hi" chip holds
+/// the upper word of every longword and the "lo" chip the lower word.
+pub fn merge_split_rom(hi: &[u8], lo: &[u8]) -> Result<Vec<u8>, RomError> {
+    if hi.len() != lo.len() {
+        return Err(RomError::MismatchedHalves {
+            hi: hi.len(),
+            lo: lo.len(),
+        });
+    }
+    Ok(hi
+        .chunks(2)
+        .zip(lo.chunks(2))
+        .flat_map(|(h, l)| h.iter().chain(l.iter()).copied())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank_rom(size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[..8].copy_from_slice(&[0x11, 0x14, 0x4E, 0xF9, 0x00, 0xF8, 0x00, 0xD2]);
+        data[0x0C..0x10].copy_from_slice(&[0x00, 0x28, 0x00, 0x44]); // 40.68
+        data
+    }
+
+    #[test]
+    fn maps_by_size() {
+        assert_eq!(RomImage::load(&blank_rom(ROM_512K)).unwrap().base_address, 0xF80000);
+        assert_eq!(RomImage::load(&blank_rom(ROM_256K)).unwrap().base_address, 0xFC0000);
+        assert!(matches!(
+            RomImage::load(&[0; 1000]),
+            Err(RomError::BadSize { size: 1000 })
+        ));
+    }
+
+    #[test]
+    fn corrects_byte_swapped_dump() {
+        let rom = blank_rom(ROM_512K);
+        let swapped: Vec<u8> = rom.chunks(2).flat_map(|w| [w[1], w[0]]).collect();
+        let image = RomImage::load(&swapped).unwrap();
+        assert!(image.byte_swapped);
+        assert_eq!(image.data, rom);
+        assert_eq!(image.version(), (40, 68));
+    }
+
+    #[test]
+    fn merges_split_pair() {
+        let merged = merge_split_rom(&[0x11, 0x14, 0x00, 0xF8], &[0x4E, 0xF9, 0x00, 0xD2]).unwrap();
+        assert_eq!(merged, vec![0x11, 0x14, 0x4E, 0xF9, 0x00, 0xF8, 0x00, 0xD2]);
+        assert!(merge_split_rom(&[0; 4], &[0; 2]).is_err());
+    }
+
+    #[test]
+    fn checksum_with_end_around_carry() {
+        let mut image = RomImage::load(&blank_rom(ROM_256K)).unwrap();
+        assert!(!image.checksum_ok());
+        // Patch a longword so the carry-folded sum becomes $FFFFFFFF
+        let mut sum: u32 = 0;
+        for chunk in image.data.chunks_exact(4) {
+            let v = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let (s, c) = sum.overflowing_add(v);
+            sum = s.wrapping_add(c as u32);
+        }
+        let fix = 0xFFFF_FFFF - sum;
+        let at = image.data.len() - 24;
+        image.data[at..at + 4].copy_from_slice(&fix.to_be_bytes());
+        assert!(image.checksum_ok());
+    }
+}
@@ -0,0 +1,465 @@
+//! RomTag (Resident structure) discovery and library vector decoding.
+//!
+//! Every ROM module announces itself with a Resident structure:
+//!
+//! ```text
+//! $00  UWORD  rt_MatchWord   $4AFC (ILLEGAL)
+//! $02  APTR   rt_MatchTag    points back at the structure
+//! $06  APTR   rt_EndSkip     where the ROM scan continues
+//! $0A  UBYTE  rt_Flags       RTF_AUTOINIT, RTF_COLDSTART, ...
+//! $0B  UBYTE  rt_Version
+//! $0C  UBYTE  rt_Type        NT_LIBRARY, NT_DEVICE, ...
+//! $0D  BYTE   rt_Pri
+//! $0E  APTR   rt_Name
+//! $12  APTR   rt_IdString
+//! $16  APTR   rt_Init        init routine, or InitTable with RTF_AUTOINIT
+//! ```
+
+use crate::m68k::addressing::EffectiveAddress;
+use crate::m68k::decode::decode_instruction;
+use crate::m68k::instruction::{Mnemonic, Operand};
+use crate::m68k::variants::CpuVariant;
+use crate::symbols::amiga;
+use crate::symbols::fd::FdFile;
+
+use super::image::RomImage;
+
+/// The `ILLEGAL` opcode that starts every Resident structure.
+pub const RTC_MATCHWORD: u16 = 0x4AFC;
+/// Size of a Resident structure in bytes.
+pub const RESIDENT_SIZE: u32 = 26;
+
+/// `rt_Flags` bits.
+pub mod rt_flags {
+    pub const RTF_COLDSTART: u8 = 1 << 0;
+    pub const RTF_SINGLETASK: u8 = 1 << 1;
+    pub const RTF_AFTERDOS: u8 = 1 << 2;
+    pub const RTF_AUTOINIT: u8 = 1 << 7;
+}
+
+/// Exec node types found in `rt_Type`.
+pub mod node_types {
+    pub const NT_TASK: u8 = 1;
+    pub const NT_DEVICE: u8 = 3;
+    pub const NT_RESOURCE: u8 = 8;
+    pub const NT_LIBRARY: u8 = 9;
+}
+
+/// LVO of exec `MakeLibrary(vectors=a0, ...)`.
+const LVO_MAKE_LIBRARY: i16 = -84;
+/// LVO of exec `MakeFunctions(target=a0, functionArray=a1, funcDispBase=a2)`.
+const LVO_MAKE_FUNCTIONS: i16 = -90;
+/// Upper bound on vectors read from one function table.
+const MAX_VECTORS: usize = 1024;
+
+/// The InitTable that `rt_Init` points to when RTF_AUTOINIT is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitTable {
+    pub address: u32,
+    /// Size of the library base structure.
+    pub data_size: u32,
+    /// Function vector table passed to MakeLibrary.
+    pub func_table: u32,
+    /// InitStruct data table (0 if none).
+    pub data_table: u32,
+    /// Routine called after the library base is built (0 if none).
+    pub init_routine: u32,
+}
+
+/// What `rt_Init` refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResidentInit {
+    /// A plain init routine.
+    Routine(u32),
+    /// An InitTable for MakeLibrary (RTF_AUTOINIT).
+    AutoInit(InitTable),
+}
+
+/// A decoded Resident (RomTag) structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resident {
+    /// Address of the `$4AFC` match word.
+    pub address: u32,
+    pub end_skip: u32,
+    pub flags: u8,
+    pub version: u8,
+    pub node_type: u8,
+    pub priority: i8,
+    pub name: String,
+    pub id_string: String,
+    pub init: ResidentInit,
+}
+
+impl Resident {
+    /// Module name without its `.library`/`.device`/... suffix, made
+    /// safe for use as a label prefix (`"exec.library"` → `"exec"`).
+    pub fn short_name(&self) -> String {
+        let stem = self.name.split('.').next().unwrap_or(&self.name);
+        let cleaned: String = stem
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if cleaned.is_empty() {
+            format!("resident_{:06X}", self.address)
+        } else {
+            cleaned
+        }
+    }
+
+    /// Human-readable `rt_Type`.
+    pub fn node_type_name(&self) -> &'static str {
+        match self.node_type {
+            node_types::NT_TASK => "NT_TASK",
+            node_types::NT_DEVICE => "NT_DEVICE",
+            node_types::NT_RESOURCE => "NT_RESOURCE",
+            node_types::NT_LIBRARY => "NT_LIBRARY",
+            0 => "NT_UNKNOWN",
+            _ => "NT_OTHER",
+        }
+    }
+
+    /// Human-readable `rt_Flags`, e.g. `"COLDSTART|AUTOINIT"`.
+    pub fn flag_names(&self) -> String {
+        let names = [
+            (rt_flags::RTF_COLDSTART, "COLDSTART"),
+            (rt_flags::RTF_SINGLETASK, "SINGLETASK"),
+            (rt_flags::RTF_AFTERDOS, "AFTERDOS"),
+            (rt_flags::RTF_AUTOINIT, "AUTOINIT"),
+        ];
+        let set: Vec<&str> = names
+            .iter()
+            .filter(|(bit, _)| self.flags & bit != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        if set.is_empty() {
+            "-".to_string()
+        } else {
+            set.join("|")
+        }
+    }
+}
+
+/// A decoded library function vector table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionTable {
+    pub address: u32,
+    /// Word-relative form (`$FFFF` header, offsets from the table start)
+    /// rather than absolute longword pointers.
+    pub word_relative: bool,
+    /// Function addresses in vector order (LVO -6, -12, ...).
+    pub entries: Vec<u32>,
+}
+
+/// One library function, named by its position in the vector table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryFunction {
+    pub address: u32,
+    /// Library vector offset (-6, -12, ...).
+    pub lvo: i16,
+    pub name: String,
+}
+
+/// A ROM module: its Resident structure, extent and library vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomModule {
+    pub resident: Resident,
+    /// First address of the module (its RomTag).
+    pub start: u32,
+    /// One past the last address of the module.
+    pub end: u32,
+    pub function_tables: Vec<FunctionTable>,
+    pub functions: Vec<LibraryFunction>,
+}
+
+/// Scan a ROM for Resident structures, the way exec does at cold start.
+///
+/// A match is a `$4AFC` word whose following longword points back at
+/// it. After each match the scan resumes at `rt_EndSkip`.
+pub fn find_residents(rom: &RomImage) -> Vec<Resident> {
+    let mut residents = Vec::new();
+    let mut addr = rom.base_address;
+
+    while addr + RESIDENT_SIZE <= rom.end_address() {
+        if rom.read_u16(addr) == Some(RTC_MATCHWORD) && rom.read_u32(addr + 2) == Some(addr) {
+            if let Some(resident) = read_resident(rom, addr) {
+                let next = resident.end_skip;
+                residents.push(resident);
+                addr = if next > addr && next < rom.end_address() {
+                    (next + 1) & !1
+                } else {
+                    addr + RESIDENT_SIZE
+                };
+                continue;
+            }
+        }
+        addr += 2;
+    }
+
+    residents
+}
+
+/// Decode the Resident at `addr`. A corrupt tag whose InitTable would
+/// run past the end of the address space is not a module.
+fn read_resident(rom: &RomImage, addr: u32) -> Option<Resident> {
+    let flags = rom.read_u8(addr + 10)?;
+    let init_ptr = rom.read_u32(addr + 22)?;
+    let init = if flags & rt_flags::RTF_AUTOINIT != 0 {
+        init_ptr.checked_add(16)?;
+        ResidentInit::AutoInit(InitTable {
+            address: init_ptr,
+            data_size: rom.read_u32(init_ptr).unwrap_or(0),
+            func_table: rom.read_u32(init_ptr + 4).unwrap_or(0),
+            data_table: rom.read_u32(init_ptr + 8).unwrap_or(0),
+            init_routine: rom.read_u32(init_ptr + 12).unwrap_or(0),
+        })
+    } else {
+        ResidentInit::Routine(init_ptr)
+    };
+
+    Some(Resident {
+        address: addr,
+        end_skip: rom.read_u32(addr + 6)?,
+        flags,
+        version: rom.read_u8(addr + 11)?,
+        node_type: rom.read_u8(addr + 12)?,
+        priority: rom.read_u8(addr + 13)? as i8,
+        name: rom.read_u32(addr + 14).and_then(|p| rom.read_cstring(p)).unwrap_or_default(),
+        id_string: rom.read_u32(addr + 18).and_then(|p| rom.read_cstring(p)).unwrap_or_default(),
+        init,
+    })
+}
+
+/// Decode a function vector table as accepted by MakeFunctions.
+///
+/// A table starting with `$FFFF` holds word offsets relative to the
+/// table start, terminated by `$FFFF`; otherwise it holds absolute
+/// longword pointers terminated by `$FFFFFFFF`. Returns `None` if any
+/// vector points outside the ROM or at an odd address.
+pub fn read_function_table(rom: &RomImage, address: u32) -> Option<FunctionTable> {
+    let mut entries = Vec::new();
+    let word_relative = rom.read_u16(address)? == 0xFFFF;
+
+    if word_relative {
+        let mut p = address + 2;
+        loop {
+            let w = rom.read_u16(p)?;
+            if w == 0xFFFF {
+                break;
+            }
+            entries.push(address.wrapping_add(w as i16 as i32 as u32));
+            p += 2;
+            if entries.len() > MAX_VECTORS {
+                return None;
+            }
+        }
+    } else {
+        let mut p = address;
+        loop {
+            let v = rom.read_u32(p)?;
+            if v == 0xFFFF_FFFF {
+                break;
+            }
+            entries.push(v);
+            p += 4;
+            if entries.len() > MAX_VECTORS {
+                return None;
+            }
+        }
+    }
+
+    if entries.is_empty() || entries.iter().any(|&e| !rom.contains(e) || e & 1 != 0) {
+        return None;
+    }
+    Some(FunctionTable {
+        address,
+        word_relative,
+        entries,
+    })
+}
+
+/// Find function tables handed to MakeFunctions/MakeLibrary in code.
+///
+/// Walks the module linearly, remembering the last `lea` into each
+/// address register. A `jsr -90(a6)` (MakeFunctions) takes its table
+/// from A1 and a `jsr -84(a6)` (MakeLibrary) from A0.
+pub(crate) fn find_make_functions_tables(rom: &RomImage, start: u32, end: u32, cpu: CpuVariant) -> Vec<u32> {
+    let (Some(lo), Some(hi)) = (rom.offset_of(start), rom.offset_of(end - 1)) else {
+        return Vec::new();
+    };
+    let code = &rom.data[lo..=hi];
+    let mut tables = Vec::new();
+    let mut areg: [Option<u32>; 8] = [None; 8];
+    let mut offset = 0usize;
+
+    while offset < code.len() {
+        let Ok(inst) = decode_instruction(code, offset, start, cpu) else {
+            offset += 2;
+            continue;
+        };
+        offset += inst.size_bytes as usize;
+
+        match (inst.mnemonic, inst.operands.as_slice()) {
+            (Mnemonic::Lea, [Operand::Ea(src), Operand::Ea(EffectiveAddress::AddressDirect(r))]) => {
+                areg[*r as usize] = match src {
+                    EffectiveAddress::PcDisplacement(d) => {
+                        Some(inst.address.wrapping_add(2).wrapping_add(*d as i32 as u32))
+                    }
+                    EffectiveAddress::AbsoluteLong(a) => Some(*a),
+                    _ => None,
+                };
+            }
+            (Mnemonic::Movea, [_, Operand::Ea(EffectiveAddress::AddressDirect(r))]) => {
+                areg[*r as usize] = None;
+            }
+            (Mnemonic::Jsr, [Operand::Ea(EffectiveAddress::AddressDisplacement(6, lvo))]) => {
+                let table = match *lvo {
+                    LVO_MAKE_FUNCTIONS => areg[1],
+                    LVO_MAKE_LIBRARY => areg[0],
+                    _ => None,
+                };
+                tables.extend(table.filter(|&t| rom.contains(t)));
+            }
+            (Mnemonic::Rts | Mnemonic::Rte | Mnemonic::Bra | Mnemonic::Jmp, _) => {
+                areg = [None; 8];
+            }
+            _ => {}
+        }
+    }
+
+    tables
+}
+
+/// Name the vectors of a function table in LVO order.
+///
+/// Names from `fd` take priority. Otherwise libraries and devices start
+/// with the standard Open/Close/Expunge/Reserved vectors (plus BeginIO/
+/// AbortIO for devices); the rest are looked up in the built-in LVO
+/// tables by module name.
+pub(crate) fn name_functions(
+    resident: &Resident,
+    table: &FunctionTable,
+    fd: Option<&FdFile>,
+) -> Vec<LibraryFunction> {
+    let prefix = resident.short_name();
+    let standard: &[&str] = match resident.node_type {
+        node_types::NT_LIBRARY => &["LibOpen", "LibClose", "LibExpunge", "LibReserved"],
+        node_types::NT_DEVICE => &[
+            "DevOpen", "DevClose", "DevExpunge", "DevReserved", "BeginIO", "AbortIO",
+        ],
+        _ => &[],
+    };
+
+    table
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, &address)| {
+            let lvo = -6 * (i as i16 + 1);
+            let func = fd
+                .and_then(|fd| fd.lookup(lvo))
+                .map(|f| f.name.clone())
+                .or_else(|| standard.get(i).map(|s| s.to_string()))
+                .or_else(|| {
+                    amiga::lookup_lvo(&prefix, lvo).map(|n| n.trim_start_matches("_LVO").to_string())
+                })
+                .unwrap_or_else(|| format!("lvo_{}", -lvo));
+            LibraryFunction {
+                address,
+                lvo,
+                name: format!("{prefix}_{func}"),
+            }
+        })
+        .collect()
+}
+
+/// Find every ROM module and name its library functions.
+///
+/// Module extents run from the RomTag to `rt_EndSkip`, clamped so
+/// modules never overlap. Function tables come from AUTOINIT
+/// InitTables and from MakeFunctions/MakeLibrary calls in the code.
+pub fn analyze_rom(rom: &RomImage, cpu: CpuVariant) -> Vec<RomModule> {
+    let residents = find_residents(rom);
+    let mut modules = Vec::with_capacity(residents.len());
+
+    for (i, resident) in residents.iter().enumerate() {
+        let start = resident.address;
+        let limit = residents
+            .get(i + 1)
+            .map(|r| r.address)
+            .unwrap_or_else(|| rom.end_address());
+        let end = if resident.end_skip > start && resident.end_skip <= limit {
+            resident.end_skip
+        } else {
+            limit
+        };
+
+        let mut table_addrs = Vec::new();
+        if let ResidentInit::AutoInit(init) = &resident.init {
+            table_addrs.push(init.func_table);
+        }
+        for t in find_make_functions_tables(rom, start, end, cpu) {
+            if !table_addrs.contains(&t) {
+                table_addrs.push(t);
+            }
+        }
+
+        let function_tables: Vec<FunctionTable> = table_addrs
+            .into_iter()
+            .filter_map(|addr| read_function_table(rom, addr))
+            .collect();
+        let functions = function_tables
+            .iter()
+            .flat_map(|t| name_functions(resident, t, None))
+            .collect();
+
+        modules.push(RomModule {
+            resident: resident.clone(),
+            start,
+            end,
+            function_tables,
+            functions,
+        });
+    }
+
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rom::image::ROM_256K;
+
+    /// A blank 256K ROM with a RomTag at `tag`.
+    fn rom_with_tag(tag: u32, flags: u8, init: u32) -> RomImage {
+        let mut data = vec![0u8; ROM_256K];
+        data[..4].copy_from_slice(&[0x11, 0x11, 0x4E, 0xF9]);
+        let mut rom = RomImage::load(&data).unwrap();
+        let at = (tag - rom.base_address) as usize;
+        rom.data[at..at + 2].copy_from_slice(&RTC_MATCHWORD.to_be_bytes());
+        rom.data[at + 2..at + 6].copy_from_slice(&tag.to_be_bytes());
+        rom.data[at + 6..at + 10].copy_from_slice(&(tag + RESIDENT_SIZE).to_be_bytes());
+        rom.data[at + 10] = flags;
+        rom.data[at + 12] = node_types::NT_LIBRARY;
+        rom.data[at + 22..at + 26].copy_from_slice(&init.to_be_bytes());
+        rom
+    }
+
+    #[test]
+    fn reads_autoinit_table() {
+        let rom = rom_with_tag(0xFC0100, rt_flags::RTF_AUTOINIT, 0xFC0200);
+        let residents = find_residents(&rom);
+        assert_eq!(residents.len(), 1);
+        assert!(matches!(&residents[0].init, ResidentInit::AutoInit(t) if t.address == 0xFC0200));
+    }
+
+    #[test]
+    fn skips_corrupt_init_pointer() {
+        let rom = rom_with_tag(0xFC0100, rt_flags::RTF_AUTOINIT, 0xFFFF_FFF8);
+        assert!(find_residents(&rom).is_empty());
+        assert!(analyze_rom(&rom, CpuVariant::M68000).is_empty());
+
+        // Without RTF_AUTOINIT the pointer is only an init routine
+        let rom = rom_with_tag(0xFC0100, 0, 0xFFFF_FFF8);
+        assert_eq!(find_residents(&rom)[0].init, ResidentInit::Routine(0xFFFF_FFF8));
+    }
+}
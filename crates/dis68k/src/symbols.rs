@@ -1,14 +1,26 @@
 //! Symbol resolution for Amiga 68k disassembly.
 //!
 //! Provides LVO (Library Vector Offset) tables for Amiga OS libraries,
-//! auto-generated labels for branch/jump targets, and a composable
-//! resolver system for mapping addresses to symbolic names.
+//! custom chip register names, OS structure layouts (built in or read
+//! from assembler include files), auto-generated labels for branch/jump
+//! targets, and a composable resolver system for mapping addresses to
+//! symbolic names.
 
 pub mod amiga;
+pub mod constants;
+pub mod fd;
+pub mod hardware;
+pub mod include;
 pub mod labels;
 pub mod resolver;
+pub mod structs;
 
 pub use resolver::{
-    AutoLabelResolver, CompositeResolver, HunkSymbolResolver, LvoResolver, SymbolResolver,
+    AutoLabelResolver, CompositeResolver, HardwareResolver, HunkSymbolResolver, LvoResolver,
+    IncludeResolver, ProjectResolver, ResolvedSymbol, StructResolver, SymbolKind, SymbolResolver,
 };
 pub use labels::collect_branch_targets;
+pub use amiga::{Library, LvoEntry, LvoParam};
+pub use constants::ConstantSet;
+pub use fd::{library_name_for_fd, parse_fd, FdFile, FdFunction};
+pub use include::{parse_includes, IncludeSymbols};
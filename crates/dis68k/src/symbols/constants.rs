@@ -0,0 +1,170 @@
+//! Named constants for library function arguments.
+//!
+//! Maps a (library, function, argument) triple to the `#define`s the
+//! argument is documented to take in the NDK includes, so a call-site
+//! annotation can say `MODE_OLDFILE` instead of `#1005`.
+
+/// The named values an argument takes.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConstantSet {
+    /// Values combine as bit flags (`MEMF_PUBLIC|MEMF_CLEAR`).
+    pub flags: bool,
+    pub values: &'static [(u32, &'static str)],
+}
+
+impl ConstantSet {
+    /// Name a value: an exact match, or for a flag set the `|`-joined
+    /// flags when every set bit has a name.
+    pub fn describe(&self, value: u32) -> Option<String> {
+        if let Some((_, name)) = self.values.iter().find(|(v, _)| *v == value) {
+            return Some(name.to_string());
+        }
+        if !self.flags || value == 0 {
+            return None;
+        }
+        let mut names = Vec::new();
+        let mut remaining = value;
+        for &(bit, name) in self.values {
+            if bit != 0 && bit.is_power_of_two() && remaining & bit != 0 {
+                names.push(name);
+                remaining &= !bit;
+            }
+        }
+        (remaining == 0).then(|| names.join("|"))
+    }
+}
+
+// exec/memory.h
+static MEMF: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0, "MEMF_ANY"),
+        (1 << 0, "MEMF_PUBLIC"),
+        (1 << 1, "MEMF_CHIP"),
+        (1 << 2, "MEMF_FAST"),
+        (1 << 8, "MEMF_LOCAL"),
+        (1 << 9, "MEMF_24BITDMA"),
+        (1 << 10, "MEMF_KICK"),
+        (1 << 16, "MEMF_CLEAR"),
+        (1 << 17, "MEMF_LARGEST"),
+        (1 << 18, "MEMF_REVERSE"),
+        (1 << 19, "MEMF_TOTAL"),
+    ],
+};
+
+// dos/dos.h
+static SIGBREAK: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (1 << 12, "SIGBREAKF_CTRL_C"),
+        (1 << 13, "SIGBREAKF_CTRL_D"),
+        (1 << 14, "SIGBREAKF_CTRL_E"),
+        (1 << 15, "SIGBREAKF_CTRL_F"),
+    ],
+};
+
+static ACCESS_MODES: ConstantSet = ConstantSet {
+    flags: false,
+    values: &[(1004, "MODE_READWRITE"), (1005, "MODE_OLDFILE"), (1006, "MODE_NEWFILE")],
+};
+
+static LOCK_TYPES: ConstantSet = ConstantSet {
+    flags: false,
+    values: &[(-2i32 as u32, "ACCESS_READ"), (-1i32 as u32, "ACCESS_WRITE")],
+};
+
+static SEEK_MODES: ConstantSet = ConstantSet {
+    flags: false,
+    values: &[(-1i32 as u32, "OFFSET_BEGINNING"), (0, "OFFSET_CURRENT"), (1, "OFFSET_END")],
+};
+
+static BUFFER_MODES: ConstantSet = ConstantSet {
+    flags: false,
+    values: &[(0, "BUF_LINE"), (1, "BUF_FULL"), (2, "BUF_NONE")],
+};
+
+// dos/dos.h, AllocDosObject types
+static DOS_OBJECTS: ConstantSet = ConstantSet {
+    flags: false,
+    values: &[
+        (0, "DOS_FILEHANDLE"),
+        (1, "DOS_EXALLCONTROL"),
+        (2, "DOS_FIB"),
+        (3, "DOS_STDPKT"),
+        (4, "DOS_CLI"),
+        (5, "DOS_RDARGS"),
+    ],
+};
+
+// dos/dosextens.h
+static DOS_LIST_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (1 << 0, "LDF_DEVICES"),
+        (1 << 1, "LDF_VOLUMES"),
+        (1 << 2, "LDF_ASSIGNS"),
+        (1 << 3, "LDF_ENTRY"),
+        (1 << 4, "LDF_DELETE"),
+        (1 << 8, "LDF_READ"),
+        (1 << 9, "LDF_WRITE"),
+    ],
+};
+
+// graphics/rastport.h
+static DRAW_MODES: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[(0, "JAM1"), (1, "JAM2"), (2, "COMPLEMENT"), (4, "INVERSVID")],
+};
+
+static ARGUMENT_CONSTANTS: &[(&str, &str, &str, &ConstantSet)] = &[
+    ("exec", "AllocMem", "requirements", &MEMF),
+    ("exec", "AllocVec", "requirements", &MEMF),
+    ("exec", "AvailMem", "requirements", &MEMF),
+    ("exec", "CreatePool", "requirements", &MEMF),
+    ("exec", "Wait", "signalSet", &SIGBREAK),
+    ("exec", "SetSignal", "signalSet", &SIGBREAK),
+    ("dos", "Open", "accessMode", &ACCESS_MODES),
+    ("dos", "Lock", "type", &LOCK_TYPES),
+    ("dos", "Seek", "offset", &SEEK_MODES),
+    ("dos", "SetVBuf", "type", &BUFFER_MODES),
+    ("dos", "AllocDosObject", "type", &DOS_OBJECTS),
+    ("dos", "FreeDosObject", "type", &DOS_OBJECTS),
+    ("dos", "CheckSignal", "mask", &SIGBREAK),
+    ("dos", "LockDosList", "flags", &DOS_LIST_FLAGS),
+    ("dos", "UnLockDosList", "flags", &DOS_LIST_FLAGS),
+    ("dos", "AttemptLockDosList", "flags", &DOS_LIST_FLAGS),
+    ("dos", "FindDosEntry", "flags", &DOS_LIST_FLAGS),
+    ("dos", "NextDosEntry", "flags", &DOS_LIST_FLAGS),
+    ("graphics", "SetDrMd", "drawMode", &DRAW_MODES),
+    ("graphics", "SetABPenDrMd", "drawmode", &DRAW_MODES),
+];
+
+/// The named constants for an argument of a library function, if any.
+pub fn argument_constants(
+    library: &str,
+    function: &str,
+    argument: &str,
+) -> Option<&'static ConstantSet> {
+    ARGUMENT_CONSTANTS
+        .iter()
+        .find(|(l, f, a, _)| *l == library && *f == function && *a == argument)
+        .map(|(_, _, _, set)| *set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_and_flags() {
+        let open = argument_constants("dos", "Open", "accessMode").unwrap();
+        assert_eq!(open.describe(1005).as_deref(), Some("MODE_OLDFILE"));
+        assert_eq!(open.describe(7), None);
+
+        let memf = argument_constants("exec", "AllocMem", "requirements").unwrap();
+        assert_eq!(memf.describe(0x10001).as_deref(), Some("MEMF_PUBLIC|MEMF_CLEAR"));
+        assert_eq!(memf.describe(0).as_deref(), Some("MEMF_ANY"));
+        assert_eq!(memf.describe(0x8000_0000), None);
+        assert!(argument_constants("dos", "Open", "name").is_none());
+    }
+}
@@ -0,0 +1,164 @@
+//! Function description (`.fd`) files.
+//!
+//! An `.fd` file lists a library's functions in vector order:
+//!
+//! ```text
+//! * comment
+//! ##base _ReqToolsBase
+//! ##bias 30
+//! ##public
+//! rtAllocRequestA(type,taglist)(d0/a0)
+//! ##private
+//! rtPrivate()()
+//! ##end
+//! ```
+//!
+//! `##bias` sets the (positive) offset of the next function; each
+//! function line takes the next vector, 6 bytes further down. The first
+//! parenthesised list names the arguments and the second gives their
+//! registers, separated by `/` (or `,` for register pairs).
+
+/// One function from an `.fd` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdFunction {
+    pub name: String,
+    /// Library vector offset (-30, -36, ...).
+    pub lvo: i16,
+    /// Argument names, e.g. `["name", "accessMode"]`.
+    pub args: Vec<String>,
+    /// Argument registers in lower case, e.g. `["d1", "d2"]`.
+    pub registers: Vec<String>,
+    /// Declared in a `##private` block.
+    pub private: bool,
+}
+
+/// A parsed `.fd` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FdFile {
+    /// Library base variable from `##base`, e.g. `_DOSBase`.
+    pub base: Option<String>,
+    pub functions: Vec<FdFunction>,
+}
+
+impl FdFile {
+    /// The function at a library vector offset.
+    pub fn lookup(&self, lvo: i16) -> Option<&FdFunction> {
+        self.functions.iter().find(|f| f.lvo == lvo)
+    }
+}
+
+/// Parse the text of an `.fd` file.
+///
+/// Parsing is lenient, as with the tools that consume these files:
+/// unknown `##` commands and malformed lines are skipped.
+pub fn parse_fd(text: &str) -> FdFile {
+    let mut fd = FdFile::default();
+    let mut bias: i32 = 30;
+    let mut private = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix("##") {
+            let (keyword, arg) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+            match keyword {
+                "base" => fd.base = Some(arg.trim().to_string()),
+                "bias" => bias = arg.trim().parse().unwrap_or(bias),
+                "public" => private = false,
+                "private" => private = true,
+                "end" => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        let Some((name, rest)) = line.split_once('(') else {
+            continue;
+        };
+        let name = name.trim();
+        let (args, rest) = rest.split_once(')').unwrap_or((rest, ""));
+        let registers = rest
+            .trim()
+            .strip_prefix('(')
+            .and_then(|r| r.split_once(')'))
+            .map_or("", |(regs, _)| regs);
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+        if let Ok(lvo) = i16::try_from(-bias) {
+            fd.functions.push(FdFunction {
+                name: name.to_string(),
+                lvo,
+                args: split_list(args, &[',']),
+                registers: split_list(&registers.to_ascii_lowercase(), &['/', ',']),
+                private,
+            });
+        }
+        bias += 6;
+    }
+
+    fd
+}
+
+/// Library name for an `.fd` file name: `dos_lib.fd` → `dos`.
+pub fn library_name_for_fd(file_name: &str) -> String {
+    let stem = file_name
+        .strip_suffix(".fd")
+        .or_else(|| file_name.strip_suffix(".FD"))
+        .unwrap_or(file_name);
+    let stem = stem
+        .strip_suffix("_lib")
+        .or_else(|| stem.strip_suffix("_LIB"))
+        .unwrap_or(stem);
+    stem.to_ascii_lowercase()
+}
+
+fn split_list(list: &str, separators: &[char]) -> Vec<String> {
+    list.split(separators)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bias_and_private_blocks() {
+        let fd = parse_fd(
+            "* reqtools\n##base _ReqToolsBase\n##bias 30\n##public\n\
+             rtAllocRequestA(type,taglist)(d0/a0)\nrtFreeRequest(req)(a1)\n\
+             ##private\nrtPrivate()()\n##bias 60\n##public\nrtEZRequestA(a,b)(a1/a2)\n\
+             ##end\nIgnored()()\n",
+        );
+        assert_eq!(fd.base.as_deref(), Some("_ReqToolsBase"));
+        let names: Vec<(&str, i16, bool)> =
+            fd.functions.iter().map(|f| (f.name.as_str(), f.lvo, f.private)).collect();
+        assert_eq!(
+            names,
+            [
+                ("rtAllocRequestA", -30, false),
+                ("rtFreeRequest", -36, false),
+                ("rtPrivate", -42, true),
+                ("rtEZRequestA", -60, false),
+            ]
+        );
+        assert_eq!(fd.lookup(-36).map(|f| f.name.as_str()), Some("rtFreeRequest"));
+        assert_eq!(fd.functions[0].args, ["type", "taglist"]);
+        assert_eq!(fd.functions[0].registers, ["d0", "a0"]);
+        assert!(fd.functions[2].registers.is_empty());
+    }
+
+    #[test]
+    fn register_groups_and_file_names() {
+        let fd = parse_fd("##bias 30\nIEEEDPAdd(leftParm,rightParm)(D0/D1,D2/D3)\n");
+        assert_eq!(fd.functions[0].registers, ["d0", "d1", "d2", "d3"]);
+        assert_eq!(library_name_for_fd("dos_lib.fd"), "dos");
+        assert_eq!(library_name_for_fd("MUIMASTER_LIB.FD"), "muimaster");
+        assert_eq!(library_name_for_fd("custom.fd"), "custom");
+    }
+}
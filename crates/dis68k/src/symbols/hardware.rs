@@ -0,0 +1,576 @@
+//! Amiga hardware register names.
+//!
+//! The custom chips (Agnus/Alice, Denise/Lisa, Paula) are mapped as 256
+//! word registers at `$DFF000`. Names follow the Hardware Reference
+//! Manual and `hardware/custom.h`, covering OCS, ECS and AGA. The
+//! SET/CLR registers (DMACON, INTENA, INTREQ, ADKCON) also have their
+//! bits named, from `hardware/dmabits.h`, `intbits.h` and `adkbits.h`.
+//!
+//! The other memory-mapped chips are listed in `CHIPS`: the two 8520
+//! CIAs (`hardware/cia.h`), Gayle's IDE and PCMCIA registers (A600 and
+//! A1200), the CD32's Akiko and the battery-backed clock. Their port,
+//! interrupt and control register bits are named too.
+
+use super::constants::ConstantSet;
+
+/// A block of memory-mapped hardware registers.
+pub struct Chip {
+    /// Short name for summaries (`custom`, `CIA-A`, ...).
+    pub name: &'static str,
+    pub base: u32,
+    /// Size of the register block in bytes.
+    pub size: u32,
+    /// Name of the register at an offset from `base`.
+    register: fn(u32) -> Option<String>,
+}
+
+impl Chip {
+    pub fn contains(&self, address: u32) -> bool {
+        address.wrapping_sub(self.base) < self.size
+    }
+}
+
+/// CIA-A, at odd addresses from `$BFE001` (ports, keyboard, timers).
+pub const CIAA_BASE: u32 = 0xBFE001;
+
+/// CIA-B, at even addresses from `$BFD000` (disk, serial, parallel).
+pub const CIAB_BASE: u32 = 0xBFD000;
+
+/// Every register block that gets names.
+pub static CHIPS: &[Chip] = &[
+    Chip { name: "custom", base: CUSTOM_BASE, size: CUSTOM_SIZE, register: custom_register_name },
+    Chip { name: "CIA-A", base: CIAA_BASE, size: 0x1000, register: |o| cia_register_name("CIAA", o) },
+    Chip { name: "CIA-B", base: CIAB_BASE, size: 0x1000, register: |o| cia_register_name("CIAB", o) },
+    Chip { name: "Gayle", base: 0xDA0000, size: 0x4000, register: gayle_ide_register_name },
+    Chip { name: "Gayle", base: 0xDA8000, size: 0x4000, register: gayle_register_name },
+    Chip { name: "Gayle", base: 0xDE1000, size: 4, register: |_| Some("GAYLE_ID".to_string()) },
+    Chip { name: "Akiko", base: 0xB80000, size: 0x40, register: akiko_register_name },
+    Chip { name: "RTC", base: 0xDC0000, size: 0x40, register: rtc_register_name },
+];
+
+/// The chip whose registers include `address`.
+pub fn chip_at(address: u32) -> Option<&'static Chip> {
+    CHIPS.iter().find(|c| c.contains(address))
+}
+
+/// Name of the hardware register at an absolute address.
+pub fn register_name(address: u32) -> Option<String> {
+    let chip = chip_at(address)?;
+    (chip.register)(address - chip.base)
+}
+
+/// Name of the register at `offset` from a chip's base address, for
+/// accesses through a base register (`$96(a5)` with A5 = `$DFF000`).
+pub fn register_offset_name(base: u32, offset: i16) -> Option<String> {
+    let chip = CHIPS.iter().find(|c| c.base == base)?;
+    let offset = u32::try_from(offset).ok().filter(|&o| o < chip.size)?;
+    (chip.register)(offset)
+}
+
+/// Base address of the custom chip registers.
+pub const CUSTOM_BASE: u32 = 0xDFF000;
+
+/// Size of the custom register block in bytes.
+pub const CUSTOM_SIZE: u32 = 0x200;
+
+/// Registers that are not part of a numbered group.
+static CUSTOM_REGISTERS: &[(u16, &str)] = &[
+    (0x000, "BLTDDAT"),
+    (0x002, "DMACONR"),
+    (0x004, "VPOSR"),
+    (0x006, "VHPOSR"),
+    (0x008, "DSKDATR"),
+    (0x00A, "JOY0DAT"),
+    (0x00C, "JOY1DAT"),
+    (0x00E, "CLXDAT"),
+    (0x010, "ADKCONR"),
+    (0x012, "POT0DAT"),
+    (0x014, "POT1DAT"),
+    (0x016, "POTGOR"),
+    (0x018, "SERDATR"),
+    (0x01A, "DSKBYTR"),
+    (0x01C, "INTENAR"),
+    (0x01E, "INTREQR"),
+    (0x020, "DSKPTH"),
+    (0x022, "DSKPTL"),
+    (0x024, "DSKLEN"),
+    (0x026, "DSKDAT"),
+    (0x028, "REFPTR"),
+    (0x02A, "VPOSW"),
+    (0x02C, "VHPOSW"),
+    (0x02E, "COPCON"),
+    (0x030, "SERDAT"),
+    (0x032, "SERPER"),
+    (0x034, "POTGO"),
+    (0x036, "JOYTEST"),
+    (0x038, "STREQU"),
+    (0x03A, "STRVBL"),
+    (0x03C, "STRHOR"),
+    (0x03E, "STRLONG"),
+    (0x040, "BLTCON0"),
+    (0x042, "BLTCON1"),
+    (0x044, "BLTAFWM"),
+    (0x046, "BLTALWM"),
+    (0x048, "BLTCPTH"),
+    (0x04A, "BLTCPTL"),
+    (0x04C, "BLTBPTH"),
+    (0x04E, "BLTBPTL"),
+    (0x050, "BLTAPTH"),
+    (0x052, "BLTAPTL"),
+    (0x054, "BLTDPTH"),
+    (0x056, "BLTDPTL"),
+    (0x058, "BLTSIZE"),
+    (0x05A, "BLTCON0L"),
+    (0x05C, "BLTSIZV"),
+    (0x05E, "BLTSIZH"),
+    (0x060, "BLTCMOD"),
+    (0x062, "BLTBMOD"),
+    (0x064, "BLTAMOD"),
+    (0x066, "BLTDMOD"),
+    (0x070, "BLTCDAT"),
+    (0x072, "BLTBDAT"),
+    (0x074, "BLTADAT"),
+    (0x078, "SPRHDAT"),
+    (0x07A, "BPLHDAT"),
+    (0x07C, "DENISEID"),
+    (0x07E, "DSKSYNC"),
+    (0x080, "COP1LCH"),
+    (0x082, "COP1LCL"),
+    (0x084, "COP2LCH"),
+    (0x086, "COP2LCL"),
+    (0x088, "COPJMP1"),
+    (0x08A, "COPJMP2"),
+    (0x08C, "COPINS"),
+    (0x08E, "DIWSTRT"),
+    (0x090, "DIWSTOP"),
+    (0x092, "DDFSTRT"),
+    (0x094, "DDFSTOP"),
+    (0x096, "DMACON"),
+    (0x098, "CLXCON"),
+    (0x09A, "INTENA"),
+    (0x09C, "INTREQ"),
+    (0x09E, "ADKCON"),
+    (0x100, "BPLCON0"),
+    (0x102, "BPLCON1"),
+    (0x104, "BPLCON2"),
+    (0x106, "BPLCON3"),
+    (0x108, "BPL1MOD"),
+    (0x10A, "BPL2MOD"),
+    (0x10C, "BPLCON4"),
+    (0x10E, "CLXCON2"),
+    (0x1C0, "HTOTAL"),
+    (0x1C2, "HSSTOP"),
+    (0x1C4, "HBSTRT"),
+    (0x1C6, "HBSTOP"),
+    (0x1C8, "VTOTAL"),
+    (0x1CA, "VSSTOP"),
+    (0x1CC, "VBSTRT"),
+    (0x1CE, "VBSTOP"),
+    (0x1D0, "SPRHSTRT"),
+    (0x1D2, "SPRHSTOP"),
+    (0x1D4, "BPLHSTRT"),
+    (0x1D6, "BPLHSTOP"),
+    (0x1D8, "HHPOSW"),
+    (0x1DA, "HHPOSR"),
+    (0x1DC, "BEAMCON0"),
+    (0x1DE, "HSSTRT"),
+    (0x1E0, "VSSTRT"),
+    (0x1E2, "HCENTER"),
+    (0x1E4, "DIWHIGH"),
+    (0x1E6, "BPLHMOD"),
+    (0x1E8, "SPRHPTH"),
+    (0x1EA, "SPRHPTL"),
+    (0x1EC, "BPLHPTH"),
+    (0x1EE, "BPLHPTL"),
+    (0x1FC, "FMODE"),
+    (0x1FE, "NOOP"),
+];
+
+/// Name of the custom register at `offset` from `$DFF000`.
+///
+/// The numbered groups (audio channels, bitplane and sprite pointers,
+/// sprite data, colour registers) are named by rule. An odd offset is a
+/// byte access to the low half of a register and is shown as `NAME+1`.
+pub fn custom_register_name(offset: u32) -> Option<String> {
+    if offset >= CUSTOM_SIZE {
+        return None;
+    }
+    if !offset.is_multiple_of(2) {
+        return custom_register_name(offset - 1).map(|name| format!("{name}+1"));
+    }
+    if let Some((_, name)) = CUSTOM_REGISTERS.iter().find(|(o, _)| *o as u32 == offset) {
+        return Some(name.to_string());
+    }
+    match offset {
+        // AUD0-AUD3, 16 bytes per channel
+        0x0A0..=0x0DF => {
+            let channel = (offset - 0x0A0) / 0x10;
+            let field = ["LCH", "LCL", "LEN", "PER", "VOL", "DAT"].get(((offset % 0x10) / 2) as usize)?;
+            Some(format!("AUD{channel}{field}"))
+        }
+        0x0E0..=0x0FF => {
+            let half = if offset.is_multiple_of(4) { "H" } else { "L" };
+            Some(format!("BPL{}PT{half}", (offset - 0x0E0) / 4 + 1))
+        }
+        0x110..=0x11F => Some(format!("BPL{}DAT", (offset - 0x110) / 2 + 1)),
+        0x120..=0x13F => {
+            let half = if offset.is_multiple_of(4) { "H" } else { "L" };
+            Some(format!("SPR{}PT{half}", (offset - 0x120) / 4))
+        }
+        // SPR0-SPR7 POS/CTL/DATA/DATB, 8 bytes per sprite
+        0x140..=0x17F => {
+            let field = ["POS", "CTL", "DATA", "DATB"][((offset % 8) / 2) as usize];
+            Some(format!("SPR{}{field}", (offset - 0x140) / 8))
+        }
+        0x180..=0x1BF => Some(format!("COLOR{:02}", (offset - 0x180) / 2)),
+        _ => None,
+    }
+}
+
+// hardware/dmabits.h
+static DMA_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x8000, "DMAF_SETCLR"),
+        (0x4000, "DMAF_BLTDONE"),
+        (0x2000, "DMAF_BLTNZERO"),
+        (0x0400, "DMAF_BLITHOG"),
+        (0x0200, "DMAF_MASTER"),
+        (0x0100, "DMAF_RASTER"),
+        (0x0080, "DMAF_COPPER"),
+        (0x0040, "DMAF_BLITTER"),
+        (0x0020, "DMAF_SPRITE"),
+        (0x0010, "DMAF_DISK"),
+        (0x0008, "DMAF_AUD3"),
+        (0x0004, "DMAF_AUD2"),
+        (0x0002, "DMAF_AUD1"),
+        (0x0001, "DMAF_AUD0"),
+    ],
+};
+
+// hardware/intbits.h
+static INT_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x8000, "INTF_SETCLR"),
+        (0x4000, "INTF_INTEN"),
+        (0x2000, "INTF_EXTER"),
+        (0x1000, "INTF_DSKSYNC"),
+        (0x0800, "INTF_RBF"),
+        (0x0400, "INTF_AUD3"),
+        (0x0200, "INTF_AUD2"),
+        (0x0100, "INTF_AUD1"),
+        (0x0080, "INTF_AUD0"),
+        (0x0040, "INTF_BLIT"),
+        (0x0020, "INTF_VERTB"),
+        (0x0010, "INTF_COPER"),
+        (0x0008, "INTF_PORTS"),
+        (0x0004, "INTF_SOFTINT"),
+        (0x0002, "INTF_DSKBLK"),
+        (0x0001, "INTF_TBE"),
+    ],
+};
+
+// hardware/adkbits.h
+static ADK_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x8000, "ADKF_SETCLR"),
+        (0x4000, "ADKF_PRECOMP1"),
+        (0x2000, "ADKF_PRECOMP0"),
+        (0x1000, "ADKF_MFMPREC"),
+        (0x0800, "ADKF_UARTBRK"),
+        (0x0400, "ADKF_WORDSYNC"),
+        (0x0200, "ADKF_MSBSYNC"),
+        (0x0100, "ADKF_FAST"),
+        (0x0080, "ADKF_USE3PN"),
+        (0x0040, "ADKF_USE2P3"),
+        (0x0020, "ADKF_USE1P2"),
+        (0x0010, "ADKF_USE0P1"),
+        (0x0008, "ADKF_USE3VN"),
+        (0x0004, "ADKF_USE2V3"),
+        (0x0002, "ADKF_USE1V2"),
+        (0x0001, "ADKF_USE0V1"),
+    ],
+};
+
+// hardware/cia.h, CIA-A port A
+static CIAA_PRA_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "CIAF_GAMEPORT1"),
+        (0x40, "CIAF_GAMEPORT0"),
+        (0x20, "CIAF_DSKRDY"),
+        (0x10, "CIAF_DSKTRACK0"),
+        (0x08, "CIAF_DSKPROT"),
+        (0x04, "CIAF_DSKCHANGE"),
+        (0x02, "CIAF_LED"),
+        (0x01, "CIAF_OVERLAY"),
+    ],
+};
+
+// CIA-B port A: serial and parallel handshake lines
+static CIAB_PRA_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "CIAF_COMDTR"),
+        (0x40, "CIAF_COMRTS"),
+        (0x20, "CIAF_COMCD"),
+        (0x10, "CIAF_COMCTS"),
+        (0x08, "CIAF_COMDSR"),
+        (0x04, "CIAF_PRTRSEL"),
+        (0x02, "CIAF_PRTRPOUT"),
+        (0x01, "CIAF_PRTRBUSY"),
+    ],
+};
+
+// CIA-B port B: floppy drive control
+static CIAB_PRB_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "CIAF_DSKMOTOR"),
+        (0x40, "CIAF_DSKSEL3"),
+        (0x20, "CIAF_DSKSEL2"),
+        (0x10, "CIAF_DSKSEL1"),
+        (0x08, "CIAF_DSKSEL0"),
+        (0x04, "CIAF_DSKSIDE"),
+        (0x02, "CIAF_DSKDIREC"),
+        (0x01, "CIAF_DSKSTEP"),
+    ],
+};
+
+static CIA_ICR_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "CIAICRF_SETCLR"),
+        (0x10, "CIAICRF_FLG"),
+        (0x08, "CIAICRF_SP"),
+        (0x04, "CIAICRF_ALRM"),
+        (0x02, "CIAICRF_TB"),
+        (0x01, "CIAICRF_TA"),
+    ],
+};
+
+static CIA_CRA_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "CIACRAF_TODIN"),
+        (0x40, "CIACRAF_SPMODE"),
+        (0x20, "CIACRAF_INMODE"),
+        (0x10, "CIACRAF_LOAD"),
+        (0x08, "CIACRAF_RUNMODE"),
+        (0x04, "CIACRAF_OUTMODE"),
+        (0x02, "CIACRAF_PBON"),
+        (0x01, "CIACRAF_START"),
+    ],
+};
+
+static CIA_CRB_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "CIACRBF_ALARM"),
+        (0x40, "CIACRBF_INMODE1"),
+        (0x20, "CIACRBF_INMODE0"),
+        (0x10, "CIACRBF_LOAD"),
+        (0x08, "CIACRBF_RUNMODE"),
+        (0x04, "CIACRBF_OUTMODE"),
+        (0x02, "CIACRBF_PBON"),
+        (0x01, "CIACRBF_START"),
+    ],
+};
+
+// Gayle card status, interrupt change and enable registers
+static GAYLE_CS_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "GAYLE_CS_IDE"),
+        (0x40, "GAYLE_CS_CCDET"),
+        (0x20, "GAYLE_CS_BVD1"),
+        (0x10, "GAYLE_CS_BVD2"),
+        (0x08, "GAYLE_CS_WR"),
+        (0x04, "GAYLE_CS_BSY"),
+        (0x02, "GAYLE_CS_DAEN"),
+        (0x01, "GAYLE_CS_DIS"),
+    ],
+};
+
+static GAYLE_IRQ_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "GAYLE_IRQ_IDE"),
+        (0x40, "GAYLE_IRQ_CCDET"),
+        (0x20, "GAYLE_IRQ_BVD1"),
+        (0x10, "GAYLE_IRQ_BVD2"),
+        (0x08, "GAYLE_IRQ_WR"),
+        (0x04, "GAYLE_IRQ_BSY"),
+        (0x02, "GAYLE_IRQ_RESET"),
+        (0x01, "GAYLE_IRQ_BERR"),
+    ],
+};
+
+static GAYLE_INT_FLAGS: ConstantSet = ConstantSet {
+    flags: true,
+    values: &[
+        (0x80, "GAYLE_INT_IDE"),
+        (0x40, "GAYLE_INT_CCDET"),
+        (0x20, "GAYLE_INT_BVD1"),
+        (0x10, "GAYLE_INT_BVD2"),
+        (0x08, "GAYLE_INT_WR"),
+        (0x04, "GAYLE_INT_BSY"),
+        (0x02, "GAYLE_INT_BVD_LEV"),
+        (0x01, "GAYLE_INT_BSY_LEV"),
+    ],
+};
+
+/// Registers whose bits have names, by absolute address.
+static REGISTER_FLAGS: &[(u32, &ConstantSet)] = &[
+    (CUSTOM_BASE + 0x096, &DMA_FLAGS),
+    (CUSTOM_BASE + 0x09A, &INT_FLAGS),
+    (CUSTOM_BASE + 0x09C, &INT_FLAGS),
+    (CUSTOM_BASE + 0x09E, &ADK_FLAGS),
+    (CIAA_BASE, &CIAA_PRA_FLAGS),
+    (CIAA_BASE + 0xD00, &CIA_ICR_FLAGS),
+    (CIAA_BASE + 0xE00, &CIA_CRA_FLAGS),
+    (CIAA_BASE + 0xF00, &CIA_CRB_FLAGS),
+    (CIAB_BASE, &CIAB_PRA_FLAGS),
+    (CIAB_BASE + 0x100, &CIAB_PRB_FLAGS),
+    (CIAB_BASE + 0xD00, &CIA_ICR_FLAGS),
+    (CIAB_BASE + 0xE00, &CIA_CRA_FLAGS),
+    (CIAB_BASE + 0xF00, &CIA_CRB_FLAGS),
+    (0xDA8000, &GAYLE_CS_FLAGS),
+    (0xDA9000, &GAYLE_IRQ_FLAGS),
+    (0xDAA000, &GAYLE_INT_FLAGS),
+];
+
+/// Describe a value written to (or a bit tested in) the register at
+/// `address`, for registers whose bits have names: `$8380` to DMACON
+/// is `DMAF_SETCLR|DMAF_MASTER|DMAF_RASTER|DMAF_COPPER`, bit 7 of
+/// CIA-A port A is `CIAF_GAMEPORT1` (the joystick fire button).
+pub fn describe_register_value(address: u32, value: u32) -> Option<String> {
+    let (_, flags) = REGISTER_FLAGS.iter().find(|(a, _)| *a == address)?;
+    flags.describe(value)
+}
+
+/// An 8520 register: sixteen registers `$100` apart.
+fn cia_register_name(chip: &str, offset: u32) -> Option<String> {
+    const REGISTERS: [&str; 16] = [
+        "PRA", "PRB", "DDRA", "DDRB", "TALO", "TAHI", "TBLO", "TBHI", "TODLOW", "TODMID",
+        "TODHI", "", "SDR", "ICR", "CRA", "CRB",
+    ];
+    if !offset.is_multiple_of(0x100) {
+        return None;
+    }
+    let register = REGISTERS.get((offset / 0x100) as usize).filter(|r| !r.is_empty())?;
+    Some(format!("{chip}{register}"))
+}
+
+/// Gayle's IDE task file: ATA registers 4 bytes apart from `$DA2000`,
+/// the alternate status / device control register at `$DA3018`.
+fn gayle_ide_register_name(offset: u32) -> Option<String> {
+    const REGISTERS: [&str; 8] = [
+        "DATA", "ERROR", "NSECTOR", "SECTOR", "LCYL", "HCYL", "SELECT", "STATUS",
+    ];
+    let name = match offset {
+        0x3018 => "CTRL",
+        0x2000..=0x201F if offset.is_multiple_of(4) => REGISTERS[((offset - 0x2000) / 4) as usize],
+        _ => return None,
+    };
+    Some(format!("GAYLE_IDE_{name}"))
+}
+
+/// Gayle's PCMCIA and interrupt control registers, `$1000` apart.
+fn gayle_register_name(offset: u32) -> Option<String> {
+    let name = match offset {
+        0x0000 => "GAYLE_CS",
+        0x1000 => "GAYLE_IRQ",
+        0x2000 => "GAYLE_INTEN",
+        0x3000 => "GAYLE_CONFIG",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// Akiko, the CD32's CD-ROM controller and chunky-to-planar converter.
+fn akiko_register_name(offset: u32) -> Option<String> {
+    let name = match offset {
+        0x00 => "AKIKO_ID",
+        0x04 => "AKIKO_CDINTREQ",
+        0x08 => "AKIKO_CDINTENA",
+        0x10 => "AKIKO_CDADRDATA",
+        0x14 => "AKIKO_CDADRCMD",
+        0x30 => "AKIKO_NVRAM",
+        0x38 => "AKIKO_C2P",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// The MSM6242/RP5C01 clock: sixteen 4-bit registers in the low byte
+/// of longwords from `$DC0000` (read as words or bytes).
+fn rtc_register_name(offset: u32) -> Option<String> {
+    const REGISTERS: [&str; 16] = [
+        "SEC1", "SEC10", "MIN1", "MIN10", "HOUR1", "HOUR10", "DAY1", "DAY10", "MON1", "MON10",
+        "YEAR1", "YEAR10", "WEEK", "CTRLD", "CTRLE", "CTRLF",
+    ];
+    if offset % 4 < 2 {
+        return None;
+    }
+    Some(format!("RTC_{}", REGISTERS.get((offset / 4) as usize)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_register_names() {
+        assert_eq!(custom_register_name(0x096).as_deref(), Some("DMACON"));
+        assert_eq!(custom_register_name(0x180).as_deref(), Some("COLOR00"));
+        assert_eq!(custom_register_name(0x1BE).as_deref(), Some("COLOR31"));
+        assert_eq!(custom_register_name(0x0D8).as_deref(), Some("AUD3VOL"));
+        assert_eq!(custom_register_name(0x0EC).as_deref(), Some("BPL4PTH"));
+        assert_eq!(custom_register_name(0x13E).as_deref(), Some("SPR7PTL"));
+        assert_eq!(custom_register_name(0x14E).as_deref(), Some("SPR1DATB"));
+        assert_eq!(custom_register_name(0x097).as_deref(), Some("DMACON+1"));
+        assert_eq!(custom_register_name(0x0AC), None);
+        assert_eq!(custom_register_name(0x200), None);
+    }
+
+    #[test]
+    fn other_chip_names() {
+        assert_eq!(register_name(0xBFE001).as_deref(), Some("CIAAPRA"));
+        assert_eq!(register_name(0xBFED01).as_deref(), Some("CIAAICR"));
+        assert_eq!(register_name(0xBFD100).as_deref(), Some("CIABPRB"));
+        assert_eq!(register_name(0xBFE801).as_deref(), Some("CIAATODLOW"));
+        assert_eq!(register_name(0xBFE000), None);
+        assert_eq!(register_name(0xDA201C).as_deref(), Some("GAYLE_IDE_STATUS"));
+        assert_eq!(register_name(0xDA9000).as_deref(), Some("GAYLE_IRQ"));
+        assert_eq!(register_name(0xB80038).as_deref(), Some("AKIKO_C2P"));
+        assert_eq!(register_name(0xDC0007).as_deref(), Some("RTC_SEC10"));
+        assert_eq!(register_name(0xDFF09A).as_deref(), Some("INTENA"));
+        assert_eq!(chip_at(0xBFD300).map(|c| c.name), Some("CIA-B"));
+
+        assert_eq!(register_offset_name(CIAA_BASE, 0x100).as_deref(), Some("CIAAPRB"));
+        assert_eq!(register_offset_name(CUSTOM_BASE, 0x96).as_deref(), Some("DMACON"));
+        assert_eq!(register_offset_name(CUSTOM_BASE + 2, 0x94), None);
+    }
+
+    #[test]
+    fn register_bits() {
+        assert_eq!(
+            describe_register_value(0xDFF096, 0x8380).as_deref(),
+            Some("DMAF_SETCLR|DMAF_MASTER|DMAF_RASTER|DMAF_COPPER")
+        );
+        assert_eq!(
+            describe_register_value(0xDFF09A, 0xC020).as_deref(),
+            Some("INTF_SETCLR|INTF_INTEN|INTF_VERTB")
+        );
+        assert_eq!(describe_register_value(0xDFF180, 0x0FFF), None);
+        assert_eq!(describe_register_value(0xBFE001, 0x80).as_deref(), Some("CIAF_GAMEPORT1"));
+        assert_eq!(
+            describe_register_value(0xBFDD00, 0x81).as_deref(),
+            Some("CIAICRF_SETCLR|CIAICRF_TA")
+        );
+        assert_eq!(describe_register_value(0xDA8000, 0x40).as_deref(), Some("GAYLE_CS_CCDET"));
+    }
+}
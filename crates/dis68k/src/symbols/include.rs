@@ -0,0 +1,508 @@
+//! Assembler include (`.i`) files.
+//!
+//! The NDK's `include_i` tree, and the assembler includes that come with
+//! third-party libraries, describe structures and constants with the
+//! macros from `exec/types.i`:
+//!
+//! ```text
+//!     STRUCTURE   MP,LN_SIZE
+//!     UBYTE       MP_FLAGS
+//!     UBYTE       MP_SIGBIT
+//!     APTR        MP_SIGTASK
+//!     STRUCT      MP_MSGLIST,LH_SIZE
+//!     LABEL       MP_SIZE
+//!
+//!     BITDEF      PF,ACTION,0
+//! NT_MSGPORT  EQU 4
+//! ```
+//!
+//! `STRUCTURE` starts a structure at an offset; each field macro names
+//! the current offset and moves it on by the field's size, and `LABEL`
+//! names it without moving. `BITDEF prefix,name,bit` defines
+//! `prefixB_name` as the bit number and `prefixF_name` as its mask.
+//! `EQU`, `SET` and `=` define constants, and `ENUM`/`EITEM` number a
+//! list. Expressions may use any symbol defined so far.
+//!
+//! A structure that starts at another's size label
+//! (`STRUCTURE TC,LN_SIZE`) extends it, and a `STRUCT` field sized by a
+//! size label embeds that structure; both are recorded, so fields of
+//! the inner structure are named too.
+//!
+//! Constants that follow a structure in the same file, up to the next
+//! `STRUCTURE`, are the values of its fields (`NT_MSGPORT` after `LN`),
+//! and are recorded as declared with it.
+
+use std::collections::BTreeMap;
+
+use super::structs::{FieldKind, StructDatabase, StructField, StructLayout};
+
+/// Upper bound on passes while symbols defined later in the set of
+/// files resolve.
+const MAX_PASSES: usize = 8;
+
+/// Structures and constants read from a set of include files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncludeSymbols {
+    pub structs: StructDatabase,
+    /// Constants from `EQU`, `SET`, `=`, `BITDEF` and `EITEM`, by name.
+    /// Field offsets and labels are not included.
+    pub constants: BTreeMap<String, i64>,
+    /// The constants declared with each structure, by structure name.
+    pub struct_constants: BTreeMap<String, Vec<String>>,
+}
+
+/// Parse a set of include files.
+///
+/// The files may refer to each other's symbols in any order: parsing is
+/// repeated until no more symbols resolve. As with the assemblers that
+/// read these files, `IFD`/`INCLUDE` and other directives are ignored,
+/// and lines that can't be evaluated are skipped.
+pub fn parse_includes(texts: &[&str]) -> IncludeSymbols {
+    let mut symbols = BTreeMap::new();
+    let mut size_labels = BTreeMap::new();
+    let mut result = IncludeSymbols::default();
+
+    for _ in 0..MAX_PASSES {
+        let mut parser = Parser {
+            symbols: symbols.clone(),
+            size_labels: size_labels.clone(),
+            ..Parser::default()
+        };
+        for text in texts {
+            parser.parse(text);
+        }
+        let settled = parser.symbols == symbols && parser.size_labels == size_labels;
+        result = IncludeSymbols {
+            structs: StructDatabase::new(parser.layouts),
+            constants: parser.constants,
+            struct_constants: parser.struct_constants,
+        };
+        symbols = parser.symbols;
+        size_labels = parser.size_labels;
+        if settled {
+            break;
+        }
+    }
+
+    result
+}
+
+#[derive(Default)]
+struct Parser {
+    /// Every symbol defined so far: constants, field offsets and labels.
+    symbols: BTreeMap<String, i64>,
+    /// `LN_SIZE` → `LN`: labels at the end of a structure.
+    size_labels: BTreeMap<String, String>,
+    constants: BTreeMap<String, i64>,
+    struct_constants: BTreeMap<String, Vec<String>>,
+    layouts: Vec<StructLayout>,
+    /// The last structure started in the current file.
+    last_struct: Option<String>,
+    /// The structure being defined, with the labels seen in it.
+    current: Option<(StructLayout, Vec<String>)>,
+    /// `SOFFSET`: the offset of the next field.
+    offset: i64,
+    enum_value: i64,
+    in_macro: bool,
+}
+
+impl Parser {
+    fn parse(&mut self, text: &str) {
+        self.last_struct = None;
+        for line in text.lines() {
+            self.line(line);
+        }
+        self.finish_struct();
+        self.in_macro = false;
+    }
+
+    fn line(&mut self, line: &str) {
+        let line = strip_comment(line);
+        if line.trim().is_empty() || line.starts_with('*') {
+            return;
+        }
+
+        // A label starts in the first column
+        let (label, rest) = if line.starts_with(char::is_whitespace) {
+            (None, line.trim())
+        } else {
+            let end = line.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(line.len());
+            (Some(line[..end].trim_end_matches(':')), line[end..].trim())
+        };
+        let (op, args) = match rest.strip_prefix('=') {
+            Some(expr) => ("=", expr.trim()),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        let op = op.to_ascii_uppercase();
+        let args = args.trim();
+
+        if self.in_macro {
+            self.in_macro = op != "ENDM";
+            return;
+        }
+        if let Some(label) = label {
+            match op.as_str() {
+                "EQU" | "SET" | "=" => {
+                    if let Some(value) = self.eval(args) {
+                        self.define_constant(label, value);
+                    }
+                }
+                "MACRO" => self.in_macro = true,
+                _ => {}
+            }
+            return;
+        }
+
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        match (op.as_str(), args.as_slice()) {
+            ("STRUCTURE", [name, start]) => self.start_struct(name, start),
+            ("BYTE" | "UBYTE", [name]) => self.field(name, 1, FieldKind::Scalar),
+            ("WORD" | "UWORD" | "SHORT" | "USHORT" | "BOOL" | "RPTR", [name]) => {
+                self.field(name, 2, FieldKind::Scalar)
+            }
+            ("LONG" | "ULONG" | "APTR" | "CPTR" | "BPTR" | "FPTR" | "FLOAT", [name]) => {
+                self.field(name, 4, FieldKind::Scalar)
+            }
+            ("DOUBLE", [name]) => self.field(name, 8, FieldKind::Scalar),
+            ("STRUCT", [name, size]) => {
+                if let Some(value) = self.eval(size) {
+                    let kind = match self.size_labels.get(*size) {
+                        Some(inner) => FieldKind::Struct(inner.clone()),
+                        None => FieldKind::Scalar,
+                    };
+                    self.field(name, value, kind);
+                }
+            }
+            ("LABEL", [name]) => {
+                self.symbols.insert(name.to_string(), self.offset);
+                if let Some((_, labels)) = &mut self.current {
+                    labels.push(name.to_string());
+                }
+            }
+            ("ALIGNWORD", _) => self.offset = (self.offset + 1) & !1,
+            ("ALIGNLONG", _) => self.offset = (self.offset + 3) & !3,
+            ("BITDEF", [prefix, name, bit]) => {
+                if let Some(bit) = self.eval(bit).filter(|b| (0..64).contains(b)) {
+                    self.define_constant(&format!("{prefix}B_{name}"), bit);
+                    self.define_constant(&format!("{prefix}F_{name}"), 1i64.wrapping_shl(bit as u32));
+                }
+            }
+            ("ENUM", [start]) => self.enum_value = self.eval(start).unwrap_or(0),
+            ("EITEM", [name]) => {
+                self.define_constant(name, self.enum_value);
+                self.enum_value += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn define_constant(&mut self, name: &str, value: i64) {
+        self.symbols.insert(name.to_string(), value);
+        self.constants.insert(name.to_string(), value);
+        if let Some(layout) = &self.last_struct {
+            let names = self.struct_constants.entry(layout.clone()).or_default();
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    fn start_struct(&mut self, name: &str, start: &str) {
+        self.finish_struct();
+        self.last_struct = Some(name.to_string());
+        let Some(offset) = self.eval(start) else { return };
+        self.offset = offset;
+        let mut fields = Vec::new();
+        // Starting at another structure's size label extends it
+        if let Some(base) = self.size_labels.get(start) {
+            fields.push(StructField {
+                name: base.clone(),
+                offset: 0,
+                size: offset as u32,
+                kind: FieldKind::Struct(base.clone()),
+            });
+        }
+        let layout = StructLayout { name: name.to_string(), size: 0, fields };
+        self.current = Some((layout, Vec::new()));
+    }
+
+    fn field(&mut self, name: &str, size: i64, kind: FieldKind) {
+        self.symbols.insert(name.to_string(), self.offset);
+        if let (Some((layout, _)), Ok(offset), Ok(size)) =
+            (&mut self.current, u32::try_from(self.offset), u32::try_from(size))
+        {
+            layout.fields.push(StructField { name: name.to_string(), offset, size, kind });
+        }
+        self.offset += size;
+    }
+
+    fn finish_struct(&mut self) {
+        let Some((mut layout, labels)) = self.current.take() else { return };
+        let Ok(size) = u32::try_from(self.offset) else { return };
+        layout.size = size;
+        for label in labels {
+            if self.symbols.get(&label) == Some(&self.offset) {
+                self.size_labels.insert(label, layout.name.clone());
+            }
+        }
+        self.layouts.retain(|l| l.name != layout.name);
+        self.layouts.push(layout);
+    }
+
+    fn eval(&self, expr: &str) -> Option<i64> {
+        let tokens = tokenize(expr)?;
+        let mut eval = Eval { tokens: &tokens, pos: 0, parser: self };
+        let value = eval.binary(0)?;
+        (eval.pos == tokens.len()).then_some(value)
+    }
+
+    fn symbol(&self, name: &str) -> Option<i64> {
+        match name.to_ascii_uppercase().as_str() {
+            "SOFFSET" => Some(self.offset),
+            _ => self.symbols.get(name).copied(),
+        }
+    }
+}
+
+/// The line without a `;` comment, outside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, ';') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Op(&'static str),
+}
+
+fn tokenize(expr: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = expr.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let operand_expected = !matches!(tokens.last(), Some(Token::Number(_) | Token::Symbol(_) | Token::Op(")")));
+        let take = |i: &mut usize, pred: fn(char) -> bool| {
+            let start = *i;
+            while *i < chars.len() && pred(chars[*i]) {
+                *i += 1;
+            }
+            chars[start..*i].iter().collect::<String>()
+        };
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '$' => {
+                i += 1;
+                let digits = take(&mut i, |c| c.is_ascii_hexdigit());
+                tokens.push(Token::Number(i64::from_str_radix(&digits, 16).ok()?));
+            }
+            '%' if operand_expected => {
+                i += 1;
+                let digits = take(&mut i, |c| c == '0' || c == '1');
+                tokens.push(Token::Number(i64::from_str_radix(&digits, 2).ok()?));
+            }
+            '@' => {
+                i += 1;
+                let digits = take(&mut i, |c| c.is_digit(8));
+                tokens.push(Token::Number(i64::from_str_radix(&digits, 8).ok()?));
+            }
+            '\'' | '"' => {
+                let end = chars[i + 1..].iter().position(|&q| q == c)? + i + 1;
+                let value = chars[i + 1..end].iter().fold(0i64, |v, &ch| (v << 8) | (ch as i64 & 0xFF));
+                tokens.push(Token::Number(value));
+                i = end + 1;
+            }
+            _ if c.is_ascii_digit() => {
+                let digits = take(&mut i, |c| c.is_ascii_alphanumeric());
+                let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+                    Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+                    None => digits.parse().ok()?,
+                };
+                tokens.push(Token::Number(value));
+            }
+            _ if c.is_alphabetic() || c == '_' || c == '.' => {
+                tokens.push(Token::Symbol(take(&mut i, |c| c.is_alphanumeric() || c == '_' || c == '.')));
+            }
+            _ => {
+                let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+                let op = ["<<", ">>"]
+                    .into_iter()
+                    .find(|op| *op == two)
+                    .or_else(|| ["+", "-", "*", "/", "&", "|", "!", "^", "~", "(", ")"].into_iter().find(|op| op.starts_with(c)))?;
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+        }
+    }
+    Some(tokens)
+}
+
+/// Precedence climbing over the tokens of one expression.
+struct Eval<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    parser: &'a Parser,
+}
+
+impl Eval<'_> {
+    fn binary(&mut self, min_precedence: u8) -> Option<i64> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            let precedence = match *op {
+                "|" | "!" | "^" => 1,
+                "&" => 2,
+                "<<" | ">>" => 3,
+                "+" | "-" => 4,
+                "*" | "/" => 5,
+                _ => break,
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let right = self.binary(precedence + 1)?;
+            left = match *op {
+                "|" | "!" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "<<" => left.wrapping_shl(right as u32),
+                ">>" => left.wrapping_shr(right as u32),
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                _ => left.checked_div(right)?,
+            };
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Some(n),
+            Token::Symbol(name) => self.parser.symbol(&name),
+            Token::Op("-") => self.unary().map(i64::wrapping_neg),
+            Token::Op("+") => self.unary(),
+            Token::Op("~") => self.unary().map(|v| !v),
+            Token::Op("(") => {
+                let value = self.binary(0)?;
+                (self.tokens.get(self.pos) == Some(&Token::Op(")"))).then(|| self.pos += 1)?;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODES_I: &str = "
+    IFND    EXEC_NODES_I
+EXEC_NODES_I    SET     1
+* List Node Structure
+    STRUCTURE   LN,0            ; List Node
+    APTR        LN_SUCC         ; Pointer to next (successor)
+    APTR        LN_PRED
+    UBYTE       LN_TYPE
+    BYTE        LN_PRI
+    APTR        LN_NAME
+    LABEL       LN_SIZE
+
+NT_UNKNOWN      EQU     0
+NT_TASK         EQU     1
+    ENDC
+";
+
+    const TASKS_I: &str = "
+    STRUCTURE   TC,LN_SIZE
+    UBYTE   TC_FLAGS
+    UBYTE   TC_STATE
+    BYTE    TC_IDNESTCNT
+    BYTE    TC_TDNESTCNT
+    ULONG   TC_SIGALLOC
+    ULONG   TC_SIGWAIT
+    ULONG   TC_SIGRECVD
+    ULONG   TC_SIGEXCEPT
+    STRUCT  TC_PRIVATE,(2*2)+(9*4)+14+4
+    LABEL   TC_SIZE
+
+    BITDEF  T,PROCTIME,0
+    BITDEF  T,STACKCHK,4
+SIGBREAKF_CTRL_C    EQU (1<<12)
+";
+
+    const PROCESS_I: &str = "
+    STRUCTURE Process,0
+    STRUCT  pr_Task,TC_SIZE
+    STRUCT  pr_MsgPort,34
+    WORD    pr_Pad
+    ALIGNLONG
+    BPTR    pr_SegList
+    LABEL   pr_SIZEOF
+
+    ENUM    $80420000
+    EITEM   MUIA_Example_One
+    EITEM   MUIA_Example_Two
+MASK = %1010!$100
+";
+
+    #[test]
+    fn structures_across_files() {
+        // Later files first: symbols resolve on a second pass
+        let includes = parse_includes(&[PROCESS_I, TASKS_I, NODES_I]);
+        let structs = &includes.structs;
+
+        assert_eq!(structs.find("LN").unwrap().size, 14);
+        assert_eq!(structs.find("TC").unwrap().size, 92);
+        assert_eq!(structs.find("TC").unwrap().extends(), Some("LN"));
+        assert_eq!(structs.field_name("TC", 15).as_deref(), Some("TC_STATE"));
+        assert_eq!(structs.field_name("TC", 8).as_deref(), Some("LN_TYPE"));
+        // Built-in C names find the include's structure
+        assert_eq!(structs.field_name("Task", 26).as_deref(), Some("TC_SIGRECVD"));
+        assert_eq!(structs.field_name("Process", 9).as_deref(), Some("LN_PRI"));
+        assert_eq!(structs.field_name("Process", 128).as_deref(), Some("pr_SegList"));
+        assert_eq!(structs.field_name("TC", 128).as_deref(), Some("pr_SegList"));
+        assert_eq!(structs.field_name("Process", 94).as_deref(), Some("pr_MsgPort+2"));
+    }
+
+    #[test]
+    fn constants() {
+        let includes = parse_includes(&[NODES_I, TASKS_I, PROCESS_I]);
+        let constant = |name: &str| includes.constants.get(name).copied();
+
+        assert_eq!(constant("NT_TASK"), Some(1));
+        assert_eq!(constant("TB_STACKCHK"), Some(4));
+        assert_eq!(constant("TF_STACKCHK"), Some(16));
+        assert_eq!(constant("SIGBREAKF_CTRL_C"), Some(0x1000));
+        assert_eq!(constant("MUIA_Example_Two"), Some(0x80420001));
+        assert_eq!(constant("MASK"), Some(0x10A));
+        assert_eq!(constant("EXEC_NODES_I"), Some(1));
+        // Fields and labels are not constants
+        assert_eq!(constant("LN_SIZE"), None);
+        assert_eq!(constant("TC_FLAGS"), None);
+    }
+
+    #[test]
+    fn constants_declared_with_a_structure() {
+        let includes = parse_includes(&[NODES_I, TASKS_I, PROCESS_I]);
+        let declared = |name: &str| includes.struct_constants.get(name).cloned().unwrap_or_default();
+
+        assert_eq!(declared("TC"), ["TB_PROCTIME", "TF_PROCTIME", "TB_STACKCHK", "TF_STACKCHK", "SIGBREAKF_CTRL_C"]);
+        assert_eq!(declared("Process"), ["MUIA_Example_One", "MUIA_Example_Two", "MASK"]);
+        // Constants before a file's first structure belong to none
+        assert!(includes.struct_constants.values().flatten().all(|n| n != "EXEC_NODES_I"));
+    }
+}
@@ -5,6 +5,7 @@
 //! addresses that should receive `loc_XXXX` labels.
 
 use std::collections::BTreeSet;
+use std::ops::Range;
 
 use crate::m68k::addressing::EffectiveAddress;
 use crate::m68k::decode::decode_instruction;
@@ -25,11 +26,24 @@ use crate::m68k::variants::CpuVariant;
 /// # Returns
 /// A sorted set of target addresses within this hunk that should get labels.
 pub fn collect_branch_targets(data: &[u8], base_address: u32, cpu: CpuVariant) -> BTreeSet<u32> {
+    collect_targets_in(data, base_address, 0..data.len(), cpu)
+}
+
+/// Like `collect_branch_targets`, decoding only the code in `range`
+/// (for hunks with data regions) but keeping targets anywhere in the
+/// hunk.
+pub fn collect_targets_in(
+    data: &[u8],
+    base_address: u32,
+    range: Range<usize>,
+    cpu: CpuVariant,
+) -> BTreeSet<u32> {
     let mut targets = BTreeSet::new();
-    let mut offset = 0usize;
+    let code = &data[..range.end.min(data.len())];
+    let mut offset = range.start;
 
-    while offset < data.len() {
-        match decode_instruction(data, offset, base_address, cpu) {
+    while offset < code.len() {
+        match decode_instruction(code, offset, base_address, cpu) {
             Ok(inst) => {
                 // TODO: Extract target addresses from this instruction.
                 //
@@ -42,7 +56,7 @@ pub fn collect_branch_targets(data: &[u8], base_address: u32, cpu: CpuVariant) -
             }
             Err(_) => {
                 // Skip undecodable words
-                offset += 2.min(data.len() - offset);
+                offset += 2.min(code.len() - offset);
             }
         }
     }
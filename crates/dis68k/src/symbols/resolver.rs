@@ -2,71 +2,469 @@
 //!
 //! The resolver system composes multiple symbol sources (hunk symbols,
 //! auto-generated labels, LVO tables) into a single lookup interface.
+//!
+//! Labels are looked up by `Location` (hunk index and offset), not by
+//! address: every hunk of an executable is listed from address 0, so
+//! an address alone cannot say whether it means hunk 0 or hunk 2.
 
 use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::hunk::types::{Hunk, HunkType};
+use crate::image::{Location, Segment, SegmentKind};
+use crate::project::Project;
+use super::amiga::{self, Library, LvoEntry};
+use super::hardware;
+use super::include::IncludeSymbols;
+use super::structs::{self, StructDatabase};
+
+/// What a resolved name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A place in a code hunk: a symbol, function or branch target.
+    CodeLabel,
+    /// A place in a data or BSS hunk.
+    DataLabel,
+    /// A library function, by its vector offset.
+    Lvo,
+    /// A memory-mapped hardware register.
+    HardwareRegister,
+    /// A field of a structure.
+    StructField,
+    /// A named constant from an include file.
+    Constant,
+}
+
+impl SymbolKind {
+    /// The kind of label for a place in a hunk of type `hunk_type`.
+    pub fn for_hunk(hunk_type: HunkType) -> Self {
+        match hunk_type {
+            HunkType::Code => SymbolKind::CodeLabel,
+            _ => SymbolKind::DataLabel,
+        }
+    }
 
-use crate::hunk::types::Hunk;
-use super::amiga;
+    /// The kind of label for a place in a segment of kind `kind`.
+    pub fn for_segment(kind: SegmentKind) -> Self {
+        match kind {
+            SegmentKind::Code => SymbolKind::CodeLabel,
+            _ => SymbolKind::DataLabel,
+        }
+    }
+}
 
-/// Trait for resolving addresses and LVO offsets to symbolic names.
+/// A name found by a resolver.
+///
+/// `offset` is how far past the named place the looked-up location
+/// lies: 0 for an exact match, and shown as `_buffer+$10` otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub offset: u32,
+}
+
+impl ResolvedSymbol {
+    pub fn new(name: impl Into<String>, kind: SymbolKind) -> Self {
+        ResolvedSymbol {
+            name: name.into(),
+            kind,
+            offset: 0,
+        }
+    }
+}
+
+impl fmt::Display for ResolvedSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.offset == 0 {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}+${:X}", self.name, self.offset)
+        }
+    }
+}
+
+/// Trait for resolving locations, LVO offsets, registers and values to
+/// symbolic names. Every lookup defaults to finding nothing.
 pub trait SymbolResolver {
+    /// Name the place at `location` exactly.
+    fn resolve_location(&self, _location: Location) -> Option<ResolvedSymbol> {
+        None
+    }
+
+    /// Name `location` by the nearest label at or before it in the same
+    /// hunk, with `offset` set to the distance past the label.
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.resolve_location(location)
+    }
+
+    /// The location of a listed address, for resolvers that know where
+    /// the hunks being listed sit.
+    fn locate(&self, _address: u32) -> Option<Location> {
+        None
+    }
+
+    /// Name a listed address exactly: `locate`, then `resolve_location`.
+    fn resolve_address(&self, address: u32) -> Option<ResolvedSymbol> {
+        self.resolve_location(self.locate(address)?)
+    }
+
+    /// Where a relocated longword operand points, for resolvers that
+    /// know the relocations of the hunk being listed: `value` is the
+    /// operand and `words` the listed addresses of the instruction's
+    /// extension words.
+    fn relocation_target(&self, _words: Range<u32>, _value: u32) -> Option<Location> {
+        None
+    }
+
+    /// Where a base-relative operand `displacement(An)` of the
+    /// instruction at `address` points, for resolvers that know the
+    /// program's small-data base register.
+    fn base_relative_target(&self, _address: u32, _register: u8, _displacement: i32) -> Option<Location> {
+        None
+    }
+
     /// Resolve a library vector offset to a function name.
-    fn resolve_lvo(&self, offset: i16) -> Option<String>;
+    fn resolve_lvo(&self, _offset: i16) -> Option<ResolvedSymbol> {
+        None
+    }
+
+    /// The table entry behind `resolve_lvo`, with the function's
+    /// arguments and registers, for resolvers that have one.
+    fn lvo_entry(&self, _offset: i16) -> Option<LvoEntry> {
+        None
+    }
 
-    /// Resolve an address within a hunk to a label name.
-    fn resolve_address(&self, address: u32) -> Option<String>;
+    /// Like `lvo_entry`, in a named library rather than the resolver's
+    /// default one, for calls whose library base is known.
+    fn library_lvo_entry(&self, _library: &str, _offset: i16) -> Option<LvoEntry> {
+        None
+    }
+
+    /// Name a memory-mapped hardware register at an absolute address.
+    fn resolve_register(&self, _address: u32) -> Option<ResolvedSymbol> {
+        None
+    }
+
+    /// Name the register at `offset` from a chip's base address, for
+    /// accesses like `$96(a5)` with A5 = `$DFF000`.
+    fn resolve_register_offset(&self, _base: u32, _offset: i16) -> Option<ResolvedSymbol> {
+        None
+    }
+
+    /// The absolute address known to be in address register `register`
+    /// at the instruction at `address`, if the listing tracked one.
+    fn address_register_base(&self, _address: u32, _register: u8) -> Option<u32> {
+        None
+    }
+
+    /// The structure known to be pointed at by address register
+    /// `register` at the instruction at `address`.
+    fn address_register_type(&self, _address: u32, _register: u8) -> Option<String> {
+        None
+    }
+
+    /// Name the field at `offset` in structure `type_name`, for
+    /// accesses like `276(a6)` with A6 = ExecBase.
+    fn resolve_struct_field(&self, _type_name: &str, _offset: i16) -> Option<ResolvedSymbol> {
+        None
+    }
+
+    /// Name an immediate value, for resolvers with a constant table.
+    fn resolve_constant(&self, _value: u32) -> Option<ResolvedSymbol> {
+        None
+    }
+
+    /// Name an immediate compared with or stored into the field at
+    /// `offset` in structure `type_name`, where even a small value is
+    /// a field value rather than a count or an address.
+    fn resolve_field_constant(&self, _type_name: &str, _offset: i16, _value: u32) -> Option<ResolvedSymbol> {
+        None
+    }
+}
+
+/// Labels keyed by location, shared by the label resolvers.
+#[derive(Debug, Clone, Default)]
+struct LabelMap {
+    labels: BTreeMap<Location, (String, SymbolKind)>,
+}
+
+impl LabelMap {
+    /// Add a label unless `location` already has one.
+    fn insert(&mut self, location: Location, name: String, kind: SymbolKind) {
+        self.labels.entry(location).or_insert((name, kind));
+    }
+
+    fn exact(&self, location: Location) -> Option<ResolvedSymbol> {
+        let (name, kind) = self.labels.get(&location)?;
+        Some(ResolvedSymbol::new(name.clone(), *kind))
+    }
+
+    fn nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        let (found, (name, kind)) = self
+            .labels
+            .range(Location::new(location.hunk, 0)..=location)
+            .next_back()?;
+        Some(ResolvedSymbol {
+            name: name.clone(),
+            kind: *kind,
+            offset: location.offset - found.offset,
+        })
+    }
 }
 
 /// Resolves symbols defined in HUNK_SYMBOL data.
+///
+/// Symbol values are hunk offsets, so each symbol is at
+/// `Location { hunk: index, offset: value }`. Where two symbols share a
+/// location the first one is kept.
 pub struct HunkSymbolResolver {
-    /// Map from address → symbol name.
-    symbols: BTreeMap<u32, String>,
+    symbols: LabelMap,
 }
 
 impl HunkSymbolResolver {
     pub fn from_hunk(hunk: &Hunk) -> Self {
-        let mut symbols = BTreeMap::new();
-        for sym in &hunk.symbols {
-            symbols.insert(sym.value, sym.name.clone());
+        Self::from_hunks(std::slice::from_ref(hunk))
+    }
+
+    pub fn from_hunks(hunks: &[Hunk]) -> Self {
+        let mut symbols = LabelMap::default();
+        for hunk in hunks {
+            let kind = SymbolKind::for_hunk(hunk.hunk_type);
+            for sym in &hunk.symbols {
+                symbols.insert(Location::new(hunk.index, sym.value), sym.name.clone(), kind);
+            }
+        }
+        HunkSymbolResolver { symbols }
+    }
+
+    /// Like `from_hunk`, for a segment of a loaded image.
+    pub fn from_segment(segment: &Segment) -> Self {
+        Self::from_segments(std::slice::from_ref(segment))
+    }
+
+    /// Like `from_hunks`, for the segments of a loaded image.
+    pub fn from_segments(segments: &[Segment]) -> Self {
+        let mut symbols = LabelMap::default();
+        for segment in segments {
+            let kind = SymbolKind::for_segment(segment.kind);
+            for sym in &segment.symbols {
+                symbols.insert(Location::new(segment.index, sym.value), sym.name.clone(), kind);
+            }
         }
         HunkSymbolResolver { symbols }
     }
 }
 
 impl SymbolResolver for HunkSymbolResolver {
-    fn resolve_lvo(&self, _offset: i16) -> Option<String> {
-        None
+    fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.symbols.exact(location)
+    }
+
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.symbols.nearest(location)
+    }
+}
+
+/// Resolves the labels and function names of a project file.
+pub struct ProjectResolver {
+    labels: LabelMap,
+}
+
+impl ProjectResolver {
+    /// The project's labels in `segments`. Labels in hunks the image
+    /// does not have are dropped.
+    pub fn new(project: &Project, segments: &[Segment]) -> Self {
+        let mut labels = LabelMap::default();
+        for segment in segments {
+            let kind = SymbolKind::for_segment(segment.kind);
+            for (offset, name) in project.labels_in(segment.index) {
+                labels.insert(Location::new(segment.index, offset), name.to_string(), kind);
+            }
+        }
+        ProjectResolver { labels }
     }
 
-    fn resolve_address(&self, address: u32) -> Option<String> {
-        self.symbols.get(&address).cloned()
+    pub fn is_empty(&self) -> bool {
+        self.labels.labels.is_empty()
     }
 }
 
-/// Resolves LVO offsets using the static Amiga OS library tables.
+impl SymbolResolver for ProjectResolver {
+    fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.labels.exact(location)
+    }
+
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.labels.nearest(location)
+    }
+}
+
+/// Resolves LVO offsets using an Amiga OS library table.
 ///
-/// For now, this resolves against a single library (typically "exec"
-/// since A6 is assumed to be ExecBase without data-flow analysis).
+/// `resolve_lvo` uses a single default library (typically "exec", for
+/// calls through A6 whose base the data-flow pass found nothing for);
+/// `library_lvo_entry` looks in any library.
 pub struct LvoResolver {
-    library_name: String,
+    library: Option<Library>,
+    libraries: Vec<Library>,
 }
 
 impl LvoResolver {
+    /// Resolve against the built-in table for `library_name`.
     pub fn new(library_name: &str) -> Self {
+        Self::with_libraries(library_name, &[])
+    }
+
+    /// Resolve against `library_name` from `libraries` (e.g. loaded from
+    /// `.fd` files), falling back to the built-in tables.
+    pub fn with_libraries(library_name: &str, libraries: &[Library]) -> Self {
         LvoResolver {
-            library_name: library_name.to_string(),
+            library: amiga::find_library(library_name, libraries).cloned(),
+            libraries: libraries.to_vec(),
         }
     }
 }
 
 impl SymbolResolver for LvoResolver {
-    fn resolve_lvo(&self, offset: i16) -> Option<String> {
-        amiga::lookup_lvo(&self.library_name, offset)
-            .map(|s| s.to_string())
+    fn resolve_lvo(&self, offset: i16) -> Option<ResolvedSymbol> {
+        self.library
+            .as_ref()?
+            .lookup(offset)
+            .map(|e| ResolvedSymbol::new(e.name.clone(), SymbolKind::Lvo))
     }
 
-    fn resolve_address(&self, _address: u32) -> Option<String> {
-        None
+    fn lvo_entry(&self, offset: i16) -> Option<LvoEntry> {
+        self.library.as_ref()?.lookup(offset).cloned()
+    }
+
+    fn library_lvo_entry(&self, library: &str, offset: i16) -> Option<LvoEntry> {
+        amiga::find_library(library, &self.libraries)?.lookup(offset).cloned()
+    }
+}
+
+/// Resolves Amiga hardware registers: the custom chips, the CIAs,
+/// Gayle, Akiko and the clock.
+pub struct HardwareResolver;
+
+impl HardwareResolver {
+    pub fn new() -> Self {
+        HardwareResolver
+    }
+}
+
+impl Default for HardwareResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolResolver for HardwareResolver {
+    fn resolve_register(&self, address: u32) -> Option<ResolvedSymbol> {
+        hardware::register_name(address)
+            .map(|name| ResolvedSymbol::new(name, SymbolKind::HardwareRegister))
+    }
+
+    fn resolve_register_offset(&self, base: u32, offset: i16) -> Option<ResolvedSymbol> {
+        hardware::register_offset_name(base, offset)
+            .map(|name| ResolvedSymbol::new(name, SymbolKind::HardwareRegister))
+    }
+}
+
+/// Resolves fields of the built-in AmigaOS structure layouts.
+pub struct StructResolver;
+
+impl StructResolver {
+    pub fn new() -> Self {
+        StructResolver
+    }
+}
+
+impl Default for StructResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolResolver for StructResolver {
+    fn resolve_struct_field(&self, type_name: &str, offset: i16) -> Option<ResolvedSymbol> {
+        structs::field_name(type_name, offset)
+            .map(|name| ResolvedSymbol::new(name, SymbolKind::StructField))
+    }
+}
+
+/// Resolves structure fields and constants read from assembler include
+/// files.
+///
+/// A constant names a value only when no other constant has it. Any
+/// immediate outside the 16-bit range is named: small numbers have too
+/// many meanings, but tag IDs and other large values rarely collide.
+/// Small values are named only as the value of a field, from the
+/// constants declared with the structure the field belongs to.
+pub struct IncludeResolver {
+    structs: StructDatabase,
+    /// Names of the constants with each value.
+    constants: BTreeMap<u32, Vec<String>>,
+    /// The constants declared with each structure, with their values.
+    struct_constants: BTreeMap<String, Vec<(String, u32)>>,
+}
+
+impl IncludeResolver {
+    pub fn new(includes: IncludeSymbols) -> Self {
+        let as_u32 = |value: i64| i32::try_from(value).map(|v| v as u32).or(u32::try_from(value)).ok();
+        let mut constants: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for (name, value) in &includes.constants {
+            if let Some(value) = as_u32(*value) {
+                constants.entry(value).or_default().push(name.clone());
+            }
+        }
+        let struct_constants = includes
+            .struct_constants
+            .into_iter()
+            .map(|(layout, names)| {
+                let values = names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let value = as_u32(*includes.constants.get(&name)?)?;
+                        Some((name, value))
+                    })
+                    .collect();
+                (layout, values)
+            })
+            .collect();
+        IncludeResolver {
+            structs: includes.structs,
+            constants,
+            struct_constants,
+        }
+    }
+}
+
+impl SymbolResolver for IncludeResolver {
+    fn resolve_struct_field(&self, type_name: &str, offset: i16) -> Option<ResolvedSymbol> {
+        self.structs
+            .field_name(type_name, offset)
+            .map(|name| ResolvedSymbol::new(name, SymbolKind::StructField))
+    }
+
+    fn resolve_constant(&self, value: u32) -> Option<ResolvedSymbol> {
+        if value <= 0xFFFF || value >= 0xFFFF_8000 {
+            return None;
+        }
+        match self.constants.get(&value)?.as_slice() {
+            [name] => Some(ResolvedSymbol::new(name.clone(), SymbolKind::Constant)),
+            _ => None,
+        }
+    }
+
+    fn resolve_field_constant(&self, type_name: &str, offset: i16, value: u32) -> Option<ResolvedSymbol> {
+        let owner = self.structs.field_owner(type_name, offset)?;
+        let mut names = self.struct_constants.get(owner)?.iter().filter(|(_, v)| *v == value);
+        match (names.next(), names.next()) {
+            (Some((name, _)), None) => Some(ResolvedSymbol::new(name.clone(), SymbolKind::Constant)),
+            _ => None,
+        }
     }
 }
 
@@ -74,37 +472,71 @@ impl SymbolResolver for LvoResolver {
 ///
 /// Built by scanning all branch/jump targets in a first pass, then
 /// assigned names like `loc_001A` based on the target address.
+#[derive(Default)]
 pub struct AutoLabelResolver {
-    labels: BTreeMap<u32, String>,
+    labels: LabelMap,
 }
 
 impl AutoLabelResolver {
-    /// Create from a set of target addresses.
-    pub fn from_targets(targets: impl IntoIterator<Item = u32>) -> Self {
-        let mut labels = BTreeMap::new();
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create from the target addresses in hunk `hunk`, which is
+    /// listed from `base_address`.
+    pub fn from_targets(
+        hunk: usize,
+        base_address: u32,
+        targets: impl IntoIterator<Item = u32>,
+    ) -> Self {
+        let mut resolver = Self::new();
+        resolver.add_targets(hunk, base_address, targets);
+        resolver
+    }
+
+    /// Add a label unless `location` already has one.
+    pub fn add_label(&mut self, location: Location, name: String, kind: SymbolKind) {
+        self.labels.insert(location, name, kind);
+    }
+
+    /// Add the target addresses in another hunk.
+    pub fn add_targets(
+        &mut self,
+        hunk: usize,
+        base_address: u32,
+        targets: impl IntoIterator<Item = u32>,
+    ) {
         for addr in targets {
-            labels.insert(addr, format!("loc_{:04X}", addr));
+            let location = Location::new(hunk, addr.wrapping_sub(base_address));
+            self.labels.insert(location, format!("loc_{:04X}", addr), SymbolKind::CodeLabel);
         }
-        AutoLabelResolver { labels }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.labels.is_empty()
     }
 }
 
 impl SymbolResolver for AutoLabelResolver {
-    fn resolve_lvo(&self, _offset: i16) -> Option<String> {
-        None
+    fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.labels.exact(location)
     }
 
-    fn resolve_address(&self, address: u32) -> Option<String> {
-        self.labels.get(&address).cloned()
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.labels.nearest(location)
     }
 }
 
 /// Chains multiple resolvers, returning the first match.
+///
+/// `resolve_nearest` is the exception: it returns the closest match of
+/// all the resolvers, the earliest one on a tie.
 pub struct CompositeResolver {
     resolvers: Vec<Box<dyn SymbolResolver>>,
 }
 
 impl CompositeResolver {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         CompositeResolver {
             resolvers: Vec::new(),
@@ -114,25 +546,78 @@ impl CompositeResolver {
     pub fn add(&mut self, resolver: Box<dyn SymbolResolver>) {
         self.resolvers.push(resolver);
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.resolvers.is_empty()
+    }
 }
 
 impl SymbolResolver for CompositeResolver {
-    fn resolve_lvo(&self, offset: i16) -> Option<String> {
-        for r in &self.resolvers {
-            if let Some(name) = r.resolve_lvo(offset) {
-                return Some(name);
-            }
-        }
-        None
+    fn resolve_location(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.resolvers.iter().find_map(|r| r.resolve_location(location))
     }
 
-    fn resolve_address(&self, address: u32) -> Option<String> {
-        for r in &self.resolvers {
-            if let Some(name) = r.resolve_address(address) {
-                return Some(name);
-            }
-        }
-        None
+    fn resolve_nearest(&self, location: Location) -> Option<ResolvedSymbol> {
+        self.resolvers
+            .iter()
+            .filter_map(|r| r.resolve_nearest(location))
+            .min_by_key(|s| s.offset)
+    }
+
+    fn locate(&self, address: u32) -> Option<Location> {
+        self.resolvers.iter().find_map(|r| r.locate(address))
+    }
+
+    fn relocation_target(&self, words: Range<u32>, value: u32) -> Option<Location> {
+        self.resolvers.iter().find_map(|r| r.relocation_target(words.clone(), value))
+    }
+
+    fn base_relative_target(&self, address: u32, register: u8, displacement: i32) -> Option<Location> {
+        self.resolvers
+            .iter()
+            .find_map(|r| r.base_relative_target(address, register, displacement))
+    }
+
+    fn resolve_lvo(&self, offset: i16) -> Option<ResolvedSymbol> {
+        self.resolvers.iter().find_map(|r| r.resolve_lvo(offset))
+    }
+
+    fn lvo_entry(&self, offset: i16) -> Option<LvoEntry> {
+        self.resolvers.iter().find_map(|r| r.lvo_entry(offset))
+    }
+
+    fn library_lvo_entry(&self, library: &str, offset: i16) -> Option<LvoEntry> {
+        self.resolvers.iter().find_map(|r| r.library_lvo_entry(library, offset))
+    }
+
+    fn resolve_register(&self, address: u32) -> Option<ResolvedSymbol> {
+        self.resolvers.iter().find_map(|r| r.resolve_register(address))
+    }
+
+    fn resolve_register_offset(&self, base: u32, offset: i16) -> Option<ResolvedSymbol> {
+        self.resolvers.iter().find_map(|r| r.resolve_register_offset(base, offset))
+    }
+
+    fn address_register_base(&self, address: u32, register: u8) -> Option<u32> {
+        self.resolvers.iter().find_map(|r| r.address_register_base(address, register))
+    }
+
+    fn address_register_type(&self, address: u32, register: u8) -> Option<String> {
+        self.resolvers.iter().find_map(|r| r.address_register_type(address, register))
+    }
+
+    fn resolve_struct_field(&self, type_name: &str, offset: i16) -> Option<ResolvedSymbol> {
+        self.resolvers.iter().find_map(|r| r.resolve_struct_field(type_name, offset))
+    }
+
+    fn resolve_constant(&self, value: u32) -> Option<ResolvedSymbol> {
+        self.resolvers.iter().find_map(|r| r.resolve_constant(value))
+    }
+
+    fn resolve_field_constant(&self, type_name: &str, offset: i16, value: u32) -> Option<ResolvedSymbol> {
+        self.resolvers
+            .iter()
+            .find_map(|r| r.resolve_field_constant(type_name, offset, value))
     }
 }
 
@@ -142,18 +627,103 @@ mod tests {
 
     #[test]
     fn auto_label_resolver() {
-        let resolver = AutoLabelResolver::from_targets(vec![0x001A, 0x0042]);
-        assert_eq!(resolver.resolve_address(0x001A), Some("loc_001A".to_string()));
-        assert_eq!(resolver.resolve_address(0x0042), Some("loc_0042".to_string()));
-        assert_eq!(resolver.resolve_address(0x0099), None);
+        let mut resolver = AutoLabelResolver::from_targets(0, 0, vec![0x001A, 0x0042]);
+        resolver.add_targets(1, 0x1000, vec![0x1010]);
+        let name = |location| resolver.resolve_location(location).map(|s| s.to_string());
+        assert_eq!(name(Location::new(0, 0x1A)), Some("loc_001A".to_string()));
+        assert_eq!(name(Location::new(0, 0x42)), Some("loc_0042".to_string()));
+        assert_eq!(name(Location::new(0, 0x99)), None);
+        assert_eq!(name(Location::new(1, 0x10)), Some("loc_1010".to_string()));
+        assert_eq!(name(Location::new(1, 0x1A)), None);
+        assert_eq!(resolver.resolve_address(0x001A), None);
     }
 
     #[test]
     fn lvo_resolver() {
         let resolver = LvoResolver::new("exec");
-        assert_eq!(resolver.resolve_lvo(-552), Some("_LVOOpenLibrary".to_string()));
+        let open = resolver.resolve_lvo(-552).unwrap();
+        assert_eq!(open.name, "_LVOOpenLibrary");
+        assert_eq!(open.kind, SymbolKind::Lvo);
         assert_eq!(resolver.resolve_lvo(-999), None);
         assert_eq!(resolver.resolve_address(0x100), None);
+
+        let open = resolver.library_lvo_entry("dos", -30).unwrap();
+        assert_eq!(open.function_name(), "Open");
+        assert!(resolver.library_lvo_entry("nosuch", -30).is_none());
+    }
+
+    #[test]
+    fn hardware_resolver() {
+        let resolver = HardwareResolver::new();
+        let register = |address| resolver.resolve_register(address).map(|s| s.name);
+        let offset = |base, offset| resolver.resolve_register_offset(base, offset).map(|s| s.name);
+        assert_eq!(register(0xDFF096), Some("DMACON".to_string()));
+        assert_eq!(register(0xDFF200), None);
+        assert_eq!(register(0x96), None);
+        assert_eq!(offset(0xDFF000, 0x9A), Some("INTENA".to_string()));
+        assert_eq!(offset(0xDFF000, -2), None);
+        assert_eq!(offset(0xDFF180, 2), None);
+        assert_eq!(register(0xBFE001), Some("CIAAPRA".to_string()));
+        assert_eq!(
+            resolver.resolve_register(0xDFF096).unwrap().kind,
+            SymbolKind::HardwareRegister
+        );
+    }
+
+    #[test]
+    fn include_resolver() {
+        let text = "
+    STRUCTURE   MyData,0
+    APTR        md_Next
+    UWORD       md_Count
+    STRUCT      md_Node,LN_SIZE
+    LABEL       md_SIZE
+MDCOUNT_MAX         EQU 1234
+MDCOUNT_NONE        EQU -2
+";
+        let globals = "
+TAG_USER            EQU $80000000
+MUIA_Window_Title   EQU TAG_USER|$42BE7
+MUIA_Alias          EQU $80000001
+MUIA_Alias2         EQU $80000001
+MEMF_CLEAR          EQU 1<<16
+NT_TASK             EQU 1
+MYCONST             EQU 1234
+MYOTHER             EQU 1235
+";
+        let nodes = "
+    STRUCTURE   LN,0
+    APTR        LN_SUCC
+    APTR        LN_PRED
+    UBYTE       LN_TYPE
+    BYTE        LN_PRI
+    APTR        LN_NAME
+    LABEL       LN_SIZE
+NT_TASK             EQU 1
+NT_PROCESS          EQU 13
+";
+        let resolver = IncludeResolver::new(super::super::include::parse_includes(&[globals, nodes, text]));
+        let field = resolver.resolve_struct_field("MyData", 4).unwrap();
+        assert_eq!((field.name.as_str(), field.kind), ("md_Count", SymbolKind::StructField));
+        let constant = |value| resolver.resolve_constant(value).map(|s| s.name);
+        assert_eq!(constant(0x80042BE7), Some("MUIA_Window_Title".to_string()));
+        assert_eq!(constant(0x10000), Some("MEMF_CLEAR".to_string()));
+        // Ambiguous and small values stay numbers
+        assert_eq!(constant(0x80000001), None);
+        assert_eq!(constant(1), None);
+        assert_eq!(constant(1234), None);
+
+        // A small value is named from the constants declared with the
+        // field's structure, and no others
+        let field_constant = |type_name, offset, value| {
+            resolver.resolve_field_constant(type_name, offset, value).map(|s| s.name)
+        };
+        assert_eq!(field_constant("MyData", 4, 1234), Some("MDCOUNT_MAX".to_string()));
+        assert_eq!(field_constant("MyData", 4, 0xFFFF_FFFE), Some("MDCOUNT_NONE".to_string()));
+        assert_eq!(field_constant("MyData", 14, 13), Some("NT_PROCESS".to_string()));
+        assert_eq!(field_constant("MyData", 4, 1235), None);
+        assert_eq!(field_constant("MyData", 14, 1234), None);
+        assert_eq!(field_constant("MyData", 40, 1234), None);
     }
 
     #[test]
@@ -161,15 +731,16 @@ mod tests {
         let mut composite = CompositeResolver::new();
 
         // Auto-labels take priority
-        let auto = AutoLabelResolver::from_targets(vec![0x001A]);
+        let auto = AutoLabelResolver::from_targets(0, 0, vec![0x001A]);
         composite.add(Box::new(auto));
 
         // LVO resolver for exec
         let lvo = LvoResolver::new("exec");
         composite.add(Box::new(lvo));
 
-        assert_eq!(composite.resolve_address(0x001A), Some("loc_001A".to_string()));
-        assert_eq!(composite.resolve_lvo(-552), Some("_LVOOpenLibrary".to_string()));
+        let label = composite.resolve_location(Location::new(0, 0x1A)).unwrap();
+        assert_eq!(label.name, "loc_001A");
+        assert_eq!(composite.resolve_lvo(-552).unwrap().name, "_LVOOpenLibrary");
     }
 
     #[test]
@@ -180,6 +751,7 @@ mod tests {
             memory_type: crate::hunk::types::MemoryType::Any,
             alloc_size: 100,
             data: vec![],
+            base_address: 0,
             relocations: vec![],
             symbols: vec![
                 crate::hunk::types::Symbol { name: "_main".to_string(), value: 0x0000 },
@@ -187,11 +759,42 @@ mod tests {
             ],
             name: None,
             debug_data: None,
+            block_offset: 0,
+            data_offset: 0,
+            metadata_offsets: vec![],
+        };
+
+        let data = Hunk {
+            index: 2,
+            hunk_type: crate::hunk::types::HunkType::Bss,
+            symbols: vec![crate::hunk::types::Symbol { name: "_buffer".to_string(), value: 0x0000 }],
+            ..hunk.clone()
         };
 
-        let resolver = HunkSymbolResolver::from_hunk(&hunk);
-        assert_eq!(resolver.resolve_address(0x0000), Some("_main".to_string()));
-        assert_eq!(resolver.resolve_address(0x0020), Some("_exit".to_string()));
-        assert_eq!(resolver.resolve_address(0x0010), None);
+        let resolver = HunkSymbolResolver::from_hunks(&[hunk, data]);
+        let name = |hunk, offset| resolver.resolve_location(Location::new(hunk, offset));
+        assert_eq!(name(0, 0x0000).unwrap().name, "_main");
+        assert_eq!(name(0, 0x0020).unwrap().name, "_exit");
+        assert_eq!(name(0, 0x0010), None);
+
+        // Hunk 2 has its own symbol at offset 0
+        let buffer = name(2, 0x0000).unwrap();
+        assert_eq!((buffer.name.as_str(), buffer.kind), ("_buffer", SymbolKind::DataLabel));
+        assert_eq!(name(0, 0x0000).unwrap().kind, SymbolKind::CodeLabel);
+    }
+
+    #[test]
+    fn nearest_symbol() {
+        let mut composite = CompositeResolver::new();
+        composite.add(Box::new(AutoLabelResolver::from_targets(0, 0, vec![0x10, 0x30])));
+        composite.add(Box::new(AutoLabelResolver::from_targets(0, 0, vec![0x28])));
+
+        let nearest = |offset| composite.resolve_nearest(Location::new(0, offset));
+        assert_eq!(nearest(0x10).unwrap().to_string(), "loc_0010");
+        assert_eq!(nearest(0x20).unwrap().to_string(), "loc_0010+$10");
+        assert_eq!(nearest(0x2C).unwrap().to_string(), "loc_0028+$4");
+        assert_eq!(nearest(0x08), None);
+        // Labels do not reach into the next hunk
+        assert_eq!(composite.resolve_nearest(Location::new(1, 0x40)), None);
     }
 }
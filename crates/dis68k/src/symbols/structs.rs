@@ -0,0 +1,570 @@
+//! AmigaOS structure layouts.
+//!
+//! Field offsets for the exec, dos, graphics and intuition structures a
+//! program most often reaches through a pointer, so `276(a6)` with A6 =
+//! ExecBase can be shown as `ThisTask(a6)`. Layouts are written as field
+//! lists in the order of the NDK's C headers and offsets are computed
+//! from them with the 68000's rules: words, longs and structures start
+//! on even offsets. Field names are the C names.
+//!
+//! A structure whose first member is another structure extends it
+//! (Process extends Task, IOStdReq extends IORequest). An offset beyond
+//! the end of a structure is looked up in the structures that extend
+//! it, when only one of them names it.
+//!
+//! `StructDatabase` holds the built-in layouts or ones read from
+//! assembler include files (`symbols::include`), where the same
+//! structures go by shorter names (`TC` for Task); lookups by C name
+//! find those too.
+
+use std::sync::OnceLock;
+
+/// What a structure field holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+    /// A number, flag set, BPTR or untyped pointer.
+    Scalar,
+    /// A pointer to a structure, by name.
+    Pointer(String),
+    /// An embedded structure, by name.
+    Struct(String),
+    /// An array of `count` elements.
+    Array { element: Box<FieldKind>, count: u32 },
+}
+
+/// One field of a structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructField {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+    pub kind: FieldKind,
+}
+
+/// A structure's fields, in offset order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout {
+    pub name: String,
+    pub size: u32,
+    pub fields: Vec<StructField>,
+}
+
+impl StructLayout {
+    /// The field containing `offset`.
+    pub fn field_at(&self, offset: u32) -> Option<&StructField> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|f| f.offset <= offset && offset < f.offset + f.size.max(1))
+    }
+
+    /// The structure this one extends, if its first field embeds one.
+    pub fn extends(&self) -> Option<&str> {
+        match self.fields.first() {
+            Some(StructField { offset: 0, kind: FieldKind::Struct(name), .. }) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Field lists: (structure, [(field, type)]). A type is a scalar
+/// (`UBYTE`, `WORD`, `ULONG`, `APTR`, `BPTR`, ...), `*Name` for a
+/// pointer to a structure, `Name` for an embedded structure, and any of
+/// these followed by `[n]` for an array. Structures are listed after
+/// the ones they embed.
+static BUILTIN_LAYOUTS: &[(&str, &[(&str, &str)])] = &[
+    // exec/nodes.h, exec/lists.h
+    ("Node", &[
+        ("ln_Succ", "*Node"), ("ln_Pred", "*Node"), ("ln_Type", "UBYTE"), ("ln_Pri", "BYTE"),
+        ("ln_Name", "APTR"),
+    ]),
+    ("MinNode", &[("mln_Succ", "*MinNode"), ("mln_Pred", "*MinNode")]),
+    ("List", &[
+        ("lh_Head", "*Node"), ("lh_Tail", "*Node"), ("lh_TailPred", "*Node"), ("lh_Type", "UBYTE"),
+        ("l_pad", "UBYTE"),
+    ]),
+    ("MinList", &[("mlh_Head", "*MinNode"), ("mlh_Tail", "*MinNode"), ("mlh_TailPred", "*MinNode")]),
+    // exec/libraries.h
+    ("Library", &[
+        ("lib_Node", "Node"), ("lib_Flags", "UBYTE"), ("lib_pad", "UBYTE"), ("lib_NegSize", "UWORD"),
+        ("lib_PosSize", "UWORD"), ("lib_Version", "UWORD"), ("lib_Revision", "UWORD"),
+        ("lib_IdString", "APTR"), ("lib_Sum", "ULONG"), ("lib_OpenCnt", "UWORD"),
+    ]),
+    // exec/interrupts.h
+    ("Interrupt", &[("is_Node", "Node"), ("is_Data", "APTR"), ("is_Code", "APTR")]),
+    ("IntVector", &[("iv_Data", "APTR"), ("iv_Code", "APTR"), ("iv_Node", "*Node")]),
+    ("SoftIntList", &[("sh_List", "List"), ("sh_Pad", "UWORD")]),
+    // exec/semaphores.h
+    ("SemaphoreRequest", &[("sr_Link", "MinNode"), ("sr_Waiter", "*Task")]),
+    ("SignalSemaphore", &[
+        ("ss_Link", "Node"), ("ss_NestCount", "WORD"), ("ss_WaitQueue", "MinList"),
+        ("ss_MultipleLink", "SemaphoreRequest"), ("ss_Owner", "*Task"), ("ss_QueueCount", "WORD"),
+    ]),
+    // exec/tasks.h
+    ("Task", &[
+        ("tc_Node", "Node"), ("tc_Flags", "UBYTE"), ("tc_State", "UBYTE"), ("tc_IDNestCnt", "BYTE"),
+        ("tc_TDNestCnt", "BYTE"), ("tc_SigAlloc", "ULONG"), ("tc_SigWait", "ULONG"),
+        ("tc_SigRecvd", "ULONG"), ("tc_SigExcept", "ULONG"), ("tc_TrapAlloc", "UWORD"),
+        ("tc_TrapAble", "UWORD"), ("tc_ExceptData", "APTR"), ("tc_ExceptCode", "APTR"),
+        ("tc_TrapData", "APTR"), ("tc_TrapCode", "APTR"), ("tc_SPReg", "APTR"), ("tc_SPLower", "APTR"),
+        ("tc_SPUpper", "APTR"), ("tc_Switch", "APTR"), ("tc_Launch", "APTR"), ("tc_MemEntry", "List"),
+        ("tc_UserData", "APTR"),
+    ]),
+    // exec/ports.h
+    ("MsgPort", &[
+        ("mp_Node", "Node"), ("mp_Flags", "UBYTE"), ("mp_SigBit", "UBYTE"), ("mp_SigTask", "*Task"),
+        ("mp_MsgList", "List"),
+    ]),
+    ("Message", &[("mn_Node", "Node"), ("mn_ReplyPort", "*MsgPort"), ("mn_Length", "UWORD")]),
+    // exec/io.h
+    ("IORequest", &[
+        ("io_Message", "Message"), ("io_Device", "*Library"), ("io_Unit", "APTR"),
+        ("io_Command", "UWORD"), ("io_Flags", "UBYTE"), ("io_Error", "BYTE"),
+    ]),
+    ("IOStdReq", &[
+        ("io_Req", "IORequest"), ("io_Actual", "ULONG"), ("io_Length", "ULONG"), ("io_Data", "APTR"),
+        ("io_Offset", "ULONG"),
+    ]),
+    // exec/execbase.h
+    ("ExecBase", &[
+        ("LibNode", "Library"), ("SoftVer", "UWORD"), ("LowMemChkSum", "WORD"), ("ChkBase", "ULONG"),
+        ("ColdCapture", "APTR"), ("CoolCapture", "APTR"), ("WarmCapture", "APTR"),
+        ("SysStkUpper", "APTR"), ("SysStkLower", "APTR"), ("MaxLocMem", "ULONG"),
+        ("DebugEntry", "APTR"), ("DebugData", "APTR"), ("AlertData", "APTR"), ("MaxExtMem", "APTR"),
+        ("ChkSum", "UWORD"), ("IntVects", "IntVector[16]"), ("ThisTask", "*Task"),
+        ("IdleCount", "ULONG"), ("DispCount", "ULONG"), ("Quantum", "UWORD"), ("Elapsed", "UWORD"),
+        ("SysFlags", "UWORD"), ("IDNestCnt", "BYTE"), ("TDNestCnt", "BYTE"), ("AttnFlags", "UWORD"),
+        ("AttnResched", "UWORD"), ("ResModules", "APTR"), ("TaskTrapCode", "APTR"),
+        ("TaskExceptCode", "APTR"), ("TaskExitCode", "APTR"), ("TaskSigAlloc", "ULONG"),
+        ("TaskTrapAlloc", "UWORD"), ("MemList", "List"), ("ResourceList", "List"),
+        ("DeviceList", "List"), ("IntrList", "List"), ("LibList", "List"), ("PortList", "List"),
+        ("TaskReady", "List"), ("TaskWait", "List"), ("SoftInts", "SoftIntList[5]"),
+        ("LastAlert", "LONG[4]"), ("VBlankFrequency", "UBYTE"), ("PowerSupplyFrequency", "UBYTE"),
+        ("SemaphoreList", "List"), ("KickMemPtr", "APTR"), ("KickTagPtr", "APTR"),
+        ("KickCheckSum", "APTR"), ("ex_Pad0", "UWORD"), ("ex_LaunchPoint", "ULONG"),
+        ("ex_RamLibPrivate", "APTR"), ("ex_EClockFrequency", "ULONG"), ("ex_CacheControl", "ULONG"),
+        ("ex_TaskID", "ULONG"), ("ex_Reserved1", "ULONG[5]"), ("ex_MMULock", "APTR"),
+        ("ex_Reserved2", "ULONG[3]"), ("ex_MemHandlers", "MinList"), ("ex_MemHandler", "APTR"),
+    ]),
+    // dos/dos.h, dos/dosextens.h
+    ("DateStamp", &[("ds_Days", "LONG"), ("ds_Minute", "LONG"), ("ds_Tick", "LONG")]),
+    ("FileInfoBlock", &[
+        ("fib_DiskKey", "LONG"), ("fib_DirEntryType", "LONG"), ("fib_FileName", "UBYTE[108]"),
+        ("fib_Protection", "LONG"), ("fib_EntryType", "LONG"), ("fib_Size", "LONG"),
+        ("fib_NumBlocks", "LONG"), ("fib_Date", "DateStamp"), ("fib_Comment", "UBYTE[80]"),
+        ("fib_OwnerUID", "UWORD"), ("fib_OwnerGID", "UWORD"), ("fib_Reserved", "UBYTE[32]"),
+    ]),
+    ("Process", &[
+        ("pr_Task", "Task"), ("pr_MsgPort", "MsgPort"), ("pr_Pad", "WORD"), ("pr_SegList", "BPTR"),
+        ("pr_StackSize", "LONG"), ("pr_GlobVec", "APTR"), ("pr_TaskNum", "LONG"),
+        ("pr_StackBase", "BPTR"), ("pr_Result2", "LONG"), ("pr_CurrentDir", "BPTR"),
+        ("pr_CIS", "BPTR"), ("pr_COS", "BPTR"), ("pr_ConsoleTask", "*MsgPort"),
+        ("pr_FileSystemTask", "*MsgPort"), ("pr_CLI", "BPTR"), ("pr_ReturnAddr", "APTR"),
+        ("pr_PktWait", "APTR"), ("pr_WindowPtr", "*Window"), ("pr_HomeDir", "BPTR"),
+        ("pr_Flags", "LONG"), ("pr_ExitCode", "APTR"), ("pr_ExitData", "LONG"),
+        ("pr_Arguments", "APTR"), ("pr_LocalVars", "MinList"), ("pr_ShellPrivate", "ULONG"),
+        ("pr_CES", "BPTR"),
+    ]),
+    ("CommandLineInterface", &[
+        ("cli_Result2", "LONG"), ("cli_SetName", "BPTR"), ("cli_CommandDir", "BPTR"),
+        ("cli_ReturnCode", "LONG"), ("cli_CommandName", "BPTR"), ("cli_FailLevel", "LONG"),
+        ("cli_Prompt", "BPTR"), ("cli_StandardInput", "BPTR"), ("cli_CurrentInput", "BPTR"),
+        ("cli_CommandFile", "BPTR"), ("cli_Interactive", "LONG"), ("cli_Background", "LONG"),
+        ("cli_CurrentOutput", "BPTR"), ("cli_DefaultStack", "LONG"), ("cli_StandardOutput", "BPTR"),
+        ("cli_Module", "BPTR"),
+    ]),
+    ("DosLibrary", &[
+        ("dl_lib", "Library"), ("dl_Root", "APTR"), ("dl_GV", "APTR"), ("dl_A2", "LONG"),
+        ("dl_A5", "LONG"), ("dl_A6", "LONG"), ("dl_Errors", "APTR"), ("dl_TimeReq", "APTR"),
+        ("dl_UtilityBase", "*Library"), ("dl_IntuitionBase", "*Library"),
+    ]),
+    // graphics/gfx.h, graphics/rastport.h, graphics/view.h, graphics/layers.h
+    ("BitMap", &[
+        ("BytesPerRow", "UWORD"), ("Rows", "UWORD"), ("Flags", "UBYTE"), ("Depth", "UBYTE"),
+        ("pad", "UWORD"), ("Planes", "APTR[8]"),
+    ]),
+    ("RastPort", &[
+        ("Layer", "APTR"), ("BitMap", "*BitMap"), ("AreaPtrn", "APTR"), ("TmpRas", "APTR"),
+        ("AreaInfo", "APTR"), ("GelsInfo", "APTR"), ("Mask", "UBYTE"), ("FgPen", "BYTE"),
+        ("BgPen", "BYTE"), ("AOlPen", "BYTE"), ("DrawMode", "BYTE"), ("AreaPtSz", "BYTE"),
+        ("linpatcnt", "BYTE"), ("dummy", "BYTE"), ("Flags", "UWORD"), ("LinePtrn", "UWORD"),
+        ("cp_x", "WORD"), ("cp_y", "WORD"), ("minterms", "UBYTE[8]"), ("PenWidth", "WORD"),
+        ("PenHeight", "WORD"), ("Font", "APTR"), ("AlgoStyle", "UBYTE"), ("TxFlags", "UBYTE"),
+        ("TxHeight", "UWORD"), ("TxWidth", "UWORD"), ("TxBaseline", "UWORD"), ("TxSpacing", "WORD"),
+        ("RP_User", "APTR"), ("longreserved", "ULONG[2]"), ("wordreserved", "UWORD[7]"),
+        ("reserved", "UBYTE[8]"),
+    ]),
+    ("ViewPort", &[
+        ("Next", "*ViewPort"), ("ColorMap", "APTR"), ("DspIns", "APTR"), ("SprIns", "APTR"),
+        ("ClrIns", "APTR"), ("UCopIns", "APTR"), ("DWidth", "WORD"), ("DHeight", "WORD"),
+        ("DxOffset", "WORD"), ("DyOffset", "WORD"), ("Modes", "UWORD"), ("SpritePriorities", "UBYTE"),
+        ("ExtendedModes", "UBYTE"), ("RasInfo", "APTR"),
+    ]),
+    ("Layer_Info", &[
+        ("top_layer", "APTR"), ("check_lp", "APTR"), ("obs", "APTR"), ("FreeClipRects", "MinList"),
+        ("PrivateReserve1", "LONG"), ("PrivateReserve2", "LONG"), ("Lock", "SignalSemaphore"),
+        ("gs_Head", "MinList"), ("PrivateReserve3", "WORD"), ("PrivateReserve4", "APTR"),
+        ("Flags", "UWORD"), ("fatten_count", "BYTE"), ("LockLayersCount", "BYTE"),
+        ("PrivateReserve5", "WORD"),
+    ]),
+    // intuition/screens.h, intuition/intuition.h
+    ("Screen", &[
+        ("NextScreen", "*Screen"), ("FirstWindow", "*Window"), ("LeftEdge", "WORD"),
+        ("TopEdge", "WORD"), ("Width", "WORD"), ("Height", "WORD"), ("MouseY", "WORD"),
+        ("MouseX", "WORD"), ("Flags", "UWORD"), ("Title", "APTR"), ("DefaultTitle", "APTR"),
+        ("BarHeight", "BYTE"), ("BarVBorder", "BYTE"), ("BarHBorder", "BYTE"), ("MenuVBorder", "BYTE"),
+        ("MenuHBorder", "BYTE"), ("WBorTop", "BYTE"), ("WBorLeft", "BYTE"), ("WBorRight", "BYTE"),
+        ("WBorBottom", "BYTE"), ("Font", "APTR"), ("ViewPort", "ViewPort"), ("RastPort", "RastPort"),
+        ("BitMap", "BitMap"), ("LayerInfo", "Layer_Info"), ("FirstGadget", "APTR"),
+        ("DetailPen", "UBYTE"), ("BlockPen", "UBYTE"), ("SaveColor0", "UWORD"), ("BarLayer", "APTR"),
+        ("ExtData", "APTR"), ("UserData", "APTR"),
+    ]),
+    ("Window", &[
+        ("NextWindow", "*Window"), ("LeftEdge", "WORD"), ("TopEdge", "WORD"), ("Width", "WORD"),
+        ("Height", "WORD"), ("MouseY", "WORD"), ("MouseX", "WORD"), ("MinWidth", "WORD"),
+        ("MinHeight", "WORD"), ("MaxWidth", "UWORD"), ("MaxHeight", "UWORD"), ("Flags", "ULONG"),
+        ("MenuStrip", "APTR"), ("Title", "APTR"), ("FirstRequest", "APTR"), ("DMRequest", "APTR"),
+        ("ReqCount", "WORD"), ("WScreen", "*Screen"), ("RPort", "*RastPort"), ("BorderLeft", "BYTE"),
+        ("BorderTop", "BYTE"), ("BorderRight", "BYTE"), ("BorderBottom", "BYTE"),
+        ("BorderRPort", "*RastPort"), ("FirstGadget", "APTR"), ("Parent", "*Window"),
+        ("Descendant", "*Window"), ("Pointer", "APTR"), ("PtrHeight", "BYTE"), ("PtrWidth", "BYTE"),
+        ("XOffset", "BYTE"), ("YOffset", "BYTE"), ("IDCMPFlags", "ULONG"), ("UserPort", "*MsgPort"),
+        ("WindowPort", "*MsgPort"), ("MessageKey", "APTR"), ("DetailPen", "UBYTE"),
+        ("BlockPen", "UBYTE"), ("CheckMark", "APTR"), ("ScreenTitle", "APTR"), ("GZZMouseX", "WORD"),
+        ("GZZMouseY", "WORD"), ("GZZWidth", "WORD"), ("GZZHeight", "WORD"), ("ExtData", "APTR"),
+        ("UserData", "APTR"), ("WLayer", "APTR"), ("IFont", "APTR"), ("MoreFlags", "ULONG"),
+    ]),
+    ("IntuiMessage", &[
+        ("ExecMessage", "Message"), ("Class", "ULONG"), ("Code", "UWORD"), ("Qualifier", "UWORD"),
+        ("IAddress", "APTR"), ("MouseX", "WORD"), ("MouseY", "WORD"), ("Seconds", "ULONG"),
+        ("Micros", "ULONG"), ("IDCMPWindow", "*Window"), ("SpecialLink", "APTR"),
+    ]),
+];
+
+/// Structure pointed to by a library's base, by library name.
+static LIBRARY_BASES: &[(&str, &str)] = &[("exec", "ExecBase"), ("dos", "DosLibrary")];
+
+/// Library functions that return a pointer to a structure in D0.
+static RETURN_TYPES: &[(&str, &str, &str)] = &[
+    ("exec", "FindTask", "Task"),
+    ("exec", "FindPort", "MsgPort"),
+    ("exec", "CreateMsgPort", "MsgPort"),
+    ("exec", "CreateIORequest", "IORequest"),
+    ("exec", "GetMsg", "Message"),
+    ("exec", "WaitPort", "Message"),
+    ("dos", "Cli", "CommandLineInterface"),
+    ("graphics", "AllocBitMap", "BitMap"),
+    ("intuition", "OpenWindow", "Window"),
+    ("intuition", "OpenWindowTagList", "Window"),
+    ("intuition", "OpenScreen", "Screen"),
+    ("intuition", "OpenScreenTagList", "Screen"),
+    ("intuition", "LockPubScreen", "Screen"),
+];
+
+/// The include files' names for built-in structures whose C and
+/// assembler names differ, so a register typed `Task` finds `TC`.
+static ASM_NAMES: &[(&str, &str)] = &[
+    ("Node", "LN"),
+    ("MinNode", "MLN"),
+    ("List", "LH"),
+    ("MinList", "MLH"),
+    ("Library", "LIB"),
+    ("Interrupt", "IS"),
+    ("SignalSemaphore", "SS"),
+    ("Task", "TC"),
+    ("MsgPort", "MP"),
+    ("Message", "MN"),
+    ("IORequest", "IO"),
+    ("IOStdReq", "IOSTD"),
+    ("BitMap", "bm"),
+    ("Screen", "sc"),
+];
+
+/// A set of structure layouts with field lookups.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructDatabase {
+    layouts: Vec<StructLayout>,
+}
+
+static BUILTIN: OnceLock<StructDatabase> = OnceLock::new();
+
+impl StructDatabase {
+    pub fn new(layouts: Vec<StructLayout>) -> Self {
+        StructDatabase { layouts }
+    }
+
+    /// The built-in structure layouts.
+    pub fn builtin() -> &'static StructDatabase {
+        BUILTIN.get_or_init(|| {
+            let mut layouts: Vec<StructLayout> = Vec::new();
+            for (name, fields) in BUILTIN_LAYOUTS {
+                let layout = build_layout(name, fields, &layouts);
+                layouts.push(layout);
+            }
+            StructDatabase { layouts }
+        })
+    }
+
+    pub fn layouts(&self) -> &[StructLayout] {
+        &self.layouts
+    }
+
+    /// Find a structure by name, or by the include files' name for a
+    /// built-in one.
+    pub fn find(&self, name: &str) -> Option<&StructLayout> {
+        let by_name = |name: &str| self.layouts.iter().find(|s| s.name == name);
+        by_name(name).or_else(|| {
+            let (_, asm) = ASM_NAMES.iter().find(|(c, _)| *c == name)?;
+            by_name(asm)
+        })
+    }
+
+    /// Name the field at `offset` in structure `name`: the outermost
+    /// field starting there, looking inside embedded structures
+    /// otherwise, with `+n` inside arrays and scalars.
+    pub fn field_name(&self, name: &str, offset: i16) -> Option<String> {
+        let offset = u32::try_from(offset).ok()?;
+        let layout = self.find(name)?;
+        if offset < layout.size {
+            return self.name_within(layout, offset);
+        }
+        // Past the end: the one extending structure that names it
+        let mut names: Vec<String> = self
+            .extensions(&layout.name)
+            .filter(|s| offset < s.size)
+            .filter_map(|s| self.name_within(s, offset))
+            .collect();
+        names.dedup();
+        match names.as_slice() {
+            [name] => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// The structure that declares the field `field_name` names at
+    /// `offset`: `LN` for `LN_TYPE` in a `TC`.
+    pub fn field_owner(&self, name: &str, offset: i16) -> Option<&str> {
+        let offset = u32::try_from(offset).ok()?;
+        let layout = self.find(name)?;
+        if offset < layout.size {
+            return self.owner_within(layout, offset);
+        }
+        let mut owners: Vec<&str> = self
+            .extensions(&layout.name)
+            .filter(|s| offset < s.size)
+            .filter_map(|s| self.owner_within(s, offset))
+            .collect();
+        owners.dedup();
+        match owners.as_slice() {
+            [owner] => Some(owner),
+            _ => None,
+        }
+    }
+
+    /// The field starting exactly at `offset`, looking inside embedded
+    /// structures, and only if it is a pointer to a structure or an
+    /// embedded structure: what a register holds after `move.l` or
+    /// `lea` of it.
+    pub fn field_type(&self, name: &str, offset: i16, address_of: bool) -> Option<&str> {
+        let mut offset = u32::try_from(offset).ok()?;
+        let mut layout = self.find(name)?;
+        if offset >= layout.size {
+            let mut matches = self.extensions(&layout.name).filter(|s| offset < s.size);
+            layout = matches.next()?;
+            if matches.next().is_some() {
+                return None;
+            }
+        }
+        loop {
+            let field = layout.field_at(offset)?;
+            if field.offset != offset {
+                // Inside an embedded structure
+                let FieldKind::Struct(inner) = &field.kind else { return None };
+                offset -= field.offset;
+                layout = self.find(inner)?;
+                continue;
+            }
+            match (&field.kind, address_of) {
+                (FieldKind::Pointer(target), false) => return self.find(target).map(|s| s.name.as_str()),
+                (FieldKind::Struct(inner), true) => return self.find(inner).map(|s| s.name.as_str()),
+                (FieldKind::Struct(inner), false) => {
+                    // The first field of an embedded structure
+                    layout = self.find(inner)?;
+                    offset = 0;
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn name_within(&self, layout: &StructLayout, offset: u32) -> Option<String> {
+        let field = layout.field_at(offset)?;
+        let within = offset - field.offset;
+        if let (FieldKind::Struct(inner), 1..) = (&field.kind, within) {
+            if let Some(name) = self.find(inner).and_then(|s| self.name_within(s, within)) {
+                return Some(name);
+            }
+        }
+        Some(match within {
+            0 => field.name.clone(),
+            n => format!("{}+{}", field.name, n),
+        })
+    }
+
+    fn owner_within<'a>(&'a self, layout: &'a StructLayout, offset: u32) -> Option<&'a str> {
+        let field = layout.field_at(offset)?;
+        let within = offset - field.offset;
+        if let (FieldKind::Struct(inner), 1..) = (&field.kind, within) {
+            if let Some(owner) = self.find(inner).and_then(|s| self.owner_within(s, within)) {
+                return Some(owner);
+            }
+        }
+        Some(&layout.name)
+    }
+
+    /// Structures that extend `name`, directly or through others.
+    fn extensions<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a StructLayout> + 'a {
+        self.layouts.iter().filter(move |s| {
+            let mut base = s.extends();
+            while let Some(b) = base {
+                if b == name {
+                    return true;
+                }
+                base = self.find(b).and_then(StructLayout::extends);
+            }
+            false
+        })
+    }
+}
+
+/// Find a built-in structure by name.
+pub fn find_struct(name: &str) -> Option<&'static StructLayout> {
+    StructDatabase::builtin().find(name)
+}
+
+/// `StructDatabase::field_name` in the built-in layouts.
+pub fn field_name(name: &str, offset: i16) -> Option<String> {
+    StructDatabase::builtin().field_name(name, offset)
+}
+
+/// `StructDatabase::field_type` in the built-in layouts.
+pub fn field_type(name: &str, offset: i16, address_of: bool) -> Option<&'static str> {
+    StructDatabase::builtin().field_type(name, offset, address_of)
+}
+
+/// The structure a library's base points to: its own for exec and dos,
+/// a plain `Library` for the rest.
+pub fn library_base_struct(library: &str) -> &'static str {
+    LIBRARY_BASES
+        .iter()
+        .find(|(l, _)| *l == library)
+        .map_or("Library", |(_, s)| s)
+}
+
+/// The library whose base a structure is, for the libraries with their
+/// own base structure (`ExecBase` → `exec`).
+pub fn base_struct_library(name: &str) -> Option<&'static str> {
+    LIBRARY_BASES
+        .iter()
+        .find(|(_, s)| *s == name)
+        .map(|(l, _)| *l)
+}
+
+/// The structure a library function returns a pointer to.
+pub fn return_type(library: &str, function: &str) -> Option<&'static str> {
+    RETURN_TYPES
+        .iter()
+        .find(|(l, f, _)| *l == library && *f == function)
+        .map(|(_, _, s)| *s)
+}
+
+fn build_layout(name: &str, fields: &[(&str, &str)], known: &[StructLayout]) -> StructLayout {
+    let mut offset = 0u32;
+    let mut out = Vec::new();
+    for (field, ty) in fields {
+        let (kind, size) = parse_type(ty, known);
+        if size > 1 {
+            offset += offset % 2;
+        }
+        out.push(StructField { name: field.to_string(), offset, size, kind });
+        offset += size;
+    }
+    StructLayout { name: name.to_string(), size: offset + offset % 2, fields: out }
+}
+
+/// Kind and size of a field type in `BUILTIN_LAYOUTS` notation.
+fn parse_type(ty: &str, known: &[StructLayout]) -> (FieldKind, u32) {
+    if let Some((element, count)) = ty.strip_suffix(']').and_then(|t| t.split_once('[')) {
+        let (element, size) = parse_type(element, known);
+        let count: u32 = count.parse().expect("array length");
+        return (FieldKind::Array { element: Box::new(element), count }, size * count);
+    }
+    match ty {
+        "BYTE" | "UBYTE" => (FieldKind::Scalar, 1),
+        "WORD" | "UWORD" => (FieldKind::Scalar, 2),
+        "LONG" | "ULONG" | "APTR" | "BPTR" => (FieldKind::Scalar, 4),
+        _ => match ty.strip_prefix('*') {
+            Some(target) => (FieldKind::Pointer(target.to_string()), 4),
+            None => {
+                let inner = known.iter().find(|s| s.name == ty).expect("structure defined earlier");
+                (FieldKind::Struct(ty.to_string()), inner.size)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_sizes() {
+        let size = |name| find_struct(name).unwrap().size;
+        assert_eq!(size("Node"), 14);
+        assert_eq!(size("Library"), 34);
+        assert_eq!(size("Task"), 92);
+        assert_eq!(size("Process"), 228);
+        assert_eq!(size("ExecBase"), 632);
+        assert_eq!(size("IOStdReq"), 48);
+        assert_eq!(size("FileInfoBlock"), 260);
+        assert_eq!(size("CommandLineInterface"), 64);
+        assert_eq!(size("RastPort"), 100);
+        assert_eq!(size("Layer_Info"), 102);
+        assert_eq!(size("Screen"), 346);
+        assert_eq!(size("Window"), 136);
+        assert_eq!(size("IntuiMessage"), 52);
+    }
+
+    #[test]
+    fn field_names() {
+        assert_eq!(field_name("ExecBase", 276).as_deref(), Some("ThisTask"));
+        assert_eq!(field_name("ExecBase", 20).as_deref(), Some("lib_Version"));
+        assert_eq!(field_name("ExecBase", 296).as_deref(), Some("AttnFlags"));
+        assert_eq!(field_name("ExecBase", 88).as_deref(), Some("IntVects+4"));
+        assert_eq!(field_name("Process", 172).as_deref(), Some("pr_CLI"));
+        assert_eq!(field_name("Process", 8).as_deref(), Some("ln_Type"));
+        assert_eq!(field_name("Process", 92).as_deref(), Some("pr_MsgPort"));
+        assert_eq!(field_name("Window", 50).as_deref(), Some("RPort"));
+        assert_eq!(field_name("BitMap", 12).as_deref(), Some("Planes+4"));
+        assert_eq!(field_name("Library", -30), None);
+
+        // Past the end of Task, only Process extends it
+        assert_eq!(field_name("Task", 172).as_deref(), Some("pr_CLI"));
+        // IORequest and IntuiMessage both extend Message
+        assert_eq!(field_name("Message", 20), None);
+        assert_eq!(field_name("IORequest", 40).as_deref(), Some("io_Data"));
+    }
+
+    #[test]
+    fn field_types() {
+        assert_eq!(field_type("ExecBase", 276, false), Some("Task"));
+        assert_eq!(field_type("Window", 86, false), Some("MsgPort"));
+        assert_eq!(field_type("Process", 92, true), Some("MsgPort"));
+        assert_eq!(field_type("Process", 0, false), Some("Node"));
+        assert_eq!(field_type("Process", 172, false), None);
+        assert_eq!(field_type("Task", 184, false), Some("Window"));
+        assert_eq!(library_base_struct("exec"), "ExecBase");
+        assert_eq!(library_base_struct("graphics"), "Library");
+        assert_eq!(return_type("intuition", "OpenWindow"), Some("Window"));
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::empty_line_after_doc_comments)]
+
 /// Tests for 68020+ extended addressing modes:
 /// - Scaled indexing (x1, x2, x4, x8)
 /// - Base displacement (word and long)
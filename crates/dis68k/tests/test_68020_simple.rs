@@ -1,3 +1,5 @@
+#![allow(clippy::empty_line_after_doc_comments)]
+
 /// Tests for simple 68020 instructions: EXTB.L, LINK.L, TRAPcc, Bcc.L
 
 use dis68k::m68k::decode::decode_instruction;
@@ -0,0 +1,53 @@
+//! Regression tests on the SAS/C startup code of tests/fixtures/test_multi_hunk.exe.
+//!
+//! The startup keeps DOSBase in an A4-relative global, so the dos calls
+//! below only resolve when base tracking follows small-data slots.
+
+use dis68k::{generate_listing, parse_hunk_file, CompositeResolver, ListingOptions, LvoResolver};
+
+fn listing() -> Vec<String> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/fixtures/test_multi_hunk.exe");
+    let data = std::fs::read(path).unwrap();
+    let hunk_file = parse_hunk_file(&data).unwrap();
+    let mut resolver = CompositeResolver::new();
+    resolver.add(Box::new(LvoResolver::new("exec")));
+    generate_listing(&hunk_file, &ListingOptions::default(), Some(&resolver))
+        .into_iter()
+        .map(|line| line.text)
+        .collect()
+}
+
+/// The listing line (number, address, ...) of the instruction at
+/// `address` in the first hunk.
+fn line_at(listing: &[String], address: u32) -> &str {
+    let address = format!("{:08X}", address);
+    listing
+        .iter()
+        .find(|line| line.split_whitespace().nth(1) == Some(address.as_str()))
+        .unwrap_or_else(|| panic!("no line at ${}", address))
+}
+
+#[test]
+fn test_dos_calls_through_small_data_global() {
+    let listing = listing();
+    for (address, call) in [
+        (0x11E, "; DupLock("),
+        (0x128, "; CurrentDir("),
+        (0x196, "; Close("),
+        (0x1A0, "; UnLock("),
+    ] {
+        let line = line_at(&listing, address);
+        assert!(line.contains(call), "${:X}: {}", address, line);
+    }
+
+    // Reached with DOSBase in A6 on one path and ExecBase on the other
+    let line = line_at(&listing, 0x138);
+    assert!(!line.contains(';'), "{}", line);
+}
+
+#[test]
+fn test_call_arguments_name_small_data() {
+    let listing = listing();
+    let line = line_at(&listing, 0x1B0);
+    assert!(line.contains("a1 = code_0_D864(a4)"), "{}", line);
+}